@@ -0,0 +1,316 @@
+use crate::config::{BackupFrequency, Config};
+use crate::cron::Cron;
+use crate::systemd::SystemdService;
+use crate::timestamp;
+use chrono::{DateTime, Duration, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use colored::*;
+use log::warn;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Признаки других агентов бэкапа в именах таймеров и командах; borgmatic проверяется раньше borg
+const AGENTS: [&str; 5] = ["restic", "borgmatic", "borg", "duplicity", "rsync"];
+
+/// Запуски сравниваются на столько дней вперёд: так в сравнение попадает и ежемесячное расписание
+const HORIZON_DAYS: i64 = 31;
+
+/// Сколько запусков одного расписания сравнивать, чтобы `* * * * *` не считался вечно
+const MAX_RUNS: usize = 2000;
+
+/// Сколько минут бэкап obt считается идущим, если не задан time_budget_minutes
+const DEFAULT_WINDOW_MINUTES: u64 = 60;
+
+const UNIT_DIRS: [&str; 3] = ["/etc/systemd/system", "/usr/lib/systemd/system", "/lib/systemd/system"];
+
+/// Другой агент бэкапа на сервере и его запуски в ближайшие `HORIZON_DAYS` дней
+pub struct Agent {
+    /// `restic`, `borgmatic`, `borg`, `duplicity` или `rsync`
+    pub name: &'static str,
+    /// Где найдено расписание: таймер systemd или строка crontab
+    pub source: String,
+    runs: Vec<DateTime<Utc>>,
+}
+
+/// Запуски агента, совпавшие с бэкапами obt
+pub struct Overlap {
+    pub name: &'static str,
+    pub source: String,
+    /// Сколько бэкапов obt за `HORIZON_DAYS` дней совпадает с агентом
+    pub count: usize,
+    /// Ближайший из них
+    pub first: DateTime<Utc>,
+}
+
+/// Итог проверки для `obt doctor` и `obt schedule`
+pub struct Report {
+    pub agents: Vec<Agent>,
+    pub overlaps: Vec<Overlap>,
+    /// Ближайший сдвиг расписания obt, при котором совпадений нет
+    pub offset: Option<Duration>,
+}
+
+fn agent(text: &str) -> Option<&'static str> {
+    let text = text.to_lowercase();
+    AGENTS.iter().copied().find(|name| text.contains(name))
+}
+
+/// Запуски по расписанию cron после `now` и не позже `until`
+fn cron_runs(cron: &Cron, zone: Tz, now: DateTime<Utc>, until: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let mut runs = Vec::new();
+    let mut after = now;
+    while let Some(next) = cron.next_after(after, zone).filter(|next| *next <= until && runs.len() < MAX_RUNS) {
+        runs.push(next);
+        after = next;
+    }
+    runs
+}
+
+/// Бэкапы obt по расписанию из config.json
+fn obt_runs(config: &Config, now: DateTime<Utc>, until: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let zone = timestamp::zone();
+    if let Ok(Some(cron)) = config.cron_schedule() {
+        return cron_runs(&cron, zone, now, until);
+    }
+    let Some(time) = config.backup_time.as_deref().and_then(|time| NaiveTime::parse_from_str(time, "%H:%M").ok()) else {
+        return Vec::new();
+    };
+    let frequency = config.backup_frequency.unwrap_or(BackupFrequency::Daily);
+    let today = now.with_timezone(&zone).date_naive();
+    (0..=HORIZON_DAYS)
+        .map(|days| today + Duration::days(days))
+        .filter(|date| frequency.runs_on(*date, Weekday::Mon))
+        .filter_map(|date| zone.from_local_datetime(&date.and_time(time)).earliest())
+        .map(|time| time.with_timezone(&Utc))
+        .filter(|time| *time > now && *time <= until)
+        .collect()
+}
+
+fn unit_file(name: &str) -> Option<String> {
+    UNIT_DIRS.iter().find_map(|dir| fs::read_to_string(Path::new(dir).join(name)).ok())
+}
+
+/// Значения ключа юнита: `OnCalendar=` может встречаться несколько раз
+fn unit_values<'a>(unit: &'a str, key: &'a str) -> impl Iterator<Item = &'a str> {
+    unit.lines()
+        .filter_map(move |line| line.trim().strip_prefix(key)?.strip_prefix('='))
+        .map(str::trim)
+}
+
+/// Запуски по `OnCalendar=` до `until` по расчёту `systemd-analyze calendar`: время в UTC —
+/// строки, оканчивающиеся на `UTC` (при системном поясе UTC строки `(in UTC):` нет)
+fn calendar_runs(calendar: &str, until: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let iterations = format!("--iterations={}", MAX_RUNS);
+    let output = match Command::new("systemd-analyze").args(["calendar", &iterations, calendar]).output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            warn!("systemd-analyze не разобрал OnCalendar={}: {}", calendar, String::from_utf8_lossy(&output.stderr).trim());
+            return Vec::new();
+        }
+        Err(e) => {
+            warn!("Не удалось запустить systemd-analyze: {}", e);
+            return Vec::new();
+        }
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().strip_suffix(" UTC"))
+        .filter_map(|line| {
+            let mut parts = line.rsplit(' ');
+            let clock = parts.next()?;
+            let date = parts.next()?;
+            NaiveDateTime::parse_from_str(&format!("{} {}", date, clock), "%Y-%m-%d %H:%M:%S").ok()
+        })
+        .map(|time| Utc.from_utc_datetime(&time))
+        .take_while(|time| *time <= until)
+        .collect()
+}
+
+/// Активные таймеры systemd, которые запускают агент бэкапа (по имени таймера или ExecStart= службы)
+fn timers(until: DateTime<Utc>) -> Vec<Agent> {
+    let mut seen = HashSet::new();
+    let mut agents = Vec::new();
+    for dir in UNIT_DIRS {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file = entry.file_name().to_string_lossy().to_string();
+            if !file.ends_with(".timer") || file.starts_with("obt") || !seen.insert(file.clone()) {
+                continue;
+            }
+            let Some(timer) = unit_file(&file) else {
+                continue;
+            };
+            let service = unit_values(&timer, "Unit")
+                .next()
+                .map(str::to_string)
+                .unwrap_or_else(|| file.replace(".timer", ".service"));
+            let service = unit_file(&service).unwrap_or_default();
+            let exec: Vec<&str> = unit_values(&service, "ExecStart").collect();
+            let Some(name) = agent(&format!("{} {}", file, exec.join(" "))) else {
+                continue;
+            };
+            if SystemdService::is_active(&file) == Some(false) {
+                continue;
+            }
+            let mut runs: Vec<DateTime<Utc>> = unit_values(&timer, "OnCalendar").flat_map(|calendar| calendar_runs(calendar, until)).collect();
+            runs.sort();
+            if !runs.is_empty() {
+                agents.push(Agent { name, source: format!("таймер {}", file), runs });
+            }
+        }
+    }
+    agents
+}
+
+/// Задания cron с агентами бэкапа: в /etc/crontab и /etc/cron.d после расписания идёт
+/// пользователь, в crontab пользователей — сразу команда. cron считает время в системном поясе
+fn cron_jobs(now: DateTime<Utc>, until: DateTime<Utc>) -> Vec<Agent> {
+    let mut files: Vec<(PathBuf, bool)> = vec![(PathBuf::from("/etc/crontab"), true)];
+    for (dir, system) in [("/etc/cron.d", true), ("/var/spool/cron/crontabs", false), ("/var/spool/cron", false)] {
+        if let Ok(entries) = fs::read_dir(dir) {
+            files.extend(entries.flatten().map(|entry| entry.path()).filter(|path| path.is_file()).map(|path| (path, system)));
+        }
+    }
+
+    let zone = timestamp::system_zone();
+    let mut agents = Vec::new();
+    for (path, system) in files {
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with("@reboot") {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (expression, rest) = if line.starts_with('@') {
+                (fields[0].to_string(), &fields[1..])
+            } else if fields.len() > 5 {
+                (fields[..5].join(" "), &fields[5..])
+            } else {
+                continue;
+            };
+            let command = if system { rest.get(1..).unwrap_or_default() } else { rest }.join(" ");
+            let Some(name) = agent(&command) else {
+                continue;
+            };
+            match Cron::parse(&expression) {
+                Ok(cron) => agents.push(Agent {
+                    name,
+                    source: format!("{}: {}", path.display(), expression),
+                    runs: cron_runs(&cron, zone, now, until),
+                }),
+                Err(e) => warn!("Расписание {} в {} не разобрано: {}", name, path.display(), e),
+            }
+        }
+    }
+    agents
+}
+
+/// Бэкапы obt (со сдвигом `shift`), которые идут одновременно с запусками агента:
+/// ближе `window` друг к другу
+fn conflicts(obt: &[DateTime<Utc>], agent: &Agent, shift: Duration, window: Duration) -> Vec<DateTime<Utc>> {
+    obt.iter()
+        .copied()
+        .filter(|run| agent.runs.iter().any(|other| (*run + shift - *other).abs() < window))
+        .collect()
+}
+
+/// Ищет другие агенты бэкапа и сравнивает их запуски с расписанием obt
+pub fn check(config: &Config) -> Report {
+    let now = Utc::now();
+    let until = now + Duration::days(HORIZON_DAYS);
+    let mut agents = timers(until);
+    agents.extend(cron_jobs(now, until));
+
+    let obt = obt_runs(config, now, until);
+    let window = Duration::minutes(config.time_budget_minutes.unwrap_or(DEFAULT_WINDOW_MINUTES) as i64);
+    let overlaps: Vec<Overlap> = agents
+        .iter()
+        .filter_map(|agent| {
+            let runs = conflicts(&obt, agent, Duration::zero(), window);
+            Some(Overlap { name: agent.name, source: agent.source.clone(), count: runs.len(), first: *runs.first()? })
+        })
+        .collect();
+
+    // Сдвиги по 15 минут в обе стороны, ближайшие первыми; время `backup_time` не должно
+    // переходить через полночь, иначе сменился бы день еженедельного и ежемесячного бэкапа
+    let time = config.backup_time.as_deref().and_then(|time| NaiveTime::parse_from_str(time, "%H:%M").ok());
+    let offset = (!overlaps.is_empty())
+        .then(|| {
+            (1..48)
+                .flat_map(|step| [Duration::minutes(15 * step), Duration::minutes(-15 * step)])
+                .filter(|shift| {
+                    config.backup_cron.is_some()
+                        || time.is_some_and(|time| (0..24 * 60).contains(&(time.num_seconds_from_midnight() as i64 / 60 + shift.num_minutes())))
+                })
+                .find(|shift| agents.iter().all(|agent| conflicts(&obt, agent, *shift, window).is_empty()))
+        })
+        .flatten();
+
+    Report { agents, overlaps, offset }
+}
+
+/// `1 ч 15 мин`, `45 мин`
+fn describe(duration: Duration) -> String {
+    let minutes = duration.num_minutes().abs();
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{} мин", minutes),
+        (hours, 0) => format!("{} ч", hours),
+        (hours, minutes) => format!("{} ч {} мин", hours, minutes),
+    }
+}
+
+impl Report {
+    /// Предупреждения о совпадениях и предлагаемый сдвиг; с `verbose` — и итог без совпадений
+    pub fn print(&self, config: &Config, verbose: bool) {
+        if self.overlaps.is_empty() {
+            if verbose {
+                if self.agents.is_empty() {
+                    println!("⚪ Других агентов бэкапа (restic, borgmatic, borg, duplicity, rsync) не найдено");
+                } else {
+                    let names: Vec<String> = self.agents.iter().map(|agent| format!("{} ({})", agent.name, agent.source)).collect();
+                    println!("{}", format!("✅ Расписание obt не совпадает с другими агентами бэкапа: {}", names.join(", ")).green());
+                }
+            }
+            return;
+        }
+
+        for overlap in &self.overlaps {
+            warn!("Бэкап obt совпадает по времени с {} ({}): запусков за месяц — {}", overlap.name, overlap.source, overlap.count);
+            println!(
+                "{}",
+                format!(
+                    "⚠️ Бэкап obt идёт одновременно с {} ({}): запусков за месяц — {}, ближайший — {}",
+                    overlap.name,
+                    overlap.source,
+                    overlap.count,
+                    timestamp::display(&timestamp::format(overlap.first))
+                )
+                .yellow()
+            );
+        }
+        println!("   Одновременные бэкапы забивают диск и канал небольшого VPS");
+
+        let Some(offset) = self.offset else {
+            println!("   Сдвиг расписания obt в пределах суток совпадений не убирает: разнесите бэкапы по дням");
+            return;
+        };
+        let direction = if offset > Duration::zero() { "позже" } else { "раньше" };
+        let time = config.backup_time.as_deref().and_then(|time| NaiveTime::parse_from_str(time, "%H:%M").ok());
+        match (&config.backup_cron, time) {
+            (None, Some(time)) => println!(
+                "   Запускайте obt на {} {}: obt schedule set {} {}",
+                describe(offset),
+                direction,
+                format!("{:?}", config.backup_frequency.unwrap_or(BackupFrequency::Daily)).to_lowercase(),
+                (time + offset).format("%H:%M")
+            ),
+            _ => println!("   Сдвиньте backup_cron на {} {}, тогда совпадений не будет", describe(offset), direction),
+        }
+    }
+}
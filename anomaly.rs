@@ -0,0 +1,87 @@
+use crate::config::Config;
+use crate::manifest::{ArchiveEntry, Manifest, SizeAnomaly};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const DEFAULT_FACTOR: f64 = 3.0;
+/// Сколько прошлых размеров пути хранится и сколько нужно для сравнения
+const HISTORY: usize = 10;
+const MIN_HISTORY: usize = 3;
+/// Колебания маленьких архивов (конфиги, ключи) не интересны
+const MIN_SIZE: u64 = 1_048_576;
+
+/// Размеры архивов каждого пути в последних полных бэкапах (~/.config/obt/sizes.json).
+/// Хранится отдельно от каталога, чтобы политика хранения не укорачивала историю.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    pub sources: BTreeMap<String, VecDeque<u64>>,
+}
+
+impl History {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_history_path()?;
+
+        if !path.exists() {
+            return Ok(History::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_history_path()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn get_history_path() -> io::Result<PathBuf> {
+        Ok(Config::config_dir()?.join("sizes.json"))
+    }
+
+    /// Архивы, размер которых отличается от медианы прошлых бэкапов того же пути больше
+    /// чем в `size_anomaly_factor` раз: например, в бэкап попал мусор или данные пропали
+    pub fn detect(&self, config: &Config, archives: &[ArchiveEntry]) -> Vec<SizeAnomaly> {
+        let factor = config.size_anomaly_factor.unwrap_or(DEFAULT_FACTOR).max(1.0);
+        archives
+            .iter()
+            .filter_map(|archive| {
+                let mut sizes: Vec<u64> = self.sources.get(&archive.source)?.iter().copied().collect();
+                if sizes.len() < MIN_HISTORY {
+                    return None;
+                }
+                sizes.sort_unstable();
+                let typical = sizes[sizes.len() / 2];
+                if typical.max(archive.size) < MIN_SIZE {
+                    return None;
+                }
+
+                let ratio = archive.size.max(1) as f64 / typical.max(1) as f64;
+                (ratio >= factor || ratio <= 1.0 / factor).then(|| SizeAnomaly {
+                    source: archive.source.clone(),
+                    size: archive.size,
+                    typical,
+                })
+            })
+            .collect()
+    }
+
+    /// Добавляет размеры архивов полного бэкапа; инкрементальные не учитываются —
+    /// их размер зависит от числа изменений
+    pub fn record(&mut self, manifest: &Manifest) {
+        if manifest.base.is_some() {
+            return;
+        }
+        for archive in &manifest.archives {
+            let sizes = self.sources.entry(archive.source.clone()).or_default();
+            sizes.push_back(archive.size);
+            while sizes.len() > HISTORY {
+                sizes.pop_front();
+            }
+        }
+    }
+}
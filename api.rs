@@ -0,0 +1,152 @@
+use crate::config::Config;
+use crate::events::Event;
+use crate::labels::Labels;
+use crate::{execute_backup, progress, staging, timestamp, BackupOptions};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// Бэкап из другой программы на Rust (например, панели управления) без запуска obt:
+/// `BackupRunner::new().profile("vpn").label("reason", "panel").run().await`.
+/// Настройки берутся из config.json профиля, а уведомления, метрики, вебхуки и запись
+/// в каталог — те же, что у `obt backup`. Ход бэкапа можно получать по каналу: `.events(sender)`
+#[derive(Debug, Clone, Default)]
+pub struct BackupRunner {
+    profile: Option<String>,
+    labels: Labels,
+    pinned: bool,
+    force: bool,
+    events: Option<Sender<Event>>,
+}
+
+/// Итог выполненного бэкапа
+#[derive(Debug, Clone)]
+pub struct BackupReport {
+    /// Имя папки бэкапа в хранилище
+    pub name: String,
+    /// UUID запуска (Obt-Run-Id коммита, `run_id` в manifest.json и уведомлениях)
+    pub run_id: Option<String>,
+    pub total_size: u64,
+    pub archives: usize,
+    /// Сколько источников пропущено (ошибка архивации, бюджет времени, мало места)
+    pub skipped: usize,
+    pub duration: Duration,
+}
+
+impl BackupRunner {
+    /// Профиль по умолчанию, без меток
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Профиль из ~/.config/obt/profiles, как `obt --profile <имя>`
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    /// Метка бэкапа, как `obt backup --label key=value`
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Закреплённый бэкап, который политика хранения не удаляет, как у `obt snapshot`
+    pub fn pinned(mut self, pinned: bool) -> Self {
+        self.pinned = pinned;
+        self
+    }
+
+    /// Выполнить бэкап, даже если другой ещё идёт, как `obt backup --force`
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// События хода бэкапа (этапы, архивы, файлы, загрузка) по порядку из потока бэкапа.
+    /// Канал можно закрыть в любой момент: бэкап от этого не прерывается
+    pub fn events(mut self, sender: Sender<Event>) -> Self {
+        self.events = Some(sender);
+        self
+    }
+
+    /// Выполняет бэкап в отдельном потоке. Будущее не зависит от среды выполнения
+    /// (tokio, async-std и т.п.): поток будит его, когда бэкап закончен. Ошибку можно
+    /// передавать между потоками
+    pub async fn run(self) -> Result<BackupReport, Box<dyn std::error::Error + Send + Sync>> {
+        let completion = Completion::default();
+        let shared = completion.shared.clone();
+        std::thread::spawn(move || {
+            // Паника в бэкапе не должна оставить будущее ждать вечно
+            let result = match panic::catch_unwind(AssertUnwindSafe(|| self.run_blocking().map_err(|e| e.to_string()))) {
+                Ok(result) => result,
+                Err(_) => Err("Бэкап аварийно завершился (panic), подробности — в журнале".to_string()),
+            };
+            let mut state = shared.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        completion.await.map_err(Into::into)
+    }
+
+    /// Бэкап в текущем потоке: профиль, часовой пояс и временная папка задаются для него, как у потоков демона
+    fn run_blocking(self) -> Result<BackupReport, Box<dyn std::error::Error>> {
+        Config::set_profile(self.profile)?;
+        let mut config = Config::load()?;
+        timestamp::set_thread_zone(config.zone()?);
+        staging::set_thread_root(config.work_dir.clone());
+        // Полосы прогресса не выводятся в терминал встраивающей программы
+        progress::set_thread_quiet(true);
+
+        let options = BackupOptions {
+            labels: self.labels,
+            pinned: self.pinned,
+            paths: None,
+            force: self.force,
+            events: self.events,
+        };
+        let started = Instant::now();
+        let summary = execute_backup(&mut config, &options)?;
+        Ok(BackupReport {
+            name: summary.name,
+            run_id: summary.run_id,
+            total_size: summary.total_size,
+            archives: summary.archives,
+            skipped: summary.skipped,
+            duration: started.elapsed(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    result: Option<Result<BackupReport, String>>,
+    waker: Option<Waker>,
+}
+
+/// Готово, когда поток бэкапа записал результат
+#[derive(Default)]
+struct Completion {
+    shared: Arc<Mutex<State>>,
+}
+
+impl Future for Completion {
+    type Output = Result<BackupReport, String>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.shared.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
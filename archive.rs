@@ -0,0 +1,240 @@
+use crate::compression::{Encoder, Selected};
+use crate::incremental::{FileState, FileStates};
+use crate::index::{self, FileEntry};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use log::warn;
+use nix::unistd::{Gid, Group, Uid, User};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use tar::{Builder, Header};
+
+/// Файл с шаблонами исключений, который владелец директории кладёт рядом с данными
+pub const OBT_IGNORE_FILE: &str = ".obtignore";
+
+/// Объём файлов пути без перехода по символическим ссылкам; исключения не учитываются,
+/// так что это оценка сверху
+pub fn estimate(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| estimate(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Сколько уже записано в архив
+#[derive(Debug, Default)]
+pub struct Progress {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Имена владельцев для заголовков: по ним tar при распаковке находит пользователей на другой системе
+#[derive(Default)]
+struct Owners {
+    users: HashMap<u32, Option<String>>,
+    groups: HashMap<u32, Option<String>>,
+}
+
+impl Owners {
+    fn user(&mut self, uid: u32) -> Option<&str> {
+        self.users
+            .entry(uid)
+            .or_insert_with(|| User::from_uid(Uid::from_raw(uid)).ok().flatten().map(|u| u.name))
+            .as_deref()
+    }
+
+    fn group(&mut self, gid: u32) -> Option<&str> {
+        self.groups
+            .entry(gid)
+            .or_insert_with(|| Group::from_gid(Gid::from_raw(gid)).ok().flatten().map(|g| g.name))
+            .as_deref()
+    }
+}
+
+/// Результат архивации: индекс записанных файлов и состояние всех найденных
+pub struct Created {
+    pub files: Vec<FileEntry>,
+    pub states: FileStates,
+}
+
+/// Считает SHA-256 прочитанного: файл хешируется тем же чтением, которым пишется в архив
+struct Hashing<'a, R> {
+    inner: R,
+    hasher: &'a mut Sha256,
+}
+
+impl<R: Read> Read for Hashing<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+struct Writer<'a, F: FnMut(&Path, &Progress)> {
+    builder: Builder<Box<dyn Encoder>>,
+    owners: Owners,
+    files: Vec<FileEntry>,
+    previous: Option<&'a FileStates>,
+    states: FileStates,
+    progress: Progress,
+    on_progress: F,
+}
+
+impl<F: FnMut(&Path, &Progress)> Writer<'_, F> {
+    /// Добавляет один путь под именем `name`; сокеты пропускаются, как это делает tar
+    fn append(&mut self, path: &Path, name: &Path) -> io::Result<()> {
+        let metadata = fs::symlink_metadata(path)?;
+        let file_type = metadata.file_type();
+        if file_type.is_socket() {
+            return Ok(());
+        }
+        if !file_type.is_dir() {
+            let key = name.to_string_lossy().to_string();
+            let state = FileState::of(&metadata);
+            let unchanged = self.previous.and_then(|previous| previous.get(&key)) == Some(&state);
+            self.states.insert(key, state);
+            // Каталоги пишутся всегда, чтобы при восстановлении цепочки сохранились их права
+            if unchanged {
+                return Ok(());
+            }
+        }
+
+        let mut header = Header::new_gnu();
+        header.set_metadata(&metadata);
+        header.set_size(0);
+        if let Some(user) = self.owners.user(metadata.uid()) {
+            // Слишком длинное имя не помещается в заголовок: остаётся числовой UID
+            let _ = header.set_username(user);
+        }
+        if let Some(group) = self.owners.group(metadata.gid()) {
+            let _ = header.set_groupname(group);
+        }
+
+        let (size, sha256) = if file_type.is_file() {
+            let size = metadata.len();
+            header.set_size(size);
+            // Если файл укоротился во время чтения, хвост дополняется нулями, как в GNU tar
+            let mut hasher = Sha256::new();
+            let data = Hashing {
+                inner: File::open(path)?.take(size).chain(io::repeat(0)).take(size),
+                hasher: &mut hasher,
+            };
+            self.builder.append_data(&mut header, name, data)?;
+            self.progress.bytes += size;
+            (size, Some(format!("{:x}", hasher.finalize())))
+        } else if file_type.is_symlink() {
+            self.builder.append_link(&mut header, name, fs::read_link(path)?)?;
+            (0, None)
+        } else {
+            if file_type.is_block_device() || file_type.is_char_device() {
+                header.set_device_major(nix::sys::stat::major(metadata.rdev()) as u32)?;
+                header.set_device_minor(nix::sys::stat::minor(metadata.rdev()) as u32)?;
+            }
+            self.builder.append_data(&mut header, name, io::empty())?;
+            return Ok(());
+        };
+
+        // Каталоги и специальные файлы в индекс не попадают
+        self.files.push(FileEntry {
+            path: name.to_string_lossy().to_string(),
+            size,
+            mtime: index::format_mtime(metadata.mtime()),
+            sha256,
+        });
+        self.progress.files += 1;
+        (self.on_progress)(name, &self.progress);
+        Ok(())
+    }
+}
+
+/// Пути директории в порядке обхода; с `ignore_files` учитываются `.obtignore` и `.gitignore`,
+/// действующие на свою директорию и все вложенные, а шаблоны `exclude` (как в .gitignore)
+/// отсчитываются от самой директории
+fn walk(
+    dir: &Path,
+    ignore_files: bool,
+    exclude: &[String],
+) -> Result<impl Iterator<Item = Result<PathBuf, ignore::Error>>, Box<dyn std::error::Error>> {
+    let mut walker = WalkBuilder::new(dir);
+    walker.standard_filters(false).sort_by_file_name(|a, b| a.cmp(b));
+    if ignore_files {
+        walker.add_custom_ignore_filename(OBT_IGNORE_FILE);
+        walker.add_custom_ignore_filename(".gitignore");
+    }
+    if !exclude.is_empty() {
+        // Только исключающие шаблоны: всё, что под них не попало, архивируется
+        let mut overrides = OverrideBuilder::new(dir);
+        for pattern in exclude {
+            overrides
+                .add(&format!("!{}", pattern))
+                .map_err(|e| format!("Неверный шаблон exclude {}: {}", pattern, e))?;
+        }
+        walker.overrides(overrides.build()?);
+    }
+    Ok(walker
+        .build()
+        .map(|entry| entry.map(|e| e.into_path())))
+}
+
+/// Создаёт tar-архив файла (под его именем) или директории (содержимое относительно неё),
+/// сжатый выбранным `compression` способом, и возвращает индекс файлов. С `previous`
+/// в архив попадают только файлы, состояние которых отличается от прошлого бэкапа. `on_progress` вызывается после каждого
+/// записанного файла с его именем в архиве.
+pub fn create(
+    source: &Path,
+    archive: &Path,
+    compression: &Selected,
+    ignore_files: bool,
+    exclude: &[String],
+    previous: Option<&FileStates>,
+    on_progress: impl FnMut(&Path, &Progress),
+) -> Result<Created, Box<dyn std::error::Error>> {
+    let mut builder = Builder::new(compression.encoder(File::create(archive)?)?);
+    builder.follow_symlinks(false);
+    let mut writer = Writer {
+        builder,
+        owners: Owners::default(),
+        files: Vec::new(),
+        previous,
+        states: FileStates::new(),
+        progress: Progress::default(),
+        on_progress,
+    };
+
+    if source.is_dir() {
+        // Директория-ссылка архивируется как её содержимое
+        let source = fs::canonicalize(source)?;
+        for entry in walk(&source, ignore_files, exclude)? {
+            let path = entry?;
+            let relative = path.strip_prefix(&source)?;
+            let name = if relative.as_os_str().is_empty() { Path::new(".") } else { relative };
+            match writer.append(&path, name) {
+                Ok(()) => {}
+                // Файл удалён между обходом и чтением (ротация логов и т.п.)
+                Err(e) if e.kind() == io::ErrorKind::NotFound => warn!("Файл исчез во время архивации: {}", path.display()),
+                Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+            }
+        }
+    } else {
+        let name = source.file_name().ok_or("У пути нет имени файла")?;
+        writer
+            .append(source, Path::new(name))
+            .map_err(|e| format!("{}: {}", source.display(), e))?;
+    }
+
+    writer.builder.into_inner()?.finish()?;
+    Ok(Created {
+        files: writer.files,
+        states: writer.states,
+    })
+}
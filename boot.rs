@@ -0,0 +1,99 @@
+use crate::config::{BootBackup, Config};
+use crate::control::{self, Request, Response, RunQueue, Task, TriggerSource};
+use crate::labels::Labels;
+use crate::{execute_backup, pause, timestamp, BackupOptions};
+use chrono::Utc;
+use log::{info, warn};
+use std::fs;
+use std::time::Duration;
+
+/// Демон, запущенный позже этого срока после загрузки, перезапущен (например, после
+/// `obt schedule set`), а не стартовал вместе с сервером
+const BOOT_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Время с загрузки системы (/proc/uptime)
+fn uptime() -> Option<Duration> {
+    let content = fs::read_to_string("/proc/uptime").ok()?;
+    let seconds: f64 = content.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Почему нужен бэкап после загрузки; `None`, если последний успешный достаточно свежий
+fn stale(config: &Config, boot: &BootBackup) -> Option<String> {
+    let Some(last) = config.last_backup.as_deref().and_then(timestamp::parse) else {
+        return Some("успешных бэкапов ещё не было".to_string());
+    };
+    (Utc::now() - last > chrono::Duration::hours(boot.older_than_hours as i64)).then(|| {
+        format!(
+            "последний успешный бэкап {} старше {} ч.",
+            timestamp::display_time(last),
+            boot.older_than_hours
+        )
+    })
+}
+
+/// Поток демона: после паузы на запуск сервисов ставит бэкап в очередь, если последний
+/// успешный устарел. Перезапуск демона на давно работающем сервере бэкап не вызывает
+pub fn watch(boot: BootBackup, queue: &RunQueue) {
+    let Some(uptime) = uptime() else {
+        warn!("Не удалось узнать время с загрузки системы, бэкап после загрузки не выполняется");
+        return;
+    };
+    if uptime > boot.delay() + BOOT_WINDOW {
+        return;
+    }
+    std::thread::sleep(boot.delay().saturating_sub(uptime));
+
+    // config.json перечитывается: за время паузы бэкап мог запустить администратор
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Бэкап после загрузки: не удалось прочитать конфигурацию: {}", e);
+            return;
+        }
+    };
+    match stale(&config, &boot) {
+        Some(reason) => {
+            let (id, position) = queue.push(TriggerSource::Boot, Task::Backup, Labels::new(), timestamp::format(Utc::now()));
+            info!("Бэкап после загрузки #{} поставлен в очередь (позиция {}): {}", id, position, reason);
+        }
+        None => info!("Бэкап после загрузки не нужен: последний успешный не старше {} ч.", boot.older_than_hours),
+    }
+}
+
+/// `obt boot-backup` из obt-boot.timer: бэкап через очередь демона, а если демон
+/// не запущен — в этом процессе
+pub fn run(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    let boot = config
+        .boot_backup
+        .clone()
+        .ok_or("Бэкап после загрузки не настроен: obt schedule boot <часы>")?;
+    let Some(reason) = stale(config, &boot) else {
+        info!("Бэкап после загрузки не нужен: последний успешный не старше {} ч.", boot.older_than_hours);
+        return Ok(());
+    };
+    if let Some(pause) = pause::active() {
+        info!("Бэкап после загрузки пропущен: плановые запуски приостановлены {}", pause.describe());
+        return Ok(());
+    }
+    info!("Бэкап после загрузки: {}", reason);
+
+    match control::send(&Request::Trigger { labels: Labels::new() }) {
+        Ok(Response::Queued { id, position }) => {
+            info!("Бэкап после загрузки #{} поставлен в очередь демона (позиция {})", id, position);
+            Ok(())
+        }
+        Ok(Response::Error { message }) => Err(message.into()),
+        Ok(other) => Err(format!("Неожиданный ответ демона: {:?}", other).into()),
+        Err(_) => {
+            let options = BackupOptions {
+                labels: Labels::new(),
+                pinned: false,
+                paths: None,
+                force: false,
+                events: None,
+            };
+            execute_backup(config, &options).map(|_| ())
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::config::{Config, EncryptionConfig, GitSshConfig, S3Config, SftpConfig};
+use crate::restore::{self, RestoreOptions};
+use crate::systemd::SystemdService;
+use crate::{git, sftp, timestamp};
+use crate::{read_input, read_input_or, split_gitea_url};
+use colored::*;
+use log::info;
+use std::fs;
+use std::path::PathBuf;
+
+/// Показывает отпечаток ключа сервера и спрашивает, тот ли это сервер
+fn confirm_host(host_key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Отпечаток ключа сервера: {}", host_key.white().bold());
+    if read_input_or("Это сервер с бэкапами? (y/n): ", "n")?.to_lowercase() != "y" {
+        return Err("Восстановление отменено".into());
+    }
+    Ok(())
+}
+
+/// Хранилище по адресу из `--bootstrap`; чего нет в адресе (ключи, пароли), спрашивается:
+/// `s3://бакет[/папка]`, `sftp://[пользователь@]сервер[:порт]/папка`,
+/// `ssh://пользователь@сервер/путь/repo.git` или адрес репозитория Gitea
+fn storage(config: &mut Config, address: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(rest) = address.strip_prefix("s3://") {
+        let (bucket, prefix) = match rest.split_once('/') {
+            Some((bucket, prefix)) if !prefix.trim_matches('/').is_empty() => {
+                (bucket.to_string(), Some(prefix.trim_matches('/').to_string()))
+            }
+            _ => (rest.trim_end_matches('/').to_string(), None),
+        };
+        let endpoint = read_input("Адрес API S3 (например, https://s3.eu-central-1.amazonaws.com): ")?;
+        let region = read_input_or("Регион (Enter — us-east-1): ", "")?;
+        config.s3 = Some(S3Config {
+            endpoint,
+            bucket,
+            region: (!region.is_empty()).then_some(region),
+            access_key: read_input("Access key: ")?,
+            secret_key: read_input("Secret key: ")?,
+            prefix,
+            path_style: None,
+            network: None,
+        });
+        return Ok(());
+    }
+
+    if address.starts_with("sftp://") {
+        let url = url::Url::parse(address).map_err(|e| format!("Неверный адрес {}: {}", address, e))?;
+        let host = url.host_str().ok_or_else(|| format!("В адресе {} нет сервера", address))?.to_string();
+        let username = match url.username() {
+            "" => read_input("Введите имя пользователя SSH: ")?,
+            username => username.to_string(),
+        };
+        let key_file = read_input_or("Введите путь к приватному ключу SSH (Enter — вход по паролю): ", "")?;
+        let (key_file, password) = if key_file.is_empty() {
+            (None, Some(read_input("Введите пароль пользователя SSH: ")?))
+        } else {
+            (Some(PathBuf::from(key_file)), None)
+        };
+        let host_key = sftp::host_fingerprint(&host, url.port())?;
+        confirm_host(&host_key)?;
+        config.sftp = Some(SftpConfig {
+            host,
+            port: url.port(),
+            username,
+            password,
+            key_file,
+            path: url.path().to_string(),
+            host_key: Some(host_key),
+            network: None,
+        });
+        return Ok(());
+    }
+
+    if address.starts_with("ssh://") {
+        let key_file = read_input_or("Введите путь к приватному ключу SSH (Enter — ssh-agent): ", "")?;
+        let host_key = git::host_fingerprint(address)?;
+        confirm_host(&host_key)?;
+        config.git_ssh = Some(GitSshConfig {
+            url: address.to_string(),
+            key_file: (!key_file.is_empty()).then(|| PathBuf::from(key_file)),
+            host_key: Some(host_key),
+            network: None,
+        });
+        return Ok(());
+    }
+
+    let (url, repo) = split_gitea_url(address).ok_or_else(|| {
+        format!("Неверный адрес {}: ожидается репозиторий Gitea, s3://, sftp:// или ssh://", address)
+    })?;
+    config.gitea_url = Some(url);
+    config.gitea_repo = Some(repo);
+    config.gitea_username = Some(read_input("Введите имя пользователя Gitea: ")?);
+    let token = read_input_or("Введите токен доступа Gitea (Enter — вход по паролю): ", "")?;
+    if token.is_empty() {
+        config.gitea_password = Some(read_input("Введите пароль пользователя Gitea: ")?);
+    } else {
+        config.gitea_token = Some(token);
+    }
+    Ok(())
+}
+
+/// Ключ зашифрованных бэкапов: файл age, если такой файл есть, иначе парольная фраза
+fn encryption(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    let key = read_input_or(
+        "Ключ шифрования бэкапов — путь к файлу age или пароль (Enter — бэкапы не зашифрованы): ",
+        "",
+    )?;
+    config.encryption = match key.as_str() {
+        "" => None,
+        path if PathBuf::from(path).is_file() => Some(EncryptionConfig {
+            passphrase: None,
+            key_file: Some(PathBuf::from(path)),
+        }),
+        passphrase => Some(EncryptionConfig {
+            passphrase: Some(passphrase.to_string()),
+            key_file: None,
+        }),
+    };
+    Ok(())
+}
+
+/// Имя сервера из имени бэкапа `<имя>_<время>[_<метка>]`
+fn server_name(backup: &str) -> Option<String> {
+    let parts: Vec<&str> = backup.split('_').collect();
+    let time = parts.iter().position(|part| timestamp::from_folder_name(part).is_some())?;
+    (time > 0).then(|| parts[..time].join("_"))
+}
+
+/// Возвращает config.json на место: из бэкапа, если он там был, иначе из введённых
+/// настроек хранилища; затем по согласию устанавливает юниты systemd
+fn recreate_config(config: &mut Config, backup: &str, options: &RestoreOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = Config::config_dir()?.join("config.json");
+    let restored = restore::destination(&config_path.to_string_lossy(), options.target.as_deref());
+
+    if restored == config_path && restored.exists() {
+        println!("{}", "✅ config.json восстановлен из бэкапа".green());
+        *config = Config::load()?;
+    } else if restored.exists() {
+        let question = format!("В бэкапе есть config.json ({}). Вернуть его на место? (y/n): ", restored.display());
+        if read_input_or(&question, "n")?.to_lowercase() != "y" {
+            return Ok(());
+        }
+        fs::create_dir_all(config_path.parent().ok_or("Неверный путь config.json")?)?;
+        fs::copy(&restored, &config_path)?;
+        println!("{}", format!("✅ config.json скопирован в {}", config_path.display()).green());
+        *config = Config::load()?;
+    } else {
+        if read_input_or("В бэкапе нет config.json. Сохранить введённые настройки хранилища? (y/n): ", "n")?.to_lowercase() != "y" {
+            return Ok(());
+        }
+        let guess = server_name(backup).unwrap_or_default();
+        let name = read_input_or(&format!("Имя бэкапа этого сервера (Enter — {}): ", guess), &guess)?;
+        config.backup_name = (!name.is_empty()).then_some(name);
+        config.save()?;
+        println!("{}", format!("✅ Настройки сохранены в {}", config_path.display()).green());
+    }
+
+    if !config.backup_scheduled() {
+        println!("Расписания бэкапов в config.json нет: задайте его в меню obt или obt schedule, юниты systemd установятся вместе с ним");
+        return Ok(());
+    }
+    if read_input_or("Установить юниты systemd (obt.service и таймер)? (y/n): ", "n")?.to_lowercase() == "y" {
+        SystemdService::create(config)?;
+        println!("{}", "✅ Юниты systemd установлены".green());
+    }
+    Ok(())
+}
+
+/// `obt restore --bootstrap <адрес>`: восстановление на новом сервере без config.json.
+/// Спрашивает доступ к хранилищу, предлагает бэкапы всех серверов в нём, восстанавливает
+/// выбранный и по согласию возвращает config.json и юниты systemd
+pub fn run(config: &mut Config, address: &str, options: &RestoreOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = Config::config_dir()?.join("config.json");
+    if config_path.exists() {
+        return Err(format!(
+            "{} уже есть: --bootstrap нужен на новом сервере, здесь используйте obt restore",
+            config_path.display()
+        )
+        .into());
+    }
+
+    println!("🆘 Восстановление на новом сервере из {}", address);
+    storage(config, address)?;
+    encryption(config)?;
+    info!("Восстановление на новом сервере из {}", address);
+
+    let backup = restore::restore(config, options)?;
+    recreate_config(config, &backup, options)
+}
@@ -0,0 +1,162 @@
+use crate::compression;
+use crate::config::Config;
+use crate::crypto;
+use crate::index;
+use crate::layout;
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::ownership::Ownership;
+use crate::restore;
+use crate::split;
+use crate::staging::StagingDir;
+use crate::timestamp;
+use crate::prepare_repository;
+use colored::*;
+use log::info;
+use std::fs;
+use std::path::Path;
+
+const BUNDLE_README: &str = "README.txt";
+
+fn bundle_readme(manifest: &Manifest) -> String {
+    let archives = manifest
+        .archives
+        .iter()
+        .map(|a| format!("  {}  ←  {}", a.name, a.source))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"Бэкап {name} ({created_at}), выгруженный OfficialVPN Backup Tool v{version}
+
+Для распаковки obt не нужен:
+
+  tar -xf <файл>.obt
+  cd {name}
+  tar -xf <архив>.tar.gz -C <куда распаковать>   (.tar.zst и .tar.xz — так же, нужны zstd и xz)
+
+Архивы и исходные пути:
+{archives}
+
+Описание бэкапа — в backup_info.txt, машиночитаемая версия — в {manifest}.
+Если obt установлен, то же самое делает команда: obt bundle extract <файл>.obt --target <папка>
+"#,
+        name = manifest.name,
+        created_at = timestamp::display(&manifest.created_at),
+        version = env!("CARGO_PKG_VERSION"),
+        archives = archives,
+        manifest = MANIFEST_FILE,
+    )
+}
+
+/// Собирает один бэкап из репозитория в самодостаточный файл (tar без дополнительного сжатия)
+pub fn create_bundle(config: &Config, name: &str, out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let staging = StagingDir::new(format!("bundle_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let work_dir = staging.path();
+
+    println!("🔄 Получение бэкапа {} из репозитория...", name);
+    prepare_repository(config, work_dir)?.download()?;
+
+    let backup_dir = layout::local_dir(Path::new(work_dir), name).ok_or(format!("Бэкап {} не найден в репозитории", name))?;
+
+    let mut manifest: Manifest = serde_json::from_str(&fs::read_to_string(backup_dir.join(MANIFEST_FILE))?)
+        .map_err(|e| format!("В бэкапе {} нет корректного {}: {}", name, MANIFEST_FILE, e))?;
+
+    // Пакет предназначен для распаковки без obt и ключа, поэтому архивы в нём целые и расшифрованы
+    if split::join_archives(&backup_dir, &manifest)? > 0 {
+        for archive in manifest.archives.iter_mut() {
+            archive.parts = 0;
+        }
+        manifest.write(&backup_dir)?;
+    }
+    if crypto::decrypt_archives(config, &backup_dir, &manifest)? > 0 {
+        for archive in manifest.archives.iter_mut().filter(|a| a.encrypted) {
+            fs::remove_file(backup_dir.join(archive.file_name()))?;
+            archive.encrypted = false;
+        }
+        manifest.write(&backup_dir)?;
+        println!("{}", "⚠️ Архивы бэкапа зашифрованы, в пакет они попадут расшифрованными".yellow());
+    }
+    fs::write(backup_dir.join(BUNDLE_README), bundle_readme(&manifest))?;
+
+    let out = if out.is_absolute() {
+        out.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(out)
+    };
+    let mut builder = tar::Builder::new(fs::File::create(&out)?);
+    // Ссылки попадают в пакет ссылками, как у `tar -c`
+    builder.follow_symlinks(false);
+    builder.append_dir_all(name, &backup_dir)?;
+    builder.into_inner()?;
+
+    let size = fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
+    info!("Создан пакет {} для бэкапа {}", out.display(), manifest.name);
+    println!(
+        "{}",
+        format!(
+            "✅ Пакет создан: {} ({} архивов, {:.2} МБ)",
+            out.display(),
+            manifest.archives.len(),
+            size as f64 / 1_048_576.0
+        )
+        .green()
+    );
+    Ok(())
+}
+
+/// Распаковывает пакет: каждый архив попадает в отдельную папку внутри `<target>/<имя бэкапа>/`
+pub fn extract_bundle(bundle: &Path, target: &Path, ownership: &Ownership) -> Result<(), Box<dyn std::error::Error>> {
+    if !bundle.is_file() {
+        return Err(format!("Файл не найден: {}", bundle.display()).into());
+    }
+    fs::create_dir_all(target)?;
+
+    println!("📂 Распаковка пакета {}...", bundle.display());
+    // В пакете ровно одна папка бэкапа — первая запись архива
+    let mut listing = tar::Archive::new(fs::File::open(bundle)?);
+    let name = match listing.entries()?.next() {
+        Some(first) => first?
+            .path()?
+            .components()
+            .find_map(|part| match part {
+                std::path::Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .ok_or("Пакет пуст")?,
+        None => return Err("Пакет пуст".into()),
+    };
+    let privileged = nix::unistd::geteuid().is_root();
+    restore::unpack(tar::Archive::new(fs::File::open(bundle)?), target, &Ownership::ByName, privileged)?;
+
+    let backup_dir = target.join(name);
+    if !backup_dir.join(MANIFEST_FILE).is_file() {
+        return Err("В пакете не найден manifest.json".into());
+    }
+    let manifest: Manifest = serde_json::from_str(&fs::read_to_string(backup_dir.join(MANIFEST_FILE))?)?;
+
+    for archive in &manifest.archives {
+        let archive_path = backup_dir.join(&archive.name);
+
+        // Дампы баз и вывод `exec` — сжатый поток, а не tar
+        if let Some(stem) = archive.name.strip_suffix(".gz").filter(|_| archive.is_stream()) {
+            let dest = backup_dir.join(stem);
+            println!("🗄️ {} → {}", archive.name, dest.display());
+            restore::gunzip(&archive_path, &dest)?;
+            continue;
+        }
+
+        let dest = backup_dir.join(compression::tar_stem(&archive.name).unwrap_or(&archive.name));
+        fs::create_dir_all(&dest)?;
+
+        println!("📦 {} → {}", archive.name, dest.display());
+        restore::unpack(index::open_archive(&archive_path)?, &dest, ownership, privileged)
+            .map_err(|e| format!("Не удалось распаковать {}: {}", archive.name, e))?;
+    }
+
+    info!("Пакет {} распакован в {}", bundle.display(), backup_dir.display());
+    println!(
+        "{}",
+        format!("✅ Бэкап {} распакован в {}", manifest.name, backup_dir.display()).green()
+    );
+    Ok(())
+}
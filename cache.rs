@@ -0,0 +1,91 @@
+use crate::config::Config;
+use crate::restore::{available_backups, Snapshot};
+use crate::{git, prepare_repository};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Список бэкапов хранилища с их manifest.json со времени последнего чтения
+/// (~/.config/obt/metadata_cache.json). Действителен, пока ветки репозитория
+/// указывают на те же коммиты: любой push, в том числе с другого сервера, его сбрасывает.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MetadataCache {
+    pub url: String,
+    /// Ветки удалённого репозитория и их коммиты на момент чтения
+    pub revision: String,
+    pub snapshots: Vec<Snapshot>,
+}
+
+impl MetadataCache {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_cache_path()?;
+
+        if !path.exists() {
+            return Ok(MetadataCache::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_cache_path()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn get_cache_path() -> io::Result<PathBuf> {
+        Ok(Config::config_dir()?.join("metadata_cache.json"))
+    }
+}
+
+/// Состояние репозитория, по которому проверяется кэш. У S3, SFTP, rclone и WebDAV нет HEAD,
+/// который можно узнать без листинга, — для них кэш не используется.
+fn revision(config: &Config) -> Option<(String, String)> {
+    if config.s3.is_some() || config.sftp.is_some() || config.rclone.is_some() || config.webdav.is_some() {
+        return None;
+    }
+    let url = config.repo_url().ok()?;
+    match git::remote_heads(config) {
+        Ok(revision) => Some((url, revision)),
+        Err(e) => {
+            warn!("Не удалось узнать состояние репозитория, кэш не используется: {}", e);
+            None
+        }
+    }
+}
+
+/// Бэкапы хранилища от старых к новым. Если репозиторий не менялся с прошлого чтения,
+/// они берутся из кэша без скачивания; иначе хранилище скачивается в `work_dir`
+/// (второе значение — `true`), а кэш обновляется.
+pub fn snapshots(config: &Config, work_dir: &str) -> Result<(Vec<Snapshot>, bool), Box<dyn std::error::Error>> {
+    let revision = revision(config);
+    if let Some((url, revision)) = &revision {
+        match MetadataCache::load() {
+            Ok(cache) if cache.url == *url && cache.revision == *revision => {
+                info!("Список бэкапов взят из кэша ({} бэкапов)", cache.snapshots.len());
+                return Ok((cache.snapshots, false));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Не удалось прочитать кэш списка бэкапов: {}", e),
+        }
+    }
+
+    prepare_repository(config, work_dir)?.download()?;
+    let snapshots = available_backups(Path::new(work_dir))?;
+    if let Some((url, revision)) = revision {
+        let cache = MetadataCache {
+            url,
+            revision,
+            snapshots,
+        };
+        if let Err(e) = cache.save() {
+            warn!("Не удалось сохранить кэш списка бэкапов: {}", e);
+        }
+        return Ok((cache.snapshots, true));
+    }
+    Ok((snapshots, true))
+}
@@ -0,0 +1,174 @@
+use crate::config::Config;
+use crate::labels::{self, Labels};
+use crate::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Завершён ли запуск: при загрузке частями в репозитории могут остаться
+/// архивы запуска, который оборвался до финального коммита
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RunStatus {
+    #[default]
+    Complete,
+    Partial,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub name: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub labels: Labels,
+    #[serde(default)]
+    pub pinned: bool,
+    pub archives: usize,
+    pub total_size: u64,
+    #[serde(default)]
+    pub status: RunStatus,
+    /// Базовый бэкап инкрементального или дифференциального
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Источники бэкапа, как в manifest.json
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Архивы с размерами и контрольными суммами (у записей старых версий obt пусто)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archive_list: Vec<CatalogArchive>,
+    /// Коммит Gitea, которым бэкап загружен в хранилище
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// Путь папки бэкапа в хранилище, если она не в корне (`shard_by_month`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Когда политика хранения отправила бэкап в корзину: он ещё в хранилище и удалится
+    /// по истечении `prune_grace_hours`, если до того не вернуть его `obt prune --undo`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trashed_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogArchive {
+    pub name: String,
+    pub source: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Локальный индекс всех выполненных бэкапов (~/.config/obt/catalog.json)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    pub entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_catalog_path()?;
+
+        if !path.exists() {
+            return Ok(Catalog::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_catalog_path()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Записывает бэкап, загружаемый в папку `path` хранилища
+    pub fn record(&mut self, manifest: &Manifest, path: &str) {
+        self.entries.retain(|e| e.name != manifest.name);
+        self.entries.push(CatalogEntry {
+            name: manifest.name.clone(),
+            created_at: manifest.created_at.clone(),
+            labels: manifest.labels.clone(),
+            pinned: manifest.pinned,
+            archives: manifest.archives.len(),
+            total_size: manifest.total_size,
+            status: RunStatus::Complete,
+            base: manifest.base.clone(),
+            paths: manifest.paths.clone(),
+            archive_list: manifest
+                .archives
+                .iter()
+                .map(|a| CatalogArchive {
+                    name: a.name.clone(),
+                    source: a.source.clone(),
+                    size: a.size,
+                    sha256: a.sha256.clone(),
+                })
+                .collect(),
+            revision: None,
+            path: Some(path.to_string()).filter(|path| *path != manifest.name),
+            trashed_at: None,
+        });
+    }
+
+    /// Запоминает версию хранилища, в которой оказался бэкап после загрузки
+    pub fn set_revision(&mut self, name: &str, revision: Option<String>) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.name == name) {
+            entry.revision = revision;
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&CatalogEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Отмечает запуск, часть которого уже загружена, но финальный коммит ещё не сделан
+    pub fn record_partial(&mut self, name: &str, created_at: &str, labels: &Labels, archives: usize) {
+        self.entries.retain(|e| e.name != name);
+        self.entries.push(CatalogEntry {
+            name: name.to_string(),
+            created_at: created_at.to_string(),
+            labels: labels.clone(),
+            pinned: false,
+            archives,
+            total_size: 0,
+            status: RunStatus::Partial,
+            base: None,
+            paths: Vec::new(),
+            archive_list: Vec::new(),
+            revision: None,
+            path: None,
+            trashed_at: None,
+        });
+    }
+
+    pub fn filter<'a>(&'a self, selectors: &'a [String]) -> impl Iterator<Item = &'a CatalogEntry> {
+        self.entries
+            .iter()
+            .filter(move |e| labels::matches(&e.labels, selectors))
+    }
+
+    /// Отправляет бэкапы в корзину: с этого момента отсчитывается срок до удаления
+    pub fn trash(&mut self, names: &[String], now: &str) {
+        for entry in self.entries.iter_mut().filter(|e| names.contains(&e.name)) {
+            entry.trashed_at = Some(now.to_string());
+        }
+    }
+
+    /// Возвращает все бэкапы из корзины; имена вернувшихся
+    pub fn restore_trash(&mut self) -> Vec<String> {
+        self.entries
+            .iter_mut()
+            .filter_map(|e| e.trashed_at.take().map(|_| e.name.clone()))
+            .collect()
+    }
+
+    pub fn remove(&mut self, names: &[String]) {
+        self.entries.retain(|e| !names.contains(&e.name));
+    }
+
+    fn get_catalog_path() -> io::Result<PathBuf> {
+        Ok(Config::config_dir()?.join("catalog.json"))
+    }
+}
@@ -0,0 +1,389 @@
+use crate::config::{BackupFrequency, DivergencePolicy, Language, LogFormat, Priority};
+use chrono::Weekday;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// Команды для скриптов и Ansible; без команды запускается интерактивное меню
+#[derive(Parser)]
+#[command(name = "obt", version, about = "OfficialVPN Backup Tool — бэкапы серверов в Gitea")]
+pub struct Cli {
+    /// Режим демона (запускается из obt.service)
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// Профиль: свои config.json и состояние в ~/.config/obt/profiles/<имя>
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Формат журнала (вместо log_format из config.json)
+    #[arg(long, value_enum, global = true)]
+    pub log_format: Option<LogFormat>,
+
+    /// Язык меню и вопросов (вместо language из config.json и LANG)
+    #[arg(long, value_enum, global = true)]
+    pub language: Option<Language>,
+
+    /// Сколько секунд ждать ответа на вопросы (вместо prompt_timeout_seconds из config.json)
+    #[arg(long, global = true, value_name = "СЕКУНДЫ")]
+    pub prompt_timeout: Option<u64>,
+
+    /// Без полос прогресса архивирования и загрузки (для cron и скриптов)
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Выполнить бэкап сейчас
+    Backup {
+        /// Метка бэкапа `key=value` или просто текст
+        #[arg(long = "label")]
+        labels: Vec<String>,
+        /// Что делать, если история ветки в хранилище переписана (вместо вопроса и on_divergence)
+        #[arg(long, value_enum)]
+        on_divergence: Option<DivergencePolicy>,
+        /// Выполнить бэкап, даже если другой (например, демона по расписанию) ещё идёт
+        #[arg(long)]
+        force: bool,
+    },
+    /// Немедленный закреплённый бэкап перед рискованными изменениями
+    Snapshot {
+        #[arg(long = "label", required = true)]
+        labels: Vec<String>,
+    },
+    /// Бэкапы этого сервера из локального каталога
+    List {
+        /// Показать только бэкапы с меткой `key=value` (или с ключом)
+        #[arg(long = "label")]
+        selectors: Vec<String>,
+    },
+    /// Подробности бэкапа из локального каталога: источники, архивы, контрольные суммы и коммит
+    Show {
+        /// Имя папки бэкапа
+        name: String,
+    },
+    /// Сравнить два бэкапа по индексам файлов: добавленные, удалённые и изменённые файлы по каждому пути
+    Diff {
+        /// Более старый бэкап
+        from: String,
+        /// Более новый бэкап
+        to: String,
+    },
+    /// Найти файл по имени или шаблону (`nginx.conf`, `*.conf`, `/etc/nginx/*`) во всех бэкапах
+    Find { pattern: String },
+    /// Поставить бэкап в очередь работающего демона
+    Trigger {
+        #[arg(long = "label")]
+        labels: Vec<String>,
+        /// Плановый запуск от таймера профиля (obt-<профиль>.service): на паузе пропускается
+        #[arg(long, hide = true, conflicts_with = "labels")]
+        timer: bool,
+    },
+    /// Состояние бэкапов: хранилище и его доступность, расписание, последний бэкап, systemd и очередь демона
+    Status {
+        /// Вывести в JSON (для мониторинга)
+        #[arg(long)]
+        json: bool,
+    },
+    /// Проверить, что установленный таймер systemd совпадает с расписанием из config.json
+    Doctor {
+        /// Пересоздать юниты без вопроса
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Отменить запуск, ожидающий в очереди
+    Cancel {
+        /// Номер запуска (можно с `#`)
+        id: String,
+    },
+    /// Бэкап, если последний успешный старше boot_backup.older_than_hours (запускается obt-boot.timer)
+    BootBackup,
+    /// Приостановить плановые запуски (на время обслуживания сервера)
+    Pause {
+        /// Срок паузы: время (`2026-10-15 06:00`) или длительность (`30m`, `4h`, `2d`)
+        #[arg(long)]
+        until: Option<String>,
+        /// Причина, видна в `obt status` и журнале
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Снять паузу плановых запусков
+    Resume,
+    /// Скопировать все бэкапы во второй репозиторий
+    Replicate,
+    /// Загрузить созданный вручную архив как обычный бэкап
+    Import {
+        archive: PathBuf,
+        /// Имя бэкапа в репозитории
+        #[arg(long = "as")]
+        name: String,
+        #[arg(long = "label")]
+        labels: Vec<String>,
+    },
+    /// Восстановить бэкап из репозитория на исходные места или в папку
+    Restore {
+        /// Самый свежий бэкап этого сервера
+        #[arg(long, conflicts_with = "name")]
+        latest: bool,
+        /// Последний бэкап этого сервера, сделанный не позже указанного времени
+        /// (`2026-10-14 18:00` в часовом поясе из config.json, `2026-10-14` — на конец дня, или RFC 3339)
+        #[arg(long, value_name = "ВРЕМЯ", conflicts_with_all = ["name", "latest"])]
+        as_of: Option<String>,
+        /// Имя папки бэкапа в репозитории
+        #[arg(long)]
+        name: Option<String>,
+        /// Распаковать в эту папку (исходные пути сохраняются внутри неё)
+        #[arg(long)]
+        target: Option<PathBuf>,
+        /// Выбирать только из бэкапов с меткой `key=value` (или с ключом)
+        #[arg(long = "label")]
+        selectors: Vec<String>,
+        /// Не спрашивать подтверждение перед записью поверх исходных путей
+        #[arg(long, short = 'y')]
+        yes: bool,
+        /// Песочница для просмотра без root: всё внутри --target, владелец — текущий пользователь,
+        /// без устройств, каналов и setuid
+        #[arg(long, requires = "target", conflicts_with_all = ["owners", "owner_map"])]
+        no_privileges: bool,
+        /// Восстановить только этот файл (исходный путь, например /etc/nginx/nginx.conf)
+        #[arg(long, value_name = "ПУТЬ")]
+        file: Option<String>,
+        /// Восстановление на новом сервере без config.json: хранилище по адресу (репозиторий Gitea,
+        /// s3://, sftp:// или ssh://), ключи спрашиваются; после — вернуть config.json и юниты systemd
+        #[arg(long, value_name = "АДРЕС")]
+        bootstrap: Option<String>,
+        #[command(flatten)]
+        ownership: OwnershipArgs,
+    },
+    /// Скачать бэкап из хранилища и сверить SHA-256 архивов с manifest.json
+    Verify {
+        /// Имя папки бэкапа; по умолчанию — бэкапы этого сервера из каталога по --sample
+        name: Option<String>,
+        /// Какие бэкапы каталога проверить, если имя не указано
+        #[arg(long, value_enum, default_value = "latest", conflicts_with = "name")]
+        sample: SampleArg,
+        /// Процент бэкапов для --sample random
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(1..=100))]
+        percent: u8,
+        /// Сколько бэкапов скачивать и сверять одновременно (по умолчанию — archive_jobs)
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+    /// Удалить устаревшие бэкапы этого сервера по политике хранения, не делая нового.
+    /// Устаревшие сначала попадают в корзину и удаляются через prune_grace_hours
+    Prune {
+        /// Вернуть из корзины бэкапы, которые ещё не удалены
+        #[arg(long)]
+        undo: bool,
+    },
+    /// Смонтировать бэкап как файловую систему только для чтения (FUSE)
+    #[cfg(feature = "fuse")]
+    Mount {
+        /// Имя папки бэкапа в репозитории
+        name: String,
+        mountpoint: PathBuf,
+    },
+    /// Выгрузить бэкап в самодостаточный файл или распаковать такой файл
+    Bundle(BundleArgs),
+    /// Тома Docker, их папки на хосте и контейнеры, которые их используют
+    Docker,
+    /// Общая политика бэкапов из центрального репозитория (поле `policy`)
+    Policy {
+        #[command(subcommand)]
+        action: PolicyAction,
+    },
+    /// Первичная настройка без вопросов из файла ответов (YAML): хранилище, имя, пути, расписание, юниты systemd
+    Provision {
+        #[arg(long)]
+        answers: PathBuf,
+    },
+    /// Изменить настройки config.json
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Пути для бэкапа
+    Paths {
+        #[command(subcommand)]
+        action: PathsAction,
+    },
+    /// Расписание бэкапов
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+    /// Хранилища бэкапов
+    Dest {
+        #[command(subcommand)]
+        action: DestAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DestAction {
+    /// Проверить хранилища без бэкапа: вход, запись, чтение и удаление пробного объекта,
+    /// время каждого шага и свободное место
+    Check {
+        /// Одно хранилище: `gitea`, `s3`, `sftp`, `git_ssh`, `rclone`, `webdav` или `replica`; по умолчанию — все настроенные
+        name: Option<String>,
+    },
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct BundleArgs {
+    #[command(subcommand)]
+    pub extract: Option<BundleAction>,
+
+    /// Имя бэкапа в репозитории
+    pub name: Option<String>,
+
+    /// Файл пакета; по умолчанию `<имя>.obt`
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum BundleAction {
+    /// Распаковать пакет: каждый архив — в отдельную папку
+    Extract {
+        bundle: PathBuf,
+        #[arg(long, default_value = ".")]
+        target: PathBuf,
+        #[command(flatten)]
+        ownership: OwnershipArgs,
+    },
+}
+
+/// Владельцы файлов при распаковке
+#[derive(Args)]
+pub struct OwnershipArgs {
+    /// Как назначать владельцев файлов
+    #[arg(long, value_enum, conflicts_with = "owner_map")]
+    pub owners: Option<OwnersArg>,
+    /// Таблица пересчёта UID/GID (`user <старый uid> <новый>`, `group <старый gid> <новая>`)
+    #[arg(long)]
+    pub owner_map: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SampleArg {
+    /// Последний бэкап
+    Latest,
+    /// Случайные бэкапы, --percent процентов от всех
+    Random,
+    /// Все бэкапы каталога
+    All,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OwnersArg {
+    /// По именам пользователей и групп из архива
+    Name,
+    /// Исходные числовые UID/GID
+    Numeric,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Задать поле: `obt config set gitea_url git.example.com`, `obt config set replica.repo user/backup`.
+    /// Значение разбирается как JSON (числа, true/false, списки, null), иначе считается строкой
+    Set { key: String, value: String },
+    /// Показать текущую конфигурацию
+    Show,
+    /// Перенести секрет в связку ключей ОС: `obt config keyring gitea_token`.
+    /// В config.json остаётся ссылка `keyring:<поле>`; если значения там нет, оно запрашивается
+    Keyring { key: String },
+}
+
+#[derive(Subcommand)]
+pub enum PolicyAction {
+    /// Скачать политику и применить её к полям config.json, которые не менялись на этом сервере
+    Sync,
+    /// Показать последнюю применённую политику и поля, заданные на сервере по-своему
+    Show,
+}
+
+#[derive(Subcommand)]
+pub enum PathsAction {
+    /// Добавить файл или директорию
+    Add {
+        path: String,
+        #[arg(long, value_enum)]
+        priority: Option<Priority>,
+        /// Смещение от времени бэкапа в минутах
+        #[arg(long)]
+        offset_minutes: Option<u64>,
+        /// Имя пути в имени архива вместо последнего компонента (`{label}` в `archive_name_template`)
+        #[arg(long)]
+        label: Option<String>,
+        /// Создать директорию, если её нет
+        #[arg(long)]
+        create: bool,
+    },
+    /// Убрать путь из бэкапа
+    Remove { path: String },
+    /// Показать пути
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ScheduleAction {
+    /// Задать периодичность и время и обновить таймер systemd
+    Set {
+        #[arg(value_enum)]
+        frequency: BackupFrequency,
+        /// Время в формате ЧЧ:ММ (часовой пояс — `timezone` из config.json, по умолчанию системный)
+        time: String,
+    },
+    /// Задать расписание выражением cron (`obt schedule cron "0 */6 * * *"`) и обновить таймер systemd
+    Cron { expression: String },
+    /// Удалять устаревшие бэкапы по своему расписанию, а не после каждого бэкапа:
+    /// `obt schedule prune weekly 04:00 --weekday sun`
+    Prune(MaintenanceArgs),
+    /// Проверять последний бэкап по своему расписанию, а не после каждого бэкапа:
+    /// `obt schedule verify monthly 05:00`
+    Verify(MaintenanceArgs),
+    /// Бэкап после загрузки сервера, если последний успешный устарел:
+    /// `obt schedule boot 24 --delay-minutes 10`
+    Boot(BootArgs),
+    /// Убрать отдельное расписание очистки или проверки (она снова выполняется после
+    /// каждого бэкапа) или бэкап после загрузки
+    Unset {
+        #[arg(value_enum)]
+        job: MaintenanceJob,
+    },
+}
+
+#[derive(Args)]
+pub struct MaintenanceArgs {
+    #[arg(value_enum)]
+    pub frequency: BackupFrequency,
+    /// Время в формате ЧЧ:ММ
+    pub time: String,
+    /// День недели для weekly (mon, tue, ..., sun); по умолчанию понедельник
+    #[arg(long)]
+    pub weekday: Option<Weekday>,
+}
+
+#[derive(Args)]
+pub struct BootArgs {
+    /// Бэкап нужен, если последний успешный старше стольких часов
+    pub older_than_hours: u64,
+    /// Пауза после загрузки, чтобы сервер успел запустить сервисы (по умолчанию 5)
+    #[arg(long)]
+    pub delay_minutes: Option<u64>,
+    /// Запускать из obt-boot.timer (OnBootSec=), а не из демона
+    #[arg(long)]
+    pub timer: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MaintenanceJob {
+    Prune,
+    Verify,
+    Boot,
+}
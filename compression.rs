@@ -0,0 +1,289 @@
+use crate::config::{Compression, CompressionFormat};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use log::info;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+
+/// Расширения tar-архивов всех форматов сжатия; `.tar` последним, чтобы не совпасть раньше остальных
+const TAR_EXTENSIONS: [&str; 4] = [".tar.gz", ".tar.zst", ".tar.xz", ".tar"];
+
+/// Имя tar-архива без расширения (`dir_1_etc.tar.zst` → `dir_1_etc`); `None` — это не tar-архив
+pub fn tar_stem(name: &str) -> Option<&str> {
+    TAR_EXTENSIONS.iter().find_map(|extension| name.strip_suffix(extension))
+}
+
+/// Способ сжатия tar-потока
+pub trait Codec: Sync {
+    /// Название для backup_info.txt и журнала
+    fn name(&self) -> &'static str;
+
+    /// Расширение архива: `tar.gz`, `tar.zst`, `tar.xz` или `tar`
+    fn extension(&self) -> &'static str;
+
+    /// Сжимающий поток в файл; без `level` — уровень формата по умолчанию
+    fn encoder(&self, file: File, level: Option<u32>) -> io::Result<Box<dyn Encoder>>;
+
+    /// Распаковывающий поток для чтения архива без tar и внешних программ
+    fn decoder(&self, reader: Box<dyn Read>) -> io::Result<Box<dyn Read>>;
+}
+
+/// Способ сжатия tar-архива по его имени; `None` — это не tar-архив
+pub fn codec_of(name: &str) -> Option<&'static dyn Codec> {
+    let codecs: [&'static dyn Codec; 4] = [&Gzip, &Zstd, &Xz, &Store];
+    // `.tar` проверяется последним: он не должен совпасть раньше `.tar.gz` и других
+    codecs.into_iter().find(|codec| name.ends_with(&format!(".{}", codec.extension())))
+}
+
+/// Сжимающий поток, в который пишется tar
+pub trait Encoder: Write + Send {
+    /// Дописывает конец сжатого потока
+    fn finish(self: Box<Self>) -> io::Result<File>;
+}
+
+impl Encoder for GzEncoder<File> {
+    fn finish(self: Box<Self>) -> io::Result<File> {
+        GzEncoder::finish(*self)
+    }
+}
+
+impl Encoder for zstd::Encoder<'static, File> {
+    fn finish(self: Box<Self>) -> io::Result<File> {
+        zstd::Encoder::finish(*self)
+    }
+}
+
+impl Encoder for XzEncoder<File> {
+    fn finish(self: Box<Self>) -> io::Result<File> {
+        XzEncoder::finish(*self)
+    }
+}
+
+impl Encoder for File {
+    fn finish(self: Box<Self>) -> io::Result<File> {
+        Ok(*self)
+    }
+}
+
+pub struct Gzip;
+pub struct Zstd;
+pub struct Xz;
+/// Без сжатия
+pub struct Store;
+
+impl Codec for Gzip {
+    fn name(&self) -> &'static str {
+        "gzip"
+    }
+
+    fn extension(&self) -> &'static str {
+        "tar.gz"
+    }
+
+    fn encoder(&self, file: File, level: Option<u32>) -> io::Result<Box<dyn Encoder>> {
+        let level = level.map(|level| flate2::Compression::new(level.min(9))).unwrap_or_default();
+        Ok(Box::new(GzEncoder::new(file, level)))
+    }
+
+    fn decoder(&self, reader: Box<dyn Read>) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(MultiGzDecoder::new(reader)))
+    }
+}
+
+impl Codec for Zstd {
+    fn name(&self) -> &'static str {
+        "zstd"
+    }
+
+    fn extension(&self) -> &'static str {
+        "tar.zst"
+    }
+
+    fn encoder(&self, file: File, level: Option<u32>) -> io::Result<Box<dyn Encoder>> {
+        let level = level.map(|level| level.min(22) as i32).unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+        Ok(Box::new(zstd::Encoder::new(file, level)?))
+    }
+
+    fn decoder(&self, reader: Box<dyn Read>) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(zstd::Decoder::new(reader)?))
+    }
+}
+
+impl Codec for Xz {
+    fn name(&self) -> &'static str {
+        "xz"
+    }
+
+    fn extension(&self) -> &'static str {
+        "tar.xz"
+    }
+
+    fn encoder(&self, file: File, level: Option<u32>) -> io::Result<Box<dyn Encoder>> {
+        Ok(Box::new(XzEncoder::new(file, level.unwrap_or(6).min(9))))
+    }
+
+    fn decoder(&self, reader: Box<dyn Read>) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(XzDecoder::new(reader)))
+    }
+}
+
+impl Codec for Store {
+    fn name(&self) -> &'static str {
+        "без сжатия"
+    }
+
+    fn extension(&self) -> &'static str {
+        "tar"
+    }
+
+    fn encoder(&self, file: File, _level: Option<u32>) -> io::Result<Box<dyn Encoder>> {
+        Ok(Box::new(file))
+    }
+
+    fn decoder(&self, reader: Box<dyn Read>) -> io::Result<Box<dyn Read>> {
+        Ok(reader)
+    }
+}
+
+/// Сжатие, выбранное для одного архива
+pub struct Selected {
+    pub codec: &'static dyn Codec,
+    pub level: Option<u32>,
+}
+
+impl Selected {
+    pub fn extension(&self) -> &'static str {
+        self.codec.extension()
+    }
+
+    pub fn encoder(&self, file: File) -> io::Result<Box<dyn Encoder>> {
+        self.codec.encoder(file, self.level)
+    }
+}
+
+/// Сколько байт читается из начала каждого файла для оценки энтропии
+const SAMPLE_BYTES: usize = 64 * 1024;
+/// Сколько файлов пути просматривается в режиме `Auto`
+const SAMPLE_FILES: usize = 64;
+/// Энтропия (бит на байт), выше которой данные уже сжаты: медиа, архивы, сжатые дампы
+const COMPRESSED_ENTROPY: f64 = 7.5;
+/// Пути меньше этого объёма сжимаются gzip: выигрыш zstd по времени на них незаметен,
+/// а tar.gz распаковывается где угодно
+const SMALL_PATH_BYTES: u64 = 64 * 1_048_576;
+
+/// Что удалось узнать о содержимом пути по выборке
+#[derive(Default)]
+struct Sample {
+    counts: Vec<u64>,
+    sampled: u64,
+    /// Объём просмотренных файлов целиком
+    size: u64,
+    files: usize,
+}
+
+impl Sample {
+    fn of(path: &Path) -> Sample {
+        let mut sample = Sample {
+            counts: vec![0; 256],
+            ..Sample::default()
+        };
+        let walker = ignore::WalkBuilder::new(path).standard_filters(false).sort_by_file_name(|a, b| a.cmp(b)).build();
+        for entry in walker.flatten() {
+            if sample.files >= SAMPLE_FILES {
+                break;
+            }
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            sample.add(entry.path());
+        }
+        sample
+    }
+
+    fn add(&mut self, path: &Path) {
+        let Ok(file) = File::open(path) else { return };
+        let mut buf = Vec::with_capacity(SAMPLE_BYTES);
+        if file.take(SAMPLE_BYTES as u64).read_to_end(&mut buf).is_err() {
+            return;
+        }
+        for byte in &buf {
+            self.counts[*byte as usize] += 1;
+        }
+        self.sampled += buf.len() as u64;
+        self.size += path.metadata().map(|m| m.len()).unwrap_or(0);
+        self.files += 1;
+    }
+
+    /// Энтропия Шеннона выборки в битах на байт: 8 — случайные (сжатые) данные
+    fn entropy(&self) -> f64 {
+        if self.sampled == 0 {
+            return 0.0;
+        }
+        let total = self.sampled as f64;
+        self.counts
+            .iter()
+            .filter(|count| **count > 0)
+            .map(|count| {
+                let p = *count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+impl CompressionFormat {
+    /// Способ сжатия формата; у `Auto` он выбирается для каждого пути
+    fn codec(&self) -> Option<&'static dyn Codec> {
+        match self {
+            CompressionFormat::Gzip => Some(&Gzip),
+            CompressionFormat::Zstd => Some(&Zstd),
+            CompressionFormat::Xz => Some(&Xz),
+            CompressionFormat::None => Some(&Store),
+            CompressionFormat::Auto => None,
+        }
+    }
+}
+
+impl Compression {
+    /// Сжатие архива пути `source`; в режиме `Auto` оно выбирается по выборке содержимого:
+    /// уже сжатые данные не сжимаются повторно, небольшие пути сжимаются gzip, остальные — zstd
+    pub fn select(&self, source: &Path) -> Selected {
+        if let Some(codec) = self.format.codec() {
+            return Selected { codec, level: self.level };
+        }
+        let sample = Sample::of(source);
+        let entropy = sample.entropy();
+        let codec: &'static dyn Codec = if entropy >= COMPRESSED_ENTROPY {
+            &Store
+        } else if sample.files < SAMPLE_FILES && sample.size < SMALL_PATH_BYTES {
+            &Gzip
+        } else {
+            &Zstd
+        };
+        info!(
+            "Сжатие {}: {} (энтропия {:.2} бит/байт по {} файлам)",
+            source.display(),
+            codec.name(),
+            entropy,
+            sample.files
+        );
+        // Уровень задаётся для конкретного формата и к выбранному автоматически не относится
+        Selected { codec, level: None }
+    }
+
+    /// Формат для backup_info.txt, например `tar.zst (zstd, уровень 3)`
+    pub fn describe(&self) -> String {
+        let codec = match self.format {
+            CompressionFormat::None => return "tar (без сжатия)".to_string(),
+            CompressionFormat::Auto => return "по содержимому пути: tar, tar.gz или tar.zst".to_string(),
+            format => format.codec().unwrap_or(&Gzip),
+        };
+        match self.level {
+            Some(level) => format!("{} ({}, уровень {})", codec.extension(), codec.name(), level),
+            None => format!("{} ({} сжатие)", codec.extension(), codec.name()),
+        }
+    }
+}
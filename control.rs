@@ -0,0 +1,301 @@
+use crate::config::Config;
+use crate::labels::Labels;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Сколько ждать запроса клиента и записи ответа: клиенты обслуживаются по очереди,
+/// и молчащий клиент не должен занимать сокет для остальных
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TriggerSource {
+    Schedule,
+    Manual,
+    /// Бэкап после загрузки сервера (`boot_backup`)
+    Boot,
+    /// Изменение наблюдаемых путей (`watch`)
+    Watch,
+}
+
+/// Что выполняет запуск: бэкап или обслуживание по отдельному расписанию
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Task {
+    #[default]
+    Backup,
+    /// Удаление устаревших бэкапов по политике хранения
+    Prune,
+    /// Проверка последнего бэкапа в хранилище
+    Verify,
+    /// Микро-бэкап путей из `watch`
+    Watch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedRun {
+    pub id: u64,
+    pub source: TriggerSource,
+    #[serde(default)]
+    pub task: Task,
+    pub labels: Labels,
+    pub queued_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+    Trigger { labels: Labels },
+    /// Плановый бэкап от таймера профиля `obt-<профиль>.timer`: как и запуски по расписанию
+    /// демона, пропускается на паузе и не копится, пока предыдущий не завершён
+    Scheduled,
+    Status,
+    Cancel { id: u64 },
+    /// Очереди всех профилей демона
+    Profiles,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+    Queued { id: u64, position: usize },
+    Status { running: Option<QueuedRun>, queued: Vec<QueuedRun> },
+    Cancelled { id: u64 },
+    Profiles { profiles: Vec<ProfileQueue> },
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileQueue {
+    /// `None` — профиль по умолчанию
+    pub profile: Option<String>,
+    pub running: Option<QueuedRun>,
+    pub queued: Vec<QueuedRun>,
+}
+
+/// Строка протокола: запрос и профиль, к очереди которого он относится
+#[derive(Deserialize)]
+struct Message {
+    #[serde(default)]
+    profile: Option<String>,
+    request: Request,
+}
+
+/// Очереди демона по профилям: у каждого профиля свои запуски, выполняемые по одному,
+/// а запуски разных профилей идут одновременно
+pub type Queues = Vec<(Option<String>, Arc<RunQueue>)>;
+
+fn profile_name(profile: &Option<String>) -> &str {
+    profile.as_deref().unwrap_or("default")
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: u64,
+    running: Option<QueuedRun>,
+    queued: VecDeque<QueuedRun>,
+}
+
+/// Очередь запусков демона: бэкапы и обслуживание выполняются строго по одному,
+/// а триггеры, пришедшие во время текущего запуска, ждут своей очереди
+#[derive(Default)]
+pub struct RunQueue {
+    state: Mutex<QueueState>,
+    available: Condvar,
+}
+
+impl RunQueue {
+    /// Ставит запуск в очередь и возвращает его id и позицию (0 — следующий)
+    pub fn push(&self, source: TriggerSource, task: Task, labels: Labels, queued_at: String) -> (u64, usize) {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let run = QueuedRun {
+            id: state.next_id,
+            source,
+            task,
+            labels,
+            queued_at,
+        };
+        let id = run.id;
+        state.queued.push_back(run);
+        let position = state.queued.len() - 1;
+        self.available.notify_one();
+        (id, position)
+    }
+
+    /// Есть ли уже ожидающий или выполняющийся запуск этой задачи из указанного источника
+    pub fn has_pending(&self, source: TriggerSource, task: Task) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .running
+            .iter()
+            .chain(state.queued.iter())
+            .any(|r| r.source == source && r.task == task)
+    }
+
+    /// Ждёт ли такая задача своей очереди (выполняющийся запуск не учитывается)
+    pub fn has_queued(&self, task: Task) -> bool {
+        self.state.lock().unwrap().queued.iter().any(|r| r.task == task)
+    }
+
+    /// Блокируется до появления запуска в очереди и помечает его как выполняющийся
+    pub fn next(&self) -> QueuedRun {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(run) = state.queued.pop_front() {
+                state.running = Some(run.clone());
+                return run;
+            }
+            state = self.available.wait(state).unwrap();
+        }
+    }
+
+    pub fn finish(&self) {
+        self.state.lock().unwrap().running = None;
+    }
+
+    /// Отменяет ожидающий запуск; уже начавшийся бэкап отменить нельзя
+    pub fn cancel(&self, id: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let before = state.queued.len();
+        state.queued.retain(|r| r.id != id);
+        state.queued.len() != before
+    }
+
+    fn status(&self) -> Response {
+        let state = self.state.lock().unwrap();
+        Response::Status {
+            running: state.running.clone(),
+            queued: state.queued.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Один сокет на демон: запросы всех профилей приходят в него
+pub fn socket_path() -> io::Result<PathBuf> {
+    Ok(Config::root_dir()?.join("obt.sock"))
+}
+
+/// Запускает управляющий сокет демона в отдельном потоке
+pub fn serve<F>(queues: Queues, now: F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: Fn() -> String + Send + 'static,
+{
+    let path = socket_path()?;
+    fs::create_dir_all(path.parent().unwrap())?;
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    info!("Управляющий сокет: {}", path.display());
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = handle_client(stream, &queues, &now) {
+                        warn!("Ошибка обработки запроса к демону: {}", e);
+                    }
+                }
+                Err(e) => warn!("Ошибка подключения к управляющему сокету: {}", e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn handle_client<F>(stream: UnixStream, queues: &Queues, now: &F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: Fn() -> String,
+{
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let response = match serde_json::from_str::<Message>(&line) {
+        Ok(message) => respond(message, queues, now).unwrap_or_else(|message| Response::Error { message }),
+        Err(e) => Response::Error {
+            message: format!("Неверный запрос: {}", e),
+        },
+    };
+
+    let mut stream = stream;
+    writeln!(stream, "{}", serde_json::to_string(&response)?)?;
+    Ok(())
+}
+
+fn respond<F>(message: Message, queues: &Queues, now: &F) -> Result<Response, String>
+where
+    F: Fn() -> String,
+{
+    let Message { profile, request } = message;
+    let name = profile_name(&profile);
+    let queue = || {
+        queues.iter().find(|(queued, _)| *queued == profile).map(|(_, queue)| queue).ok_or_else(|| {
+            format!(
+                "Профиль {} не обслуживается демоном (новые профили подхватываются после перезапуска: systemctl restart obt.service)",
+                name
+            )
+        })
+    };
+
+    Ok(match request {
+        Request::Trigger { labels } => {
+            let (id, position) = queue()?.push(TriggerSource::Manual, Task::Backup, labels, now());
+            info!("Ручной запуск #{} профиля {} поставлен в очередь (позиция {})", id, name, position);
+            Response::Queued { id, position }
+        }
+        Request::Scheduled => {
+            let queue = queue()?;
+            if queue.has_pending(TriggerSource::Schedule, Task::Backup) {
+                return Err(format!("Предыдущий плановый бэкап профиля {} ещё не завершён, новый пропущен", name));
+            }
+            let (id, position) = queue.push(TriggerSource::Schedule, Task::Backup, Labels::new(), now());
+            info!("Плановый запуск #{} профиля {} от таймера поставлен в очередь (позиция {})", id, name, position);
+            Response::Queued { id, position }
+        }
+        Request::Status => queue()?.status(),
+        Request::Cancel { id } => {
+            if !queue()?.cancel(id) {
+                return Err(format!("Запуск #{} не найден в очереди профиля {}", id, name));
+            }
+            info!("Запуск #{} профиля {} отменён", id, name);
+            Response::Cancelled { id }
+        }
+        Request::Profiles => Response::Profiles {
+            profiles: queues
+                .iter()
+                .map(|(profile, queue)| {
+                    let state = queue.state.lock().unwrap();
+                    ProfileQueue {
+                        profile: profile.clone(),
+                        running: state.running.clone(),
+                        queued: state.queued.iter().cloned().collect(),
+                    }
+                })
+                .collect(),
+        },
+    })
+}
+
+/// Отправляет работающему демону запрос к очереди профиля текущего потока
+pub fn send(request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+    let path = socket_path()?;
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|e| format!("Демон не запущен или недоступен ({}): {}", path.display(), e))?;
+
+    let message = serde_json::json!({ "profile": Config::profile(), "request": request });
+    writeln!(stream, "{}", message)?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
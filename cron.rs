@@ -0,0 +1,175 @@
+use crate::config::Config;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Допустимые значения одного поля cron в виде битовой маски
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    mask: u64,
+    /// Поле задано не `*`: нужно для дней месяца и недели
+    restricted: bool,
+}
+
+impl Field {
+    fn contains(&self, value: u32) -> bool {
+        self.mask & (1 << value) != 0
+    }
+
+    fn values(&self, min: u32, max: u32) -> impl Iterator<Item = u32> + '_ {
+        (min..=max).filter(|value| self.contains(*value))
+    }
+
+    /// Поле для `OnCalendar=`: `*` или список значений через запятую
+    fn calendar(&self, min: u32, max: u32, format: impl Fn(u32) -> String) -> String {
+        if !self.restricted {
+            return "*".to_string();
+        }
+        self.values(min, max).map(format).collect::<Vec<_>>().join(",")
+    }
+}
+
+/// Значение поля: число или трёхбуквенное английское имя (`mon`, `jan`)
+fn value(text: &str, min: u32, names: &[&str]) -> Result<u32, String> {
+    if let Some(index) = names.iter().position(|name| name.eq_ignore_ascii_case(text)) {
+        return Ok(index as u32 + min);
+    }
+    text.parse().map_err(|_| format!("неверное значение «{}»", text))
+}
+
+fn field(text: &str, min: u32, max: u32, names: &[&str]) -> Result<Field, String> {
+    let mut mask = 0;
+    for item in text.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or(format!("неверный шаг «{}»", step))?),
+            None => (item, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (value(start, min, names)?, value(end, min, names)?),
+                // `5/15` — с 5 до конца с шагом 15
+                None if item.contains('/') => (value(range, min, names)?, max),
+                None => {
+                    let value = value(range, min, names)?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("«{}» вне диапазона {}-{}", item, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(Field {
+        mask,
+        restricted: text != "*",
+    })
+}
+
+/// Расписание в формате cron: минута, час, день месяца, месяц, день недели
+#[derive(Debug, Clone)]
+pub struct Cron {
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+}
+
+impl Cron {
+    /// Разбирает выражение `0 */6 * * *` или сокращение `@hourly`, `@daily`, `@weekly`, `@monthly`
+    pub fn parse(expression: &str) -> Result<Cron, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "Неверное выражение cron «{}»: ожидается 5 полей — минута, час, день месяца, месяц, день недели",
+                expression
+            ));
+        };
+        let parse = |name: &str, result: Result<Field, String>| result.map_err(|e| format!("Неверное поле «{}» выражения cron: {}", name, e));
+        let mut weekdays = parse("день недели", field(weekdays, 0, 7, &WEEKDAYS))?;
+        // Воскресенье можно записать и как 7
+        if weekdays.contains(7) {
+            weekdays.mask = (weekdays.mask | 1) & !(1 << 7);
+        }
+        let cron = Cron {
+            minutes: parse("минута", field(minutes, 0, 59, &[]))?,
+            hours: parse("час", field(hours, 0, 23, &[]))?,
+            days: parse("день месяца", field(days, 1, 31, &[]))?,
+            months: parse("месяц", field(months, 1, 12, &MONTHS))?,
+            weekdays,
+        };
+        // cron запускает по любому из них, а OnCalendar= таймера — только по обоим сразу
+        if cron.days.restricted && cron.weekdays.restricted {
+            return Err("Выражения cron с днём месяца и днём недели одновременно не поддерживаются: задайте одно из них".into());
+        }
+        Ok(cron)
+    }
+
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        self.months.contains(date.month())
+            && self.days.contains(date.day())
+            && self.weekdays.contains(date.weekday().num_days_from_sunday())
+    }
+
+    /// Приходится ли запуск на эту минуту (местное время расписания)
+    pub fn matches(&self, time: NaiveDateTime) -> bool {
+        self.runs_on(time.date()) && self.hours.contains(time.hour()) && self.minutes.contains(time.minute())
+    }
+
+    /// Ближайший запуск после `now` в часовом поясе `zone`; ищется в пределах года
+    pub fn next_after(&self, now: DateTime<Utc>, zone: Tz) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&zone).date_naive();
+        (0..=366)
+            .map(|days| today + Duration::days(days))
+            .filter(|date| self.runs_on(*date))
+            .flat_map(|date| {
+                self.hours
+                    .values(0, 23)
+                    .flat_map(move |hour| self.minutes.values(0, 59).filter_map(move |minute| date.and_hms_opt(hour, minute, 0)))
+            })
+            .filter_map(|time| zone.from_local_datetime(&time).earliest())
+            .map(|time| time.with_timezone(&Utc))
+            .find(|time| *time > now)
+    }
+
+    /// `OnCalendar=` таймера без часового пояса: `Mon,Thu *-*-* 00,06,12,18:00:00`
+    pub fn on_calendar(&self) -> String {
+        let weekdays = match self.weekdays.restricted {
+            true => format!("{} ", self.weekdays.calendar(0, 6, |day| WEEKDAYS[day as usize].to_string())),
+            false => String::new(),
+        };
+        format!(
+            "{}*-{}-{} {}:{}:00",
+            weekdays,
+            self.months.calendar(1, 12, |month| format!("{:02}", month)),
+            self.days.calendar(1, 31, |day| format!("{:02}", day)),
+            self.hours.calendar(0, 23, |hour| format!("{:02}", hour)),
+            self.minutes.calendar(0, 59, |minute| format!("{:02}", minute)),
+        )
+    }
+}
+
+impl Config {
+    /// Расписание бэкапов из `backup_cron`; `None`, если оно не задано
+    pub fn cron_schedule(&self) -> Result<Option<Cron>, String> {
+        self.backup_cron.as_deref().map(Cron::parse).transpose()
+    }
+
+    /// Задано ли расписание бэкапов: временем или выражением cron
+    pub fn backup_scheduled(&self) -> bool {
+        self.backup_time.is_some() || self.backup_cron.is_some()
+    }
+}
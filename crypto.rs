@@ -0,0 +1,117 @@
+use crate::config::Config;
+use crate::manifest::Manifest;
+use age::secrecy::SecretString;
+use age::{Decryptor, Encryptor, Identity, Recipient};
+use log::info;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Суффикс зашифрованного архива: `dir_1_etc.tar.gz.age`
+pub const ENCRYPTED_SUFFIX: &str = ".age";
+
+/// Ключ из `encryption` в config.json
+pub enum Key {
+    Passphrase(SecretString),
+    Identity(age::x25519::Identity),
+}
+
+impl Key {
+    /// `None`, если шифрование не настроено
+    pub fn load(config: &Config) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let Some(encryption) = &config.encryption else {
+            return Ok(None);
+        };
+
+        match (&encryption.passphrase, &encryption.key_file) {
+            (Some(_), Some(_)) => Err("В encryption укажите либо passphrase, либо key_file".into()),
+            (Some(passphrase), None) => Ok(Some(Key::Passphrase(SecretString::from(passphrase.clone())))),
+            (None, Some(path)) => {
+                let content = fs::read_to_string(path)
+                    .map_err(|e| format!("Не удалось прочитать ключ шифрования {}: {}", path.display(), e))?;
+                let identity = content
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+                    .ok_or_else(|| format!("В {} нет ключа AGE-SECRET-KEY-...", path.display()))?
+                    .parse()
+                    .map_err(|e| format!("Неверный ключ в {}: {}", path.display(), e))?;
+                Ok(Some(Key::Identity(identity)))
+            }
+            (None, None) => Err("В encryption не задан ни passphrase, ни key_file".into()),
+        }
+    }
+
+    fn encryptor(&self) -> Result<Encryptor, Box<dyn std::error::Error>> {
+        Ok(match self {
+            Key::Passphrase(passphrase) => Encryptor::with_user_passphrase(passphrase.clone()),
+            Key::Identity(identity) => {
+                let recipient = identity.to_public();
+                Encryptor::with_recipients(std::iter::once(&recipient as &dyn Recipient))?
+            }
+        })
+    }
+
+    fn decrypt(&self, encrypted: &Path, plain: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut reader = self
+            .reader(File::open(encrypted)?)
+            .map_err(|e| format!("Не удалось расшифровать {}: {}", encrypted.display(), e))?;
+        io::copy(&mut reader, &mut File::create(plain)?)?;
+        Ok(())
+    }
+
+    /// Расшифровывает поток на лету, не записывая расшифрованный архив на диск
+    pub fn reader<R: Read + 'static>(&self, encrypted: R) -> Result<Box<dyn Read>, Box<dyn std::error::Error>> {
+        let decryptor = Decryptor::new_buffered(BufReader::new(encrypted))?;
+        let reader = match self {
+            Key::Passphrase(passphrase) => {
+                let identity = age::scrypt::Identity::new(passphrase.clone());
+                decryptor.decrypt(std::iter::once(&identity as &dyn Identity))
+            }
+            Key::Identity(identity) => decryptor.decrypt(std::iter::once(identity as &dyn Identity)),
+        }?;
+        Ok(Box::new(reader))
+    }
+}
+
+/// Шифрует файл в `<файл>.age` и удаляет исходный; возвращает путь зашифрованного файла
+pub fn encrypt_file(key: &Key, path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let encrypted = PathBuf::from(format!("{}{}", path.display(), ENCRYPTED_SUFFIX));
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = key.encryptor()?.wrap_output(File::create(&encrypted)?)?;
+        io::copy(&mut File::open(path)?, &mut writer)?;
+        writer.finish()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        // Незашифрованный архив не должен попасть в репозиторий
+        let _ = fs::remove_file(&encrypted);
+        let _ = fs::remove_file(path);
+        return Err(format!("Не удалось зашифровать {}: {}", path.display(), e).into());
+    }
+
+    fs::remove_file(path)?;
+    Ok(encrypted)
+}
+
+/// Расшифровывает архивы бэкапа в его папке (рабочей копии), чтобы дальше с ними
+/// можно было работать как с обычными; возвращает число расшифрованных
+pub fn decrypt_archives(config: &Config, backup_dir: &Path, manifest: &Manifest) -> Result<usize, Box<dyn std::error::Error>> {
+    let encrypted: Vec<_> = manifest.archives.iter().filter(|a| a.encrypted).collect();
+    if encrypted.is_empty() {
+        return Ok(0);
+    }
+    let key = Key::load(config)?.ok_or_else(|| {
+        format!("Бэкап {} зашифрован: задайте ключ в поле encryption config.json", manifest.name)
+    })?;
+
+    for archive in &encrypted {
+        let plain = backup_dir.join(&archive.name);
+        key.decrypt(&backup_dir.join(archive.file_name()), &plain)
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&plain);
+            })?;
+    }
+    info!("Расшифровано архивов бэкапа {}: {}", manifest.name, encrypted.len());
+    Ok(encrypted.len())
+}
@@ -0,0 +1,224 @@
+use crate::config::{DatabaseKind, DatabaseSource};
+use crate::forensics;
+use log::info;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Служебные базы MySQL, которые `mysqldump --all-databases` тоже не выгружает
+const MYSQL_SYSTEM_DATABASES: &[&str] = &["information_schema", "performance_schema"];
+
+impl DatabaseSource {
+    /// Человекочитаемый адрес источника, например `postgres://localhost/panel`
+    pub fn describe(&self) -> String {
+        let scheme = match self.kind {
+            DatabaseKind::MySql => "mysql",
+            DatabaseKind::Postgres => "postgres",
+        };
+        let databases = match &self.database {
+            Some(database) => database.clone(),
+            None if !self.include.is_empty() => self.include.join(","),
+            None => "*".to_string(),
+        };
+        format!("{}://{}/{}", scheme, self.host.as_deref().unwrap_or("localhost"), databases)
+    }
+
+    /// Дамп одной базы в старом формате: без CREATE DATABASE, как при ручном `pg_dump <база>`
+    fn is_single(&self) -> bool {
+        self.database.is_some() && self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Команда клиента СУБД с параметрами подключения этого источника
+    fn command(&self, program: &str) -> Command {
+        let mut cmd = Command::new(program);
+        match self.kind {
+            DatabaseKind::Postgres => {
+                if let Some(host) = &self.host {
+                    cmd.args(["-h", host]);
+                }
+                if let Some(port) = self.port {
+                    cmd.args(["-p", &port.to_string()]);
+                }
+                if let Some(user) = &self.user {
+                    cmd.args(["-U", user]);
+                }
+                if let Some(password) = &self.password {
+                    cmd.env("PGPASSWORD", password);
+                }
+            }
+            DatabaseKind::MySql => {
+                if let Some(host) = &self.host {
+                    cmd.args(["-h", host]);
+                }
+                if let Some(port) = self.port {
+                    cmd.args(["-P", &port.to_string()]);
+                }
+                if let Some(user) = &self.user {
+                    cmd.args(["-u", user]);
+                }
+                if let Some(password) = &self.password {
+                    cmd.env("MYSQL_PWD", password);
+                }
+            }
+        }
+        cmd
+    }
+
+    fn list_databases(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut cmd = match self.kind {
+            DatabaseKind::Postgres => {
+                let mut cmd = self.command("psql");
+                cmd.args(["-d", "postgres", "-At", "-c"])
+                    .arg("SELECT datname FROM pg_database WHERE datallowconn AND NOT datistemplate");
+                cmd
+            }
+            DatabaseKind::MySql => {
+                let mut cmd = self.command("mysql");
+                cmd.args(["-N", "-e", "SHOW DATABASES"]);
+                cmd
+            }
+        };
+
+        let output = cmd.stdin(Stdio::null()).output()?;
+        if !output.status.success() {
+            forensics::record_output(&output.stderr);
+            return Err(format!(
+                "Не удалось получить список баз {}: {}",
+                self.describe(),
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|db| !db.is_empty())
+            .filter(|db| self.kind != DatabaseKind::MySql || !MYSQL_SYSTEM_DATABASES.contains(db))
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Базы, которые попадут в дамп; `None` — все базы сервера без исключений
+    pub fn selected_databases(&self) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+        let mut selected: Vec<String> = self.database.iter().chain(&self.include).cloned().collect();
+        if selected.is_empty() {
+            if self.exclude.is_empty() {
+                return Ok(None);
+            }
+            selected = self.list_databases()?;
+        }
+
+        selected.retain(|db| !self.exclude.contains(db));
+        if selected.is_empty() {
+            return Err(format!("После исключений в {} не осталось баз для дампа", self.describe()).into());
+        }
+        Ok(Some(selected))
+    }
+
+    /// Команды, вывод которых по очереди попадает в один сжатый дамп
+    fn dump_commands(&self, databases: Option<&[String]>) -> Vec<Command> {
+        match (self.kind, databases) {
+            (DatabaseKind::Postgres, None) => vec![self.command("pg_dumpall")],
+            (DatabaseKind::Postgres, Some(databases)) if self.is_single() => {
+                let mut cmd = self.command("pg_dump");
+                cmd.args(databases);
+                vec![cmd]
+            }
+            (DatabaseKind::Postgres, Some(databases)) => {
+                let mut commands = Vec::new();
+                // «Все, кроме» — сохраняем роли, как это сделал бы pg_dumpall
+                if self.database.is_none() && self.include.is_empty() {
+                    let mut globals = self.command("pg_dumpall");
+                    globals.arg("--globals-only");
+                    commands.push(globals);
+                }
+                // С --create дампы нескольких баз можно подряд восстановить одним psql
+                for database in databases {
+                    let mut cmd = self.command("pg_dump");
+                    cmd.args(["--create", database]);
+                    commands.push(cmd);
+                }
+                commands
+            }
+            (DatabaseKind::MySql, databases) => {
+                let mut cmd = self.command("mysqldump");
+                cmd.args(["--single-transaction", "--routines", "--triggers"]);
+                match databases {
+                    None => cmd.arg("--all-databases"),
+                    Some(databases) if self.is_single() => cmd.args(databases),
+                    Some(databases) => cmd.arg("--databases").args(databases),
+                };
+                vec![cmd]
+            }
+        }
+    }
+}
+
+pub struct Dump {
+    pub archive_name: String,
+    pub size: Option<u64>,
+    /// Выгруженные базы; пусто, если выгружены все базы сервера
+    pub databases: Vec<String>,
+}
+
+/// Снимает дамп и сжимает его на лету (`dump | gzip > архив`), не создавая
+/// промежуточного .sql файла
+pub fn dump(source: &DatabaseSource, index: usize, target_dir: &str) -> Result<Dump, Box<dyn std::error::Error>> {
+    let databases = source.selected_databases()?;
+
+    let name = source.name.as_deref().or(source.database.as_deref()).unwrap_or(
+        if databases.is_some() { "selected" } else { "all" },
+    );
+    let archive_name = format!("db_{}_{}.sql.gz", index + 1, name);
+    let archive_path = Path::new(target_dir).join(&archive_name);
+
+    println!("🗄️ Дамп базы: {} → {}", source.describe(), archive_name);
+    if let Some(databases) = &databases {
+        info!("Базы в дампе {}: {}", archive_name, databases.join(", "));
+    }
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let mut gzip = Command::new("gzip")
+            .arg("-c")
+            .stdin(Stdio::piped())
+            .stdout(File::create(&archive_path)?)
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut gzip_stdin = gzip.stdin.take().ok_or("Не удалось открыть вход gzip")?;
+
+        for mut cmd in source.dump_commands(databases.as_deref()) {
+            let mut dump = cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+            let mut dump_stdout = dump.stdout.take().ok_or("Не удалось получить вывод дампа")?;
+            io::copy(&mut dump_stdout, &mut gzip_stdin)?;
+
+            let dump = dump.wait_with_output()?;
+            if !dump.status.success() {
+                forensics::record_output(&dump.stderr);
+                return Err(format!("Ошибка дампа {}: {}", source.describe(), String::from_utf8_lossy(&dump.stderr)).into());
+            }
+        }
+
+        drop(gzip_stdin);
+        let gzip = gzip.wait_with_output()?;
+        if !gzip.status.success() {
+            forensics::record_output(&gzip.stderr);
+            return Err(format!("Ошибка сжатия дампа: {}", String::from_utf8_lossy(&gzip.stderr)).into());
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&archive_path);
+        return Err(e);
+    }
+
+    let size = fs::metadata(&archive_path).ok().map(|m| m.len());
+    info!("Дамп создан: {} (размер: {:?} байт)", archive_name, size);
+    Ok(Dump {
+        archive_name,
+        size,
+        databases: databases.unwrap_or_default(),
+    })
+}
@@ -0,0 +1,149 @@
+use crate::config::Config;
+use crate::git::{self, Author, Credentials};
+use crate::staging::StagingDir;
+use crate::{gitea, rclone, s3, sftp, timestamp, webdav};
+use chrono::Utc;
+use colored::*;
+use log::{info, warn};
+use std::time::{Duration, Instant};
+
+/// Проверка одного хранилища: пройденные шаги с их длительностью и сведения о месте
+#[derive(Default)]
+pub struct Check {
+    pub steps: Vec<(&'static str, Duration)>,
+    pub space: Option<String>,
+}
+
+impl Check {
+    /// Выполняет шаг и запоминает, сколько он занял; ошибка называет шаг
+    pub fn step<T>(
+        &mut self,
+        name: &'static str,
+        operation: impl FnOnce() -> Result<T, Box<dyn std::error::Error>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        let value = operation().map_err(|e| format!("{}: {}", name, e))?;
+        self.steps.push((name, started.elapsed()));
+        Ok(value)
+    }
+}
+
+/// Имя пробного объекта или ветки: с именем сервера, чтобы проверки разных серверов
+/// одного хранилища не мешали друг другу
+pub fn probe_name(config: &Config) -> String {
+    format!(
+        "obt-probe-{}-{}",
+        config.backup_name.as_deref().unwrap_or("obt"),
+        timestamp::folder(Utc::now())
+    )
+}
+
+/// Содержимое пробного объекта, которое сверяется после чтения
+pub fn probe_content(name: &str) -> String {
+    format!("Проверка хранилища OfficialVPN Backup Tool: {}\n", name)
+}
+
+/// Хранилища из config.json: основное под именем своего поля и реплика
+pub fn names(config: &Config) -> Vec<&'static str> {
+    let primary = if config.s3.is_some() {
+        "s3"
+    } else if config.sftp.is_some() {
+        "sftp"
+    } else if config.git_ssh.is_some() {
+        "git_ssh"
+    } else if config.rclone.is_some() {
+        "rclone"
+    } else if config.webdav.is_some() {
+        "webdav"
+    } else {
+        "gitea"
+    };
+    let mut names = vec![primary];
+    if config.replica.is_some() {
+        names.push("replica");
+    }
+    names
+}
+
+fn probe(config: &Config, name: &str, check: &mut Check) -> Result<(), Box<dyn std::error::Error>> {
+    let staging = StagingDir::new(format!("obt_dest_check_{}", timestamp::folder(Utc::now())));
+    match name {
+        "s3" => {
+            let s3 = config.s3.as_ref().ok_or("S3 не настроен")?;
+            let storage = check.step("подключение", || s3::Storage::open(s3, staging.path(), None))?;
+            storage.probe(&probe_name(config), check)
+        }
+        "sftp" => {
+            let sftp = config.sftp.as_ref().ok_or("SFTP не настроен")?;
+            let storage = check.step("подключение", || sftp::Storage::open(sftp, staging.path(), None))?;
+            storage.probe(&probe_name(config), check)
+        }
+        "rclone" => {
+            let rclone = config.rclone.as_ref().ok_or("rclone не настроен")?;
+            let storage = check.step("подключение", || rclone::Storage::open(rclone, staging.path(), None))?;
+            storage.probe(&probe_name(config), check)
+        }
+        "webdav" => {
+            let webdav = config.webdav.as_ref().ok_or("WebDAV не настроен")?;
+            let storage = check.step("подключение", || webdav::Storage::open(webdav, staging.path(), None))?;
+            storage.probe(&probe_name(config), check)
+        }
+        "gitea" | "git_ssh" => {
+            git::probe(
+                &config.repo_url()?,
+                &Credentials::primary(config)?,
+                &Author::from_config(config),
+                staging.path(),
+                &probe_name(config),
+                check,
+            )?;
+            if name == "gitea" {
+                check.space = gitea::repo_size(config).ok().map(|kb| format!("репозиторий занимает {:.1} МБ", kb as f64 / 1024.0));
+            }
+            Ok(())
+        }
+        "replica" => {
+            // Реплика проверяется так же, как основное хранилище того же вида
+            let replica = config.replica.as_ref().ok_or("Реплика не настроена")?.as_primary(config)?;
+            probe(&replica, names(&replica)[0], check)
+        }
+        other => Err(format!("Неизвестное хранилище {}: в config.json настроены {}", other, names(config).join(", ")).into()),
+    }
+}
+
+/// `obt dest check [имя]`: подключается к каждому хранилищу (или к одному), записывает,
+/// читает и удаляет пробный объект и показывает, сколько занял каждый шаг
+pub fn run_check(config: &Config, only: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let selected: Vec<&str> = match only {
+        Some(name) => vec![name],
+        None => names(config),
+    };
+    let mut failed = Vec::new();
+    for name in selected {
+        println!("🔎 Проверка хранилища {}...", name);
+        let mut check = Check::default();
+        let result = probe(config, name, &mut check);
+        for (step, duration) in &check.steps {
+            println!("  ✅ {}: {} мс", step, duration.as_millis());
+        }
+        match result {
+            Ok(()) => {
+                match &check.space {
+                    Some(space) => println!("  💾 {}", space),
+                    None => println!("  💾 сведения о месте хранилище не сообщает"),
+                }
+                info!("Хранилище {} прошло проверку", name);
+            }
+            Err(e) => {
+                println!("{}", format!("  ❌ {}", e).red());
+                warn!("Хранилище {} не прошло проверку: {}", name, e);
+                failed.push(name);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!("Проверку не прошли хранилища: {}", failed.join(", ")).into());
+    }
+    println!("{}", "✅ Все хранилища доступны".green());
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use crate::cache;
+use crate::config::Config;
+use crate::find::original_path;
+use crate::index::{self, FileEntry};
+use crate::manifest::ArchiveEntry;
+use crate::restore::{self, Snapshot};
+use crate::staging::StagingDir;
+use crate::timestamp;
+use crate::prepare_repository;
+use colored::*;
+use log::{info, warn};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Содержимое бэкапа по путям из config.json: исходный путь файла → запись индекса.
+/// Дампы и другие потоки — одной записью с размером архива и без времени изменения
+type Contents = BTreeMap<String, BTreeMap<String, FileEntry>>;
+
+/// Файлы архива: из индекса manifest.json, а у бэкапов старых версий — из самого архива
+fn archive_files(snapshot: &Snapshot, archive: &ArchiveEntry, work_dir: &str) -> Vec<FileEntry> {
+    if !archive.files.is_empty() || archive.encrypted {
+        return archive.files.clone();
+    }
+    let path = Path::new(work_dir).join(snapshot.path()).join(&archive.name);
+    index::list_archive(&path).unwrap_or_else(|e| {
+        warn!("Не удалось прочитать {}/{}: {}", snapshot.name, archive.name, e);
+        Vec::new()
+    })
+}
+
+/// У бэкапа есть архивы без индекса файлов: их содержимое читается из самих архивов
+fn needs_archives(snapshot: &Snapshot) -> bool {
+    snapshot.manifest.as_ref().is_some_and(|manifest| {
+        manifest
+            .archives
+            .iter()
+            .any(|archive| !archive.is_stream() && archive.files.is_empty() && !archive.encrypted)
+    })
+}
+
+/// Содержимое бэкапа целиком: для сделанного поверх базового — полный бэкап цепочки,
+/// к которому по порядку применены изменения и удаления следующих
+fn contents(chain: &[&Snapshot], work_dir: &str) -> Result<Contents, Box<dyn std::error::Error>> {
+    let mut contents = Contents::new();
+    for snapshot in chain {
+        let manifest = snapshot
+            .manifest
+            .as_ref()
+            .ok_or_else(|| format!("У бэкапа {} нет manifest.json: он сделан старой версией obt", snapshot.name))?;
+        for archive in &manifest.archives {
+            let files = contents.entry(archive.source.clone()).or_default();
+            if archive.is_stream() {
+                let entry = FileEntry { path: archive.name.clone(), size: archive.size, mtime: String::new(), sha256: None };
+                files.clear();
+                files.insert(archive.source.clone(), entry);
+                continue;
+            }
+            if manifest.base.is_none() {
+                files.clear();
+            }
+            for deleted in &archive.deleted {
+                files.remove(&original_path(archive, deleted));
+            }
+            for file in archive_files(snapshot, archive, work_dir) {
+                files.insert(original_path(archive, &file.path), file);
+            }
+        }
+    }
+    Ok(contents)
+}
+
+/// Изменён ли файл: по SHA-256 содержимого, а если его нет в одном из индексов (бэкап старой
+/// версии) — по размеру и времени изменения. У дампов — только размер архива
+fn modified(old: &FileEntry, new: &FileEntry) -> bool {
+    match (&old.sha256, &new.sha256) {
+        (Some(old_sha256), Some(new_sha256)) => old_sha256 != new_sha256,
+        _ => old.size != new.size || old.modified_at() != new.modified_at(),
+    }
+}
+
+fn describe_change(old: &FileEntry, new: &FileEntry) -> String {
+    if new.mtime.is_empty() {
+        format!("{} → {} байт", old.size, new.size)
+    } else {
+        format!("{} → {} байт, изменён {}", old.size, new.size, new.display_mtime())
+    }
+}
+
+/// `obt diff <a> <b>`: добавленные, удалённые и изменённые файлы по каждому пути бэкапа
+pub fn run(config: &Config, from: &str, to: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let staging = StagingDir::new(format!("diff_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let work_dir = staging.path();
+
+    println!("🔄 Получение бэкапов из репозитория...");
+    let (snapshots, downloaded) = cache::snapshots(config, work_dir)?;
+    let find = |name: &str| {
+        snapshots
+            .iter()
+            .find(|snapshot| snapshot.name == name)
+            .ok_or_else(|| format!("Бэкап {} не найден в хранилище (obt list — все бэкапы)", name))
+    };
+    let (old, new) = (find(from)?, find(to)?);
+    let old_chain = restore::chain(&snapshots, old)?;
+    let new_chain = restore::chain(&snapshots, new)?;
+
+    // Бэкапы старых версий без индекса файлов в manifest.json приходится скачивать
+    let missing: BTreeSet<String> = old_chain
+        .iter()
+        .chain(&new_chain)
+        .filter(|snapshot| needs_archives(snapshot))
+        .map(|snapshot| snapshot.path())
+        .collect();
+    if !downloaded && !missing.is_empty() {
+        let storage = prepare_repository(config, work_dir)?;
+        for path in &missing {
+            storage.download_backup(path)?;
+        }
+    }
+
+    let old_contents = contents(&old_chain, work_dir)?;
+    let new_contents = contents(&new_chain, work_dir)?;
+    println!(
+        "📦 {} ({}) → {} ({})",
+        old.name.white().bold(),
+        timestamp::display(&old.created_at),
+        new.name.white().bold(),
+        timestamp::display(&new.created_at)
+    );
+
+    let empty = BTreeMap::new();
+    let sources: BTreeSet<&String> = old_contents.keys().chain(new_contents.keys()).collect();
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for source in sources {
+        let old_files = old_contents.get(source).unwrap_or(&empty);
+        let new_files = new_contents.get(source).unwrap_or(&empty);
+        let mut lines = Vec::new();
+        for (path, file) in new_files {
+            match old_files.get(path) {
+                None => {
+                    lines.push(format!("  {} {} ({} байт)", "+".green(), path, file.size));
+                    added += 1;
+                }
+                Some(old_file) if modified(old_file, file) => {
+                    lines.push(format!("  {} {} ({})", "~".yellow(), path, describe_change(old_file, file)));
+                    changed += 1;
+                }
+                Some(_) => {}
+            }
+        }
+        for path in old_files.keys().filter(|path| !new_files.contains_key(*path)) {
+            lines.push(format!("  {} {}", "-".red(), path));
+            removed += 1;
+        }
+        if lines.is_empty() {
+            continue;
+        }
+        let note = match (old_contents.contains_key(source), new_contents.contains_key(source)) {
+            (false, _) => format!(" (только в {})", new.name),
+            (_, false) => format!(" (только в {})", old.name),
+            _ => String::new(),
+        };
+        println!("\n📂 {}{}", source.white().bold(), note);
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+
+    info!("Сравнение {} и {}: добавлено {}, удалено {}, изменено {}", from, to, added, removed, changed);
+    if added + removed + changed == 0 {
+        println!("\n{}", "Бэкапы не отличаются".green());
+    } else {
+        println!("\nДобавлено: {}, удалено: {}, изменено: {}", added, removed, changed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(mtime: &str, sha256: Option<&str>) -> FileEntry {
+        FileEntry {
+            path: "./app.db".to_string(),
+            size: 4096,
+            mtime: mtime.to_string(),
+            sha256: sha256.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn compares_content_hash() {
+        // Файл перезаписан тем же содержимым: время другое, а содержимое то же
+        assert!(!modified(&entry("2024-05-10T01:00:00Z", Some("aa")), &entry("2024-05-11T01:00:00Z", Some("aa"))));
+        // Содержимое изменилось при том же размере и времени (например, после `touch -r`)
+        assert!(modified(&entry("2024-05-10T01:00:00Z", Some("aa")), &entry("2024-05-10T01:00:00Z", Some("bb"))));
+    }
+
+    #[test]
+    fn falls_back_to_mtime_without_hash() {
+        assert!(modified(&entry("2024-05-10T01:00:00Z", None), &entry("2024-05-11T01:00:00Z", Some("aa"))));
+        assert!(!modified(&entry("2024-05-10T01:00:00Z", None), &entry("2024-05-10T01:00:00Z", Some("aa"))));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::config::{Config, DivergencePolicy};
+use crate::{prompt, timestamp};
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Решение из `obt backup --on-divergence`: важнее поля on_divergence в config.json
+static OVERRIDE: OnceLock<DivergencePolicy> = OnceLock::new();
+
+pub fn set_override(policy: DivergencePolicy) {
+    let _ = OVERRIDE.set(policy);
+}
+
+/// Как поступать с переписанной удалённой веткой: флаг запуска, затем config.json.
+/// `None` — спросить в терминале
+pub fn policy(config: &Config) -> Option<DivergencePolicy> {
+    OVERRIDE.get().copied().or(config.on_divergence)
+}
+
+/// Решение, когда политика не задана: вопрос в терминале, а в демоне и cron — отмена,
+/// чтобы не переписывать чужую историю без ведома администратора
+pub fn ask(branch: &str, expected: &str, found: &str) -> Result<DivergencePolicy, Box<dyn std::error::Error>> {
+    if !io::stdin().is_terminal() {
+        warn!("Нет терминала для выбора, бэкап отменён (задайте on_divergence в config.json)");
+        return Ok(DivergencePolicy::Abort);
+    }
+    println!("⚠️ История ветки {} в хранилище переписана (например, force-push с другого сервера):", branch);
+    println!("   ожидался потомок {}, в хранилище {}", short(expected), short(found));
+    println!("1. Перенести свои коммиты поверх новой истории (rebase)");
+    println!("2. Отправить бэкап в отдельную ветку, не трогая {}", branch);
+    println!("3. Отменить загрузку");
+    loop {
+        // Без ответа за `prompt_timeout_seconds` загрузка отменяется, как и без терминала
+        match prompt::read("Выберите действие (1-3): ", Some("3"))?.as_str() {
+            "1" => return Ok(DivergencePolicy::Rebase),
+            "2" => return Ok(DivergencePolicy::Branch),
+            "3" => return Ok(DivergencePolicy::Abort),
+            _ => println!("Неверный выбор"),
+        }
+    }
+}
+
+fn short(oid: &str) -> &str {
+    &oid[..oid.len().min(8)]
+}
+
+/// Найденное расхождение и принятое решение
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub detected_at: String,
+    pub branch: String,
+    /// Коммит удалённой ветки, от которого продолжал этот запуск
+    pub expected: String,
+    /// Коммит, на который ветка указывает теперь
+    pub found: String,
+    pub resolution: DivergencePolicy,
+    /// Ветка, куда ушёл бэкап при решении Branch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side_branch: Option<String>,
+}
+
+/// Журнал расхождений (~/.config/obt/divergence.json), чтобы позже разобрать,
+/// куда ушли бэкапы и чья история была перенесена
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DivergenceLog {
+    pub records: Vec<Record>,
+}
+
+impl DivergenceLog {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_log_path()?;
+
+        if !path.exists() {
+            return Ok(DivergenceLog::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_log_path()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn get_log_path() -> io::Result<PathBuf> {
+        Ok(Config::config_dir()?.join("divergence.json"))
+    }
+}
+
+/// Записывает решение в журнал; сбой записи не мешает загрузке
+pub fn record(branch: &str, expected: &str, found: &str, resolution: DivergencePolicy, side_branch: Option<&str>) {
+    info!(
+        "Расхождение ветки {}: ожидался потомок {}, найден {}; решение: {:?}{}",
+        branch,
+        expected,
+        found,
+        resolution,
+        side_branch.map(|b| format!(" ({})", b)).unwrap_or_default()
+    );
+    let result = DivergenceLog::load().and_then(|mut log| {
+        log.records.push(Record {
+            detected_at: timestamp::format(Utc::now()),
+            branch: branch.to_string(),
+            expected: expected.to_string(),
+            found: found.to_string(),
+            resolution,
+            side_branch: side_branch.map(str::to_string),
+        });
+        log.save()
+    });
+    if let Err(e) = result {
+        warn!("Не удалось записать решение о расхождении в divergence.json: {}", e);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::config::{DockerSource, ImageBackup};
+use crate::forensics;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{info, warn};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+const SOCKET: &str = "/var/run/docker.sock";
+
+/// Сколько stderr команды `exec` сохранять для сообщения об ошибке
+const STDERR_LIMIT: usize = 64 * 1024;
+
+/// Предел размера образов проекта compose для `docker save` по умолчанию (МБ)
+const DEFAULT_MAX_IMAGES_MB: u64 = 2048;
+
+impl DockerSource {
+    pub fn describe(&self) -> String {
+        match (&self.volume, &self.compose, &self.container, &self.exec) {
+            (Some(volume), _, _, _) => format!("docker:volume/{}", volume),
+            (None, Some(project), _, _) => format!("docker:compose/{}", project),
+            (None, None, Some(container), Some(exec)) => format!("docker:{} $ {}", container, exec),
+            _ => "docker:?".to_string(),
+        }
+    }
+}
+
+/// Сокет Docker Engine API: `DOCKER_HOST=unix://...`, как у клиента docker, иначе стандартный
+fn socket() -> String {
+    std::env::var("DOCKER_HOST")
+        .ok()
+        .and_then(|host| host.strip_prefix("unix://").map(str::to_string))
+        .unwrap_or_else(|| SOCKET.to_string())
+}
+
+/// Запрос к Docker по HTTP/1.0: сервер закрывает соединение после ответа,
+/// так что тело (в том числе поток `exec`) читается до конца потока
+fn send(method: &str, path: &str, body: Option<Value>) -> Result<(u16, BufReader<UnixStream>), Box<dyn std::error::Error>> {
+    let socket = socket();
+    let mut stream = UnixStream::connect(&socket).map_err(|e| format!("Нет доступа к Docker через {}: {}", socket, e))?;
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    write!(
+        stream,
+        "{} /v1.41{} HTTP/1.0\r\nHost: docker\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )?;
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("Неверный ответ Docker: {}", line.trim()))?;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+    Ok((status, reader))
+}
+
+fn request(method: &str, path: &str, body: Option<Value>) -> Result<Value, Box<dyn std::error::Error>> {
+    let (status, mut reader) = send(method, path, body)?;
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    if !(200..300).contains(&status) {
+        let message = serde_json::from_str::<Value>(&content)
+            .ok()
+            .and_then(|error| error["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| content.trim().to_string());
+        return Err(format!("Docker ответил {}: {}", status, message).into());
+    }
+    if content.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Папка тома на хосте, например `/var/lib/docker/volumes/panel_data/_data`
+pub fn mountpoint(volume: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let info = request("GET", &format!("/volumes/{}", volume), None)
+        .map_err(|e| format!("Том Docker {} не найден: {}", volume, e))?;
+    Ok(info["Mountpoint"].as_str().ok_or("Docker не сообщил папку тома")?.to_string())
+}
+
+pub struct Volume {
+    pub name: String,
+    pub mountpoint: String,
+    /// Контейнеры (в том числе остановленные), к которым подключён том
+    pub containers: Vec<String>,
+}
+
+/// Все именованные тома вместе с контейнерами, которые их используют
+pub fn volumes() -> Result<Vec<Volume>, Box<dyn std::error::Error>> {
+    let listed = request("GET", "/volumes", None)?;
+    let containers = request("GET", "/containers/json?all=1", None)?;
+    let containers = containers.as_array().cloned().unwrap_or_default();
+
+    let mut volumes: Vec<Volume> = listed["Volumes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|volume| {
+            let name = volume["Name"].as_str()?.to_string();
+            let users = containers
+                .iter()
+                .filter(|container| {
+                    container["Mounts"]
+                        .as_array()
+                        .is_some_and(|mounts| mounts.iter().any(|m| m["Type"] == "volume" && m["Name"] == name.as_str()))
+                })
+                .filter_map(|container| container["Names"][0].as_str().map(|n| n.trim_start_matches('/').to_string()))
+                .collect();
+            Some(Volume {
+                mountpoint: volume["Mountpoint"].as_str().unwrap_or_default().to_string(),
+                name,
+                containers: users,
+            })
+        })
+        .collect();
+    volumes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(volumes)
+}
+
+/// Выполняет `exec` источника в контейнере и сжимает его stdout в `exec_<n>_<имя>.gz`,
+/// как `docker exec <контейнер> sh -c '<команда>' | gzip`; возвращает имя и размер архива
+pub fn exec_dump(source: &DockerSource, index: usize, target_dir: &str) -> Result<(String, Option<u64>), Box<dyn std::error::Error>> {
+    let (Some(container), Some(command)) = (&source.container, &source.exec) else {
+        return Err("Для источника docker укажите volume или container вместе с exec".into());
+    };
+    let name = source.name.as_deref().unwrap_or(container);
+    let archive_name = format!("exec_{}_{}.gz", index + 1, name);
+    let archive_path = Path::new(target_dir).join(&archive_name);
+    println!("🐳 {} → {}", source.describe(), archive_name);
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let exec = request(
+            "POST",
+            &format!("/containers/{}/exec", container),
+            Some(json!({"AttachStdout": true, "AttachStderr": true, "Cmd": ["sh", "-c", command]})),
+        )
+        .map_err(|e| format!("Не удалось запустить команду в контейнере {}: {}", container, e))?;
+        let id = exec["Id"].as_str().ok_or("Docker не вернул id exec")?.to_string();
+
+        let (status, mut stream) = send("POST", &format!("/exec/{}/start", id), Some(json!({"Detach": false, "Tty": false})))?;
+        if status != 200 {
+            return Err(format!("Docker ответил {} на запуск exec", status).into());
+        }
+        // Без TTY вывод разбит на кадры: байт потока (1 — stdout, 2 — stderr), три нуля и длина
+        let mut gzip = GzEncoder::new(File::create(&archive_path)?, Compression::default());
+        let mut stderr = Vec::new();
+        let mut header = [0u8; 8];
+        loop {
+            match stream.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as u64;
+            let mut frame = (&mut stream).take(size);
+            if header[0] == 2 {
+                let mut chunk = Vec::new();
+                frame.read_to_end(&mut chunk)?;
+                stderr.extend(chunk.into_iter().take(STDERR_LIMIT.saturating_sub(stderr.len())));
+            } else {
+                std::io::copy(&mut frame, &mut gzip)?;
+            }
+        }
+        gzip.finish()?;
+
+        let exit_code = request("GET", &format!("/exec/{}/json", id), None)?["ExitCode"].as_i64();
+        if exit_code != Some(0) {
+            forensics::record_output(&stderr);
+            return Err(format!(
+                "Команда в контейнере {} завершилась с кодом {}: {}",
+                container,
+                exit_code.map_or("?".to_string(), |code| code.to_string()),
+                String::from_utf8_lossy(&stderr).trim()
+            )
+            .into());
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&archive_path);
+        return Err(e);
+    }
+    let size = fs::metadata(&archive_path).ok().map(|m| m.len());
+    info!("Вывод команды из контейнера {} сохранён: {} (размер: {:?} байт)", container, archive_name, size);
+    Ok((archive_name, size))
+}
+
+/// Образ, который использует контейнер проекта compose
+#[derive(Debug, Serialize)]
+struct Image {
+    /// Сервис compose (`com.docker.compose.service`)
+    services: Vec<String>,
+    /// Имя, с которым образ указан в проекте, например `nginx:1.27`
+    image: String,
+    id: String,
+    /// `nginx@sha256:...`: по нему `docker pull` скачивает ровно этот образ
+    repo_digests: Vec<String>,
+    size: u64,
+}
+
+/// Образы контейнеров проекта compose (в том числе остановленных), по одному на id
+fn compose_images(project: &str) -> Result<Vec<Image>, Box<dyn std::error::Error>> {
+    let filters = json!({"label": [format!("com.docker.compose.project={}", project)]}).to_string();
+    let containers = request(
+        "GET",
+        &format!("/containers/json?all=1&filters={}", utf8_percent_encode(&filters, NON_ALPHANUMERIC)),
+        None,
+    )?;
+
+    let mut images: Vec<Image> = Vec::new();
+    for container in containers.as_array().into_iter().flatten() {
+        let (Some(name), Some(id)) = (container["Image"].as_str(), container["ImageID"].as_str()) else {
+            continue;
+        };
+        let service = container["Labels"]["com.docker.compose.service"].as_str().unwrap_or_default().to_string();
+        if let Some(image) = images.iter_mut().find(|image| image.id == id) {
+            if !image.services.contains(&service) {
+                image.services.push(service);
+            }
+            continue;
+        }
+        let info = request("GET", &format!("/images/{}/json", id), None)?;
+        images.push(Image {
+            services: vec![service],
+            image: name.to_string(),
+            id: id.to_string(),
+            repo_digests: info["RepoDigests"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|digest| digest.as_str().map(str::to_string))
+                .collect(),
+            size: info["Size"].as_u64().unwrap_or(0),
+        });
+    }
+    if images.is_empty() {
+        return Err(format!("Контейнеры проекта compose {} не найдены", project).into());
+    }
+    images.sort_by(|a, b| a.image.cmp(&b.image));
+    Ok(images)
+}
+
+/// Сохраняет образы проекта compose источника: с `Save` — `docker save` всех образов,
+/// сжатый в `images_<n>_<проект>.tar.gz` (его принимает `docker load`), иначе или если
+/// образы больше `max_images_mb` — их имена и дайджесты в `images_<n>_<проект>.json.gz`.
+/// Возвращает имя и размер архива
+pub fn images_dump(source: &DockerSource, index: usize, target_dir: &str) -> Result<(String, Option<u64>), Box<dyn std::error::Error>> {
+    let project = source.compose.as_deref().ok_or("Для образов укажите проект compose")?;
+    let images = compose_images(project)?;
+
+    let total: u64 = images.iter().map(|image| image.size).sum();
+    let limit = source.max_images_mb.unwrap_or(DEFAULT_MAX_IMAGES_MB) * 1_048_576;
+    let save = match source.images {
+        ImageBackup::Save if total > limit => {
+            warn!(
+                "Образы проекта {} занимают {} МБ, больше max_images_mb ({} МБ): сохраняются только дайджесты",
+                project,
+                total / 1_048_576,
+                limit / 1_048_576
+            );
+            false
+        }
+        ImageBackup::Save => true,
+        ImageBackup::Digests => false,
+    };
+
+    let archive_name = format!("images_{}_{}.{}.gz", index + 1, project, if save { "tar" } else { "json" });
+    let archive_path = Path::new(target_dir).join(&archive_name);
+    println!("🐳 {} → {} (образов: {})", source.describe(), archive_name, images.len());
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let mut gzip = GzEncoder::new(File::create(&archive_path)?, Compression::default());
+        if save {
+            // Образ без тега сохраняется по id, с тегом — под своим именем, чтобы `docker load` его вернул
+            let names: Vec<String> = images
+                .iter()
+                .map(|image| format!("names={}", utf8_percent_encode(&image.image, NON_ALPHANUMERIC)))
+                .collect();
+            let (status, mut stream) = send("GET", &format!("/images/get?{}", names.join("&")), None)?;
+            if status != 200 {
+                let mut message = String::new();
+                stream.read_to_string(&mut message)?;
+                return Err(format!("Docker ответил {} на сохранение образов: {}", status, message.trim()).into());
+            }
+            std::io::copy(&mut stream, &mut gzip)?;
+        } else {
+            serde_json::to_writer_pretty(&mut gzip, &images)?;
+        }
+        gzip.finish()?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&archive_path);
+        return Err(e);
+    }
+    let size = fs::metadata(&archive_path).ok().map(|m| m.len());
+    info!("Образы проекта compose {} сохранены: {} (размер: {:?} байт)", project, archive_name, size);
+    Ok((archive_name, size))
+}
@@ -0,0 +1,68 @@
+use crate::{archive, progress};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+
+/// Ход бэкапа для встраивания движка: события приходят по порядку из потока бэкапа.
+/// В JSON — объект с полем `event` (`phase_started`, `file_archived`, ...), чтобы
+/// их можно было без преобразований отдавать через SSE.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Начался этап; те же названия попадают в отчёт о сбое
+    PhaseStarted { phase: String },
+    ArchiveStarted { source: String, archive: String },
+    /// Файл записан в архив; `files` и `bytes` — всего по этому архиву
+    FileArchived { archive: String, path: String, files: u64, bytes: u64 },
+    ArchiveFinished { archive: String, size: Option<u64>, files: usize },
+    /// Загружена очередная часть бэкапа: `bytes` в ней, `uploaded` — всего за запуск
+    BytesUploaded { bytes: u64, uploaded: u64 },
+}
+
+/// Получатель событий; при `archive_jobs` больше 1 вызывается из нескольких потоков
+pub trait EventSink: Sync {
+    fn emit(&self, event: Event);
+}
+
+/// Отправка в канал: приёмник читает события в своём потоке
+impl EventSink for Sender<Event> {
+    fn emit(&self, event: Event) {
+        // Приёмник мог уже закрыться — бэкап от этого не прерывается
+        let _ = self.send(event);
+    }
+}
+
+/// Вывод для `obt backup`: полоса прогресса каждого создаваемого архива — прочитанный
+/// объём из примерного объёма пути, а для баз данных и Docker, где он заранее не известен, — счётчик
+#[derive(Default)]
+pub struct Console {
+    archives: Mutex<BTreeMap<String, ProgressBar>>,
+}
+
+impl EventSink for Console {
+    fn emit(&self, event: Event) {
+        let mut archives = self.archives.lock().unwrap();
+        match event {
+            Event::ArchiveStarted { source, archive } => {
+                let path = Path::new(&source);
+                // Обход пути ради оценки нужен только видимой полосе
+                let total = (progress::visible() && path.exists()).then(|| archive::estimate(path));
+                archives.insert(archive.clone(), progress::bar(total, archive));
+            }
+            Event::FileArchived { archive, bytes, .. } => {
+                if let Some(bar) = archives.get(&archive) {
+                    bar.set_position(bytes);
+                }
+            }
+            Event::ArchiveFinished { archive, .. } => {
+                if let Some(bar) = archives.remove(&archive) {
+                    bar.finish_and_clear();
+                }
+            }
+            Event::PhaseStarted { .. } | Event::BytesUploaded { .. } => {}
+        }
+    }
+}
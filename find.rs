@@ -0,0 +1,153 @@
+use crate::cache;
+use crate::compression;
+use crate::config::Config;
+use crate::index::{self, FileEntry};
+use crate::manifest::{ArchiveEntry, ArchiveKind};
+use crate::restore::Snapshot;
+use crate::staging::StagingDir;
+use crate::timestamp;
+use crate::prepare_repository;
+use colored::*;
+use log::{info, warn};
+use std::fs;
+use std::path::Path;
+
+/// Сопоставление с шаблоном оболочки: `*` — любая последовательность, `?` — один символ
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Путь файла на исходном сервере по его пути внутри архива
+pub fn original_path(archive: &ArchiveEntry, inner: &str) -> String {
+    let inner = inner.trim_start_matches("./");
+    match archive.kind() {
+        Some(ArchiveKind::Dir | ArchiveKind::Volume) => Path::new(&archive.source).join(inner).display().to_string(),
+        Some(ArchiveKind::File) => {
+            let parent = Path::new(&archive.source).parent().unwrap_or(Path::new("/"));
+            parent.join(inner).display().to_string()
+        }
+        None => inner.to_string(),
+    }
+}
+
+/// Шаблон без `/` сравнивается с именем файла, со `/` — с полным исходным путём
+fn matches(pattern: &str, path: &str) -> bool {
+    if pattern.contains('/') {
+        glob_match(pattern, path)
+    } else {
+        glob_match(pattern, path.rsplit('/').next().unwrap_or(path))
+    }
+}
+
+fn print_hit(archive: &str, path: &str, file: &FileEntry) {
+    println!(
+        "    {} ({} байт, изменён {}) в {}",
+        path.white().bold(),
+        file.size,
+        file.display_mtime(),
+        archive
+    );
+}
+
+/// Искать придётся по самим архивам: у бэкапа нет manifest.json или индекса файлов
+fn needs_archives(snapshot: &Snapshot) -> bool {
+    match &snapshot.manifest {
+        Some(manifest) => manifest
+            .archives
+            .iter()
+            .any(|archive| !archive.is_stream() && archive.files.is_empty() && !archive.encrypted),
+        None => true,
+    }
+}
+
+/// Ищет файл по имени или шаблону во всех бэкапах репозитория
+pub fn find(config: &Config, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let staging = StagingDir::new(format!("find_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let work_dir = staging.path();
+
+    println!("🔄 Получение бэкапов из репозитория...");
+    let (snapshots, downloaded) = cache::snapshots(config, work_dir)?;
+    if !downloaded && snapshots.iter().any(needs_archives) {
+        prepare_repository(config, work_dir)?.download()?;
+    }
+
+    let mut total = 0;
+    for snapshot in snapshots {
+        let backup_dir = Path::new(work_dir).join(snapshot.path());
+        let mut hits = Vec::new();
+
+        match &snapshot.manifest {
+            Some(manifest) => {
+                for archive in &manifest.archives {
+                    if archive.is_stream() {
+                        continue;
+                    }
+                    // Бэкапы до появления индекса просматриваются по самим архивам
+                    // (у зашифрованных индекс есть всегда)
+                    let files = if archive.files.is_empty() && !archive.encrypted {
+                        index::list_archive(&backup_dir.join(&archive.name)).unwrap_or_else(|e| {
+                            warn!("Не удалось прочитать {}/{}: {}", snapshot.name, archive.name, e);
+                            Vec::new()
+                        })
+                    } else {
+                        archive.files.clone()
+                    };
+                    for file in files {
+                        let path = original_path(archive, &file.path);
+                        if matches(pattern, &path) {
+                            hits.push((archive.name.clone(), path, file));
+                        }
+                    }
+                }
+            }
+            None => {
+                for entry in fs::read_dir(&backup_dir)? {
+                    let name = entry?.file_name().to_string_lossy().to_string();
+                    if compression::tar_stem(&name).is_none() {
+                        continue;
+                    }
+                    for file in index::list_archive(&backup_dir.join(&name)).unwrap_or_default() {
+                        let path = file.path.trim_start_matches("./").to_string();
+                        if matches(pattern, &path) {
+                            hits.push((name.clone(), path, file));
+                        }
+                    }
+                }
+            }
+        }
+
+        if !hits.is_empty() {
+            println!("📦 {} | {}", snapshot.name.white().bold(), timestamp::display(&snapshot.created_at));
+            for (archive, path, file) in &hits {
+                print_hit(archive, path, file);
+            }
+            total += hits.len();
+        }
+    }
+
+    info!("Поиск '{}': найдено {}", pattern, total);
+    if total == 0 {
+        println!("{}", format!("Файлы по шаблону {} не найдены", pattern).yellow());
+    }
+    Ok(())
+}
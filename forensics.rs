@@ -0,0 +1,290 @@
+use crate::archive;
+use crate::config::{Compression, Config, LogFormat};
+use crate::{staging, timestamp};
+use log::{info, warn, Record};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+/// Сколько строк журнала текущего запуска попадает в отчёт
+const LOG_LINES: usize = 2000;
+/// Сколько последних строк вывода упавшей команды попадает в уведомление по умолчанию
+const OUTPUT_LINES: usize = 20;
+/// Сколько последних строк журнала запуска прикладывается к письму об ошибке
+const EXCERPT_LINES: usize = 200;
+/// Сколько отчётов хранить: демон, падающий каждую ночь, не должен заполнить диск
+const KEEP_BUNDLES: usize = 5;
+
+/// Журнал и этап текущего запуска профиля
+#[derive(Default)]
+struct RunState {
+    log: VecDeque<String>,
+    phase: String,
+    /// stderr последней упавшей команды текущего этапа
+    output: Option<String>,
+    /// Для строк JSON
+    backup_name: Option<String>,
+    /// UUID выполняемого бэкапа: попадает в коммит, метаданные объектов S3, manifest.json,
+    /// уведомления и строки JSON, чтобы по оповещению найти коммит и объекты его запуска
+    run_id: Option<String>,
+}
+
+/// По профилям: профили демона выполняют запуски одновременно, и в отчёт об ошибке
+/// попадает журнал только своего запуска
+static RUNS: Mutex<BTreeMap<Option<String>, RunState>> = Mutex::new(BTreeMap::new());
+/// До `set_log_format` — текст
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+fn with_run<T>(f: impl FnOnce(&mut RunState) -> T) -> Option<T> {
+    let mut runs = RUNS.lock().ok()?;
+    Some(f(runs.entry(Config::profile()).or_default()))
+}
+
+/// Задаёт формат журнала один раз, после чтения config.json
+pub fn set_log_format(format: LogFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Имя бэкапа профиля текущего потока для строк JSON
+pub fn set_backup_name(name: Option<String>) {
+    with_run(|run| run.backup_name = name);
+}
+
+/// Поля `key = value` из вызовов `info!(event = "...", size = ...; "...")` для JSON:
+/// числа остаются числами, остальное — строками
+struct Fields<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> log::kv::VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: log::kv::Key<'kvs>, value: log::kv::Value<'kvs>) -> Result<(), log::kv::Error> {
+        let value = if let Some(number) = value.to_u64() {
+            number.into()
+        } else if let Some(number) = value.to_i64() {
+            number.into()
+        } else if let Some(number) = value.to_f64() {
+            number.into()
+        } else if let Some(flag) = value.to_bool() {
+            flag.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// Строка журнала в JSON: `timestamp`, `level`, `target`, `message`, `profile`, `backup_name`,
+/// `run_id` и поля события (`event`, `duration`, `size`, ...)
+fn json_line(record: &Record, backup_name: Option<String>, run_id: Option<String>) -> String {
+    let mut line = serde_json::Map::new();
+    line.insert(
+        "timestamp".to_string(),
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true).into(),
+    );
+    line.insert("level".to_string(), record.level().as_str().into());
+    line.insert("target".to_string(), record.target().into());
+    line.insert("message".to_string(), record.args().to_string().into());
+    if let Some(profile) = Config::profile() {
+        line.insert("profile".to_string(), profile.into());
+    }
+    if let Some(name) = backup_name {
+        line.insert("backup_name".to_string(), name.into());
+    }
+    if let Some(run_id) = run_id {
+        line.insert("run_id".to_string(), run_id.into());
+    }
+    let _ = record.key_values().visit(&mut Fields(&mut line));
+    serde_json::Value::Object(line).to_string()
+}
+
+/// Формат env_logger, дополнительно запоминающий строки для отчёта об ошибке
+pub fn format_record(buf: &mut env_logger::fmt::Formatter, record: &Record) -> io::Result<()> {
+    let timestamp = buf.timestamp();
+    let names = with_run(|run| {
+        if run.log.len() == LOG_LINES {
+            run.log.pop_front();
+        }
+        run.log.push_back(format!("[{} {:<5} {}] {}", timestamp, record.level(), record.target(), record.args()));
+        (run.backup_name.clone(), run.run_id.clone())
+    });
+    if FORMAT.get() == Some(&LogFormat::Json) {
+        let (backup_name, run_id) = names.unwrap_or_default();
+        return writeln!(buf, "{}", json_line(record, backup_name, run_id));
+    }
+    let level = buf.default_styled_level(record.level());
+    writeln!(buf, "[{} {:<5} {}] {}", timestamp, level, record.target(), record.args())
+}
+
+/// Начало запуска: отчёт об ошибке содержит журнал только этого запуска
+pub fn begin_run() {
+    with_run(|run| {
+        run.log.clear();
+        run.run_id = Some(uuid::Uuid::new_v4().to_string());
+    });
+    set_phase("подготовка");
+}
+
+/// Запуск завершён: загрузки вне бэкапа (очистка, репликация) не получают его UUID
+pub fn end_run() {
+    with_run(|run| run.run_id = None);
+}
+
+/// Последние строки журнала текущего запуска для письма об ошибке
+pub fn log_excerpt() -> String {
+    let log: Vec<String> = with_run(|run| run.log.iter().rev().take(EXCERPT_LINES).rev().cloned().collect()).unwrap_or_default();
+    log.join("\n") + "\n"
+}
+
+/// UUID выполняемого бэкапа профиля текущего потока; вне бэкапа — `None`
+pub fn run_id() -> Option<String> {
+    with_run(|run| run.run_id.clone()).flatten()
+}
+
+/// Этап запуска, на котором он находится (попадает в отчёт об ошибке)
+pub fn set_phase(phase: impl Into<String>) {
+    let phase = phase.into();
+    with_run(|run| {
+        run.phase = phase;
+        run.output = None;
+    });
+}
+
+/// Запоминает stderr упавшей команды: если на этом этапе запуск и завершится ошибкой,
+/// конец вывода попадёт в уведомление и в `obt status`
+pub fn record_output(stderr: &[u8]) {
+    let output = String::from_utf8_lossy(stderr).trim().to_string();
+    if !output.is_empty() {
+        with_run(|run| run.output = Some(output));
+    }
+}
+
+/// Последние `failure_output_lines` строк вывода упавшей команды этапа, на котором
+/// остановился запуск, без паролей из config.json
+pub fn failure_output(config: &Config) -> Option<String> {
+    let output = with_run(|run| run.output.clone()).flatten()?;
+    let limit = config.failure_output_lines.unwrap_or(OUTPUT_LINES);
+    let lines: Vec<&str> = output.lines().collect();
+    let tail = lines[lines.len().saturating_sub(limit)..].join("\n");
+    (!tail.is_empty()).then(|| config.redact_text(&tail))
+}
+
+fn command_line(cmd: &str) -> String {
+    Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+fn environment() -> String {
+    let mut lines = vec![format!(
+        "obt: {}{}",
+        env!("CARGO_PKG_VERSION"),
+        if cfg!(feature = "fuse") { " (fuse)" } else { "" }
+    )];
+
+    if let Ok(uname) = nix::sys::utsname::uname() {
+        lines.push(format!(
+            "Система: {} {} {}",
+            uname.sysname().to_string_lossy(),
+            uname.release().to_string_lossy(),
+            uname.machine().to_string_lossy()
+        ));
+        lines.push(format!("Хост: {}", uname.nodename().to_string_lossy()));
+    }
+    let os = fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|content| {
+            content
+                .lines()
+                .find_map(|line| line.strip_prefix("PRETTY_NAME=").map(|v| v.trim_matches('"').to_string()))
+        })
+        .unwrap_or_else(|| "неизвестно".to_string());
+    lines.push(format!("Дистрибутив: {}", os));
+    lines.push(format!("Пользователь: uid {}", nix::unistd::getuid()));
+    lines.push(format!("Время: {}", timestamp::now().format("%Y-%m-%d %H:%M:%S %Z")));
+
+    if let Some(free) = staging::free_space(&staging::root()) {
+        lines.push(format!("Свободно в {}: {:.1} МБ", staging::root().display(), free as f64 / 1_048_576.0));
+    }
+    for tool in ["tar", "gzip", "pg_dump", "pg_dumpall", "mysqldump", "systemctl"] {
+        let path = command_line(&format!("command -v {}", tool));
+        lines.push(format!("{}: {}", tool, if path.is_empty() { "не найден" } else { &path }));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Удаляет старые отчёты, оставляя `KEEP_BUNDLES` последних
+fn prune(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut bundles: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("obt-debug-")))
+        .collect();
+    // Имена содержат время, поэтому сортировка по имени — по возрасту
+    bundles.sort();
+    let excess = bundles.len().saturating_sub(KEEP_BUNDLES);
+    for old in &bundles[..excess] {
+        let _ = fs::remove_file(old);
+    }
+}
+
+/// Собирает `obt-debug-<время>.tar.gz` в `~/.config/obt/debug/`: ошибку и этап, журнал запуска,
+/// config.json без паролей и сведения о системе — для приложения к сообщению об ошибке
+pub fn collect(config: &Config, error: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let timestamp = timestamp::now().format("%Y%m%d_%H%M%S").to_string();
+    let name = format!("obt-debug-{}", timestamp);
+    // Содержимое лежит в папке с именем отчёта, чтобы распаковка не разбрасывала файлы
+    let work_root = staging::root().join(format!("forensics_{}", timestamp));
+    let work_dir = work_root.join(&name);
+    let out_dir = Config::config_dir()?.join("debug");
+    let out = out_dir.join(format!("{}.tar.gz", name));
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(&work_dir)?;
+        fs::create_dir_all(&out_dir)?;
+
+        let phase = with_run(|run| run.phase.clone()).unwrap_or_default();
+        fs::write(
+            work_dir.join("error.txt"),
+            format!(
+                "Время: {}\nЭтап: {}\nОшибка: {}\n",
+                timestamp::now().format("%Y-%m-%d %H:%M:%S %Z"),
+                phase,
+                error
+            ),
+        )?;
+        let log: Vec<String> = with_run(|run| run.log.iter().cloned().collect()).unwrap_or_default();
+        fs::write(work_dir.join("log.txt"), log.join("\n") + "\n")?;
+        fs::write(work_dir.join("config.json"), serde_json::to_string_pretty(&config.redacted()?)?)?;
+        fs::write(work_dir.join("environment.txt"), environment())?;
+
+        archive::create(&work_root, &out, &Compression::default().select(&work_root), false, &[], None, |_, _| {})?;
+        Ok(())
+    })();
+
+    let _ = fs::remove_dir_all(&work_root);
+    if let Err(e) = result {
+        let _ = fs::remove_file(&out);
+        return Err(e);
+    }
+    prune(&out_dir);
+    info!("Отчёт об ошибке сохранён: {}", out.display());
+    Ok(out)
+}
+
+/// Собирает отчёт после неудачного запуска и сообщает, где он лежит
+pub fn report_failure(config: &Config, error: &str) {
+    match collect(config, error) {
+        Ok(path) => println!(
+            "🧾 Отчёт для сообщения об ошибке: {} (пароли из config.json в нём скрыты)",
+            path.display()
+        ),
+        Err(e) => warn!("Не удалось собрать отчёт об ошибке: {}", e),
+    }
+}
@@ -0,0 +1,111 @@
+use crate::config::{Config, Language};
+use crate::i18n::{self, t_in, tf_in};
+use crate::notify::{self, Level, Notification};
+use crate::pause;
+use crate::timestamp;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use std::time::Duration;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+fn format_age(age: chrono::Duration, language: Language) -> String {
+    let hours = age.num_hours();
+    if hours >= 48 {
+        tf_in(language, "{} дн. {} ч.", &[&(hours / 24), &(hours % 24)])
+    } else {
+        tf_in(language, "{} ч. {} мин.", &[&hours, &(age.num_minutes() % 60)])
+    }
+}
+
+/// Следит за тем, чтобы успешный бэкап был не старше `freshness_window_hours`.
+/// Конфигурация перечитывается с диска на каждой проверке, поэтому учитываются и ручные бэкапы.
+pub fn run_watchdog() {
+    let started = Utc::now();
+    let mut last_alert: Option<DateTime<Utc>> = None;
+
+    loop {
+        std::thread::sleep(CHECK_INTERVAL);
+
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Контроль свежести: не удалось прочитать конфигурацию: {}", e);
+                continue;
+            }
+        };
+        let window = match config.freshness_window_hours {
+            Some(hours) => chrono::Duration::hours(hours as i64),
+            None => continue,
+        };
+
+        // Во время паузы бэкапы устаревают намеренно
+        if pause::active().is_some() {
+            continue;
+        }
+
+        let language = i18n::notify_language(&config);
+        let last_success = config.last_backup.as_deref().and_then(timestamp::parse);
+        let now = Utc::now();
+        let age = now - last_success.unwrap_or(started);
+
+        if age <= window {
+            if last_alert.take().is_some() {
+                info!("Контроль свежести: бэкапы снова актуальны");
+                notify::send(
+                    &config,
+                    &Notification {
+                        level: Level::Info,
+                        title: t_in(language, "Бэкапы снова актуальны").to_string(),
+                        text: tf_in(
+                            language,
+                            "Сервер {}: последний успешный бэкап {}",
+                            &[
+                                &config.backup_name.as_deref().unwrap_or("obt"),
+                                &config
+                                    .last_backup
+                                    .as_deref()
+                                    .map(timestamp::display)
+                                    .unwrap_or_else(|| t_in(language, "неизвестно").to_string()),
+                            ],
+                        ),
+                        run_id: None,
+                        attachments: Vec::new(),
+                    },
+                );
+            }
+            continue;
+        }
+
+        // Повторяем предупреждение не чаще одного раза за окно свежести
+        if last_alert.is_some_and(|t| now - t < window) {
+            continue;
+        }
+
+        warn!("Контроль свежести: нет успешного бэкапа уже {}", format_age(age, Language::Ru));
+        notify::send(
+            &config,
+            &Notification {
+                level: Level::Error,
+                title: t_in(language, "Бэкапы устарели").to_string(),
+                text: tf_in(
+                    language,
+                    "Сервер {}: нет успешного бэкапа уже {} (допустимо {} ч.). Последний успешный: {}. Проверьте obt.timer и obt.service.",
+                    &[
+                        &config.backup_name.as_deref().unwrap_or("obt"),
+                        &format_age(age, language),
+                        &window.num_hours(),
+                        &config
+                            .last_backup
+                            .as_deref()
+                            .map(timestamp::display)
+                            .unwrap_or_else(|| t_in(language, "никогда").to_string()),
+                    ],
+                ),
+                run_id: None,
+                attachments: Vec::new(),
+            },
+        );
+        last_alert = Some(now);
+    }
+}
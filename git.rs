@@ -0,0 +1,839 @@
+use crate::config::{Config, DivergencePolicy, GitSshConfig, NetworkConfig, SftpConfig};
+use crate::dest::{self, Check};
+use crate::network::{self, Failure};
+use crate::{divergence, forensics, progress, sftp, system, throttle, timestamp};
+use crate::storage::{self, Backend};
+use git2::build::CheckoutBuilder;
+use git2::cert::Cert;
+use git2::{
+    BranchType, CertificateCheckStatus, Cred, CredentialType, Direction, FetchOptions, IndexAddOption, Oid,
+    PushOptions, RemoteCallbacks, Repository, Signature,
+};
+use log::{info, warn};
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+const ATTEMPTS: u32 = 3;
+
+/// Тайм-ауты libgit2 общие для процесса и читаются ею на всём протяжении операции, поэтому
+/// сетевые операции git потоков демона с разными профилями идут по очереди под этой блокировкой
+static TIMEOUTS: Mutex<()> = Mutex::new(());
+
+/// Автор коммитов: `git_author` из config.json, иначе имя сервера и `obt@<имя сервера>`
+pub struct Author {
+    name: String,
+    email: String,
+}
+
+impl Author {
+    pub fn from_config(config: &Config) -> Self {
+        let host = nix::unistd::gethostname()
+            .ok()
+            .and_then(|host| host.into_string().ok())
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| "obt".to_string());
+        let author = config.git_author.clone().unwrap_or_default();
+        Author {
+            name: author.name.unwrap_or_else(|| host.clone()),
+            email: author.email.unwrap_or_else(|| format!("obt@{}", host)),
+        }
+    }
+
+    fn signature(&self) -> Result<Signature<'static>, git2::Error> {
+        Signature::now(&self.name, &self.email)
+    }
+}
+
+/// Адрес `ssh://user@host[:port]/path` из git_ssh
+struct SshRemote {
+    host: String,
+    port: Option<u16>,
+    username: String,
+    /// Путь к репозиторию на сервере; `ssh://host/~/repo.git` — относительно домашней папки
+    path: String,
+}
+
+impl SshRemote {
+    fn parse(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let invalid = || format!("Неверный адрес git_ssh {}: ожидается ssh://user@host/path/repo.git", url);
+        let parsed = url::Url::parse(url).map_err(|_| invalid())?;
+        if parsed.scheme() != "ssh" || parsed.username().is_empty() {
+            return Err(invalid().into());
+        }
+        let path = percent_encoding::percent_decode_str(parsed.path()).decode_utf8()?;
+        let path = path.strip_prefix("/~/").unwrap_or(&path).to_string();
+        if path.trim_matches('/').is_empty() {
+            return Err(invalid().into());
+        }
+        Ok(SshRemote {
+            host: parsed.host_str().ok_or_else(invalid)?.to_string(),
+            port: parsed.port(),
+            username: parsed.username().to_string(),
+            path,
+        })
+    }
+}
+
+/// Подключение по SSH к серверу из git_ssh — с той же проверкой ключа сервера, что и для SFTP
+fn ssh_session(git_ssh: &GitSshConfig) -> Result<(ssh2::Session, SshRemote), Box<dyn std::error::Error>> {
+    let remote = SshRemote::parse(&git_ssh.url)?;
+    let session = sftp::login(&SftpConfig {
+        host: remote.host.clone(),
+        port: remote.port,
+        username: remote.username.clone(),
+        password: None,
+        key_file: git_ssh.key_file.clone(),
+        path: remote.path.clone(),
+        host_key: git_ssh.host_key.clone(),
+        network: git_ssh.network.clone(),
+    }, "git_ssh.url")?;
+    Ok((session, remote))
+}
+
+/// Отпечаток ключа сервера из адреса репозитория для подтверждения при настройке
+pub fn host_fingerprint(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let remote = SshRemote::parse(url)?;
+    sftp::host_fingerprint(&remote.host, remote.port)
+}
+
+/// Проверяет, что на сервер из git_ssh можно войти
+pub fn check_ssh_access(git_ssh: &GitSshConfig) -> Result<(), Box<dyn std::error::Error>> {
+    ssh_session(git_ssh).map(|_| ())
+}
+
+/// Создаёт голый репозиторий на сервере, если его там ещё нет: кроме git и sshd
+/// на сервере ничего не нужно
+pub fn init_bare(git_ssh: &GitSshConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let (session, remote) = ssh_session(git_ssh)?;
+    let path = format!("'{}'", remote.path.replace('\'', "'\\''"));
+    let mut channel = session.channel_session()?;
+    channel.exec(&format!(
+        "test -d {0} || {{ git -c init.defaultBranch=main init --bare --quiet {0} && echo created; }}",
+        path
+    ))?;
+    let mut output = String::new();
+    channel.read_to_string(&mut output)?;
+    channel.stderr().read_to_string(&mut output)?;
+    channel.wait_close()?;
+    if channel.exit_status()? != 0 {
+        return Err(format!("Не удалось создать репозиторий {} на {}: {}", remote.path, remote.host, output.trim()).into());
+    }
+    if output.trim() == "created" {
+        println!("📁 Создан репозиторий {} на {}", remote.path, remote.host);
+        info!("Создан голый репозиторий {} на {}", remote.path, remote.host);
+    }
+    Ok(())
+}
+
+/// Логин и пароль (или токен) Gitea либо ключ SSH: передаются libgit2 при запросе, а не в URL или командной строке
+#[derive(Clone)]
+pub struct Credentials {
+    username: String,
+    password: String,
+    /// Вход по SSH вместо логина и пароля Gitea
+    ssh: Option<GitSshConfig>,
+    /// Параметр config.json с адресом сервера — для подсказки, если сервер переехал
+    setting: &'static str,
+    /// `gitea_network`, `git_ssh.network` или `replica.network`
+    network: NetworkConfig,
+}
+
+impl Credentials {
+    pub fn primary(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(git_ssh) = &config.git_ssh {
+            return Ok(Credentials {
+                username: SshRemote::parse(&git_ssh.url)?.username,
+                password: String::new(),
+                ssh: Some(git_ssh.clone()),
+                setting: "git_ssh.url",
+                network: git_ssh.network.clone().unwrap_or_default(),
+            });
+        }
+        Ok(Credentials {
+            username: config.gitea_username.clone().ok_or("Не настроен логин Gitea")?,
+            password: config.gitea_secret()?.as_str().to_string(),
+            ssh: None,
+            setting: "gitea_url",
+            network: config.gitea_network.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Вход в сторонний репозиторий (например, с политикой бэкапов): логин основного
+    /// хранилища передаётся только тому же серверу, остальным — никакого
+    pub fn for_url(config: &Config, url: &str, setting: &'static str) -> Self {
+        let same_host = config.repo_url().is_ok_and(|repo| network::host_of(&repo) == network::host_of(url));
+        match Credentials::primary(config) {
+            Ok(credentials) if same_host => Credentials { setting, ..credentials },
+            _ => Credentials {
+                username: String::new(),
+                password: String::new(),
+                ssh: None,
+                setting,
+                network: NetworkConfig::default(),
+            },
+        }
+    }
+
+    fn callbacks(&self) -> RemoteCallbacks<'_> {
+        let mut callbacks = RemoteCallbacks::new();
+        // libgit2 повторяет запрос после отказа сервера — неверный пароль не должен зацикливать
+        let asked = Cell::new(false);
+        callbacks.credentials(move |_url, _username, allowed| {
+            // По SSH libgit2 сначала отдельно спрашивает имя пользователя
+            if allowed.contains(CredentialType::USERNAME) {
+                return Cred::username(&self.username);
+            }
+            if asked.replace(true) {
+                let rejected = if self.ssh.is_some() { "Сервер отклонил ключ SSH" } else { "Gitea отклонил логин, пароль или токен" };
+                return Err(git2::Error::from_str(rejected));
+            }
+            match &self.ssh {
+                Some(GitSshConfig { key_file: Some(key_file), .. }) => Cred::ssh_key(&self.username, None, key_file, None),
+                Some(_) => Cred::ssh_key_from_agent(&self.username),
+                None => Cred::userpass_plaintext(&self.username, &self.password),
+            }
+        });
+        // Без host_key ключ сервера проверяет сама libgit2 по ~/.ssh/known_hosts
+        if let Some(expected) = self.ssh.as_ref().and_then(|ssh| ssh.host_key.as_deref()) {
+            callbacks.certificate_check(move |cert, host| check_host_key(cert, host, expected));
+        }
+        callbacks
+    }
+
+    /// Задаёт тайм-ауты подключения и обмена данными и возвращает блокировку TIMEOUTS: пока она
+    /// держится, другие профили не перезапишут их посреди операции
+    fn apply_timeouts(&self) -> Result<MutexGuard<'static, ()>, git2::Error> {
+        let millis = |timeout: std::time::Duration| timeout.as_millis().min(i32::MAX as u128) as i32;
+        let guard = TIMEOUTS.lock().unwrap_or_else(|e| e.into_inner());
+        // SAFETY: глобальные настройки libgit2 меняются только здесь под TIMEOUTS, а вызывающий
+        // держит блокировку до конца сетевой операции, которая их читает
+        unsafe {
+            git2::opts::set_server_connect_timeout_in_milliseconds(millis(self.network.connect_timeout()))?;
+            git2::opts::set_server_timeout_in_milliseconds(millis(self.network.timeout()))?;
+        }
+        Ok(guard)
+    }
+
+    /// `slow` получает причину, если передача оборвана как слишком медленная (`low_speed_kbps`)
+    fn fetch_options<'a>(&'a self, slow: &'a RefCell<Option<String>>) -> FetchOptions<'a> {
+        let mut callbacks = self.callbacks();
+        if let Some(mut low_speed) = throttle::LowSpeed::new(&self.network) {
+            let mut received = 0;
+            callbacks.transfer_progress(move |progress| {
+                let bytes = progress.received_bytes() as u64;
+                let result = low_speed.transferred(bytes.saturating_sub(received));
+                received = bytes;
+                match result {
+                    Ok(()) => true,
+                    Err(e) => {
+                        *slow.borrow_mut() = Some(e);
+                        false
+                    }
+                }
+            });
+        }
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks);
+        options
+    }
+}
+
+fn check_host_key(cert: &Cert<'_>, host: &str, expected: &str) -> Result<CertificateCheckStatus, git2::Error> {
+    use base64::prelude::{Engine, BASE64_STANDARD_NO_PAD};
+    let hash = cert
+        .as_hostkey()
+        .and_then(|key| key.hash_sha256())
+        .ok_or_else(|| git2::Error::from_str(&format!("Сервер {} не передал ключ", host)))?;
+    let actual = format!("SHA256:{}", BASE64_STANDARD_NO_PAD.encode(hash));
+    if actual != expected {
+        return Err(git2::Error::from_str(&format!(
+            "Ключ сервера {} не совпадает с host_key: {} (ожидался {})",
+            host, actual, expected
+        )));
+    }
+    Ok(CertificateCheckStatus::CertificateOk)
+}
+
+/// Повторяет сетевую операцию с паузой. Сбои, за которыми
+/// обычно стоит смена адреса или сертификата сервера, вслепую не повторяются, а объясняются
+/// с подсказкой, какой параметр поправить
+fn with_retry<T>(
+    what: &str,
+    url: &str,
+    credentials: &Credentials,
+    operation: impl FnMut() -> Result<T, git2::Error>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    retry_on(&system::SystemClock, what, url, credentials, operation)
+}
+
+/// `with_retry` с заданными часами для пауз между попытками
+fn retry_on<T>(
+    clock: &dyn system::Clock,
+    what: &str,
+    url: &str,
+    credentials: &Credentials,
+    mut operation: impl FnMut() -> Result<T, git2::Error>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let mut attempt = 1;
+    loop {
+        let guard = credentials.apply_timeouts()?;
+        let result = operation();
+        // Пауза перед повтором не должна задерживать операции других профилей
+        drop(guard);
+        let e = match result {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        let failure = Failure::classify(e.message());
+        if attempt < ATTEMPTS && failure.is_none_or(Failure::retriable) {
+            warn!("Попытка {} из {} не удалась ({}): {}", attempt, ATTEMPTS, what, e.message());
+            println!("Попытка {} не удалась, повтор через 5 сек...", attempt);
+            clock.sleep(std::time::Duration::from_secs(5));
+            attempt += 1;
+            continue;
+        }
+        return Err(match failure {
+            Some(failure) => failure.explain(&network::host_of(url), credentials.setting, e.message()),
+            None => format!("Ошибка git ({}): {}", what, e.message()),
+        }
+        .into());
+    }
+}
+
+/// Забирает ветки удалённого репозитория по refspec
+pub fn fetch(repo: &Repository, remote: &str, credentials: &Credentials, refspecs: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut remote = repo.find_remote(remote)?;
+    let url = remote.url().unwrap_or_default().to_string();
+    let slow = RefCell::new(None);
+    with_retry(&format!("fetch {}", remote.name().unwrap_or_default()), &url, credentials, || {
+        remote
+            .fetch(refspecs, Some(&mut credentials.fetch_options(&slow)), None)
+            .map_err(|e| match slow.borrow_mut().take() {
+                Some(reason) => git2::Error::from_str(&reason),
+                None => e,
+            })
+    })
+}
+
+/// Отправляет ветки не быстрее `upload_limit_kbps`; отказ сервера принять ветку
+/// (не fast-forward, защита) — ошибка
+pub fn push(
+    repo: &Repository,
+    remote: &str,
+    credentials: &Credentials,
+    refspecs: &[String],
+    upload_limit_kbps: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut remote = repo.find_remote(remote)?;
+    let url = remote.url().unwrap_or_default().to_string();
+    let rejected = RefCell::new(Vec::new());
+    with_retry(&format!("push {}", remote.name().unwrap_or_default()), &url, credentials, || {
+        rejected.borrow_mut().clear();
+        let mut callbacks = credentials.callbacks();
+        callbacks.push_update_reference(|reference, status| {
+            if let Some(status) = status {
+                rejected.borrow_mut().push(format!("{}: {}", reference, status));
+            }
+            Ok(())
+        });
+        // libgit2 передаёт пакет из этого же потока, так что пауза в отчёте о ходе отправки
+        // придерживает саму отправку
+        let mut throttle = throttle::Throttle::new(upload_limit_kbps);
+        let mut sent = 0;
+        callbacks.push_transfer_progress(move |_, _, bytes| {
+            progress::uploaded((bytes as u64).saturating_sub(sent));
+            sent = bytes as u64;
+            if let Some(throttle) = &mut throttle {
+                throttle.sent_total(bytes as u64);
+            }
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote.push(refspecs, Some(&mut options))
+    })?;
+
+    let rejected = rejected.into_inner();
+    if !rejected.is_empty() {
+        return Err(format!("Сервер отклонил push: {}", rejected.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// Ветки основного репозитория и коммиты, на которые они указывают, без скачивания содержимого
+pub fn remote_heads(config: &Config) -> Result<String, Box<dyn std::error::Error>> {
+    let credentials = Credentials::primary(config)?;
+    let url = config.repo_url()?;
+    let mut remote = git2::Remote::create_detached(url.as_str())?;
+    let heads: Vec<String> = with_retry("ls-remote origin", &url, &credentials, || {
+        let connection = remote.connect_auth(Direction::Fetch, Some(credentials.callbacks()), None)?;
+        Ok(connection.list()?.iter().map(|head| format!("{} {}", head.oid(), head.name())).collect())
+    })?;
+    Ok(heads.join("\n"))
+}
+
+/// Файл `path` из ветки `branch` (по умолчанию main, а если её нет — master) репозитория `url`
+/// и коммит, из которого он прочитан. Скачивается только история, без рабочей копии
+pub fn read_file(
+    url: &str,
+    branch: Option<&str>,
+    path: &str,
+    credentials: &Credentials,
+    dir: &str,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let repo = Repository::init_bare(dir)?;
+    repo.remote("origin", url)?;
+    fetch(&repo, "origin", credentials, &["+refs/heads/*:refs/remotes/origin/*"])?;
+
+    let branch = match branch {
+        Some(branch) => branch,
+        None if repo.find_branch("origin/main", BranchType::Remote).is_ok() => "main",
+        None => "master",
+    };
+    let commit = repo
+        .find_branch(&format!("origin/{}", branch), BranchType::Remote)
+        .map_err(|_| format!("В репозитории {} нет ветки {}", url, branch))?
+        .get()
+        .peel_to_commit()?;
+    let entry = commit
+        .tree()?
+        .get_path(Path::new(path))
+        .map_err(|_| format!("В репозитории {} нет файла {} (ветка {})", url, path, branch))?;
+    let blob = repo.find_blob(entry.id())?;
+    let content = String::from_utf8(blob.content().to_vec()).map_err(|_| format!("{} не является текстовым файлом", path))?;
+    Ok((content, commit.id().to_string()))
+}
+
+/// `obt dest check`: отправляет во временную ветку `name` коммит с пробным файлом, читает
+/// его с сервера и удаляет ветку. Ветки с бэкапами при этом не затрагиваются
+pub fn probe(
+    url: &str,
+    credentials: &Credentials,
+    author: &Author,
+    dir: &str,
+    name: &str,
+    check: &mut Check,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let repo = Repository::init_bare(dir)?;
+    repo.remote("origin", url)?;
+    check.step("подключение", || {
+        let mut remote = repo.find_remote("origin")?;
+        with_retry("ls-remote origin", url, credentials, || {
+            remote.connect_auth(Direction::Fetch, Some(credentials.callbacks()), None).map(|_| ())
+        })
+    })?;
+
+    let content = dest::probe_content(name);
+    let mut tree = repo.treebuilder(None)?;
+    tree.insert("probe.txt", repo.blob(content.as_bytes())?, 0o100644)?;
+    let tree = repo.find_tree(tree.write()?)?;
+    let signature = author.signature()?;
+    let commit = repo.commit(None, &signature, &signature, "Проверка хранилища", &tree, &[])?;
+    let branch = format!("refs/heads/{}", name);
+    repo.reference(&branch, commit, true, "obt dest check")?;
+
+    check.step("запись", || push(&repo, "origin", credentials, &[format!("{0}:{0}", branch)], None))?;
+    check.step("чтение", || {
+        fetch(&repo, "origin", credentials, &[&format!("+{}:refs/probe/read", branch)])?;
+        let read = repo.find_reference("refs/probe/read")?.peel_to_commit()?;
+        let entry = read.tree()?.get_name("probe.txt").ok_or("в прочитанной ветке нет пробного файла")?.id();
+        if repo.find_blob(entry)?.content() != content.as_bytes() {
+            return Err("прочитанный файл отличается от записанного".into());
+        }
+        Ok(())
+    })?;
+    check.step("удаление", || push(&repo, "origin", credentials, &[format!(":{}", branch)], None))
+}
+
+/// Рабочая копия репозитория бэкапов во временной папке
+pub struct Workspace {
+    repo: Repository,
+    credentials: Credentials,
+    author: Author,
+    branch: String,
+    /// Коммит удалённой ветки, на котором стоит рабочая копия: следующий fetch
+    /// должен найти его или его потомка, иначе историю переписали
+    base: Cell<Option<Oid>>,
+    policy: Option<DivergencePolicy>,
+    server: String,
+    upload_limit_kbps: Option<u64>,
+    /// Ветка, куда после расхождения уходят все оставшиеся части бэкапа
+    side_branch: RefCell<Option<String>>,
+    /// Коммитить только метаданные: архивы лежат в реестре пакетов Gitea (`gitea_archives`)
+    metadata_only: bool,
+}
+
+impl Workspace {
+    /// Создаёт рабочую копию в `dir` и переключает её на актуальное состояние основной ветки
+    pub fn open(config: &Config, dir: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let credentials = Credentials::primary(config)?;
+        let repo = Repository::init(dir)?;
+        let url = config.repo_url()?;
+        repo.remote("origin", &url)?;
+
+        // Определяем ветку: main, если она есть (или репозиторий пуст), иначе master
+        let mut remote = repo.find_remote("origin")?;
+        let heads: Vec<String> = with_retry("ls-remote origin", &url, &credentials, || {
+            let connection = remote.connect_auth(Direction::Fetch, Some(credentials.callbacks()), None)?;
+            Ok(connection.list()?.iter().map(|head| head.name().to_string()).collect())
+        })?;
+        drop(remote);
+        let branch = if heads.iter().any(|h| h == "refs/heads/master") && !heads.iter().any(|h| h == "refs/heads/main") {
+            "master"
+        } else {
+            "main"
+        };
+        info!("Используем ветку: {}", branch);
+
+        let workspace = Workspace {
+            repo,
+            credentials,
+            author: Author::from_config(config),
+            branch: branch.to_string(),
+            base: Cell::new(None),
+            policy: divergence::policy(config),
+            server: config.backup_name.clone().unwrap_or_else(|| "obt".to_string()),
+            upload_limit_kbps: config.upload_limit_kbps,
+            side_branch: RefCell::new(None),
+            metadata_only: config.archives_in_packages(),
+        };
+
+        println!("🔄 Синхронизация с удаленным репозиторием...");
+        workspace.fetch()?;
+        match workspace.remote_commit()? {
+            Some(oid) => {
+                workspace.base.set(Some(oid));
+                let commit = workspace.repo.find_commit(oid)?;
+                workspace.repo.branch(branch, &commit, true)?;
+                workspace.repo.set_head(&format!("refs/heads/{}", branch))?;
+                workspace.repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+            }
+            // Пустой репозиторий: ветка появится с первым коммитом
+            None => workspace.repo.set_head(&format!("refs/heads/{}", branch))?,
+        }
+        Ok(workspace)
+    }
+
+    fn fetch(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let refspec = format!("+refs/heads/{0}:refs/remotes/origin/{0}", self.branch);
+        fetch(&self.repo, "origin", &self.credentials, &[&refspec])
+    }
+
+    fn remote_commit(&self) -> Result<Option<Oid>, Box<dyn std::error::Error>> {
+        match self.repo.find_branch(&format!("origin/{}", self.branch), BranchType::Remote) {
+            Ok(branch) => Ok(branch.get().target()),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn workdir(&self) -> Result<&Path, Box<dyn std::error::Error>> {
+        Ok(self.repo.workdir().ok_or("Репозиторий без рабочей копии")?)
+    }
+
+    fn signature(&self) -> Result<Signature<'static>, git2::Error> {
+        self.author.signature()
+    }
+
+    /// Коммитит всё содержимое рабочей копии и отправляет в удалённый репозиторий
+    pub fn commit_and_push(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut index = self.repo.index()?;
+        let metadata_only = self.metadata_only;
+        let mut skip_archives =
+            |path: &Path, _: &[u8]| i32::from(metadata_only && !storage::is_metadata(&path.to_string_lossy()));
+        index.add_all(["*"], IndexAddOption::DEFAULT, Some(&mut skip_archives))?;
+        // add_all не замечает удалённые папки (бэкапы по политике хранения)
+        index.update_all(["*"], None)?;
+        index.write()?;
+        self.commit_index(&mut index, message)?;
+        self.sync_and_push()
+    }
+
+    /// Коммитит только указанные файлы (пути относительно рабочей копии) и отправляет их
+    pub fn commit_paths_and_push(&self, files: &[String], message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut index = self.repo.index()?;
+        for file in files.iter().filter(|file| !self.metadata_only || storage::is_metadata(file)) {
+            index.add_path(Path::new(file))?;
+        }
+        index.write()?;
+        self.commit_index(&mut index, message)?;
+        self.sync_and_push()
+    }
+
+    fn commit_index(&self, index: &mut git2::Index, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        let parent = match self.repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(e) => return Err(e.into()),
+        };
+        if parent.as_ref().is_some_and(|p| p.tree_id() == tree.id()) {
+            info!("Нет изменений для коммита: {}", message);
+            return Ok(());
+        }
+
+        // Трейлер связывает коммит с запуском бэкапа из уведомления
+        let message = match forensics::run_id() {
+            Some(run_id) => format!("{}\n\nObt-Run-Id: {}", message, run_id),
+            None => message.to_string(),
+        };
+        let signature = self.signature()?;
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        self.repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents)?;
+        info!("Коммит: {}", message);
+        Ok(())
+    }
+
+    /// Подтягивает коммиты, появившиеся в удалённой ветке (бэкапы других серверов), и отправляет свою
+    fn sync_and_push(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(side_branch) = self.side_branch.borrow().as_deref() {
+            return self.push_to(side_branch);
+        }
+        self.fetch()?;
+        if let Some(remote) = self.remote_commit()? {
+            if let Some(base) = self.base.get() {
+                if remote != base && !self.repo.graph_descendant_of(remote, base)? {
+                    return self.diverged(base, remote);
+                }
+            }
+            self.merge(remote)?;
+        }
+        self.push_to(&self.branch)?;
+        self.base.set(self.repo.head()?.target());
+        Ok(())
+    }
+
+    fn push_to(&self, target: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let refspec = format!("refs/heads/{}:refs/heads/{}", self.branch, target);
+        push(&self.repo, "origin", &self.credentials, &[refspec], self.upload_limit_kbps)
+    }
+
+    /// Удалённая ветка больше не содержит `base`: её переписали, и слияние молча
+    /// смешало бы две истории. Решение берётся из политики или у администратора и записывается.
+    fn diverged(&self, base: Oid, remote: Oid) -> Result<(), Box<dyn std::error::Error>> {
+        let (expected, found) = (base.to_string(), remote.to_string());
+        let policy = match self.policy {
+            Some(policy) => policy,
+            None => divergence::ask(&self.branch, &expected, &found)?,
+        };
+        match policy {
+            DivergencePolicy::Abort => {
+                divergence::record(&self.branch, &expected, &found, policy, None);
+                Err(format!(
+                    "История ветки {} в хранилище переписана (ожидался потомок {}, найден {}) — загрузка отменена. \
+                     Повторите с --on-divergence rebase или branch либо задайте on_divergence в config.json",
+                    self.branch, expected, found
+                )
+                .into())
+            }
+            DivergencePolicy::Rebase => {
+                self.rebase(base, remote)?;
+                divergence::record(&self.branch, &expected, &found, policy, None);
+                println!("🔀 Коммиты бэкапа перенесены поверх новой истории origin/{}", self.branch);
+                self.push_to(&self.branch)?;
+                self.base.set(self.repo.head()?.target());
+                Ok(())
+            }
+            DivergencePolicy::Branch => {
+                let side_branch = format!("obt/{}-{}", self.server, timestamp::now().format("%Y%m%d_%H%M%S"));
+                self.push_to(&side_branch)?;
+                divergence::record(&self.branch, &expected, &found, policy, Some(&side_branch));
+                println!("🌿 Бэкап отправлен в отдельную ветку {}", side_branch);
+                *self.side_branch.borrow_mut() = Some(side_branch);
+                Ok(())
+            }
+        }
+    }
+
+    /// Переносит коммиты после `base` на `remote`; при конфликте ветка остаётся как была.
+    /// Перенос идёт в памяти: в рабочей копии лежат ещё не загруженные части бэкапа
+    fn rebase(&self, base: Oid, remote: Oid) -> Result<(), Box<dyn std::error::Error>> {
+        let head = self.repo.reference_to_annotated_commit(&self.repo.head()?)?;
+        let upstream = self.repo.find_annotated_commit(base)?;
+        let onto = self.repo.find_annotated_commit(remote)?;
+        let mut options = git2::RebaseOptions::new();
+        options.inmemory(true);
+        let mut rebase = self.repo.rebase(Some(&head), Some(&upstream), Some(&onto), Some(&mut options))?;
+        let signature = self.signature()?;
+        let mut rebased = remote;
+        while let Some(operation) = rebase.next() {
+            operation?;
+            if rebase.inmemory_index()?.has_conflicts() {
+                rebase.abort()?;
+                return Err(format!(
+                    "Не удалось перенести коммиты бэкапа поверх origin/{}: конфликт. \
+                     Повторите с --on-divergence branch",
+                    self.branch
+                )
+                .into());
+            }
+            match rebase.commit(None, &signature, None) {
+                Ok(oid) => rebased = oid,
+                // Коммит уже есть в новой истории
+                Err(e) if e.code() == git2::ErrorCode::Applied => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        rebase.finish(Some(&signature))?;
+
+        let commit = self.repo.find_commit(rebased)?;
+        self.repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))?;
+        self.repo
+            .reference(&format!("refs/heads/{}", self.branch), rebased, true, "rebase")?;
+        Ok(())
+    }
+
+    fn merge(&self, remote: Oid) -> Result<(), Box<dyn std::error::Error>> {
+        let head = self.repo.head()?.peel_to_commit()?;
+        if head.id() == remote || self.repo.graph_descendant_of(head.id(), remote)? {
+            return Ok(());
+        }
+
+        // Рабочая копия обновляется до переноса HEAD: checkout сравнивает с текущим HEAD,
+        // а файлы, ещё не попавшие в коммит (следующие части бэкапа), остаются на месте
+        if self.repo.graph_descendant_of(remote, head.id())? {
+            let theirs = self.repo.find_commit(remote)?;
+            self.repo.checkout_tree(theirs.as_object(), Some(CheckoutBuilder::new().safe()))?;
+            self.repo
+                .reference(&format!("refs/heads/{}", self.branch), remote, true, "fast-forward")?;
+        } else {
+            let theirs = self.repo.find_commit(remote)?;
+            let mut merged = self.repo.merge_commits(&head, &theirs, None)?;
+            if merged.has_conflicts() {
+                return Err(format!("Конфликт при слиянии с origin/{}", self.branch).into());
+            }
+            let tree = self.repo.find_tree(merged.write_tree_to(&self.repo)?)?;
+            self.repo.checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))?;
+            let signature = self.signature()?;
+            self.repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &format!("Merge branch '{}' of origin", self.branch),
+                &tree,
+                &[&head, &theirs],
+            )?;
+        }
+        info!("Рабочая копия синхронизирована с origin/{}", self.branch);
+        Ok(())
+    }
+}
+
+impl Backend for Workspace {
+    fn location(&self) -> String {
+        self.side_branch.borrow().clone().unwrap_or_else(|| self.branch.clone())
+    }
+
+    fn describe(&self) -> String {
+        match &self.credentials.ssh {
+            Some(git_ssh) => format!("Git по SSH, {}, ветка {}", git_ssh.url, self.branch),
+            None => format!("Gitea, ветка {}", self.branch),
+        }
+    }
+
+    fn download(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn download_backup(&self, _name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn backups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(storage::backup_folders(&storage::local_files(self.workdir()?)?))
+    }
+
+    fn contains(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.workdir()?.join(name).exists())
+    }
+
+    fn remove(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let dir = self.workdir()?.join(name);
+        if !dir.exists() {
+            return Ok(false);
+        }
+        fs::remove_dir_all(dir)?;
+        Ok(true)
+    }
+
+    fn upload_paths(&self, files: &[String], message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.commit_paths_and_push(files, message)
+    }
+
+    fn upload_all(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.commit_and_push(message)
+    }
+
+    fn revision(&self) -> Option<String> {
+        self.repo.head().ok()?.target().map(|oid| oid.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fake::ManualClock;
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    fn credentials() -> Credentials {
+        Credentials {
+            username: "backup".to_string(),
+            password: String::new(),
+            ssh: None,
+            setting: "gitea_url",
+            network: NetworkConfig::default(),
+        }
+    }
+
+    fn clock() -> ManualClock {
+        ManualClock::new(chrono::Utc.with_ymd_and_hms(2024, 5, 10, 3, 0, 0).unwrap())
+    }
+
+    #[test]
+    fn retries_unreachable_server() {
+        let clock = clock();
+        let mut attempts = 0;
+        let result: Result<(), _> = retry_on(&clock, "push origin", "https://git.example.com/backup.git", &credentials(), || {
+            attempts += 1;
+            Err(git2::Error::from_str("failed to connect to git.example.com: Connection refused"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, ATTEMPTS);
+        assert_eq!(clock.slept(), vec![Duration::from_secs(5); ATTEMPTS as usize - 1]);
+    }
+
+    #[test]
+    fn succeeds_after_retry() {
+        let clock = clock();
+        let mut attempts = 0;
+        let result = retry_on(&clock, "fetch origin", "https://git.example.com/backup.git", &credentials(), || {
+            attempts += 1;
+            match attempts {
+                1 => Err(git2::Error::from_str("operation timed out")),
+                _ => Ok(attempts),
+            }
+        });
+
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(clock.slept(), vec![Duration::from_secs(5)]);
+    }
+
+    #[test]
+    fn does_not_retry_rejected_credentials() {
+        let clock = clock();
+        let mut attempts = 0;
+        let result: Result<(), _> = retry_on(&clock, "push origin", "https://git.example.com/backup.git", &credentials(), || {
+            attempts += 1;
+            Err(git2::Error::from_str("unexpected http status code: 401"))
+        });
+
+        let error = result.unwrap_err().to_string();
+        assert_eq!(attempts, 1);
+        assert!(clock.slept().is_empty());
+        assert!(error.contains("git.example.com"), "{}", error);
+    }
+}
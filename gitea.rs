@@ -0,0 +1,381 @@
+use crate::config::{Config, GiteaSecret, NetworkConfig};
+use crate::git::Workspace;
+use crate::network::{self, Failure};
+use crate::storage::{self, Backend};
+use crate::{restore, throttle, timestamp};
+use crate::BackupSummary;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use log::info;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+
+/// Тема, по которой репозитории бэкапов находятся в поиске Gitea
+const BACKUP_TOPIC: &str = "obt-backup";
+
+#[derive(Deserialize)]
+struct Topics {
+    topics: Vec<String>,
+}
+
+/// Тема Gitea: строчные латинские буквы, цифры и дефисы, не длиннее 35 символов
+fn topic(name: &str) -> String {
+    let topic: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    topic.trim_matches('-').chars().take(35).collect()
+}
+
+struct Api {
+    host: String,
+    /// `https://<gitea_url>`
+    root: String,
+    /// `/api/v1/repos/<владелец>/<репозиторий>`
+    repo_path: String,
+    authorization: String,
+    /// `gitea_network`
+    network: NetworkConfig,
+    agent: ureq::Agent,
+}
+
+impl Api {
+    fn new(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let authorization = match config.gitea_secret()? {
+            GiteaSecret::Token(token) => format!("token {}", token),
+            GiteaSecret::Password(password) => {
+                let username = config.gitea_username.as_ref().ok_or("Не настроен логин Gitea")?;
+                format!("Basic {}", BASE64_STANDARD.encode(format!("{}:{}", username, password)))
+            }
+        };
+        let url = config.gitea_url.as_ref().ok_or("Не настроен URL Gitea")?;
+        let root = format!("https://{}", url);
+        let network = config.gitea_network.clone().unwrap_or_default();
+        Ok(Api {
+            host: network::host_of(url),
+            repo_path: format!(
+                "/api/v1/repos/{}",
+                config.gitea_repo.as_ref().ok_or("Не настроен репозиторий Gitea")?
+            ),
+            root,
+            authorization,
+            agent: network::agent(&network),
+            network,
+        })
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        self.root_request(method, &format!("{}{}", self.repo_path, path))
+    }
+
+    /// Запрос к API вне репозитория: `/api/v1/user` и т.п.
+    fn root_request(&self, method: &str, path: &str) -> ureq::Request {
+        self.agent
+            .request(method, &format!("{}{}", self.root, path))
+            .set("Authorization", &self.authorization)
+    }
+
+    /// Ошибка запроса; смена адреса или сертификата сервера и отказ в доступе объясняются с подсказкой
+    fn error(&self, what: &str, e: ureq::Error) -> String {
+        let detail = e.to_string();
+        let failure = match &e {
+            ureq::Error::Status(code, _) => Failure::from_status(*code),
+            ureq::Error::Transport(_) => Failure::classify(&detail),
+        };
+        match failure {
+            Some(failure) => failure.explain(&self.host, "gitea_url", &detail),
+            None => format!("{}: {}", what, detail),
+        }
+    }
+}
+
+/// Проверяет, что репозиторий доступен с настроенными логином и паролем или токеном
+pub fn check_access(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let api = Api::new(config)?;
+    api.request("GET", "")
+        .call()
+        .map_err(|e| api.error("Репозиторий Gitea недоступен", e))?;
+    Ok(())
+}
+
+/// Есть ли репозиторий бэкапов; `false` — Gitea ответил 404 (его нет или он не виден пользователю)
+pub fn repo_exists(config: &Config) -> Result<bool, Box<dyn std::error::Error>> {
+    let api = Api::new(config)?;
+    match api.request("GET", "").call() {
+        Ok(_) => Ok(true),
+        Err(ureq::Error::Status(404, _)) => Ok(false),
+        Err(e) => Err(api.error("Репозиторий Gitea недоступен", e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+/// Создаёт закрытый репозиторий бэкапов с веткой main: у самого пользователя
+/// или в организации, если репозиторий указан в ней
+pub fn create_repo(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let api = Api::new(config)?;
+    let repo = config.gitea_repo.as_ref().ok_or("Не настроен репозиторий Gitea")?;
+    let (owner, name) = repo.split_once('/').ok_or_else(|| format!("Неверный репозиторий Gitea: {}", repo))?;
+    let user: User = api
+        .root_request("GET", "/api/v1/user")
+        .call()
+        .map_err(|e| api.error("Не удалось получить пользователя Gitea", e))?
+        .into_json()?;
+    let path = if user.login.eq_ignore_ascii_case(owner) {
+        "/api/v1/user/repos".to_string()
+    } else {
+        format!("/api/v1/orgs/{}/repos", owner)
+    };
+    api.root_request("POST", &path)
+        .send_json(serde_json::json!({
+            "name": name,
+            "private": true,
+            "description": "Бэкапы OfficialVPN Backup Tool",
+            "default_branch": "main",
+        }))
+        .map_err(|e| api.error(&format!("Не удалось создать репозиторий {}", repo), e))?;
+    info!("Создан репозиторий Gitea {}", repo);
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    /// Размер в КБ по подсчёту Gitea
+    size: u64,
+}
+
+/// Сколько занимает репозиторий бэкапов в Gitea, КБ
+pub fn repo_size(config: &Config) -> Result<u64, Box<dyn std::error::Error>> {
+    let api = Api::new(config)?;
+    let repository: Repository = api
+        .request("GET", "")
+        .call()
+        .map_err(|e| api.error("Репозиторий Gitea недоступен", e))?
+        .into_json()?;
+    Ok(repository.size)
+}
+
+/// Обновляет описание и темы репозитория в Gitea после успешного бэкапа, чтобы
+/// свежесть бэкапов была видна прямо в интерфейсе. Отключается `"repo_status": false`.
+pub fn update_repo_status(config: &Config, summary: &BackupSummary) -> Result<(), Box<dyn std::error::Error>> {
+    if !config.uses_gitea() || config.repo_status == Some(false) {
+        return Ok(());
+    }
+
+    let api = Api::new(config)?;
+    let server = config.backup_name.as_deref().unwrap_or("obt");
+    let description = format!(
+        "🕒 Последний бэкап: {} · сервер {} · {} архивов, {:.1} МБ",
+        config.last_backup.as_deref().map(timestamp::display).unwrap_or_else(|| "неизвестно".to_string()),
+        server,
+        summary.archives,
+        summary.total_size as f64 / 1_048_576.0
+    );
+    api.request("PATCH", "")
+        .send_json(serde_json::json!({ "description": description }))
+        .map_err(|e| api.error("Не удалось обновить описание репозитория в Gitea", e))?;
+
+    // Темы других серверов и выставленные вручную сохраняются
+    let mut topics = api
+        .request("GET", "/topics")
+        .call()
+        .map_err(|e| api.error("Не удалось получить темы репозитория из Gitea", e))?
+        .into_json::<Topics>()?
+        .topics;
+    let mut changed = false;
+    for wanted in [BACKUP_TOPIC.to_string(), topic(&format!("obt-{}", server))] {
+        if !topics.contains(&wanted) {
+            topics.push(wanted);
+            changed = true;
+        }
+    }
+    if changed {
+        api.request("PUT", "/topics")
+            .send_json(serde_json::json!({ "topics": topics }))
+            .map_err(|e| api.error("Не удалось обновить темы репозитория в Gitea", e))?;
+    }
+
+    info!("Описание репозитория в Gitea обновлено: {}", description);
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct PackageFile {
+    name: String,
+}
+
+/// Версия пакета и имя файла для архива `<папка бэкапа>/<файл>`: версия — имя бэкапа
+/// без папки месяца, ведь `/` в версии пакета недопустим
+fn package_path(file: &str) -> Option<(&str, &str)> {
+    let (folder, name) = file.rsplit_once('/')?;
+    Some((folder.rsplit('/').next().unwrap_or(folder), name))
+}
+
+fn encode(segment: &str) -> String {
+    utf8_percent_encode(segment, NON_ALPHANUMERIC).to_string()
+}
+
+/// Gitea с архивами в реестре пакетов (`"gitea_archives": "Packages"`): каждый бэкап —
+/// версия generic-пакета с именем репозитория, а рабочая копия коммитит только метаданные
+pub struct Packages {
+    workspace: Workspace,
+    api: Api,
+    dir: PathBuf,
+    /// `<владелец>/generic/<пакет>`
+    package: String,
+    upload_limit_kbps: Option<u64>,
+    /// Архивы, которые уже есть в реестре (загружены или скачаны этим процессом)
+    uploaded: RefCell<HashSet<String>>,
+    /// Версии удалённых бэкапов: удаляются из реестра при `upload_all`
+    removed: RefCell<Vec<String>>,
+}
+
+impl Packages {
+    pub fn new(config: &Config, workspace: Workspace, dir: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let repo = config.gitea_repo.as_ref().ok_or("Не настроен репозиторий Gitea")?;
+        let (owner, name) = repo.split_once('/').ok_or_else(|| format!("Неверный репозиторий Gitea: {}", repo))?;
+        Ok(Packages {
+            workspace,
+            api: Api::new(config)?,
+            dir: PathBuf::from(dir),
+            package: format!("{}/generic/{}", encode(owner), encode(name)),
+            upload_limit_kbps: config.upload_limit_kbps,
+            uploaded: RefCell::new(HashSet::new()),
+            removed: RefCell::new(Vec::new()),
+        })
+    }
+
+    fn url(&self, version: &str, file: Option<&str>) -> String {
+        let mut url = format!("{}/api/packages/{}/{}", self.api.root, self.package, encode(version));
+        if let Some(file) = file {
+            url.push('/');
+            url.push_str(&encode(file));
+        }
+        url
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        self.api.agent.request(method, url).set("Authorization", &self.api.authorization)
+    }
+
+    fn put(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (version, name) = package_path(relative).ok_or_else(|| format!("Архив вне папки бэкапа: {}", relative))?;
+        let path = self.dir.join(relative);
+        let size = fs::metadata(&path)?.len();
+        self.request("PUT", &self.url(version, Some(name)))
+            .set("Content-Length", &size.to_string())
+            .send(throttle::Reader::new(File::open(&path)?, self.upload_limit_kbps).abort_below(&self.api.network))
+            .map_err(|e| self.api.error(&format!("Не удалось загрузить {} в реестр пакетов Gitea", relative), e))?;
+        Ok(())
+    }
+
+    /// Файлы версии в реестре; у бэкапов, сделанных до `gitea_archives`, версии нет
+    fn files(&self, version: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let url = format!("{}/api/v1/packages/{}/{}/files", self.api.root, self.package, encode(version));
+        match self.request("GET", &url).call() {
+            Ok(response) => Ok(response.into_json::<Vec<PackageFile>>()?.into_iter().map(|f| f.name).collect()),
+            Err(ureq::Error::Status(404, _)) => Ok(Vec::new()),
+            Err(e) => Err(self.api.error("Не удалось получить список файлов из реестра пакетов Gitea", e).into()),
+        }
+    }
+}
+
+impl Backend for Packages {
+    fn location(&self) -> String {
+        self.workspace.location()
+    }
+
+    fn describe(&self) -> String {
+        format!("{}, архивы в реестре пакетов ({})", self.workspace.describe(), self.package)
+    }
+
+    fn download(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for snapshot in restore::available_backups(&self.dir)? {
+            self.download_backup(&snapshot.path())?;
+        }
+        Ok(())
+    }
+
+    fn download_backup(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.workspace.download_backup(name)?;
+        let version = name.rsplit('/').next().unwrap_or(name);
+        for file in self.files(version)? {
+            let relative = format!("{}/{}", name, file);
+            let response = self
+                .request("GET", &self.url(version, Some(&file)))
+                .call()
+                .map_err(|e| self.api.error(&format!("Не удалось скачать {} из реестра пакетов Gitea", relative), e))?;
+            let path = self.dir.join(&relative);
+            fs::create_dir_all(path.parent().ok_or("Неверный путь архива")?)?;
+            io::copy(&mut response.into_reader(), &mut File::create(&path)?)?;
+            self.uploaded.borrow_mut().insert(relative);
+        }
+        Ok(())
+    }
+
+    fn backups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        // Метаданные в рабочей копии есть у каждого бэкапа, архивы для списка не нужны
+        self.workspace.backups()
+    }
+
+    fn contains(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        self.workspace.contains(name)
+    }
+
+    fn remove(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let removed = self.workspace.remove(name)?;
+        if removed {
+            self.removed.borrow_mut().push(name.rsplit('/').next().unwrap_or(name).to_string());
+        }
+        Ok(removed)
+    }
+
+    fn upload_paths(&self, files: &[String], message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (metadata, archives): (Vec<String>, Vec<String>) = files.iter().cloned().partition(|file| storage::is_metadata(file));
+        for file in &archives {
+            if self.uploaded.borrow().contains(file) {
+                continue;
+            }
+            self.put(file)?;
+            self.uploaded.borrow_mut().insert(file.clone());
+        }
+        if !archives.is_empty() {
+            info!("Загружено в реестр пакетов Gitea: {}", message);
+        }
+        if metadata.is_empty() {
+            return Ok(());
+        }
+        self.workspace.upload_paths(&metadata, message)
+    }
+
+    fn upload_all(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let archives: Vec<String> = storage::local_files(&self.dir)?
+            .into_iter()
+            .filter(|file| !storage::is_metadata(file))
+            .collect();
+        self.upload_paths(&archives, message)?;
+        self.workspace.upload_all(message)?;
+
+        // Архивы удалённых бэкапов удаляются после того, как новый целиком загружен
+        let removed: Vec<String> = self.removed.borrow_mut().drain(..).collect();
+        for version in &removed {
+            match self.request("DELETE", &self.url(version, None)).call() {
+                Ok(_) | Err(ureq::Error::Status(404, _)) => {}
+                Err(e) => return Err(self.api.error(&format!("Не удалось удалить {} из реестра пакетов Gitea", version), e).into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn revision(&self) -> Option<String> {
+        self.workspace.revision()
+    }
+}
@@ -0,0 +1,256 @@
+use crate::config::{Config, Language};
+use std::sync::OnceLock;
+
+/// Язык интерфейса: `--language`, затем `language` из config.json, затем переменные локали
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Язык из `LC_ALL`, `LC_MESSAGES` или `LANG`: русский для `ru_*`, а также без локали
+/// или с `C`/`POSIX`, как у obt до появления выбора языка; для остальных — английский
+fn detect() -> Language {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+    match language {
+        "" | "C" | "POSIX" | "ru" => Language::Ru,
+        _ => Language::En,
+    }
+}
+
+pub fn set_language(language: Option<Language>) {
+    let _ = LANGUAGE.set(language.unwrap_or_else(detect));
+}
+
+pub fn language() -> Language {
+    *LANGUAGE.get_or_init(detect)
+}
+
+/// Язык уведомлений и HTML-отчёта: `notify_language` из config.json, иначе язык интерфейса.
+/// Так консоль может быть на русском, а оповещения в общий канал — на английском
+pub fn notify_language(config: &Config) -> Language {
+    config.notify_language.unwrap_or_else(language)
+}
+
+/// Перевод строки интерфейса. Ключ — сама русская строка, поэтому без перевода
+/// (или с русским языком) выводится она же
+pub fn t(message: &'static str) -> &'static str {
+    t_in(language(), message)
+}
+
+/// Перевод строки с подстановками `{}` по порядку
+pub fn tf(message: &'static str, args: &[&dyn std::fmt::Display]) -> String {
+    tf_in(language(), message, args)
+}
+
+/// Перевод на заданный язык, а не на язык интерфейса: для уведомлений и отчётов
+pub fn t_in(language: Language, message: &'static str) -> &'static str {
+    if language == Language::Ru {
+        return message;
+    }
+    EN.iter().find(|(ru, _)| *ru == message).map_or(message, |(_, en)| en)
+}
+
+pub fn tf_in(language: Language, message: &'static str, args: &[&dyn std::fmt::Display]) -> String {
+    let mut parts = t_in(language, message).split("{}");
+    let mut result = parts.next().unwrap_or_default().to_string();
+    for (index, part) in parts.enumerate() {
+        if let Some(arg) = args.get(index) {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+    result
+}
+
+/// Английский каталог строк интерактивного меню и настройки
+const EN: &[(&str, &str)] = &[
+    // Главное меню
+    ("Добро пожаловать в OBT! Давайте настроим резервное копирование.", "Welcome to OBT! Let's set up backups."),
+    ("Время: {}", "Time: {}"),
+    ("Последний бэкап: {}", "Last backup: {}"),
+    ("Имя бэкапа: {}", "Backup name: {}"),
+    ("Расписание cron: {}", "Cron schedule: {}"),
+    ("Время бэкапа: {}", "Backup time: {}"),
+    ("Меню:", "Menu:"),
+    ("1. Сделать бэкап", "1. Run a backup"),
+    ("2. Добавить/изменить файлы для бэкапа", "2. Add/change files to back up"),
+    ("3. Изменить хранилище бэкапов", "3. Change backup storage"),
+    ("4. Изменить расписание бэкапов", "4. Change backup schedule"),
+    ("5. Изменить имя бэкапа", "5. Change backup name"),
+    ("6. Выход", "6. Exit"),
+    ("Выберите действие (1-6): ", "Choose an action (1-6): "),
+    ("Неверный выбор, попробуйте снова", "Invalid choice, try again"),
+    ("Неверный выбор", "Invalid choice"),
+    // Хранилище
+    ("Где хранить бэкапы?", "Where should backups be stored?"),
+    ("1. Репозиторий Gitea", "1. Gitea repository"),
+    ("2. Другой сервер по SFTP (SSH)", "2. Another server over SFTP (SSH)"),
+    ("3. Git-репозиторий на сервере по SSH (без Gitea)", "3. Git repository on a server over SSH (no Gitea)"),
+    ("Выберите хранилище (1-3): ", "Choose storage (1-3): "),
+    ("Настройка Gitea", "Gitea setup"),
+    (
+        "Введите полный URL репозитория Gitea (например, backups.tgvpnbot.com/alex/backup): ",
+        "Enter the full Gitea repository URL (e.g. backups.tgvpnbot.com/alex/backup): ",
+    ),
+    ("Введите имя пользователя Gitea: ", "Enter the Gitea username: "),
+    ("Введите токен доступа Gitea (Enter — вход по паролю): ", "Enter a Gitea access token (Enter — log in with a password): "),
+    ("Введите пароль пользователя Gitea: ", "Enter the Gitea user's password: "),
+    (
+        "Хранить его в связке ключей ОС, а не в config.json? (y/n): ",
+        "Store it in the OS keyring instead of config.json? (y/n): ",
+    ),
+    ("Настройки Gitea успешно сохранены!", "Gitea settings saved!"),
+    (
+        "Репозиторий {} не найден в Gitea. Создать его (закрытый)? (y/n): ",
+        "Repository {} was not found in Gitea. Create it (private)? (y/n): ",
+    ),
+    ("✅ Репозиторий {} создан", "✅ Repository {} created"),
+    ("⚠️ Создайте репозиторий {} в Gitea до первого бэкапа", "⚠️ Create the repository {} in Gitea before the first backup"),
+    ("⚠️ Не удалось проверить репозиторий: {}", "⚠️ Could not check the repository: {}"),
+    ("Настройка SFTP", "SFTP setup"),
+    ("Введите адрес сервера: ", "Enter the server address: "),
+    ("Введите порт SSH (Enter — 22): ", "Enter the SSH port (Enter — 22): "),
+    ("Неверный порт: {}", "Invalid port: {}"),
+    ("Введите имя пользователя SSH: ", "Enter the SSH username: "),
+    (
+        "Введите путь к приватному ключу SSH (Enter — вход по паролю): ",
+        "Enter the path to the SSH private key (Enter — log in with a password): ",
+    ),
+    ("Введите пароль пользователя SSH: ", "Enter the SSH user's password: "),
+    (
+        "Введите папку для бэкапов на сервере (например, /srv/backups): ",
+        "Enter the backup folder on the server (e.g. /srv/backups): ",
+    ),
+    ("Отпечаток ключа сервера: {}", "Server key fingerprint: {}"),
+    ("Это ваш сервер? (y/n): ", "Is this your server? (y/n): "),
+    ("Настройка SFTP отменена", "SFTP setup cancelled"),
+    ("Настройки SFTP успешно сохранены!", "SFTP settings saved!"),
+    ("Настройка репозитория по SSH", "SSH repository setup"),
+    (
+        "На сервере нужны только git и sshd; репозиторий создаётся при первом бэкапе.",
+        "The server only needs git and sshd; the repository is created on the first backup.",
+    ),
+    (
+        "Введите адрес репозитория (например, ssh://obt@backup.local/srv/backups.git): ",
+        "Enter the repository address (e.g. ssh://obt@backup.local/srv/backups.git): ",
+    ),
+    (
+        "Введите путь к приватному ключу SSH (Enter — ssh-agent): ",
+        "Enter the path to the SSH private key (Enter — ssh-agent): ",
+    ),
+    ("Настройка репозитория по SSH отменена", "SSH repository setup cancelled"),
+    ("Настройки репозитория по SSH успешно сохранены!", "SSH repository settings saved!"),
+    // Набор настроек
+    ("Набор настроек бэкапа", "Backup preset"),
+    (
+        "1. Fast — быстрое сжатие zstd, без проверки после загрузки, 7 последних бэкапов",
+        "1. Fast — quick zstd compression, no check after upload, last 7 backups",
+    ),
+    ("2. Small — сильное сжатие xz, 5 последних бэкапов", "2. Small — strong xz compression, last 5 backups"),
+    (
+        "3. Paranoid — шифрование, проверка после загрузки, 30 последних бэкапов",
+        "3. Paranoid — encryption, check after upload, last 30 backups",
+    ),
+    (
+        "4. Без набора — настроить поля config.json самостоятельно",
+        "4. No preset — set config.json fields yourself",
+    ),
+    ("Выберите набор (1-4, Enter — без набора): ", "Choose a preset (1-4, Enter — no preset): "),
+    (
+        "Путь к файлу ключа age или пароль для шифрования архивов: ",
+        "Path to an age key file or a passphrase to encrypt archives: ",
+    ),
+    ("Без ключа набор Paranoid не выбран", "Paranoid preset not selected without a key"),
+    ("Выбран набор {}", "Preset {} selected"),
+    // Имя и расписание
+    ("Настройка имени для бэкапов", "Backup name setup"),
+    ("Введите имя для бэкапов (например, название сервера): ", "Enter a name for backups (e.g. the server name): "),
+    ("Имя бэкапа установлено!", "Backup name set!"),
+    ("Настройка расписания бэкапов", "Backup schedule setup"),
+    ("⏰ Время указывается в часовом поясе {}", "⏰ Times are in the {} time zone"),
+    ("Выберите периодичность бэкапов:", "Choose how often to back up:"),
+    ("1. Ежедневно", "1. Daily"),
+    ("2. Еженедельно", "2. Weekly"),
+    ("3. Ежемесячно", "3. Monthly"),
+    ("Выберите вариант (1-3): ", "Choose an option (1-3): "),
+    ("Текущее время: {}", "Current time: {}"),
+    ("Введите время для бэкапа (ЧЧ:ММ): ", "Enter the backup time (HH:MM): "),
+    ("Неверный формат времени. Попробуйте снова.", "Invalid time format. Try again."),
+    ("Расписание бэкапов настроено ({})!", "Backup schedule set ({})!"),
+    ("⚠️ Перезапустите демон вручную: sudo systemctl restart obt.service", "⚠️ Restart the daemon manually: sudo systemctl restart obt.service"),
+    ("✅ Демон перезапущен для применения нового времени", "✅ Daemon restarted to apply the new time"),
+    // Пути
+    ("Текущие пути для бэкапа:", "Current backup paths:"),
+    ("Нет добавленных путей", "No paths added"),
+    ("{}. {} [{}, +{} мин]", "{}. {} [{}, +{} min]"),
+    ("Действия:", "Actions:"),
+    ("1. Добавить новый путь", "1. Add a new path"),
+    ("2. Удалить все пути", "2. Remove all paths"),
+    ("3. Вернуться в главное меню", "3. Back to the main menu"),
+    ("Выберите действие (1-3): ", "Choose an action (1-3): "),
+    (
+        "Добавьте директорию или файл для бэкапирования (укажите путь): ",
+        "Add a directory or file to back up (enter its path): ",
+    ),
+    ("Приоритет (1 — критичный, 2 — обычный, 3 — объёмный) [2]: ", "Priority (1 — critical, 2 — normal, 3 — bulk) [2]: "),
+    ("Смещение от времени бэкапа в минутах [0]: ", "Offset from the backup time in minutes [0]: "),
+    ("Введите целое число минут", "Enter a whole number of minutes"),
+    ("Путь успешно добавлен!", "Path added!"),
+    ("Этот путь уже добавлен!", "This path is already added!"),
+    ("Указанный путь не существует!", "The path does not exist!"),
+    ("Создать директорию? (y/n): ", "Create the directory? (y/n): "),
+    ("Директория создана и добавлена!", "Directory created and added!"),
+    ("Внимание! Это действие удалит все пути для бэкапа!", "Warning! This removes all backup paths!"),
+    ("Вы уверены? (y/n): ", "Are you sure? (y/n): "),
+    ("Все пути успешно удалены!", "All paths removed!"),
+    ("Список путей уже пуст!", "The path list is already empty!"),
+    // Уведомления и отчёт
+    ("Бэкап выполнен", "Backup completed"),
+    ("Бэкап не выполнен", "Backup failed"),
+    ("Сервер {}: {}\nАрхивов: {}, размер {} МБ, за {} сек.", "Server {}: {}\nArchives: {}, size {} MB, in {} s."),
+    ("\n⚠️ Пропущено источников: {}", "\n⚠️ Sources skipped: {}"),
+    ("\n📈 Необычный размер архивов:\n{}", "\n📈 Unusual archive sizes:\n{}"),
+    ("{}: {} МБ, обычно {} МБ (больше в {} раза)", "{}: {} MB, usually {} MB ({}x larger)"),
+    ("{}: {} МБ, обычно {} МБ (меньше в {} раза)", "{}: {} MB, usually {} MB ({}x smaller)"),
+    ("Сервер {}: бэкап завершился ошибкой после {} сек.\n\n{}", "Server {}: backup failed after {} s.\n\n{}"),
+    ("\n\n📄 Вывод команды:\n{}", "\n\n📄 Command output:\n{}"),
+    ("Бэкапы снова актуальны", "Backups are up to date again"),
+    ("Сервер {}: последний успешный бэкап {}", "Server {}: last successful backup {}"),
+    ("Бэкапы устарели", "Backups are stale"),
+    (
+        "Сервер {}: нет успешного бэкапа уже {} (допустимо {} ч.). Последний успешный: {}. Проверьте obt.timer и obt.service.",
+        "Server {}: no successful backup for {} (allowed {} h). Last successful: {}. Check obt.timer and obt.service.",
+    ),
+    ("{} дн. {} ч.", "{} d {} h"),
+    ("{} ч. {} мин.", "{} h {} min"),
+    ("неизвестно", "unknown"),
+    ("никогда", "never"),
+    ("{} МБ", "{} MB"),
+    ("Бэкап", "Backup"),
+    ("Сервер", "Server"),
+    ("Дата", "Date"),
+    ("Метки", "Labels"),
+    ("Архивов", "Archives"),
+    ("Общий размер", "Total size"),
+    ("Архивы", "Archives"),
+    ("Архив", "Archive"),
+    ("Исходный путь", "Source path"),
+    ("Приоритет", "Priority"),
+    ("Размер", "Size"),
+    ("Обычно", "Usually"),
+    ("Путь", "Path"),
+    ("Причина", "Reason"),
+    ("Предупреждения", "Warnings"),
+    ("Размер архивов сильно отличается от прошлых бэкапов:", "Archive sizes differ a lot from previous backups:"),
+    ("Все пути заархивированы без ошибок.", "All paths archived without errors."),
+    ("Успешно", "Success"),
+    ("Успешно, с пропущенными путями", "Success, with skipped paths"),
+    ("Успешно, с предупреждениями", "Success, with warnings"),
+    // Вопросы с тайм-аутом
+    ("Нет ответа за {} сек. на вопрос «{}»", "No answer within {} s to «{}»"),
+    ("⏱️ Нет ответа за {} сек., выбран {}", "⏱️ No answer within {} s, chose {}"),
+    ("ответ по умолчанию", "the default answer"),
+    ("ответ «{}»", "«{}»"),
+];
@@ -0,0 +1,138 @@
+use crate::catalog::Catalog;
+use crate::config::{Config, Priority};
+use crate::crypto;
+use crate::index;
+use crate::labels::Labels;
+use crate::layout;
+use crate::manifest::{ArchiveEntry, Manifest};
+use crate::portable;
+use crate::split;
+use crate::staging::StagingDir;
+use crate::timestamp;
+use crate::verify;
+use crate::{prepare_repository, sanitize_label};
+use chrono::{DateTime, Utc};
+use colored::*;
+use log::info;
+use std::fs;
+use std::path::Path;
+
+/// Превращает созданный вручную tar.gz в полноценный бэкап obt:
+/// папка в репозитории, manifest.json, backup_info.txt и запись в каталоге
+pub fn import_archive(
+    config: &Config,
+    archive: &Path,
+    name: &str,
+    mut labels: Labels,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !archive.is_file() {
+        return Err(format!("Файл не найден: {}", archive.display()).into());
+    }
+
+    // Убеждаемся, что это читаемый tar.gz, до того как что-либо загружать
+    let readable = || -> std::io::Result<()> {
+        let mut reader = tar::Archive::new(flate2::read::MultiGzDecoder::new(fs::File::open(archive)?));
+        for entry in reader.entries()? {
+            std::io::copy(&mut entry?, &mut std::io::sink())?;
+        }
+        Ok(())
+    };
+    readable().map_err(|_| format!("{} не является корректным tar.gz архивом", archive.display()))?;
+
+    let folder_name = sanitize_label(name);
+    let original_name = archive
+        .file_name()
+        .ok_or("Не удалось определить имя файла архива")?
+        .to_string_lossy()
+        .to_string();
+    let archive_name = match config.fat_safe_names.unwrap_or(false) {
+        true => portable::name(&original_name),
+        false => original_name.clone(),
+    };
+    let metadata = fs::metadata(archive)?;
+
+    // Датой бэкапа считаем время изменения исходного архива, чтобы он встал на своё место в истории
+    let created_at = timestamp::format(DateTime::<Utc>::from(metadata.modified()?));
+    labels.insert("imported".to_string(), original_name.clone());
+
+    let encryption = crypto::Key::load(config)?;
+    let staging = StagingDir::new(format!("backup_import_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let backup_dir = staging.path();
+    let storage = prepare_repository(config, backup_dir)?;
+
+    let backup_path = layout::path(config, &folder_name);
+    let target_dir = Path::new(backup_dir).join(&backup_path);
+    if layout::locate(storage.as_ref(), &folder_name)?.is_some() {
+        return Err(format!("Бэкап с именем {} уже есть в репозитории", folder_name).into());
+    }
+    fs::create_dir_all(&target_dir)?;
+
+    println!("📥 Импорт: {} → {}", archive.display(), folder_name);
+    fs::copy(archive, target_dir.join(&archive_name))?;
+    let uploaded = match &encryption {
+        Some(key) => crypto::encrypt_file(key, &target_dir.join(&archive_name))?,
+        None => target_dir.join(&archive_name),
+    };
+    let size = fs::metadata(&uploaded)?.len();
+
+    let manifest = Manifest {
+        name: folder_name.clone(),
+        created_at,
+        labels,
+        pinned: false,
+        base: None,
+        branch: storage.location(),
+        paths: Vec::new(),
+        archives: vec![ArchiveEntry {
+            name: archive_name.clone(),
+            source: archive.display().to_string(),
+            priority: Priority::Normal,
+            size,
+            databases: Vec::new(),
+            excluded_databases: Vec::new(),
+            files: index::list_archive(archive)?,
+            encrypted: encryption.is_some(),
+            deleted: Vec::new(),
+            sha256: Some(verify::sha256_file(&uploaded)?),
+            parts: split::split_above(&uploaded, config.split_archive_mb)?,
+            original_name: (original_name != archive_name).then_some(original_name),
+            kind: None,
+        }],
+        skipped: Vec::new(),
+        anomalies: Vec::new(),
+        total_size: size,
+        run_id: None,
+        original_name: None,
+        backup_name: None,
+    };
+    manifest.write(&target_dir)?;
+
+    let backup_info = format!(
+        r#"🌍 OfficialVPN Backup Tool v{} - Импортированный бэкап
+
+📅 Дата исходного архива: {}
+🏷️  Имя бэкапа: {}
+📦 Архив: {} ({:.2} МБ)
+📥 Импортирован: {}
+"#,
+        env!("CARGO_PKG_VERSION"),
+        timestamp::display(&manifest.created_at),
+        folder_name,
+        archive_name,
+        metadata.len() as f64 / 1_048_576.0,
+        timestamp::now().format("%Y-%m-%d %H:%M:%S %Z")
+    );
+    fs::write(target_dir.join("backup_info.txt"), backup_info)?;
+
+    println!("🚀 Загрузка в хранилище...");
+    storage.upload_all(&format!("📥 Import {} ({})", folder_name, archive_name))?;
+
+    let mut catalog = Catalog::load()?;
+    catalog.record(&manifest, &backup_path);
+    catalog.set_revision(&manifest.name, storage.revision());
+    catalog.save()?;
+
+    info!("Архив {} импортирован как {}", archive.display(), manifest.name);
+    println!("{}", format!("✅ Архив импортирован как {}", manifest.name).green());
+    Ok(())
+}
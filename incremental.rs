@@ -0,0 +1,99 @@
+use crate::config::Config;
+use crate::layout;
+use crate::storage::Backend;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, Metadata};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+
+/// Полный бэкап делается раз в столько запусков, если `full_every` не задан
+const DEFAULT_FULL_EVERY: usize = 7;
+
+/// Признаки, по которым файл считается изменённым. ctime меняется при любой записи
+/// и смене прав, даже если mtime вернули назад (`touch -r`, распаковка архива).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileState {
+    pub size: u64,
+    pub mtime_ns: i64,
+    pub ctime_ns: i64,
+    pub inode: u64,
+}
+
+impl FileState {
+    pub fn of(metadata: &Metadata) -> Self {
+        FileState {
+            size: metadata.len(),
+            mtime_ns: metadata.mtime() * 1_000_000_000 + metadata.mtime_nsec(),
+            ctime_ns: metadata.ctime() * 1_000_000_000 + metadata.ctime_nsec(),
+            inode: metadata.ino(),
+        }
+    }
+}
+
+/// Файлы одного источника: путь внутри архива (как в индексе manifest.json) → состояние
+pub type FileStates = BTreeMap<String, FileState>;
+
+/// Состояние файлов на момент последнего бэкапа (~/.config/obt/incremental.json):
+/// по нему следующий запуск архивирует только изменившиеся файлы
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    /// Бэкап, которому соответствует состояние
+    pub backup: Option<String>,
+    /// Сколько инкрементальных бэкапов сделано после последнего полного
+    #[serde(default)]
+    pub chain_length: usize,
+    /// Путь из backup_paths → его файлы
+    #[serde(default)]
+    pub sources: BTreeMap<String, FileStates>,
+}
+
+impl Index {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_index_path()?;
+
+        if !path.exists() {
+            return Ok(Index::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_index_path()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn get_index_path() -> io::Result<PathBuf> {
+        Ok(Config::config_dir()?.join("incremental.json"))
+    }
+
+    /// Бэкап, поверх которого делать этот запуск; `None` — нужен полный. Полный делается,
+    /// если инкрементальный режим выключен, цепочка достигла `full_every` или базового
+    /// бэкапа уже нет в хранилище.
+    pub fn base_for(&self, config: &Config, storage: &dyn Backend) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let (Some(incremental), Some(backup)) = (&config.incremental, &self.backup) else {
+            return Ok(None);
+        };
+        let full_every = incremental.full_every.unwrap_or(DEFAULT_FULL_EVERY).max(1);
+        if self.chain_length + 1 >= full_every {
+            info!("Цепочка из {} инкрементальных бэкапов, делается полный", self.chain_length);
+            return Ok(None);
+        }
+        if layout::locate(storage, backup)?.is_none() {
+            info!("Базового бэкапа {} нет в хранилище, делается полный", backup);
+            return Ok(None);
+        }
+        Ok(Some(backup.clone()))
+    }
+}
+
+/// Файлы, которые были в базовом бэкапе, но исчезли к этому
+pub fn deleted(previous: &FileStates, current: &FileStates) -> Vec<String> {
+    previous.keys().filter(|path| !current.contains_key(*path)).cloned().collect()
+}
@@ -0,0 +1,100 @@
+use crate::compression;
+use crate::timestamp;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+use std::path::Path;
+use tar::EntryType;
+
+/// Файл внутри архива для индекса в manifest.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEntry {
+    /// Путь внутри архива (`./nginx.conf`)
+    pub path: String,
+    pub size: u64,
+    /// Время изменения в UTC, как все отметки времени obt (`timestamp::format`); у индексов
+    /// старых версий — `ГГГГ-ММ-ДД ЧЧ:ММ:СС` по часовому поясу сервера
+    pub mtime: String,
+    /// SHA-256 содержимого обычного файла; в индексах старых версий его нет
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl FileEntry {
+    /// Время изменения; индекс старой версии читается по часовому поясу этого сервера
+    pub fn modified_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.mtime)
+            .map(|time| time.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                let naive = NaiveDateTime::parse_from_str(&self.mtime, "%Y-%m-%d %H:%M:%S").ok()?;
+                Local.from_local_datetime(&naive).earliest().map(|time| time.with_timezone(&Utc))
+            })
+    }
+
+    /// Время изменения для пользователя, в его часовом поясе
+    pub fn display_mtime(&self) -> String {
+        self.modified_at().map(timestamp::display_time).unwrap_or_else(|| self.mtime.clone())
+    }
+}
+
+/// Время изменения для индекса из секунд Unix
+pub fn format_mtime(seconds: i64) -> String {
+    Utc.timestamp_opt(seconds, 0).single().map(timestamp::format).unwrap_or_default()
+}
+
+/// Открывает tar-архив по имени файла; импортированный архив с другим именем — всегда tar.gz
+pub fn open_archive(archive: &Path) -> Result<tar::Archive<Box<dyn Read>>, Box<dyn std::error::Error>> {
+    let name = archive.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let codec = compression::codec_of(&name).unwrap_or(&compression::Gzip);
+    let file = File::open(archive).map_err(|e| format!("Не удалось открыть {}: {}", archive.display(), e))?;
+    Ok(tar::Archive::new(codec.decoder(Box::new(file))?))
+}
+
+/// Список файлов tar-архива для индекса в manifest.json
+pub fn list_archive(archive: &Path) -> Result<Vec<FileEntry>, Box<dyn std::error::Error>> {
+    let mut reader = open_archive(archive)?;
+    let mut files = Vec::new();
+    for entry in reader.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        // Каталоги и специальные файлы в индекс не попадают
+        if !matches!(kind, EntryType::Regular | EntryType::Continuous | EntryType::Link | EntryType::Symlink) {
+            continue;
+        }
+        let sha256 = match kind {
+            EntryType::Regular | EntryType::Continuous => {
+                let mut hasher = Sha256::new();
+                io::copy(&mut entry, &mut hasher)?;
+                Some(format!("{:x}", hasher.finalize()))
+            }
+            _ => None,
+        };
+        files.push(FileEntry {
+            path: entry.path()?.to_string_lossy().into_owned(),
+            size: entry.header().size()?,
+            mtime: format_mtime(entry.header().mtime()? as i64),
+            sha256,
+        });
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mtime_is_stored_in_utc() {
+        let entry = FileEntry {
+            path: "./nginx.conf".to_string(),
+            size: 10,
+            mtime: format_mtime(1_715_302_800),
+            sha256: None,
+        };
+        assert_eq!(entry.mtime, "2024-05-10T01:00:00Z");
+        assert_eq!(entry.modified_at(), Utc.timestamp_opt(1_715_302_800, 0).single());
+    }
+}
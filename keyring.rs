@@ -0,0 +1,107 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Значение поля config.json вида `keyring:<имя>`: сам секрет лежит в связке ключей ОС
+/// (Secret Service — GNOME Keyring, KeePassXC и т.п.) с атрибутами `service obt key <имя>`
+const PREFIX: &str = "keyring:";
+
+const SERVICE: &str = "obt";
+
+pub fn is_reference(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+pub fn reference(name: &str) -> String {
+    format!("{}{}", PREFIX, name)
+}
+
+/// Есть ли `secret-tool` (пакет libsecret-tools), через который идёт обращение к связке ключей
+pub fn available() -> bool {
+    Command::new("secret-tool")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Проверяет, что связка ключей отвечает и этому процессу, и демону. Демон работает под systemd
+/// без сеанса D-Bus, и Secret Service ему недоступен, даже если в сеансе `obt setup` он есть;
+/// ссылка `keyring:` в config.json тогда ломала бы каждый бэкап по расписанию
+pub fn check() -> Result<(), Box<dyn std::error::Error>> {
+    probe(&mut Command::new("secret-tool")).map_err(|e| format!("Связка ключей недоступна: {}", e))?;
+    let mut daemon = Command::new("secret-tool");
+    for variable in ["DBUS_SESSION_BUS_ADDRESS", "XDG_RUNTIME_DIR", "DISPLAY"] {
+        daemon.env_remove(variable);
+    }
+    probe(&mut daemon).map_err(|e| {
+        format!(
+            "Связка ключей недоступна демону obt: под systemd у него нет сеанса D-Bus и Secret Service ({}). \
+             Оставьте секрет в config.json",
+            e
+        )
+    })?;
+    Ok(())
+}
+
+/// Запрос несуществующего секрета: пустой stderr значит, что Secret Service ответил
+fn probe(command: &mut Command) -> Result<(), String> {
+    let output = command
+        .args(["lookup", "service", SERVICE, "key", "obt-probe"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("не удалось запустить secret-tool (пакет libsecret-tools): {}", e))?;
+    match String::from_utf8_lossy(&output.stderr).trim() {
+        "" => Ok(()),
+        error => Err(error.to_string()),
+    }
+}
+
+/// Сохраняет секрет в связке ключей; секрет передаётся через stdin, а не в командной строке
+pub fn store(name: &str, secret: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new("secret-tool")
+        .args(["store", &format!("--label=obt: {}", name), "service", SERVICE, "key", name])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Не удалось запустить secret-tool (пакет libsecret-tools): {}", e))?;
+    child.stdin.take().ok_or("Нет stdin у secret-tool")?.write_all(secret.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "Связка ключей не приняла секрет {}: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn lookup(name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::new("secret-tool")
+        .args(["lookup", "service", SERVICE, "key", name])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Не удалось запустить secret-tool (пакет libsecret-tools): {}", e))?;
+    // Ненайденный секрет — пустой вывод и код 1
+    if !output.status.success() || output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => format!("Секрета {} нет в связке ключей", name),
+            error => format!("Связка ключей недоступна ({}): {}", name, error),
+        }
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Значение секретного поля: ссылка `keyring:<имя>` заменяется секретом из связки ключей,
+/// остальные значения возвращаются как есть
+pub fn resolve(value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    match value.strip_prefix(PREFIX) {
+        Some(name) => lookup(name),
+        None => Ok(value.to_string()),
+    }
+}
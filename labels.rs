@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+
+pub type Labels = BTreeMap<String, String>;
+
+/// Ключ, под которым сохраняется метка из `obt snapshot --label <текст>`
+pub const DEFAULT_KEY: &str = "label";
+
+/// Разбирает `key=value`; строка без `=` считается значением ключа `label`
+pub fn parse_label(input: &str) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let (key, value) = match input.split_once('=') {
+        Some((key, value)) => (key.trim(), value.trim()),
+        None => (DEFAULT_KEY, input.trim()),
+    };
+
+    if key.is_empty() || value.is_empty() {
+        return Err(format!("Неверный формат метки '{}', ожидается key=value", input).into());
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Разбирает вывод хука: каждая непустая строка вида `key=value`
+pub fn parse_hook_output(output: &str) -> Labels {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| parse_label(line).ok())
+        .collect()
+}
+
+/// Селектор `key=value` требует точного совпадения, селектор `key` — только наличия ключа
+pub fn matches(labels: &Labels, selectors: &[String]) -> bool {
+    selectors
+        .iter()
+        .all(|selector| match selector.split_once('=') {
+            Some((key, value)) => labels.get(key.trim()).map(String::as_str) == Some(value.trim()),
+            None => labels.contains_key(selector.trim()),
+        })
+}
+
+pub fn format(labels: &Labels) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,141 @@
+use crate::config::{BackupPath, Config};
+use crate::storage::Backend;
+use crate::{sanitize_label, timestamp};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Шаблон имени архива пути по умолчанию: `dir_1_etc`
+const ARCHIVE_TEMPLATE: &str = "{kind}_{index}_{label}";
+
+/// Подставляет значения в шаблон имени; неизвестная подстановка или `/` в имени — ошибка
+fn render(setting: &str, template: &str, values: &[(&str, String)]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut name = template.to_string();
+    for (key, value) in values {
+        name = name.replace(&format!("{{{}}}", key), value);
+    }
+    if let Some(start) = name.find('{') {
+        let placeholder = name[start..].split_inclusive('}').next().unwrap_or(&name[start..]);
+        return Err(format!("Неизвестная подстановка {} в {}", placeholder, setting).into());
+    }
+    if name.is_empty() || name.contains('/') {
+        return Err(format!("{} даёт недопустимое имя «{}»", setting, name).into());
+    }
+    Ok(name)
+}
+
+fn hostname() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|host| host.into_string().ok())
+        .map(|host| sanitize_label(&host))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Имя архива пути без номера: по `archive_name_template` из вида, номера, метки и даты
+fn render_archive(config: &Config, index: usize, entry: &BackupPath) -> Result<String, Box<dyn std::error::Error>> {
+    let path = Path::new(&entry.path);
+    let kind = if path.is_file() { "file" } else { "dir" };
+    let label = match &entry.label {
+        Some(label) => sanitize_label(label),
+        None => path.file_name().unwrap_or(std::ffi::OsStr::new("unknown")).to_string_lossy().to_string(),
+    };
+    let values = [
+        ("kind", kind.to_string()),
+        ("index", (index + 1).to_string()),
+        ("label", label),
+        ("date", timestamp::now().format("%Y-%m-%d").to_string()),
+        ("hostname", hostname()),
+    ];
+    let template = config.archive_name_template.as_deref().unwrap_or(ARCHIVE_TEMPLATE);
+    render("archive_name_template", template, &values)
+}
+
+/// Имя архива пути `index` без расширения. Если по шаблону у другого пути из config.json
+/// получается то же имя (две папки `data` без меток), к нему добавляется номер пути
+pub fn archive_name(config: &Config, index: usize, entry: &BackupPath) -> Result<String, Box<dyn std::error::Error>> {
+    let name = render_archive(config, index, entry)?;
+    let collides = config
+        .backup_paths
+        .iter()
+        .enumerate()
+        .filter(|(_, other)| other.path != entry.path)
+        .any(|(other_index, other)| render_archive(config, other_index, other).is_ok_and(|other| other == name));
+    Ok(if collides { format!("{}_{}", name, index + 1) } else { name })
+}
+
+/// Имя папки нового бэкапа по `folder_name_template`; время в нём должно разбираться
+/// обратно (`timestamp::from_folder_name`), на нём держатся шардирование, восстановление и хранение
+pub fn folder_name(config: &Config, started_at: DateTime<Utc>) -> Result<String, Box<dyn std::error::Error>> {
+    let template = match (&config.folder_name_template, &config.backup_name) {
+        (Some(template), _) => template.as_str(),
+        (None, Some(_)) => "{name}_{timestamp}",
+        (None, None) => "{timestamp}",
+    };
+    let values = [
+        ("name", config.backup_name.clone().unwrap_or_default()),
+        ("timestamp", timestamp::folder(started_at)),
+        ("date", started_at.with_timezone(&timestamp::zone()).format("%Y-%m-%d").to_string()),
+        ("hostname", hostname()),
+    ];
+    let name = render("folder_name_template", template, &values)?;
+    if timestamp::from_folder_name(&name).map(|time| time.timestamp()) != Some(started_at.timestamp()) {
+        return Err(format!(
+            "В folder_name_template должен быть {{timestamp}}, отделённый от остального имени `_` (получилось «{}»)",
+            name
+        )
+        .into());
+    }
+    Ok(name)
+}
+
+/// Папка месяца бэкапа `<год>/<месяц>` (UTC, как и время в имени); `None` у бэкапов
+/// без времени в имени, например импортированных
+fn month_folder(name: &str) -> Option<String> {
+    timestamp::from_folder_name(name).map(|created_at| created_at.format("%Y/%m").to_string())
+}
+
+/// Путь папки нового бэкапа в хранилище: `<год>/<месяц>/<имя>` с `shard_by_month`, иначе `<имя>`
+pub fn path(config: &Config, name: &str) -> String {
+    match month_folder(name).filter(|_| config.shard_by_month.unwrap_or(false)) {
+        Some(folder) => format!("{}/{}", folder, name),
+        None => name.to_string(),
+    }
+}
+
+/// Где может лежать бэкап: в папке своего месяца или в корне — бэкапы, сделанные
+/// до включения `shard_by_month` или после его отключения, остаются на своих местах
+fn candidates(name: &str) -> Vec<String> {
+    month_folder(name)
+        .map(|folder| format!("{}/{}", folder, name))
+        .into_iter()
+        .chain([name.to_string()])
+        .collect()
+}
+
+/// Папка бэкапа `name` в хранилище; `None`, если его там нет
+pub fn locate(storage: &dyn Backend, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    for path in candidates(name) {
+        if storage.contains(&path)? {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// Удаляет бэкап `name` из хранилища, где бы он ни лежал; `false`, если его нет
+pub fn remove(storage: &dyn Backend, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    match locate(storage, name)? {
+        Some(path) => storage.remove(&path),
+        None => Ok(false),
+    }
+}
+
+/// Папка бэкапа `name` в скачанной рабочей папке хранилища
+pub fn local_dir(work_dir: &Path, name: &str) -> Option<PathBuf> {
+    candidates(name).into_iter().map(|path| work_dir.join(path)).find(|dir| dir.is_dir())
+}
+
+/// Папки годов и месяцев: `2026`, `10`
+pub fn is_shard(name: &str, len: usize) -> bool {
+    name.len() == len && name.chars().all(|c| c.is_ascii_digit())
+}
@@ -0,0 +1,2713 @@
+mod agents;
+mod api;
+mod anomaly;
+mod archive;
+mod boot;
+mod bootstrap;
+mod bundle;
+mod cache;
+mod catalog;
+mod cli;
+mod compression;
+mod config;
+mod control;
+mod cron;
+mod crypto;
+mod database;
+mod dest;
+mod diff;
+mod divergence;
+mod docker;
+mod events;
+mod freshness;
+mod find;
+mod forensics;
+mod git;
+mod gitea;
+mod i18n;
+mod import;
+mod incremental;
+mod index;
+mod keyring;
+mod labels;
+mod layout;
+mod lock;
+mod manifest;
+mod metrics;
+#[cfg(feature = "fuse")]
+mod mount;
+mod network;
+mod notify;
+mod ownership;
+mod pause;
+mod policy;
+mod portable;
+mod preset;
+mod progress;
+mod prompt;
+mod provision;
+mod rclone;
+mod replicate;
+mod report;
+mod restore;
+mod retention;
+mod s3;
+mod secrets;
+mod smtp;
+mod sftp;
+mod source;
+mod split;
+mod stamp;
+mod staging;
+mod status;
+mod storage;
+mod system;
+mod systemd;
+mod throttle;
+mod timestamp;
+mod verify;
+mod watch;
+mod webdav;
+mod webhook;
+
+pub use api::{BackupReport, BackupRunner};
+pub use events::Event;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Timelike, Utc};
+use colored::*;
+use catalog::{Catalog, RunStatus};
+use clap::Parser;
+use cli::{
+    BootArgs, BundleAction, BundleArgs, Cli, Commands, ConfigAction, DestAction, MaintenanceArgs, MaintenanceJob, OwnersArg, OwnershipArgs,
+    PathsAction, PolicyAction, SampleArg, ScheduleAction,
+};
+use config::{BackupFrequency, BackupPath, Config, Priority};
+use i18n::{t, t_in, tf, tf_in};
+use control::{Request, Response, RunQueue, Task, TriggerSource};
+use events::EventSink;
+use index::FileEntry;
+use labels::Labels;
+use log::{info, warn, error};
+use manifest::{ArchiveEntry, ArchiveKind, Manifest, SizeAnomaly, SkippedSource};
+use network::Failure;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal};
+use ownership::{OwnerMap, Ownership};
+use restore::{RestoreOptions, Selection};
+use source::Source;
+use staging::StagingDir;
+use std::sync::Arc;
+use storage::Backend;
+use systemd::{SystemdService, TimerCheck};
+
+fn read_input(prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    prompt::read(prompt, None)
+}
+
+/// Вопрос с ответом по умолчанию: он же выбирается, если истёк `prompt_timeout_seconds`
+fn read_input_or(prompt: &str, default: &str) -> Result<String, Box<dyn std::error::Error>> {
+    prompt::read(prompt, Some(default))
+}
+
+/// `backups.example.com/alex/backup` → адрес Gitea для gitea_url и имя репозитория
+fn split_gitea_url(full_repo_url: &str) -> Option<(String, String)> {
+    let clean_url = full_repo_url.replace("https://", "");
+    let (base_url, repo_path) = clean_url.split_at(clean_url.rfind('/')?);
+    Some((base_url.to_string(), repo_path.trim_start_matches('/').to_string()))
+}
+
+fn setup_gitea(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n{}", t("Настройка Gitea"));
+
+    let full_repo_url = read_input(t(
+        "Введите полный URL репозитория Gitea (например, backups.tgvpnbot.com/alex/backup): ",
+    ))?;
+    if let Some((url, repo)) = split_gitea_url(&full_repo_url) {
+        config.gitea_url = Some(url);
+        config.gitea_repo = Some(repo);
+    }
+
+    config.gitea_username = Some(read_input(t("Введите имя пользователя Gitea: "))?);
+    let token = read_input_or(t("Введите токен доступа Gitea (Enter — вход по паролю): "), "")?;
+    let (field, secret) = if token.is_empty() {
+        ("gitea_password", read_input(t("Введите пароль пользователя Gitea: "))?)
+    } else {
+        ("gitea_token", token)
+    };
+    let keyring = keyring::available() && match keyring::check() {
+        Ok(()) => true,
+        Err(e) => {
+            println!("{}", format!("⚠️ {}", e).yellow());
+            false
+        }
+    };
+    let secret = if keyring
+        && read_input_or(t("Хранить его в связке ключей ОС, а не в config.json? (y/n): "), "n")?.to_lowercase() == "y"
+    {
+        keyring::store(field, &secret)?;
+        keyring::reference(field)
+    } else {
+        secret
+    };
+    if field == "gitea_token" {
+        config.gitea_token = Some(secret);
+        config.gitea_password = None;
+    } else {
+        config.gitea_password = Some(secret);
+        config.gitea_token = None;
+    }
+
+    config.save()?;
+    println!("{}", t("Настройки Gitea успешно сохранены!").green());
+
+    let repo = config.gitea_repo.clone().unwrap_or_default();
+    match gitea::repo_exists(config) {
+        Ok(true) => {}
+        Ok(false) => {
+            let question = tf("Репозиторий {} не найден в Gitea. Создать его (закрытый)? (y/n): ", &[&repo]);
+            if read_input_or(&question, "y")?.to_lowercase() == "y" {
+                gitea::create_repo(config)?;
+                println!("{}", tf("✅ Репозиторий {} создан", &[&repo]).green());
+            } else {
+                println!("{}", tf("⚠️ Создайте репозиторий {} в Gitea до первого бэкапа", &[&repo]).yellow());
+            }
+        }
+        Err(e) => println!("{}", tf("⚠️ Не удалось проверить репозиторий: {}", &[&e]).yellow()),
+    }
+    Ok(())
+}
+
+fn setup_sftp(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n{}", t("Настройка SFTP"));
+
+    let host = read_input(t("Введите адрес сервера: "))?;
+    let port = match read_input_or(t("Введите порт SSH (Enter — 22): "), "")?.as_str() {
+        "" => None,
+        port => Some(port.parse::<u16>().map_err(|_| tf("Неверный порт: {}", &[&port]))?),
+    };
+    let username = read_input(t("Введите имя пользователя SSH: "))?;
+    let key_file = read_input_or(t("Введите путь к приватному ключу SSH (Enter — вход по паролю): "), "")?;
+    let (key_file, password) = if key_file.is_empty() {
+        (None, Some(read_input(t("Введите пароль пользователя SSH: "))?))
+    } else {
+        (Some(std::path::PathBuf::from(key_file)), None)
+    };
+    let path = read_input(t("Введите папку для бэкапов на сервере (например, /srv/backups): "))?;
+
+    let host_key = sftp::host_fingerprint(&host, port)?;
+    println!("{}", tf("Отпечаток ключа сервера: {}", &[&host_key.white().bold()]));
+    if read_input_or(t("Это ваш сервер? (y/n): "), "n")?.to_lowercase() != "y" {
+        return Err(t("Настройка SFTP отменена").into());
+    }
+
+    config.sftp = Some(config::SftpConfig {
+        host,
+        port,
+        username,
+        password,
+        key_file,
+        path,
+        host_key: Some(host_key),
+        // Тайм-ауты, подобранные под канал, переживают смену адреса сервера
+        network: config.sftp.as_ref().and_then(|sftp| sftp.network.clone()),
+    });
+    config.s3 = None;
+    config.git_ssh = None;
+    config.rclone = None;
+    config.webdav = None;
+    config.save()?;
+    println!("{}", t("Настройки SFTP успешно сохранены!").green());
+    Ok(())
+}
+
+fn setup_git_ssh(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n{}", t("Настройка репозитория по SSH"));
+    println!("{}", t("На сервере нужны только git и sshd; репозиторий создаётся при первом бэкапе."));
+    let url = read_input(t("Введите адрес репозитория (например, ssh://obt@backup.local/srv/backups.git): "))?;
+    let key_file = read_input_or(t("Введите путь к приватному ключу SSH (Enter — ssh-agent): "), "")?;
+
+    let host_key = git::host_fingerprint(&url)?;
+    println!("{}", tf("Отпечаток ключа сервера: {}", &[&host_key.white().bold()]));
+    if read_input_or(t("Это ваш сервер? (y/n): "), "n")?.to_lowercase() != "y" {
+        return Err(t("Настройка репозитория по SSH отменена").into());
+    }
+
+    config.git_ssh = Some(config::GitSshConfig {
+        url,
+        key_file: (!key_file.is_empty()).then(|| std::path::PathBuf::from(key_file)),
+        host_key: Some(host_key),
+        network: config.git_ssh.as_ref().and_then(|git_ssh| git_ssh.network.clone()),
+    });
+    config.s3 = None;
+    config.sftp = None;
+    config.rclone = None;
+    config.webdav = None;
+    config.save()?;
+    println!("{}", t("Настройки репозитория по SSH успешно сохранены!").green());
+    Ok(())
+}
+
+/// Выбор хранилища бэкапов: репозиторий Gitea, другой сервер по SFTP или голый репозиторий по SSH
+fn setup_storage(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n{}", t("Где хранить бэкапы?"));
+    println!("{}", t("1. Репозиторий Gitea"));
+    println!("{}", t("2. Другой сервер по SFTP (SSH)"));
+    println!("{}", t("3. Git-репозиторий на сервере по SSH (без Gitea)"));
+
+    loop {
+        match read_input(t("Выберите хранилище (1-3): "))?.as_str() {
+            "1" => {
+                config.s3 = None;
+                config.sftp = None;
+                config.git_ssh = None;
+                config.rclone = None;
+    config.webdav = None;
+                config.webdav = None;
+                return setup_gitea(config);
+            }
+            "2" => return setup_sftp(config),
+            "3" => return setup_git_ssh(config),
+            _ => println!("{}", t("Неверный выбор, попробуйте снова")),
+        }
+    }
+}
+
+fn setup_backup_name(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n{}", t("Настройка имени для бэкапов"));
+    let name = read_input(t("Введите имя для бэкапов (например, название сервера): "))?;
+    config.backup_name = Some(name);
+    config.save()?;
+    println!("{}", t("Имя бэкапа установлено!").green());
+    Ok(())
+}
+
+fn restart_daemon() -> Result<(), Box<dyn std::error::Error>> {
+    info!("Перезапуск демона для применения новых настроек времени...");
+    
+    std::process::Command::new("systemctl")
+        .args(["restart", "obt.service"])
+        .output()?;
+        
+    std::process::Command::new("systemctl")
+        .args(["restart", &SystemdService::timer_unit()])
+        .output()?;
+        
+    println!("{}", t("✅ Демон перезапущен для применения нового времени").green());
+    Ok(())
+}
+
+fn setup_backup_schedule(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n{}", t("Настройка расписания бэкапов"));
+    println!("{}", tf("⏰ Время указывается в часовом поясе {}", &[&timestamp::zone()]).yellow());
+
+    println!("{}", t("Выберите периодичность бэкапов:"));
+    println!("{}", t("1. Ежедневно"));
+    println!("{}", t("2. Еженедельно"));
+    println!("{}", t("3. Ежемесячно"));
+
+    let frequency = match read_input(t("Выберите вариант (1-3): "))?.as_str() {
+        "1" => BackupFrequency::Daily,
+        "2" => BackupFrequency::Weekly,
+        "3" => BackupFrequency::Monthly,
+        _ => return Err(t("Неверный выбор").into()),
+    };
+
+    println!("{}", tf("Текущее время: {}", &[&timestamp::now().format("%H:%M:%S %Z")]));
+
+    let time = loop {
+        let input = read_input(t("Введите время для бэкапа (ЧЧ:ММ): "))?;
+        if NaiveTime::parse_from_str(&input, "%H:%M").is_ok() {
+            break input;
+        }
+        println!("{}", t("Неверный формат времени. Попробуйте снова."));
+    };
+
+    apply_schedule(config, frequency, time)
+}
+
+/// Сохраняет расписание, обновляет юниты systemd и перезапускает демон
+fn apply_schedule(config: &mut Config, frequency: BackupFrequency, time: String) -> Result<(), Box<dyn std::error::Error>> {
+    if NaiveTime::parse_from_str(&time, "%H:%M").is_err() {
+        return Err(format!("Неверный формат времени: {} (ожидается ЧЧ:ММ)", time).into());
+    }
+
+    config.backup_frequency = Some(frequency);
+    config.backup_time = Some(time);
+    config.backup_cron = None;
+    install_schedule(config)
+}
+
+/// `obt schedule cron <выражение>`: расписание cron вместо периодичности и времени
+fn apply_cron_schedule(config: &mut Config, expression: String) -> Result<(), Box<dyn std::error::Error>> {
+    let cron = cron::Cron::parse(&expression)?;
+    config.backup_cron = Some(expression);
+    install_schedule(config)?;
+    println!("Таймер: OnCalendar={}", cron.on_calendar());
+    Ok(())
+}
+
+/// Сохраняет расписание бэкапов, обновляет юниты systemd и перезапускает демон
+fn install_schedule(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    // Часовой пояс закрепляется вместе с расписанием, чтобы оно не сдвинулось при смене системного
+    if config.timezone.is_none() {
+        config.timezone = Some(timestamp::zone().name().to_string());
+    }
+    config.save()?;
+
+    SystemdService::create(config)?;
+
+    // Автоматически перезапускаем демон
+    if let Err(e) = restart_daemon() {
+        warn!("Не удалось перезапустить демон: {}", e);
+        println!("{}", t("⚠️ Перезапустите демон вручную: sudo systemctl restart obt.service").yellow());
+    }
+
+    println!("{}", tf("Расписание бэкапов настроено ({})!", &[&timestamp::zone()]).green());
+    agents::check(config).print(config, false);
+    Ok(())
+}
+
+/// `obt schedule prune|verify ...` и `obt schedule unset prune|verify`
+fn apply_maintenance_schedule(
+    config: &mut Config,
+    job: MaintenanceJob,
+    args: Option<MaintenanceArgs>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let schedule = args.map(|args| config::MaintenanceSchedule {
+        frequency: args.frequency,
+        time: args.time,
+        weekday: args.weekday,
+    });
+    if let Some(schedule) = &schedule {
+        schedule.parsed_time()?;
+        if config.timezone.is_none() {
+            config.timezone = Some(timestamp::zone().name().to_string());
+        }
+    }
+    let (slot, name) = match job {
+        MaintenanceJob::Prune => (&mut config.prune_schedule, "очистки"),
+        MaintenanceJob::Verify => (&mut config.verify_schedule, "проверки"),
+        MaintenanceJob::Boot => return apply_boot_schedule(config, None),
+    };
+    let enabled = schedule.is_some();
+    *slot = schedule;
+    config.save()?;
+
+    if let Err(e) = restart_daemon() {
+        warn!("Не удалось перезапустить демон: {}", e);
+        println!("{}", t("⚠️ Перезапустите демон вручную: sudo systemctl restart obt.service").yellow());
+    }
+    if enabled {
+        println!("{}", format!("Расписание {} настроено ({})!", name, timestamp::zone()).green());
+    } else {
+        println!("{}", format!("Отдельное расписание {} убрано: она снова выполняется после каждого бэкапа", name).green());
+    }
+    Ok(())
+}
+
+/// `obt schedule boot <часы> [--delay-minutes N] [--timer]` и `obt schedule unset boot`
+fn apply_boot_schedule(config: &mut Config, args: Option<BootArgs>) -> Result<(), Box<dyn std::error::Error>> {
+    config.boot_backup = args.map(|args| config::BootBackup {
+        older_than_hours: args.older_than_hours,
+        delay_minutes: args.delay_minutes,
+        timer: args.timer,
+    });
+    config.save()?;
+
+    if let Err(e) = SystemdService::install_boot(config) {
+        warn!("Не удалось обновить юниты systemd: {}", e);
+        println!("{}", format!("⚠️ Не удалось обновить юниты systemd: {}", e).yellow());
+    }
+    if let Err(e) = restart_daemon() {
+        warn!("Не удалось перезапустить демон: {}", e);
+        println!("{}", t("⚠️ Перезапустите демон вручную: sudo systemctl restart obt.service").yellow());
+    }
+    match &config.boot_backup {
+        Some(boot) => println!(
+            "{}",
+            format!(
+                "Бэкап через {} мин. после загрузки, если последний успешный старше {} ч. ({})",
+                boot.delay().as_secs() / 60,
+                boot.older_than_hours,
+                if boot.timer { "obt-boot.timer" } else { "демон" }
+            )
+            .green()
+        ),
+        None => println!("{}", "Бэкап после загрузки выключен".green()),
+    }
+    Ok(())
+}
+
+fn read_priority() -> Result<Priority, Box<dyn std::error::Error>> {
+    loop {
+        match read_input_or(t("Приоритет (1 — критичный, 2 — обычный, 3 — объёмный) [2]: "), "")?.as_str() {
+            "1" => return Ok(Priority::Critical),
+            "" | "2" => return Ok(Priority::Normal),
+            "3" => return Ok(Priority::Bulk),
+            _ => println!("{}", t("Неверный выбор, попробуйте снова")),
+        }
+    }
+}
+
+fn read_offset() -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    loop {
+        let input = read_input_or(t("Смещение от времени бэкапа в минутах [0]: "), "")?;
+        match input.as_str() {
+            "" | "0" => return Ok(None),
+            value => match value.parse::<u64>() {
+                Ok(minutes) => return Ok(Some(minutes)),
+                Err(_) => println!("{}", t("Введите целое число минут")),
+            },
+        }
+    }
+}
+
+fn manage_backup_paths(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        println!("\n{}", t("Текущие пути для бэкапа:"));
+        if config.backup_paths.is_empty() {
+            println!("{}", t("Нет добавленных путей"));
+        } else {
+            for (i, source) in config.backup_paths.iter().enumerate() {
+                match source.offset_minutes {
+                    Some(offset) => println!("{}", tf("{}. {} [{}, +{} мин]", &[&(i + 1), &source.path, &source.priority, &offset])),
+                    None => println!("{}. {} [{}]", i + 1, source.path, source.priority),
+                }
+            }
+        }
+
+        println!("\n{}", t("Действия:"));
+        println!("{}", t("1. Добавить новый путь"));
+        println!("{}", t("2. Удалить все пути"));
+        println!("{}", t("3. Вернуться в главное меню"));
+
+        match read_input(&format!("\n{}", t("Выберите действие (1-3): ")))?.as_str() {
+            "1" => {
+                let path = read_input(&format!(
+                    "\n{}",
+                    t("Добавьте директорию или файл для бэкапирования (укажите путь): ")
+                ))?;
+                let path_obj = std::path::Path::new(&path);
+
+                if path_obj.exists() {
+                    if !config.backup_paths.iter().any(|s| s.path == path) {
+                        let priority = read_priority()?;
+                        let offset_minutes = read_offset()?;
+                        config.backup_paths.push(BackupPath { path, priority, offset_minutes, label: None });
+                        println!("{}", t("Путь успешно добавлен!").green());
+                    } else {
+                        println!("{}", t("Этот путь уже добавлен!").yellow());
+                    }
+                } else {
+                    println!("{}", t("Указанный путь не существует!").red());
+                    if read_input_or(t("Создать директорию? (y/n): "), "n")?.to_lowercase() == "y"
+                    {
+                        fs::create_dir_all(path_obj)?;
+                        let priority = read_priority()?;
+                        let offset_minutes = read_offset()?;
+                        config.backup_paths.push(BackupPath { path, priority, offset_minutes, label: None });
+                        println!("{}", t("Директория создана и добавлена!").green());
+                    }
+                }
+                config.save()?;
+            }
+            "2" => {
+                if !config.backup_paths.is_empty() {
+                    println!(
+                        "{}",
+                        t("Внимание! Это действие удалит все пути для бэкапа!").red()
+                    );
+                    if read_input_or(t("Вы уверены? (y/n): "), "n")?.to_lowercase() == "y" {
+                        config.backup_paths.clear();
+                        config.save()?;
+                        println!("{}", t("Все пути успешно удалены!").green());
+                    }
+                } else {
+                    println!("{}", t("Список путей уже пуст!").yellow());
+                }
+            }
+            "3" => break,
+            _ => println!("{}", t("Неверный выбор, попробуйте снова")),
+        }
+    }
+    Ok(())
+}
+
+fn create_gitignore(backup_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let gitignore_content = r#"# Временные файлы
+*.tmp
+*.temp
+*.log
+*.pid
+*.swp
+*.swo
+*~
+
+# Системные файлы
+.DS_Store
+Thumbs.db
+desktop.ini
+
+# Большие файлы (больше 100MB будут игнорироваться)
+*.iso
+*.img
+*.dmg
+*.vdi
+*.vmdk
+
+# Кэши
+*.cache
+cache/
+.cache/
+node_modules/
+.npm/
+.yarn/
+
+# Личные данные
+*.key
+*.pem
+*.p12
+*.pfx
+id_rsa
+id_ecdsa
+id_ed25519
+"#;
+    let gitignore_path = format!("{}/.gitignore", backup_dir);
+    fs::write(gitignore_path, gitignore_content)?;
+    Ok(())
+}
+
+/// Созданный архив пути
+struct PathArchive {
+    name: String,
+    size: Option<u64>,
+    /// Индекс файлов для manifest.json
+    files: Vec<FileEntry>,
+    /// Состояние всех файлов пути для следующего инкрементального бэкапа
+    states: incremental::FileStates,
+    /// Файлы базового бэкапа, которых больше нет
+    deleted: Vec<String>,
+}
+
+/// Создаёт tar-архив одного пути; с `previous` архивируются только файлы,
+/// изменившиеся после базового бэкапа
+#[allow(clippy::too_many_arguments)]
+fn create_archive(
+    path: &str,
+    archive_name: String,
+    target_dir: &str,
+    compression: &compression::Selected,
+    ignore_files: bool,
+    exclude: &[String],
+    previous: Option<&incremental::FileStates>,
+    events: &dyn EventSink,
+) -> Result<PathArchive, Box<dyn std::error::Error>> {
+    let path_obj = std::path::Path::new(path);
+    let archive_path = std::path::Path::new(target_dir).join(&archive_name);
+
+    println!("📁 Архивирование: {} → {}", path, archive_name);
+
+    events.emit(Event::ArchiveStarted {
+        source: path.to_string(),
+        archive: archive_name.clone(),
+    });
+    let created = archive::create(path_obj, &archive_path, compression, ignore_files, exclude, previous, |name, progress| {
+        events.emit(Event::FileArchived {
+            archive: archive_name.clone(),
+            path: name.to_string_lossy().to_string(),
+            files: progress.files,
+            bytes: progress.bytes,
+        });
+    });
+    let created = match created {
+        Ok(created) => created,
+        Err(e) => {
+            // Не оставляем в бэкапе недописанный архив
+            let _ = fs::remove_file(&archive_path);
+            events.emit(Event::ArchiveFinished {
+                archive: archive_name,
+                size: None,
+                files: 0,
+            });
+            return Err(e);
+        }
+    };
+
+    // Получаем размер архива
+    let size = fs::metadata(&archive_path).ok().map(|m| m.len());
+    events.emit(Event::ArchiveFinished {
+        archive: archive_name.clone(),
+        size,
+        files: created.files.len(),
+    });
+    match size {
+        Some(size) => info!(
+            event = "archive_created", archive = archive_name.as_str(), size = size, files = created.files.len();
+            "Архив создан: {} (размер: {} байт, файлов: {})", archive_name, size, created.files.len()
+        ),
+        None => warn!("Не удалось определить размер архива {}", archive_name),
+    }
+    let deleted = previous.map(|previous| incremental::deleted(previous, &created.states)).unwrap_or_default();
+    Ok(PathArchive {
+        name: archive_name,
+        size,
+        files: created.files,
+        states: created.states,
+        deleted,
+    })
+}
+
+/// Архив источника: имя, размер (у зашифрованного — размер .age), выгруженные базы,
+/// индекс файлов, файлы базового бэкапа, которых больше нет, и состояние файлов пути
+type SourceArchive = (String, Option<u64>, Vec<String>, Vec<FileEntry>, Vec<String>, Option<incremental::FileStates>);
+
+/// Создаёт архив одного источника и шифрует его, если задан ключ.
+/// С `previous` архивируются только файлы, изменившиеся после базового бэкапа
+fn archive_source(
+    config: &Config,
+    source: &Source,
+    previous: Option<&incremental::FileStates>,
+    target_dir: &str,
+    encryption: Option<&crypto::Key>,
+    events: &dyn EventSink,
+    file_system: &dyn system::FileSystem,
+) -> Result<SourceArchive, Box<dyn std::error::Error>> {
+    let compression = config.compression();
+    let created = match source {
+        Source::Path { index, entry } => {
+            let selected = compression.select(std::path::Path::new(&entry.path));
+            // По умолчанию `dir_<n>_<имя>.<расширение>` или `file_<n>_<имя>.<расширение>`
+            let archive_name = format!("{}.{}", layout::archive_name(config, *index, entry)?, selected.extension());
+            create_archive(
+                &entry.path,
+                archive_name,
+                target_dir,
+                &selected,
+                config.ignore_files.unwrap_or(false),
+                &config.exclude,
+                previous,
+                events,
+            )
+            .map(|archive| (archive.name, archive.size, Vec::new(), archive.files, archive.deleted, Some(archive.states)))
+        }
+        // Тома, как и дампы, всегда архивируются целиком
+        Source::Docker { index, entry } => match (&entry.volume, &entry.compose) {
+            (Some(volume), _) => docker::mountpoint(volume).and_then(|mountpoint| {
+                let selected = compression.select(std::path::Path::new(&mountpoint));
+                let archive_name = format!("volume_{}_{}.{}", index + 1, volume, selected.extension());
+                create_archive(
+                    &mountpoint,
+                    archive_name,
+                    target_dir,
+                    &selected,
+                    config.ignore_files.unwrap_or(false),
+                    &config.exclude,
+                    None,
+                    events,
+                )
+                .map(|archive| (archive.name, archive.size, Vec::new(), archive.files, Vec::new(), None))
+            }),
+            (None, Some(_)) => docker::images_dump(entry, *index, target_dir)
+                .map(|(archive_name, size)| (archive_name, size, Vec::new(), Vec::new(), Vec::new(), None)),
+            (None, None) => docker::exec_dump(entry, *index, target_dir)
+                .map(|(archive_name, size)| (archive_name, size, Vec::new(), Vec::new(), Vec::new(), None)),
+        },
+        // Дамп базы всегда полный
+        Source::Database { index, entry } => database::dump(entry, *index, target_dir)
+            .map(|dump| (dump.archive_name, dump.size, dump.databases, Vec::new(), Vec::new(), None)),
+    }?;
+
+    // В репозиторий уходит зашифрованный файл, поэтому и размер берётся у него
+    match encryption {
+        Some(key) => {
+            let (archive_name, _, databases, files, deleted, states) = created;
+            let path = crypto::encrypt_file(key, &std::path::Path::new(target_dir).join(&archive_name))?;
+            Ok((archive_name, file_system.file_size(&path).ok(), databases, files, deleted, states))
+        }
+        None => Ok(created),
+    }
+}
+
+/// Выполняет `f` для каждого элемента не более чем в `jobs` потоках и возвращает
+/// результаты в порядке элементов. Потоки работают в профиле и часовом поясе текущего
+fn run_parallel<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let results: Vec<std::sync::Mutex<Option<R>>> = items.iter().map(|_| std::sync::Mutex::new(None)).collect();
+    let profile = Config::profile();
+    let zone = timestamp::zone();
+    let root = staging::root();
+    let quiet = progress::thread_quiet();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            scope.spawn(|| {
+                // Имя уже проверено при выборе профиля в текущем потоке
+                let _ = Config::set_profile(profile.clone());
+                timestamp::set_thread_zone(zone);
+                staging::set_thread_root(Some(root.clone()));
+                progress::set_thread_quiet(quiet);
+                loop {
+                    let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    let Some(item) = items.get(i) else { break };
+                    *results[i].lock().unwrap() = Some(f(item));
+                }
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap().expect("каждый элемент обработан одним из потоков"))
+        .collect()
+}
+
+/// Создаёт рабочую папку `backup_dir` и подключает к ней хранилище: рабочую копию
+/// репозитория Gitea, синхронизированную с удалённым, или бакет S3
+fn prepare_repository(config: &Config, backup_dir: &str) -> Result<Box<dyn Backend>, Box<dyn std::error::Error>> {
+    fs::create_dir_all(backup_dir)?;
+    info!("Создана временная папка: {}", backup_dir);
+
+    let storages = [
+        config.s3.is_some(),
+        config.sftp.is_some(),
+        config.git_ssh.is_some(),
+        config.rclone.is_some(),
+        config.webdav.is_some(),
+    ];
+    if storages.iter().filter(|set| **set).count() > 1 {
+        return Err("В config.json задано несколько хранилищ (s3, sftp, git_ssh, rclone, webdav) — оставьте одно".into());
+    }
+    if let Some(s3) = &config.s3 {
+        println!("⚙️ Подключение к S3...");
+        return Ok(Box::new(s3::Storage::open(s3, backup_dir, config.upload_limit_kbps)?));
+    }
+    if let Some(sftp) = &config.sftp {
+        println!("⚙️ Подключение к {} по SFTP...", sftp.host);
+        return Ok(Box::new(sftp::Storage::open(sftp, backup_dir, config.upload_limit_kbps)?));
+    }
+    if let Some(rclone) = &config.rclone {
+        println!("⚙️ Подключение к {} через rclone...", rclone.remote);
+        return Ok(Box::new(rclone::Storage::open(rclone, backup_dir, config.upload_limit_kbps)?));
+    }
+    if let Some(webdav) = &config.webdav {
+        println!("⚙️ Подключение к {} по WebDAV...", webdav.url);
+        return Ok(Box::new(webdav::Storage::open(webdav, backup_dir, config.upload_limit_kbps)?));
+    }
+    if let Some(git_ssh) = &config.git_ssh {
+        println!("⚙️ Подключение к {} по SSH...", git_ssh.url);
+        git::init_bare(git_ssh)?;
+    }
+
+    println!("⚙️ Настройка Git репозитория...");
+    let workspace = git::Workspace::open(config, backup_dir)?;
+
+    // Создаем .gitignore только если его нет
+    let gitignore_path = format!("{}/.gitignore", backup_dir);
+    if !std::path::Path::new(&gitignore_path).exists() {
+        create_gitignore(backup_dir)?;
+        info!("Создан .gitignore файл");
+    }
+
+    if config.archives_in_packages() {
+        return Ok(Box::new(gitea::Packages::new(config, workspace, backup_dir)?));
+    }
+    Ok(Box::new(workspace))
+}
+
+/// Делит архивы на группы, каждая из которых не превышает `limit` байт.
+/// Архив больше лимита уходит отдельной группой.
+fn split_into_batches(archives: &[ArchiveEntry], limit: u64) -> Vec<Vec<&ArchiveEntry>> {
+    let mut batches: Vec<Vec<&ArchiveEntry>> = Vec::new();
+    let mut current_size = 0;
+
+    for archive in archives {
+        if archive.size > limit {
+            warn!("Архив {} больше лимита одного push и будет загружен отдельно", archive.name);
+        }
+        match batches.last_mut() {
+            Some(batch) if current_size + archive.size <= limit => {
+                batch.push(archive);
+                current_size += archive.size;
+            }
+            _ => {
+                batches.push(vec![archive]);
+                current_size = archive.size;
+            }
+        }
+    }
+    batches
+}
+
+/// Сохраняет в каталоге отметку о незавершённом запуске до первой промежуточной загрузки
+fn mark_run_partial(name: &str, created_at: &str, run_labels: &Labels, archives: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut catalog = Catalog::load()?;
+    catalog.record_partial(name, created_at, run_labels, archives);
+    catalog.save()
+}
+
+/// Параметры конкретного запуска бэкапа
+#[derive(Debug, Default)]
+struct BackupOptions {
+    labels: Labels,
+    pinned: bool,
+    /// Микро-бэкап режима наблюдения: только эти пути вместо всех источников конфигурации.
+    /// Он всегда полный и не меняет ни `last_backup`, ни индекс инкрементальных бэкапов
+    paths: Option<Vec<BackupPath>>,
+    /// Выполнить бэкап, даже если другой ещё идёт (`obt backup --force`)
+    force: bool,
+    /// Куда отправлять ход бэкапа вместо полос прогресса (`BackupRunner::events`)
+    events: Option<std::sync::mpsc::Sender<Event>>,
+}
+
+/// Приводит метку к виду, безопасному для имени папки
+fn sanitize_label(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect()
+}
+
+/// Собирает метки запуска: сначала из `label_hook`, затем из флагов (флаги приоритетнее)
+fn collect_labels(
+    config: &Config,
+    options: &BackupOptions,
+    runner: &dyn system::Runner,
+) -> Result<Labels, Box<dyn std::error::Error>> {
+    let mut result = Labels::new();
+
+    if let Some(hook) = &config.label_hook {
+        let output = runner.run(hook)?;
+        if !output.status.success() {
+            forensics::record_output(&output.stderr);
+            return Err(format!(
+                "Хук меток завершился с ошибкой: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        result.extend(labels::parse_hook_output(&String::from_utf8_lossy(&output.stdout)));
+    }
+
+    result.extend(options.labels.clone());
+    Ok(result)
+}
+
+/// Запускает `pre_upload_check` над готовой папкой бэкапа; ненулевой код выхода отменяет
+/// загрузку бэкапа и удаление старых по политике хранения
+fn run_pre_upload_check(
+    check: &str,
+    name: &str,
+    backup_dir: &str,
+    runner: &dyn system::Runner,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("🔎 Проверка бэкапа перед загрузкой...");
+    let output = runner.run_with_env(check, &[("OBT_BACKUP_NAME", name), ("OBT_BACKUP_DIR", backup_dir)])?;
+    if !output.status.success() {
+        forensics::record_output(&output.stderr);
+        let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        warn!("Проверка перед загрузкой отклонила бэкап {}: {}", name, reason);
+        return Err(format!("Проверка перед загрузкой отклонила бэкап, он не загружен: {}", reason).into());
+    }
+    info!("Проверка перед загрузкой пройдена: {}", name);
+    Ok(())
+}
+
+/// Итоги успешного бэкапа
+#[derive(Debug)]
+struct BackupSummary {
+    name: String,
+    total_size: u64,
+    archives: usize,
+    skipped: usize,
+    /// Описания архивов с необычным размером
+    anomalies: Vec<SizeAnomaly>,
+    /// Содержимое backup_info.txt, для письма об итогах
+    info: String,
+    run_id: Option<String>,
+}
+
+/// Выполняет бэкап и все действия после него (метрики и т.п.) независимо от результата
+fn execute_backup(config: &mut Config, options: &BackupOptions) -> Result<BackupSummary, Box<dyn std::error::Error>> {
+    let _lock = lock::BackupLock::acquire(options.force)?;
+    let started = std::time::Instant::now();
+    forensics::begin_run();
+    let mut system = system::System::real();
+    if let Some(events) = &options.events {
+        system.events = Box::new(events.clone());
+    }
+    let result = perform_backup(config, options, &system);
+    let duration = started.elapsed();
+
+    match &result {
+        Ok(summary) => info!(
+            event = "backup_finished", backup = summary.name.as_str(), duration = duration.as_secs_f64(),
+            size = summary.total_size, archives = summary.archives;
+            "Бэкап {} выполнен за {:.1} сек.", summary.name, duration.as_secs_f64()
+        ),
+        Err(e) => error!(
+            event = "backup_failed", duration = duration.as_secs_f64(), error = e.to_string().as_str();
+            "Бэкап завершился ошибкой за {:.1} сек.: {}", duration.as_secs_f64(), e
+        ),
+    }
+    if let Err(e) = &result {
+        forensics::report_failure(config, &e.to_string());
+        config.last_failure = Some(timestamp::format(Utc::now()));
+        config.last_error = Some(e.to_string());
+        config.last_error_output = forensics::failure_output(config);
+        if let Err(e) = config.save() {
+            warn!("Не удалось сохранить результат запуска: {}", e);
+        }
+    }
+
+    if let Err(e) = metrics::push(config, result.as_ref().ok(), duration) {
+        warn!("{}", e);
+    }
+    if let Ok(summary) = &result {
+        if let Err(e) = stamp::write(config, summary, duration) {
+            warn!("{}", e);
+        }
+        if let Err(e) = gitea::update_repo_status(config, summary) {
+            warn!("{}", e);
+        }
+    }
+    notify::send(config, &backup_notification(config, &result, duration));
+    webhook::send(config, &backup_payload(config, &result, duration));
+    forensics::end_run();
+
+    result
+}
+
+/// Итог запуска для вебхуков
+fn backup_payload(
+    config: &Config,
+    result: &Result<BackupSummary, Box<dyn std::error::Error>>,
+    duration: std::time::Duration,
+) -> webhook::Payload {
+    let mut payload = webhook::Payload {
+        status: "success",
+        server: config.backup_name.clone().unwrap_or_else(|| "obt".to_string()),
+        name: None,
+        duration_seconds: duration.as_secs_f64(),
+        size: None,
+        archives: None,
+        skipped: None,
+        error: None,
+        run_id: forensics::run_id(),
+    };
+    match result {
+        Ok(summary) => {
+            payload.name = Some(summary.name.clone());
+            payload.size = Some(summary.total_size);
+            payload.archives = Some(summary.archives);
+            payload.skipped = Some(summary.skipped);
+        }
+        Err(e) => {
+            payload.status = "failure";
+            payload.error = Some(e.to_string());
+        }
+    }
+    payload
+}
+
+/// Итог запуска для notify_command, Telegram и писем: об ошибке — с её текстом, к письму
+/// прикладывается backup_info.txt или конец журнала запуска
+fn backup_notification(
+    config: &Config,
+    result: &Result<BackupSummary, Box<dyn std::error::Error>>,
+    duration: std::time::Duration,
+) -> notify::Notification {
+    let server = config.backup_name.as_deref().unwrap_or("obt");
+    let language = i18n::notify_language(config);
+    match result {
+        Ok(summary) => {
+            let mut text = tf_in(
+                language,
+                "Сервер {}: {}\nАрхивов: {}, размер {} МБ, за {} сек.",
+                &[
+                    &server,
+                    &summary.name,
+                    &summary.archives,
+                    &format!("{:.1}", summary.total_size as f64 / 1_048_576.0),
+                    &duration.as_secs(),
+                ],
+            );
+            if summary.skipped > 0 {
+                text.push_str(&tf_in(language, "\n⚠️ Пропущено источников: {}", &[&summary.skipped]));
+            }
+            if !summary.anomalies.is_empty() {
+                let anomalies: Vec<String> = summary.anomalies.iter().map(|a| a.describe_in(language)).collect();
+                text.push_str(&tf_in(language, "\n📈 Необычный размер архивов:\n{}", &[&anomalies.join("\n")]));
+            }
+            notify::Notification {
+                level: notify::Level::Info,
+                title: t_in(language, "Бэкап выполнен").to_string(),
+                text,
+                run_id: forensics::run_id(),
+                attachments: vec![smtp::Attachment { name: "backup_info.txt".to_string(), content: summary.info.clone() }],
+            }
+        }
+        Err(e) => {
+            let mut text = tf_in(
+                language,
+                "Сервер {}: бэкап завершился ошибкой после {} сек.\n\n{}",
+                &[&server, &duration.as_secs(), e],
+            );
+            if let Some(output) = &config.last_error_output {
+                text.push_str(&tf_in(language, "\n\n📄 Вывод команды:\n{}", &[output]));
+            }
+            notify::Notification {
+                level: notify::Level::Error,
+                title: t_in(language, "Бэкап не выполнен").to_string(),
+                text,
+                run_id: forensics::run_id(),
+                attachments: vec![smtp::Attachment { name: "log.txt".to_string(), content: forensics::log_excerpt() }],
+            }
+        }
+    }
+}
+
+/// Этап бэкапа: для отчёта о сбое и для получателя событий
+fn start_phase(system: &system::System, phase: impl Into<String>) {
+    let phase = phase.into();
+    forensics::set_phase(phase.clone());
+    system.events.emit(Event::PhaseStarted { phase });
+}
+
+/// Отсчёт времени архивации: смещения путей от начала бэкапа и `time_budget_minutes`
+struct Timeline<'a> {
+    clock: &'a dyn system::Clock,
+    started: DateTime<Utc>,
+    /// Сколько ушло на ожидание смещений
+    waited: std::time::Duration,
+    budget: Option<std::time::Duration>,
+}
+
+impl<'a> Timeline<'a> {
+    fn new(clock: &'a dyn system::Clock, budget_minutes: Option<u64>) -> Self {
+        Timeline {
+            clock,
+            started: clock.now(),
+            waited: std::time::Duration::ZERO,
+            budget: budget_minutes.map(|minutes| std::time::Duration::from_secs(minutes * 60)),
+        }
+    }
+
+    /// Сколько ещё ждать пути со смещением `offset` минут; `None` — его время уже настало
+    fn until_offset(&self, offset: u64) -> Option<std::time::Duration> {
+        std::time::Duration::from_secs(offset * 60)
+            .checked_sub(self.clock.elapsed_since(self.started))
+            .filter(|delay| !delay.is_zero())
+    }
+
+    fn wait(&mut self, delay: std::time::Duration) {
+        self.clock.sleep(delay);
+        self.waited += delay;
+    }
+
+    /// Ожидание смещений в бюджет времени не входит
+    fn budget_exhausted(&self) -> bool {
+        self.budget
+            .is_some_and(|budget| self.clock.elapsed_since(self.started).saturating_sub(self.waited) > budget)
+    }
+}
+
+fn perform_backup(
+    config: &mut Config,
+    options: &BackupOptions,
+    system: &system::System,
+) -> Result<BackupSummary, Box<dyn std::error::Error>> {
+    let all_sources = match &options.paths {
+        Some(paths) => paths.iter().enumerate().map(|(index, entry)| Source::Path { index, entry }).collect(),
+        None => Source::all(config),
+    };
+    if all_sources.is_empty() {
+        return Err("Нет путей для бэкапа! Сначала добавьте файлы/директории.".into());
+    }
+    let source_list: Vec<(String, Priority)> = all_sources.iter().map(|s| (s.describe(), s.priority())).collect();
+
+    let run_labels = collect_labels(config, options, system.runner.as_ref())?;
+    preset::check(config)?;
+    let encryption = crypto::Key::load(config)?;
+    start_phase(system, "проверка секретов");
+    secrets::check(config)?;
+
+    let run_id = forensics::run_id();
+    info!(
+        event = "backup_started", run_id = run_id.as_deref().unwrap_or_default();
+        "Начинаем выполнение бэкапа (запуск {})...", run_id.as_deref().unwrap_or("-")
+    );
+    let compression = config.compression();
+    println!("🚀 Выполняется бэкап, сжатие: {}...", compression.describe());
+
+    let started_at = system.clock.now();
+    let local_time = started_at.with_timezone(&timestamp::zone());
+    let staging = StagingDir::new(format!("backup_{}", local_time.format("%Y%m%d_%H%M%S")));
+    let backup_dir = staging.path();
+    start_phase(system, "получение репозитория");
+    let storage = prepare_repository(config, backup_dir)?;
+    // Меняется, если история хранилища переписана и бэкап ушёл в отдельную ветку
+    let location = storage.location();
+
+    // Создаем папку для бэкапов
+    let mut backup_folder_name = layout::folder_name(config, started_at)?;
+    if let Some(label) = run_labels.get(labels::DEFAULT_KEY) {
+        backup_folder_name = format!("{}_{}", backup_folder_name, sanitize_label(label));
+    }
+    // Исходное имя с `:` или другими запрещёнными на FAT/exFAT символами остаётся в manifest.json
+    let mut original_folder_name = None;
+    if config.fat_safe_names.unwrap_or(false) {
+        let portable = portable::name(&backup_folder_name);
+        if portable != backup_folder_name {
+            original_folder_name = Some(std::mem::replace(&mut backup_folder_name, portable));
+        }
+    }
+    let mut file_index = incremental::Index::load()?;
+    // Запуск в ту же секунду получает имя базового бэкапа и заменяет его: тогда только полный
+    let base = match options.paths {
+        Some(_) => None,
+        None => file_index.base_for(config, storage.as_ref())?,
+    }
+    .filter(|base| *base != backup_folder_name);
+    if let Some(base) = &base {
+        println!("🔁 Инкрементальный бэкап поверх {}", base);
+    }
+    let mut file_states = BTreeMap::new();
+
+    let backup_path = layout::path(config, &backup_folder_name);
+    let current_backup_dir = format!("{}/{}", backup_dir, backup_path);
+    system.fs.create_dir_all(std::path::Path::new(&current_backup_dir))?;
+    // Инкрементальный бэкап берёт только изменившиеся файлы, и полный объём путей для него
+    // не оценка; объём дампов баз данных и Docker заранее не известен
+    if base.is_none() {
+        start_phase(system, "проверка места");
+        let required = all_sources
+            .iter()
+            .filter_map(|source| match source {
+                Source::Path { entry, .. } => Some(archive::estimate(std::path::Path::new(&entry.path))),
+                _ => None,
+            })
+            .sum();
+        staging.preflight(required)?;
+    }
+
+    // Переменные для статистики
+    let mut total_size = 0u64;
+    let mut archive_info = Vec::new();
+    let mut archive_entries: Vec<ArchiveEntry> = Vec::new();
+
+    // Создаем архивы: пути со смещением ждут своего времени,
+    // внутри одного времени сначала критичные, затем обычные и объёмные
+    println!("📦 Создание архивов...");
+    let mut timeline = Timeline::new(system.clock.as_ref(), config.time_budget_minutes);
+    let low_disk_threshold = config.low_disk_mb.unwrap_or(512) * 1_048_576;
+    // Причина пропуска оставшихся путей, когда во временной папке кончается место
+    let mut low_disk: Option<String> = None;
+    let mut skipped = Vec::new();
+
+    let mut sources = all_sources;
+    sources.sort_by_key(|source| (source.offset_minutes().unwrap_or(0), source.priority()));
+    let mut remaining_critical = sources.iter().filter(|s| s.priority() == Priority::Critical).count();
+    // С проверкой перед загрузкой ничего не загружается до её решения, в том числе критичные архивы
+    let has_critical = remaining_critical > 0 && config.pre_upload_check.is_none();
+    let mut critical_uploaded = false;
+    let mut uploaded_archives = 0;
+    let mut uploaded_bytes = 0;
+    let run_created_at = timestamp::format(started_at);
+
+    let jobs = config.archive_jobs.unwrap_or(1).max(1);
+    let mut sources = sources.into_iter().peekable();
+    while let Some(first) = sources.next() {
+        // Источники с одним смещением и приоритетом архивируются вместе, до `archive_jobs` одновременно
+        let key = (first.offset_minutes().unwrap_or(0), first.priority());
+        let mut group = vec![first];
+        while let Some(source) = sources.next_if(|source| (source.offset_minutes().unwrap_or(0), source.priority()) == key) {
+            group.push(source);
+        }
+        let (offset, priority) = key;
+        let description = group.iter().map(Source::describe).collect::<Vec<_>>().join(", ");
+        if let Some(delay) = timeline.until_offset(offset) {
+            info!("Путь {} ждёт своего смещения: {} мин", description, offset);
+            println!("⏳ {} начнётся через {} мин (смещение {} мин)", description, delay.as_secs().div_ceil(60), offset);
+            timeline.wait(delay);
+        }
+
+        if priority != Priority::Critical {
+            // Критичные данные загружаем отдельным коммитом до начала остальных архивов,
+            // чтобы они оказались в репозитории, даже если запуск прервётся
+            if has_critical && !critical_uploaded && remaining_critical == 0 {
+                println!("🚀 Загрузка критичных архивов...");
+                start_phase(system, "загрузка критичных архивов");
+                mark_run_partial(&backup_folder_name, &run_created_at, &run_labels, archive_entries.len())?;
+                let bytes = archive_entries.iter().map(|a| a.size).sum::<u64>();
+                progress::upload(bytes, || {
+                    storage.upload_all(&format!("🌍 Backup {} - критичные архивы ({})", backup_folder_name, archive_entries.len()))
+                })?;
+                critical_uploaded = true;
+                uploaded_archives = archive_entries.len();
+                uploaded_bytes += bytes;
+                system.events.emit(Event::BytesUploaded { bytes, uploaded: uploaded_bytes });
+            }
+
+            if timeline.budget_exhausted() {
+                for source in &group {
+                    let description = source.describe();
+                    warn!("Бюджет времени исчерпан, путь пропущен: {}", description);
+                    println!("{}", format!("⏱️ Бюджет времени исчерпан, пропуск: {}", description).yellow());
+                    skipped.push(SkippedSource {
+                        path: description,
+                        reason: "исчерпан бюджет времени".to_string(),
+                    });
+                }
+                continue;
+            }
+
+            // Вместо ENOSPC посреди архива: бэкап доводится до конца с тем, что уже готово
+            if low_disk.is_none() && low_disk_threshold > 0 {
+                if let Some(free) = staging.low_on_space(low_disk_threshold) {
+                    let free = format!("{:.0}", free as f64 / 1_048_576.0);
+                    warn!("Во временной папке осталось {} МБ: оставшиеся некритичные пути пропускаются", free);
+                    println!(
+                        "{}",
+                        format!("💽 Во временной папке осталось {} МБ: остальные пути пропускаются, готовые архивы будут загружены", free)
+                            .yellow()
+                    );
+                    low_disk = Some(format!("мало места во временной папке (свободно {} МБ)", free));
+                }
+            }
+            if let Some(reason) = &low_disk {
+                for source in &group {
+                    let description = source.describe();
+                    warn!("Путь пропущен из-за нехватки места: {}", description);
+                    skipped.push(SkippedSource {
+                        path: description,
+                        reason: reason.clone(),
+                    });
+                }
+                continue;
+            }
+        } else {
+            remaining_critical -= group.len();
+        }
+
+        start_phase(system, format!("архивация {}", description));
+        let (events, file_system) = (system.events.as_ref(), system.fs.as_ref());
+        let results = run_parallel(&group, jobs, |source| {
+            let previous = match source {
+                Source::Path { entry, .. } => base.as_ref().and(file_index.sources.get(&entry.path)),
+                Source::Database { .. } | Source::Docker { .. } => None,
+            };
+            archive_source(config, source, previous, &current_backup_dir, encryption.as_ref(), events, file_system)
+                .map_err(|e| e.to_string())
+        });
+
+        // Результаты разбираются в порядке источников, независимо от того, какой архив был готов раньше
+        for (source, created) in group.iter().zip(results) {
+            let description = source.describe();
+            match created {
+                Ok((archive_name, size, databases, files, deleted, states)) => {
+                    if let (Source::Path { entry, .. }, Some(states)) = (source, states) {
+                        file_states.insert(entry.path.clone(), states);
+                    }
+                    match size {
+                        Some(size) => {
+                            total_size += size;
+                            archive_info.push(format!("  📦 {} ({:.2} МБ)", archive_name, size as f64 / 1_048_576.0));
+                        }
+                        None => archive_info.push(format!("  📦 {} (размер неизвестен)", archive_name)),
+                    }
+                    archive_entries.push(ArchiveEntry {
+                        name: archive_name,
+                        source: description,
+                        priority: source.priority(),
+                        size: size.unwrap_or(0),
+                        databases,
+                        excluded_databases: match source {
+                            Source::Database { entry, .. } => entry.exclude.clone(),
+                            Source::Path { .. } | Source::Docker { .. } => Vec::new(),
+                        },
+                        files,
+                        encrypted: encryption.is_some(),
+                        deleted,
+                        sha256: None,
+                        parts: 0,
+                        original_name: None,
+                        kind: match source {
+                            Source::Path { entry, .. } if std::path::Path::new(&entry.path).is_file() => Some(ArchiveKind::File),
+                            Source::Path { .. } => Some(ArchiveKind::Dir),
+                            Source::Docker { entry, .. } if entry.volume.is_some() => Some(ArchiveKind::Volume),
+                            Source::Docker { .. } | Source::Database { .. } => None,
+                        },
+                    });
+                    let entry = archive_entries.last_mut().unwrap();
+                    if config.fat_safe_names.unwrap_or(false) {
+                        portable::rename_archive(std::path::Path::new(&current_backup_dir), entry)?;
+                    }
+                    let archive_path = std::path::Path::new(&current_backup_dir).join(entry.file_name());
+                    match verify::sha256_file(&archive_path) {
+                        Ok(sha256) => entry.sha256 = Some(sha256),
+                        Err(e) => warn!("Не удалось вычислить SHA-256 {}: {}", archive_path.display(), e),
+                    }
+                    // Делится уже готовый архив: SHA-256 считается по целому, как он соберётся при восстановлении
+                    match split::split_above(&archive_path, config.split_archive_mb) {
+                        Ok(0) => {}
+                        Ok(parts) => {
+                            info!("Архив {} разделён на {} частей", entry.name, parts);
+                            entry.parts = parts;
+                        }
+                        Err(e) => return Err(format!("Не удалось разделить архив {}: {}", entry.name, e).into()),
+                    }
+                }
+                Err(e) if source.priority() == Priority::Critical => {
+                    return Err(format!("Не удалось заархивировать критичный путь {}: {}", description, e).into());
+                }
+                Err(e) => {
+                    error!("Не удалось заархивировать {}: {}", description, e);
+                    println!("{}", format!("⚠️ Путь пропущен из-за ошибки: {}", description).yellow());
+                    // Недописанный архив уже удалён; следующие пути упёрлись бы в то же место
+                    if Failure::classify(&e) == Some(Failure::DiskFull) && low_disk.is_none() {
+                        low_disk = Some("закончилось место во временной папке".to_string());
+                    }
+                    skipped.push(SkippedSource {
+                        path: description,
+                        reason: e,
+                    });
+                }
+            }
+        }
+    }
+
+    // Инкрементальные бэкапы не сравниваются: их размер зависит от числа изменений
+    let mut size_history = anomaly::History::load()?;
+    let anomalies = if base.is_none() && options.paths.is_none() {
+        size_history.detect(config, &archive_entries)
+    } else {
+        Vec::new()
+    };
+    for anomaly in &anomalies {
+        warn!("Необычный размер архива: {}", anomaly.describe());
+        println!("{}", format!("📈 Необычный размер архива: {}", anomaly.describe()).yellow());
+    }
+
+    // Создаем файл с информацией о бэкапе
+    let mut backup_info = format!(
+        r#"🌍 OfficialVPN Backup Tool v0.1.3 - Информация о бэкапе
+
+📅 Дата и время: {}
+🏷️  Имя бэкапа: {}
+🔖 Метки: {}{}
+📊 Общий размер архивов: {:.2} МБ
+📦 Количество архивов: {}
+
+📋 Архивы:
+{}{}{}
+
+💾 Исходные пути:
+{}
+
+🔧 Технические детали:
+- Формат: {}  
+- Тип: {}
+- Временная зона: дата выше — {}, в manifest.json и имени папки — UTC
+- Хранилище: {}
+- Кодировка: UTF-8
+
+🌍 Сервер: {}
+👤 Пользователь: {}
+"#,
+        local_time.format("%Y-%m-%d %H:%M:%S %Z"),
+        backup_folder_name,
+        if run_labels.is_empty() { "нет".to_string() } else { labels::format(&run_labels) },
+        if options.pinned { " (закреплён)" } else { "" },
+        total_size as f64 / 1_048_576.0,
+        archive_info.len(),
+        archive_info.join("\n"),
+        if skipped.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\n\n⚠️ Пропущенные пути:\n{}",
+                skipped.iter().map(|s| format!("  ⏭️ {} ({})", s.path, s.reason)).collect::<Vec<_>>().join("\n")
+            )
+        },
+        if anomalies.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\n\n📈 Необычный размер архивов:\n{}",
+                anomalies.iter().map(|a| format!("  ⚠️ {}", a.describe())).collect::<Vec<_>>().join("\n")
+            )
+        },
+        source_list.iter().map(|(source, priority)| format!("  📂 {} [{}]", source, priority)).collect::<Vec<_>>().join("\n"),
+        compression.describe(),
+        match &base {
+            Some(base) => format!("инкрементальный поверх {}", base),
+            None => "полный".to_string(),
+        },
+        timestamp::zone(),
+        storage.describe(),
+        config.gitea_url.as_ref().unwrap_or(&"неизвестно".to_string()),
+        config.gitea_username.as_ref().unwrap_or(&"неизвестно".to_string())
+    );
+
+    let info_path = format!("{}/backup_info.txt", current_backup_dir);
+    system.fs.write(std::path::Path::new(&info_path), backup_info.as_bytes())?;
+    info!("Создан файл backup_info.txt");
+
+    let manifest = Manifest {
+        name: backup_folder_name.clone(),
+        created_at: run_created_at.clone(),
+        labels: run_labels,
+        pinned: options.pinned,
+        base: base.clone(),
+        branch: storage.location(),
+        paths: source_list.into_iter().map(|(source, _)| source).collect(),
+        archives: archive_entries,
+        skipped,
+        anomalies,
+        total_size,
+        run_id,
+        original_name: original_folder_name,
+        backup_name: config.backup_name.clone(),
+    };
+    manifest.write(std::path::Path::new(&current_backup_dir))?;
+    info!("Создан файл {}", manifest::MANIFEST_FILE);
+
+    report::write(
+        &manifest,
+        config.backup_name.as_deref().unwrap_or("неизвестно"),
+        i18n::notify_language(config),
+        std::path::Path::new(&current_backup_dir),
+    )?;
+    info!("Создан отчёт {}", report::REPORT_FILE);
+
+    if let Some(check) = &config.pre_upload_check {
+        run_pre_upload_check(check, &backup_folder_name, &current_backup_dir, system.runner.as_ref())?;
+    }
+
+    // Применяем политику хранения: удаляем устаревшие бэкапы этого сервера из репозитория
+    let mut catalog = Catalog::load()?;
+    catalog.record(&manifest, &backup_path);
+    // С отдельным расписанием очистки бэкап ничего не удаляет
+    let expired = if config.prune_schedule.is_none() {
+        retention::sweep(config, &mut catalog)
+    } else {
+        Vec::new()
+    };
+    for name in &expired {
+        if layout::remove(storage.as_ref(), name)? {
+            println!("🗑️ Удаляется устаревший бэкап: {}", name);
+            info!("Бэкап удалён по политике хранения: {}", name);
+        }
+    }
+
+    // Если задан лимит размера одного push, сначала загружаем архивы частями,
+    // а метаданные и удаления по политике хранения — финальным коммитом
+    if let Some(limit_mb) = config.max_push_mb {
+        let pending = &manifest.archives[uploaded_archives..];
+        let batches = split_into_batches(pending, limit_mb * 1_048_576);
+        if batches.len() > 1 {
+            mark_run_partial(&backup_folder_name, &run_created_at, &manifest.labels, uploaded_archives)?;
+            for (i, batch) in batches.iter().enumerate() {
+                println!("🚀 Загрузка части {} из {} ({} архивов)...", i + 1, batches.len(), batch.len());
+                start_phase(system, format!("загрузка части {}/{}", i + 1, batches.len()));
+                let files: Vec<String> = batch
+                    .iter()
+                    .flat_map(|a| a.stored_files())
+                    .map(|file| format!("{}/{}", backup_path, file))
+                    .collect();
+                let bytes = batch.iter().map(|a| a.size).sum::<u64>();
+                progress::upload(bytes, || {
+                    storage.upload_paths(&files, &format!("🌍 Backup {} - часть {}/{}", backup_folder_name, i + 1, batches.len()))
+                })?;
+                uploaded_archives += batch.len();
+                uploaded_bytes += bytes;
+                system.events.emit(Event::BytesUploaded { bytes, uploaded: uploaded_bytes });
+                mark_run_partial(&backup_folder_name, &run_created_at, &manifest.labels, uploaded_archives)?;
+            }
+        }
+    }
+
+    if storage.location() != location {
+        println!("⚠️ Бэкап загружен в ветку {}, проверка после загрузки пропущена", storage.location());
+        warn!("Проверка после загрузки пропущена: бэкап в ветке {}", storage.location());
+    } else if config.verify_after_upload() {
+        // Архивы загружаются и сверяются до метаданных и удаления устаревших бэкапов:
+        // если в хранилище что-то не так, прежние бэкапы остаются на месте
+        let pending = &manifest.archives[uploaded_archives..];
+        if !pending.is_empty() {
+            println!("🚀 Загрузка архивов...");
+            start_phase(system, "загрузка архивов");
+            mark_run_partial(&backup_folder_name, &run_created_at, &manifest.labels, uploaded_archives)?;
+            let files: Vec<String> = pending
+                .iter()
+                .flat_map(|a| a.stored_files())
+                .map(|file| format!("{}/{}", backup_path, file))
+                .collect();
+            let bytes = pending.iter().map(|a| a.size).sum::<u64>();
+            progress::upload(bytes, || {
+                storage.upload_paths(&files, &format!("🌍 Backup {} - архивы ({})", backup_folder_name, files.len()))
+            })?;
+            uploaded_bytes += bytes;
+            system.events.emit(Event::BytesUploaded { bytes, uploaded: uploaded_bytes });
+            uploaded_archives = manifest.archives.len();
+            mark_run_partial(&backup_folder_name, &run_created_at, &manifest.labels, uploaded_archives)?;
+        }
+
+        println!("🔍 Проверка загруженного бэкапа...");
+        start_phase(system, "проверка после загрузки");
+        let verification = verify::remote(config, &backup_folder_name, Some(&manifest))?;
+        verify::print(&verification);
+        backup_info = format!("{}\n{}\n", backup_info, verify::summary(&verification));
+        system.fs.write(std::path::Path::new(&info_path), backup_info.as_bytes())?;
+
+        let problems = verification.problems.join("; ");
+        config.last_verification = Some(verification);
+        if !problems.is_empty() {
+            config.save()?;
+            return Err(format!("Бэкап в хранилище не совпадает с загруженным: {}", problems).into());
+        }
+    }
+
+    // Коммитим и пушим все оставшиеся изменения
+    println!("🚀 Загрузка в хранилище...");
+    start_phase(system, "загрузка в хранилище");
+
+    // Метаданные в счёт не идут: события и полоса загрузки считают только архивы
+    let bytes = total_size.saturating_sub(uploaded_bytes);
+    progress::upload(bytes, || {
+        storage.upload_all(&format!(
+            "🌍 Backup {} - {} архивов ({:.1} МБ) - {}",
+            backup_folder_name,
+            archive_info.len(),
+            total_size as f64 / 1_048_576.0,
+            local_time.format("%Y-%m-%d %H:%M %Z")
+        ))
+    })?;
+    catalog.set_revision(&backup_folder_name, storage.revision());
+    system.events.emit(Event::BytesUploaded { bytes, uploaded: total_size });
+
+    // Очистка
+    system.fs.remove_dir_all(std::path::Path::new(backup_dir))?;
+    info!("Временные файлы удалены");
+
+    // Микро-бэкап сохраняет лишь часть данных и не считается полноценным бэкапом
+    let micro = options.paths.is_some();
+    if !micro {
+        config.last_backup = Some(run_created_at.clone());
+        config.save()?;
+    }
+
+    catalog.remove(&expired);
+    catalog.save()?;
+
+    if !micro {
+        size_history.record(&manifest);
+        size_history.save()?;
+    }
+
+    if config.incremental.is_some() && !micro {
+        // После полного бэкапа пропущенные пути не должны считаться сохранёнными:
+        // следующий запуск заархивирует их целиком
+        if base.is_none() {
+            file_index.sources.clear();
+        }
+        file_index.sources.extend(file_states);
+        file_index.sources.retain(|path, _| config.backup_paths.iter().any(|p| &p.path == path));
+        file_index.chain_length = if base.is_some() { file_index.chain_length + 1 } else { 0 };
+        file_index.backup = Some(backup_folder_name.clone());
+        file_index.save()?;
+    }
+
+    println!("{}", "✅ Бэкап успешно выполнен!".green());
+    println!("📊 Общий размер архивов: {:.2} МБ", total_size as f64 / 1_048_576.0);
+    println!("📦 Создано архивов: {}", archive_info.len());
+    info!("Бэкап завершен успешно. Общий размер: {} байт", total_size);
+
+    Ok(BackupSummary {
+        name: manifest.name,
+        total_size,
+        archives: manifest.archives.len(),
+        skipped: manifest.skipped.len(),
+        anomalies: manifest.anomalies.clone(),
+        info: backup_info,
+        run_id: forensics::run_id(),
+    })
+}
+
+/// Поток с тем же профилем, часовым поясом и временной папкой, что и у текущего
+fn spawn_in_profile<F>(f: F) -> std::thread::JoinHandle<()>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let profile = Config::profile();
+    let zone = timestamp::zone();
+    let root = staging::root();
+    std::thread::spawn(move || {
+        // Имя уже проверено при выборе профиля в текущем потоке
+        let _ = Config::set_profile(profile);
+        timestamp::set_thread_zone(zone);
+        staging::set_thread_root(Some(root));
+        f()
+    })
+}
+
+/// Демон обслуживает профиль по умолчанию и все профили из ~/.config/obt/profiles:
+/// у каждого своё расписание и своя очередь, а запуски разных профилей идут одновременно
+fn run_daemon_mode(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Запуск в режиме демона...");
+    let mut queues: control::Queues = vec![(None, Arc::new(RunQueue::default()))];
+    for profile in Config::profiles()? {
+        queues.push((Some(profile), Arc::new(RunQueue::default())));
+    }
+    control::serve(queues.clone(), || timestamp::format(Utc::now()))?;
+
+    for (profile, queue) in queues.iter().skip(1).cloned() {
+        std::thread::spawn(move || {
+            let name = profile.clone().unwrap_or_default();
+            let result = Config::set_profile(profile).and_then(|_| {
+                let mut config = Config::load()?;
+                timestamp::set_thread_zone(config.zone()?);
+                // Остатки сбоев убираются и в work_dir профиля, а не только в папке по умолчанию
+                staging::set_thread_root(config.work_dir.clone());
+                staging::clean_stale();
+                run_profile(&mut config, &queue)
+            });
+            if let Err(e) = result {
+                error!("Профиль {} не обслуживается: {}", name, e);
+            }
+        });
+    }
+    run_profile(config, &queues[0].1)
+}
+
+/// Расписание и очередь одного профиля демона
+fn run_profile(config: &mut Config, queue: &Arc<RunQueue>) -> Result<(), Box<dyn std::error::Error>> {
+    let profile = Config::profile().unwrap_or_else(|| "default".to_string());
+    forensics::set_backup_name(config.backup_name.clone());
+    info!("Профиль {}: расписание {:?} ({})", profile, config.backup_cron.as_ref().or(config.backup_time.as_ref()), timestamp::zone());
+    println!("{}", format!("⏰ Профиль {}: расписание в часовом поясе {}", profile, timestamp::zone()).yellow());
+    let timer = SystemdService::check_timer(config);
+    if config.backup_scheduled() {
+        if let TimerCheck::Drift { installed, expected } = &timer {
+            warn!(
+                "Профиль {}: {} срабатывает по {}, а в config.json расписание {} — выполните obt doctor --fix",
+                profile,
+                SystemdService::timer_unit(),
+                installed,
+                expected
+            );
+            println!(
+                "{}",
+                format!("⚠️ Таймер {} не совпадает с config.json: obt doctor --fix пересоздаст его", SystemdService::timer_unit()).yellow()
+            );
+        }
+    }
+
+    let scheduler_queue = Arc::clone(queue);
+    // Плановые бэкапы профиля ставит в очередь его таймер obt-<профиль>.timer, а пока таймер
+    // не установлен — сам демон; у профиля по умолчанию obt.timer лишь будит демон
+    let timer_owned = Config::profile().is_some() && !matches!(timer, TimerCheck::NotInstalled);
+    if timer_owned {
+        info!("Профиль {}: бэкапы по расписанию запускает {}", profile, SystemdService::timer_unit());
+    }
+    let backup_time = config.backup_time.clone().filter(|_| !timer_owned);
+    let backup_cron = match config.cron_schedule() {
+        Ok(cron) => cron.filter(|_| !timer_owned),
+        Err(e) => {
+            warn!("Расписание backup_cron не применяется: {}", e);
+            None
+        }
+    };
+    let mut maintenance = Vec::new();
+    for (task, schedule) in [(Task::Prune, &config.prune_schedule), (Task::Verify, &config.verify_schedule)] {
+        let Some(schedule) = schedule else { continue };
+        match schedule.parsed_time() {
+            Ok(time) => {
+                info!("Расписание {:?}: {:?} в {}", task, schedule.frequency, schedule.time);
+                maintenance.push((task, schedule.clone(), time));
+            }
+            Err(e) => warn!("Расписание {:?} не применяется: {}", task, e),
+        }
+    }
+    spawn_in_profile(move || run_scheduler(backup_time, backup_cron, &maintenance, &scheduler_queue, &system::SystemClock));
+    spawn_in_profile(freshness::run_watchdog);
+    if let Some(boot) = config.boot_backup.clone().filter(|boot| !boot.timer) {
+        let boot_queue = Arc::clone(queue);
+        spawn_in_profile(move || boot::watch(boot, &boot_queue));
+    }
+    if let Some(watch) = config.watch.clone().filter(|watch| !watch.paths.is_empty()) {
+        let watch_queue = Arc::clone(queue);
+        spawn_in_profile(move || watch::run(watch, &watch_queue));
+    }
+
+    // Все запуски профиля (по расписанию и ручные) выполняются последовательно из его очереди
+    loop {
+        let run = queue.next();
+        info!("Профиль {}: начинается запуск #{} ({:?})", profile, run.id, run.source);
+
+        if run.source != TriggerSource::Manual {
+            if let Some(pause) = pause::active() {
+                info!("Запуск #{} ({:?}) пропущен: плановые запуски приостановлены {}", run.id, run.task, pause.describe());
+                queue.finish();
+                continue;
+            }
+        }
+
+        let mut reschedule = false;
+        match run.task {
+            // Политика скачивается перед каждым бэкапом; если репозиторий недоступен,
+            // бэкап идёт по последней применённой
+            Task::Backup if config.policy.is_some() => match policy::sync(config) {
+                Ok(changed) => reschedule = policy::changes_schedule(&changed),
+                Err(e) => warn!("Не удалось применить политику перед запуском #{}: {}", run.id, e),
+            },
+            Task::Backup | Task::Watch => {}
+            Task::Prune => {
+                match retention::prune(config) {
+                    Ok(removed) => info!("Запуск #{}: удалено устаревших бэкапов: {}", run.id, removed.len()),
+                    Err(e) => error!("Ошибка очистки по политике хранения (запуск #{}): {}", run.id, e),
+                }
+                queue.finish();
+                continue;
+            }
+            Task::Verify => {
+                if let Err(e) = verify::run(config, None, verify::Sample::Latest, 1) {
+                    error!("Ошибка проверки бэкапа (запуск #{}): {}", run.id, e);
+                }
+                queue.finish();
+                continue;
+            }
+        }
+
+        let options = BackupOptions {
+            labels: run.labels.clone(),
+            pinned: false,
+            paths: match (run.task, &config.watch) {
+                (Task::Watch, Some(watch)) => Some(watch.backup_paths()),
+                (Task::Watch, None) => {
+                    info!("Запуск #{} пропущен: режим наблюдения выключен", run.id);
+                    queue.finish();
+                    continue;
+                }
+                _ => None,
+            },
+            force: false,
+            events: None,
+        };
+        match execute_backup(config, &options) {
+            Ok(summary) => {
+                info!("Запуск #{} выполнен успешно: {}", run.id, summary.name);
+                if config.replica.is_some() {
+                    if let Err(e) = run_replicate(config) {
+                        error!("Ошибка репликации после запуска #{}: {}", run.id, e);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Ошибка при выполнении запуска #{}: {}", run.id, e);
+                eprintln!("Ошибка при выполнении автоматического бэкапа: {}", e);
+            }
+        }
+
+        queue.finish();
+        // Новое расписание из политики: юниты переписываются, а демон перезапускается с ним
+        if reschedule {
+            if let Err(e) = SystemdService::create(config).and_then(|_| restart_daemon()) {
+                warn!("Не удалось применить расписание из политики: {}", e);
+            }
+        }
+    }
+}
+
+/// Настало ли время планового бэкапа; в одни сутки (в часовом поясе расписания) — не больше одного запуска
+fn backup_due(now: DateTime<Utc>, target_time: NaiveTime, last_backup_day: &mut Option<NaiveDate>) -> bool {
+    let local_now = now.with_timezone(&timestamp::zone());
+    let current_time = local_now.time();
+    let current_day = local_now.date_naive();
+
+    if current_time.hour() == target_time.hour()
+        && current_time.minute() == target_time.minute()
+        && *last_backup_day != Some(current_day)
+    {
+        *last_backup_day = Some(current_day);
+        return true;
+    }
+    false
+}
+
+/// Следит за расписанием и ставит в очередь демона автоматические бэкапы, а также
+/// очистку и проверку, если для них задано отдельное расписание
+fn run_scheduler(
+    backup_time: Option<String>,
+    backup_cron: Option<cron::Cron>,
+    maintenance: &[(Task, config::MaintenanceSchedule, NaiveTime)],
+    queue: &RunQueue,
+    clock: &dyn system::Clock,
+) {
+    let mut last_backup_day = None;
+    let mut last_cron_minute = None;
+    let mut last_maintenance_days = vec![None; maintenance.len()];
+
+    loop {
+        if let Some(cron) = &backup_cron {
+            let now = clock.now();
+            // Проверка идёт раз в 30 секунд: одна минута не должна поставить два бэкапа
+            let minute = now.with_timezone(&timestamp::zone()).naive_local().with_second(0).unwrap_or_default();
+            if cron.matches(minute) && last_cron_minute != Some(minute) {
+                last_cron_minute = Some(minute);
+                info!("Настало время автоматического бэкапа по cron: {}", timestamp::display_time(now));
+                schedule_task(queue, Task::Backup, now);
+            }
+        } else if let Some(backup_time) = &backup_time {
+            if let Ok(target_time) = NaiveTime::parse_from_str(backup_time, "%H:%M") {
+                let now = clock.now();
+
+                // Проверяем, что настало время бэкапа и мы еще не ставили бэкап сегодня
+                if backup_due(now, target_time, &mut last_backup_day) {
+                    info!("Настало время автоматического бэкапа: {}", timestamp::display_time(now));
+                    schedule_task(queue, Task::Backup, now);
+                }
+            } else {
+                warn!("Неверный формат времени в конфигурации: {}", backup_time);
+            }
+        } else {
+            warn!("Время бэкапа не настроено");
+        }
+
+        let now = clock.now();
+        for ((task, schedule, time), last_day) in maintenance.iter().zip(last_maintenance_days.iter_mut()) {
+            let today = now.with_timezone(&timestamp::zone()).date_naive();
+            if schedule.frequency.runs_on(today, schedule.weekday()) && backup_due(now, *time, last_day) {
+                info!("Настало время запуска {:?} по расписанию: {}", task, timestamp::display_time(now));
+                schedule_task(queue, *task, now);
+            }
+        }
+
+        clock.sleep(std::time::Duration::from_secs(30));
+    }
+}
+
+fn schedule_task(queue: &RunQueue, task: Task, now: DateTime<Utc>) {
+    // Не копим пропущенные запуски, если предыдущий плановый ещё не завершился
+    if queue.has_pending(TriggerSource::Schedule, task) {
+        warn!("Предыдущий плановый запуск {:?} ещё не завершён, новый пропущен", task);
+    } else {
+        let (id, position) = queue.push(TriggerSource::Schedule, task, Labels::new(), timestamp::format(now));
+        info!("Плановый запуск #{} ({:?}) поставлен в очередь (позиция {})", id, task, position);
+    }
+}
+
+/// `obt import <архив.tar.gz> --as <имя> [--label key=value]...`
+fn run_import(config: &Config, archive: &std::path::Path, name: &str, label_args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    import::import_archive(config, archive, name, parse_labels(label_args)?)
+}
+
+fn parse_ownership(args: OwnershipArgs) -> Result<Ownership, Box<dyn std::error::Error>> {
+    Ok(match (args.owners, args.owner_map) {
+        (_, Some(map)) => Ownership::Remap(OwnerMap::load(&map)?),
+        (Some(OwnersArg::Numeric), None) => Ownership::Numeric,
+        (Some(OwnersArg::Name), None) | (None, None) => Ownership::ByName,
+    })
+}
+
+/// `obt bundle <имя> --out <файл.obt>` и `obt bundle extract <файл.obt> [--target <папка>]`
+fn run_bundle(config: &Config, args: BundleArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match args.extract {
+        Some(BundleAction::Extract { bundle, target, ownership }) => {
+            bundle::extract_bundle(&bundle, &target, &parse_ownership(ownership)?)
+        }
+        None => {
+            let name = args.name.ok_or("Укажите имя бэкапа: obt bundle <имя> [--out <файл.obt>]")?;
+            let out = args.out.unwrap_or_else(|| format!("{}.obt", name).into());
+            bundle::create_bundle(config, &name, &out)
+        }
+    }
+}
+
+/// `obt prune` — очистка по политике хранения без нового бэкапа, `obt prune --undo` —
+/// возврат бэкапов из корзины
+fn run_prune(config: &Config, undo: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if undo {
+        let restored = retention::undo()?;
+        if restored.is_empty() {
+            println!("{}", "Корзина пуста".yellow());
+            return Ok(());
+        }
+        for name in &restored {
+            println!("♻️ {}", name);
+        }
+        println!("{}", format!("✅ Возвращено бэкапов: {}", restored.len()).green());
+        println!("💡 Исправьте политику хранения, иначе следующая очистка снова отправит их в корзину");
+        return Ok(());
+    }
+    if config.keep_last().is_none() {
+        return Err("Политика хранения не настроена: задайте retention_keep_last или preset".into());
+    }
+    println!("🧹 Очистка по политике хранения...");
+    let removed = retention::prune(config)?;
+    if removed.is_empty() {
+        println!("{}", "✅ Из хранилища ничего не удалено".green());
+    } else {
+        println!("{}", format!("✅ Удалено бэкапов: {}", removed.len()).green());
+    }
+    Ok(())
+}
+
+/// `obt replicate` — догоняющая синхронизация бэкапов в реплику
+fn run_replicate(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("🔁 Репликация бэкапов во второе хранилище...");
+    let report = replicate::replicate(config)?;
+
+    if report.copied.is_empty() {
+        println!("{}", "✅ Реплика уже содержит все бэкапы".green());
+    } else {
+        for name in &report.copied {
+            println!("  ➕ {}", name);
+        }
+        println!("{}", format!("✅ Скопировано бэкапов: {}", report.copied.len()).green());
+    }
+    info!("Репликация завершена, новых бэкапов в реплике: {}", report.copied.len());
+    Ok(())
+}
+
+/// `obt trigger [--label key=value]...` — поставить бэкап в очередь работающего демона;
+/// с `--timer` — плановый бэкап от таймера профиля
+fn run_trigger(label_args: &[String], timer: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let request = if timer {
+        Request::Scheduled
+    } else {
+        Request::Trigger {
+            labels: parse_labels(label_args)?,
+        }
+    };
+    match control::send(&request)? {
+        Response::Queued { id, position: 0 } => {
+            println!("{}", format!("Запуск #{} поставлен в очередь и начнётся следующим", id).green())
+        }
+        Response::Queued { id, position } => println!(
+            "{}",
+            format!("Запуск #{} поставлен в очередь, перед ним ещё {}", id, position).green()
+        ),
+        Response::Error { message } => return Err(message.into()),
+        other => return Err(format!("Неожиданный ответ демона: {:?}", other).into()),
+    }
+    Ok(())
+}
+
+/// `obt status` — текущий запуск и очередь демона
+/// `obt status [--json]`
+fn run_status(config: &Config, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let status = status::collect(config)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+    } else {
+        status::print(&status);
+    }
+    Ok(())
+}
+
+/// `obt doctor [--fix]`: сверяет установленный таймер systemd с config.json и по согласию
+/// (или с `--fix`) пересоздаёт юниты
+fn run_doctor(config: &Config, fix: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if config.backup_scheduled() {
+        agents::check(config).print(config, true);
+    }
+    let timer = SystemdService::timer_unit();
+    let problem = match (config.backup_scheduled(), SystemdService::check_timer(config)) {
+        (false, _) => {
+            println!("⚪ Расписание не настроено (obt schedule set)");
+            None
+        }
+        (true, TimerCheck::Matches) => {
+            println!("{}", format!("✅ {} совпадает с расписанием из config.json", timer).green());
+            None
+        }
+        (true, TimerCheck::NotInstalled) => Some(format!("{} не установлен, хотя расписание задано", timer)),
+        (true, TimerCheck::Drift { installed, expected }) => Some(format!(
+            "{} срабатывает по {}, а в config.json расписание {}",
+            timer, installed, expected
+        )),
+    };
+    let Some(problem) = problem else {
+        return Ok(());
+    };
+
+    println!("{}", format!("❌ {}", problem).red());
+    let regenerate = fix
+        || (io::stdin().is_terminal()
+            && read_input_or("Пересоздать юниты systemd по config.json? (y/n): ", "n")?.to_lowercase() == "y");
+    if !regenerate {
+        return Err(format!("{} (obt doctor --fix пересоздаст юниты)", problem).into());
+    }
+    SystemdService::create(config)?;
+    if let Err(e) = restart_daemon() {
+        warn!("Не удалось перезапустить демон: {}", e);
+    }
+    info!("Юниты systemd пересозданы по config.json: {}", problem);
+    println!("{}", format!("✅ {} пересоздан по config.json", timer).green());
+    Ok(())
+}
+
+/// `obt cancel <id>` — отменить ожидающий в очереди запуск
+fn run_cancel(id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let id = id
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| format!("Неверный номер запуска: {}", id))?;
+    match control::send(&Request::Cancel { id })? {
+        Response::Cancelled { id } => println!("{}", format!("Запуск #{} отменён", id).green()),
+        Response::Error { message } => return Err(message.into()),
+        other => return Err(format!("Неожиданный ответ демона: {:?}", other).into()),
+    }
+    Ok(())
+}
+
+/// `obt pause [--until <время>] [--reason <текст>]`
+fn run_pause(until: Option<&str>, reason: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let pause = pause::Pause {
+        since: timestamp::format(Utc::now()),
+        until: until.map(pause::parse_until).transpose()?.map(timestamp::format),
+        reason,
+    };
+    pause.save()?;
+    info!("Плановые запуски приостановлены {}", pause.describe());
+    println!("{}", format!("⏸️ Плановые запуски приостановлены {}", pause.describe()).yellow());
+    println!("Ручные `obt backup` и `obt trigger` по-прежнему выполняются");
+    Ok(())
+}
+
+/// `obt resume`
+fn run_resume() -> Result<(), Box<dyn std::error::Error>> {
+    if pause::Pause::clear()? {
+        info!("Плановые запуски возобновлены");
+        println!("{}", "▶️ Плановые запуски возобновлены".green());
+    } else {
+        println!("Плановые запуски не были приостановлены");
+    }
+    Ok(())
+}
+
+/// Разбирает значения повторяющихся флагов `--label key=value`
+fn parse_labels(values: &[String]) -> Result<Labels, Box<dyn std::error::Error>> {
+    let mut result = Labels::new();
+    for value in values {
+        let (key, value) = labels::parse_label(value)?;
+        result.insert(key, value);
+    }
+    Ok(result)
+}
+
+/// `obt backup [--label key=value]...` — бэкап без интерактивного меню
+fn run_backup(config: &mut Config, label_args: &[String], force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(pause) = pause::active() {
+        println!("{}", format!("⚠️ Плановые запуски приостановлены {}; ручной бэкап выполняется", pause.describe()).yellow());
+    }
+    let options = BackupOptions {
+        labels: parse_labels(label_args)?,
+        pinned: false,
+        paths: None,
+        force,
+        events: None,
+    };
+    execute_backup(config, &options).map(|_| ())
+}
+
+/// `obt snapshot --label <текст>` — немедленный закреплённый бэкап перед рискованными изменениями
+fn run_snapshot(config: &mut Config, label_args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let run_labels = parse_labels(label_args)?;
+
+    info!("Запуск снапшота с метками: {}", labels::format(&run_labels));
+    println!("📸 Снапшот перед изменениями: {}", labels::format(&run_labels).white().bold());
+
+    let options = BackupOptions {
+        labels: run_labels,
+        pinned: true,
+        paths: None,
+        force: false,
+        events: None,
+    };
+    execute_backup(config, &options).map(|_| ())
+}
+
+/// `obt docker` — тома, которые можно добавить в поле `docker` без поиска их папок на хосте
+fn run_docker(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let volumes = docker::volumes()?;
+    if volumes.is_empty() {
+        println!("Томов Docker нет");
+        return Ok(());
+    }
+    for volume in volumes {
+        let configured = config.docker.iter().any(|d| d.volume.as_deref() == Some(volume.name.as_str()));
+        let containers = if volume.containers.is_empty() {
+            "не подключён к контейнерам".to_string()
+        } else {
+            volume.containers.join(", ")
+        };
+        let mark = if configured { "✅" } else { "  " };
+        println!("{} {} ({}) — {}", mark, volume.name.white().bold(), containers, volume.mountpoint);
+    }
+    println!("\n✅ — том уже в бэкапе. Добавить: {{\"volume\": \"<имя>\"}} в поле docker config.json");
+    Ok(())
+}
+
+/// `obt list [--label key=value]...` — бэкапы этого сервера из локального каталога
+fn run_list(selectors: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let catalog = Catalog::load()?;
+    let mut entries: Vec<_> = catalog.filter(selectors).collect();
+    entries.sort_by_key(|e| timestamp::parse(&e.created_at));
+
+    for entry in &entries {
+        println!(
+            "{} {} | {} | {} архивов, {:.2} МБ{}{}{}",
+            if entry.pinned { "📌" } else { "  " },
+            entry.name.white().bold(),
+            timestamp::display(&entry.created_at),
+            entry.archives,
+            entry.total_size as f64 / 1_048_576.0,
+            if entry.labels.is_empty() { String::new() } else { format!(" | {}", labels::format(&entry.labels)) },
+            if entry.status == RunStatus::Partial { " | ⚠️ не завершён" } else { "" },
+            if entry.trashed_at.is_some() { " | 🗑️ в корзине" } else { "" }
+        );
+        if let Some(base) = &entry.base {
+            println!("     ↳ поверх {}", base);
+        }
+    }
+
+    if entries.is_empty() {
+        println!("{}", "Бэкапы не найдены".yellow());
+    }
+    Ok(())
+}
+
+/// `obt show <имя>`: всё, что каталог знает о бэкапе, без обращения к хранилищу
+fn run_show(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let catalog = Catalog::load()?;
+    let entry = catalog
+        .find(name)
+        .ok_or_else(|| format!("Бэкап {} не найден в каталоге (obt list — все бэкапы)", name))?;
+
+    println!("{} {}", "📦".bold(), entry.name.white().bold());
+    println!("📅 Дата: {}", timestamp::display(&entry.created_at));
+    println!(
+        "📊 Статус: {}",
+        match entry.status {
+            RunStatus::Complete => "завершён".green(),
+            RunStatus::Partial => "не завершён".yellow(),
+        }
+    );
+    if !entry.labels.is_empty() {
+        println!("🏷️  Метки: {}", labels::format(&entry.labels));
+    }
+    if entry.pinned {
+        println!("📌 Закреплён");
+    }
+    match &entry.base {
+        Some(base) => println!("🧩 Тип: инкрементальный поверх {}", base),
+        None => println!("🧩 Тип: полный"),
+    }
+    if let Some(path) = &entry.path {
+        println!("📁 Папка в хранилище: {}", path);
+    }
+    if let Some(revision) = &entry.revision {
+        println!("🔖 Коммит: {}", revision);
+    }
+    println!("💾 Размер: {:.2} МБ, архивов: {}", entry.total_size as f64 / 1_048_576.0, entry.archives);
+
+    if !entry.paths.is_empty() {
+        println!("\n📂 Источники:");
+        for path in &entry.paths {
+            println!("  {}", path);
+        }
+    }
+    if entry.archive_list.is_empty() {
+        if entry.status == RunStatus::Complete {
+            println!("\n{}", "Список архивов не сохранён: бэкап записан в каталог старой версией obt".yellow());
+        }
+    } else {
+        println!("\n📋 Архивы:");
+        for archive in &entry.archive_list {
+            println!("  {} ({:.2} МБ) ← {}", archive.name, archive.size as f64 / 1_048_576.0, archive.source);
+            if let Some(sha256) = &archive.sha256 {
+                println!("    sha256 {}", sha256);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `obt paths add|remove|list` — пути для бэкапа без интерактивного меню
+fn run_paths(config: &mut Config, action: PathsAction) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        PathsAction::Add { path, priority, offset_minutes, label, create } => {
+            if config.backup_paths.iter().any(|s| s.path == path) {
+                return Err(format!("Путь уже добавлен: {}", path).into());
+            }
+            let path_obj = std::path::Path::new(&path);
+            if !path_obj.exists() {
+                if !create {
+                    return Err(format!("Путь не существует: {} (используйте --create, чтобы создать директорию)", path).into());
+                }
+                fs::create_dir_all(path_obj)?;
+            }
+            config.backup_paths.push(BackupPath {
+                path: path.clone(),
+                priority: priority.unwrap_or_default(),
+                offset_minutes,
+                label,
+            });
+            config.save()?;
+            println!("{}", format!("Путь успешно добавлен: {}", path).green());
+        }
+        PathsAction::Remove { path } => {
+            let before = config.backup_paths.len();
+            config.backup_paths.retain(|s| s.path != path);
+            if config.backup_paths.len() == before {
+                return Err(format!("Путь не найден в списке: {}", path).into());
+            }
+            config.save()?;
+            println!("{}", format!("Путь удалён: {}", path).green());
+        }
+        PathsAction::List => {
+            for source in &config.backup_paths {
+                let label = source.label.as_ref().map(|label| format!(", «{}»", label)).unwrap_or_default();
+                match source.offset_minutes {
+                    Some(offset) => println!("{} [{}, +{} мин{}]", source.path, source.priority, offset, label),
+                    None => println!("{} [{}{}]", source.path, source.priority, label),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Подставляет значение по ключу вида `replica.repo` в JSON-представление конфигурации
+fn set_config_value(tree: &serde_json::Value, key: &str, value: serde_json::Value) -> Result<Config, Box<dyn std::error::Error>> {
+    let mut tree = tree.clone();
+    let parts: Vec<&str> = key.split('.').collect();
+    let (field, sections) = parts.split_last().ok_or("Пустой ключ")?;
+
+    let mut node = &mut tree;
+    for section in sections {
+        let object = node.as_object_mut().ok_or(format!("{} не является разделом настроек", key))?;
+        node = object.entry(section.to_string()).or_insert(serde_json::Value::Null);
+        if node.is_null() {
+            *node = serde_json::json!({});
+        }
+    }
+    let object = node.as_object_mut().ok_or(format!("{} не является разделом настроек", key))?;
+    let known = !sections.is_empty() || object.contains_key(*field);
+    object.insert(field.to_string(), value);
+
+    let config: Config = serde_json::from_value(tree).map_err(|e| format!("Неверное значение для {}: {}", key, e))?;
+    // Незаданные необязательные поля в config.json не выводятся: такое поле известно,
+    // если после подстановки оно появилось в конфигурации
+    if !known && serde_json::to_value(&config)?.get(*field).is_none() {
+        return Err(format!("Неизвестный параметр: {}", key).into());
+    }
+    Ok(config)
+}
+
+/// `obt config set <ключ> <значение>` и `obt config show`
+/// Поля, которые читаются через `keyring::resolve`
+const KEYRING_FIELDS: [&str; 5] = ["gitea_password", "gitea_token", "replica.password", "webdav.password", "smtp.password"];
+
+fn run_config(config: &mut Config, action: ConfigAction) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        ConfigAction::Set { key, value } => {
+            let tree = serde_json::to_value(&*config)?;
+            let as_string = serde_json::Value::String(value.clone());
+            // Сначала как JSON, затем как строка: пароль `12345` остаётся строкой, а не числом
+            *config = match serde_json::from_str(&value) {
+                Ok(parsed) => set_config_value(&tree, &key, parsed).or_else(|_| set_config_value(&tree, &key, as_string))?,
+                Err(_) => set_config_value(&tree, &key, as_string)?,
+            };
+            config.zone()?;
+            if let Some(smtp) = &config.smtp {
+                smtp.validate()?;
+            }
+            config.save()?;
+            println!("{}", format!("✅ {} = {}", key, value).green());
+        }
+        ConfigAction::Show => {
+            let tree = config.redacted()?;
+            println!("{}", serde_json::to_string_pretty(&tree)?);
+        }
+        ConfigAction::Keyring { key } => {
+            if !KEYRING_FIELDS.contains(&key.as_str()) {
+                return Err(format!("В связке ключей можно хранить: {}", KEYRING_FIELDS.join(", ")).into());
+            }
+            keyring::check()?;
+            let tree = serde_json::to_value(&*config)?;
+            let current = key
+                .split('.')
+                .try_fold(&tree, |value, part| value.get(part))
+                .and_then(|value| value.as_str())
+                .filter(|value| !keyring::is_reference(value))
+                .map(str::to_string);
+            let secret = match current {
+                Some(secret) => secret,
+                None => read_input(&format!("Введите значение {}: ", key))?,
+            };
+            keyring::store(&key, &secret)?;
+            let reference = keyring::reference(&key);
+            *config = set_config_value(&tree, &key, reference.clone().into())?;
+            config.save()?;
+            println!("{}", format!("✅ {} хранится в связке ключей, в config.json — {}", key, reference).green());
+        }
+    }
+    Ok(())
+}
+
+/// `obt policy sync` и `obt policy show`
+fn run_policy(config: &mut Config, action: PolicyAction) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        PolicyAction::Sync => {
+            let changed = policy::sync(config)?;
+            if changed.is_empty() {
+                println!("{}", "✅ config.json уже соответствует политике".green());
+                return Ok(());
+            }
+            println!("{}", format!("✅ Политика применена, изменены поля: {}", changed.join(", ")).green());
+            if policy::changes_schedule(&changed) {
+                if config.backup_cron.is_some() {
+                    install_schedule(config)?;
+                } else if let (Some(frequency), Some(time)) = (config.backup_frequency, config.backup_time.clone()) {
+                    apply_schedule(config, frequency, time)?;
+                } else {
+                    restart_daemon()?;
+                }
+            }
+        }
+        PolicyAction::Show => {
+            let state = policy::PolicyState::load()?;
+            if state.url.is_empty() {
+                println!("Политика ещё не применялась");
+                return Ok(());
+            }
+            println!("Политика: {} ({})", state.url, &state.revision[..state.revision.len().min(8)]);
+            println!("Применена: {}", timestamp::display(&state.synced_at));
+            println!("{}", serde_json::to_string_pretty(&state.applied)?);
+            let overridden = state.overridden(config)?;
+            if !overridden.is_empty() {
+                println!("{}", format!("Задано на сервере по-своему: {}", overridden.join(", ")).yellow());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Командная строка obt: разбирает аргументы и выполняет команду; двоичный файл obt
+/// только вызывает эту функцию
+pub fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    // Инициализируем логгер
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .format(forensics::format_record)
+        .init();
+    
+    if cli.daemon && cli.profile.is_some() {
+        return Err("Демон обслуживает все профили сразу: запускайте его без --profile".into());
+    }
+    Config::set_profile(cli.profile.clone())?;
+    let mut config = Config::load()?;
+    forensics::set_log_format(cli.log_format.or(config.log_format).unwrap_or_default());
+    prompt::set_timeout(cli.prompt_timeout.or(config.prompt_timeout_seconds));
+    progress::set_quiet(cli.quiet);
+    staging::set_root(config.work_dir.clone());
+    staging::clean_stale();
+    i18n::set_language(cli.language.or(config.language));
+    forensics::set_backup_name(config.backup_name.clone());
+    info!("Запуск OfficialVPN Backup Tool v{}", env!("CARGO_PKG_VERSION"));
+
+    match config.zone() {
+        Ok(zone) => timestamp::set_zone(zone),
+        // Демон не должен молча запускать бэкапы в другом часовом поясе, а остальные
+        // команды (в том числе `obt config set timezone`) работают и с ошибочным значением
+        Err(e) if !cli.daemon => {
+            warn!("{}", e);
+            println!("{}", format!("⚠️ {}; используется системный часовой пояс", e).yellow());
+        }
+        Err(e) => return Err(e),
+    }
+
+    if cli.daemon {
+        info!("Запуск в режиме демона");
+        return run_daemon_mode(&mut config);
+    }
+    if let Some(command) = cli.command {
+        return match command {
+            Commands::Backup { labels, on_divergence, force } => {
+                if let Some(policy) = on_divergence {
+                    divergence::set_override(policy);
+                }
+                run_backup(&mut config, &labels, force)
+            }
+            Commands::Snapshot { labels } => run_snapshot(&mut config, &labels),
+            Commands::List { selectors } => run_list(&selectors),
+            Commands::Show { name } => run_show(&name),
+            Commands::Find { pattern } => find::find(&config, &pattern),
+            Commands::Diff { from, to } => diff::run(&config, &from, &to),
+            Commands::Trigger { labels, timer } => run_trigger(&labels, timer),
+            Commands::Status { json } => run_status(&config, json),
+            Commands::Doctor { fix } => run_doctor(&config, fix),
+            Commands::Cancel { id } => run_cancel(&id),
+            Commands::Pause { until, reason } => run_pause(until.as_deref(), reason),
+            Commands::Resume => run_resume(),
+            Commands::BootBackup => boot::run(&mut config),
+            Commands::Replicate => run_replicate(&config),
+            Commands::Import { archive, name, labels } => run_import(&config, &archive, &name, &labels),
+            Commands::Restore { latest, as_of, name, target, selectors, yes, no_privileges, file, bootstrap, ownership } => {
+                let options = RestoreOptions {
+                    selection: match (latest, as_of, name) {
+                        (_, _, Some(name)) => Selection::Name(name),
+                        (_, Some(time), None) => Selection::AsOf(
+                            timestamp::parse_input(&time).ok_or_else(|| format!("Неверное время: {}", time))?,
+                        ),
+                        (true, None, None) => Selection::Latest,
+                        (false, None, None) => Selection::Interactive,
+                    },
+                    selectors,
+                    target,
+                    ownership: parse_ownership(ownership)?,
+                    assume_yes: yes,
+                    no_privileges,
+                    file,
+                };
+                match bootstrap {
+                    Some(address) => bootstrap::run(&mut config, &address, &options),
+                    None => restore::restore(&config, &options).map(|_| ()),
+                }
+            }
+            Commands::Verify { name, sample, percent, jobs } => {
+                let sample = match sample {
+                    SampleArg::Latest => verify::Sample::Latest,
+                    SampleArg::Random => verify::Sample::Random(percent),
+                    SampleArg::All => verify::Sample::All,
+                };
+                let jobs = jobs.or(config.archive_jobs).unwrap_or(1);
+                verify::run(&mut config, name.as_deref(), sample, jobs)
+            }
+            Commands::Prune { undo } => run_prune(&config, undo),
+            #[cfg(feature = "fuse")]
+            Commands::Mount { name, mountpoint } => mount::mount(&config, &name, &mountpoint),
+            Commands::Bundle(args) => run_bundle(&config, args),
+            Commands::Docker => run_docker(&config),
+            Commands::Provision { answers } => provision::run(&mut config, &answers),
+            Commands::Config { action } => run_config(&mut config, action),
+            Commands::Policy { action } => run_policy(&mut config, action),
+            Commands::Paths { action } => run_paths(&mut config, action),
+            Commands::Dest { action: DestAction::Check { name } } => dest::run_check(&config, name.as_deref()),
+            Commands::Schedule { action: ScheduleAction::Set { frequency, time } } => {
+                apply_schedule(&mut config, frequency, time)
+            }
+            Commands::Schedule { action: ScheduleAction::Cron { expression } } => apply_cron_schedule(&mut config, expression),
+            Commands::Schedule { action: ScheduleAction::Prune(args) } => {
+                apply_maintenance_schedule(&mut config, MaintenanceJob::Prune, Some(args))
+            }
+            Commands::Schedule { action: ScheduleAction::Verify(args) } => {
+                apply_maintenance_schedule(&mut config, MaintenanceJob::Verify, Some(args))
+            }
+            Commands::Schedule { action: ScheduleAction::Boot(args) } => apply_boot_schedule(&mut config, Some(args)),
+            Commands::Schedule { action: ScheduleAction::Unset { job } } => apply_maintenance_schedule(&mut config, job, None),
+        };
+    }
+
+    if config.gitea_repo.is_none() && config.uses_gitea() {
+        println!("{}", t("Добро пожаловать в OBT! Давайте настроим резервное копирование."));
+        setup_storage(&mut config)?;
+        setup_backup_name(&mut config)?;
+        setup_backup_schedule(&mut config)?;
+        preset::setup(&mut config)?;
+        manage_backup_paths(&mut config)?;
+    }
+
+    loop {
+        println!("\n{}", "OfficialVPN Backup Tools".green());
+        println!("{}", tf("Время: {}", &[&timestamp::display_time(Utc::now())]));
+
+        if let Some(last_backup) = &config.last_backup {
+            println!("{}", tf("Последний бэкап: {}", &[&timestamp::display(last_backup).white().bold()]));
+        }
+        if let Some(name) = &config.backup_name {
+            println!("{}", tf("Имя бэкапа: {}", &[&name.white().bold()]));
+        }
+        if let Some(expression) = &config.backup_cron {
+            println!("{}", tf("Расписание cron: {}", &[&expression.white().bold()]));
+        } else if let Some(time) = &config.backup_time {
+            println!("{}", tf("Время бэкапа: {}", &[&time.white().bold()]));
+        }
+
+        println!("\n{}", t("Меню:"));
+        println!("{}", t("1. Сделать бэкап"));
+        println!("{}", t("2. Добавить/изменить файлы для бэкапа"));
+        println!("{}", t("3. Изменить хранилище бэкапов"));
+        println!("{}", t("4. Изменить расписание бэкапов"));
+        println!("{}", t("5. Изменить имя бэкапа"));
+        println!("{}", t("6. Выход"));
+
+        match read_input(&format!("\n{}", t("Выберите действие (1-6): ")))?.as_str() {
+            "1" => {
+                execute_backup(&mut config, &BackupOptions::default())?;
+            }
+            "2" => manage_backup_paths(&mut config)?,
+            "3" => setup_storage(&mut config)?,
+            "4" => setup_backup_schedule(&mut config)?,
+            "5" => setup_backup_name(&mut config)?,
+            "6" => break,
+            _ => println!("{}", t("Неверный выбор, попробуйте снова")),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::time::Duration;
+    use system::fake::{ManualClock, ScriptedRunner};
+    use system::Clock;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn backup_due_once_per_day() {
+        timestamp::set_thread_zone(chrono_tz::UTC);
+        let clock = ManualClock::new(utc(2024, 5, 10, 3, 0));
+        let target = NaiveTime::from_hms_opt(3, 0, 0).unwrap();
+        let mut last_day = None;
+
+        assert!(backup_due(clock.now(), target, &mut last_day));
+        // Планировщик проверяет время каждые 30 секунд: вторая проверка той же минуты не запускает бэкап
+        clock.sleep(Duration::from_secs(30));
+        assert!(!backup_due(clock.now(), target, &mut last_day));
+        clock.advance(Duration::from_secs(60));
+        assert!(!backup_due(clock.now(), target, &mut last_day));
+        clock.advance(Duration::from_secs(24 * 3600 - 90));
+        assert!(backup_due(clock.now(), target, &mut last_day));
+    }
+
+    #[test]
+    fn backup_due_across_month_boundary() {
+        timestamp::set_thread_zone(chrono_tz::Europe::Moscow);
+        let target = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        let mut last_day = None;
+
+        assert!(backup_due(utc(2024, 1, 30, 23, 30), target, &mut last_day));
+        assert_eq!(last_day, NaiveDate::from_ymd_opt(2024, 1, 31));
+        // 23:30 UTC 31 января — уже 02:30 1 февраля по Москве
+        assert!(backup_due(utc(2024, 1, 31, 23, 30), target, &mut last_day));
+        assert_eq!(last_day, NaiveDate::from_ymd_opt(2024, 2, 1));
+        assert!(!backup_due(utc(2024, 2, 1, 0, 0), target, &mut last_day));
+    }
+
+    #[test]
+    fn backup_due_once_when_clocks_go_back() {
+        timestamp::set_thread_zone(chrono_tz::Europe::Berlin);
+        let target = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        let clock = ManualClock::new(utc(2024, 10, 26, 0, 30));
+        let mut last_day = None;
+        assert!(backup_due(clock.now(), target, &mut last_day));
+
+        // 27 октября 02:30 по Берлину наступает дважды: в 00:30 и в 01:30 UTC
+        clock.set(utc(2024, 10, 27, 0, 30));
+        assert!(backup_due(clock.now(), target, &mut last_day));
+        clock.advance(Duration::from_secs(3600));
+        assert!(!backup_due(clock.now(), target, &mut last_day));
+    }
+
+    #[test]
+    fn backup_due_after_clocks_go_forward() {
+        timestamp::set_thread_zone(chrono_tz::Europe::Berlin);
+        let target = NaiveTime::from_hms_opt(3, 15, 0).unwrap();
+        let mut last_day = None;
+
+        // 31 марта после 02:00 сразу наступает 03:00, смещение от UTC меняется с +1 на +2
+        assert!(!backup_due(utc(2024, 3, 31, 2, 15), target, &mut last_day));
+        assert!(backup_due(utc(2024, 3, 31, 1, 15), target, &mut last_day));
+    }
+
+    #[test]
+    fn timeline_waits_for_offset() {
+        let clock = ManualClock::new(utc(2024, 5, 10, 3, 0));
+        let mut timeline = Timeline::new(&clock, None);
+        assert_eq!(timeline.until_offset(0), None);
+
+        clock.advance(Duration::from_secs(3 * 60));
+        let delay = timeline.until_offset(10).unwrap();
+        assert_eq!(delay, Duration::from_secs(7 * 60));
+        timeline.wait(delay);
+        assert_eq!(clock.slept(), vec![Duration::from_secs(7 * 60)]);
+        assert_eq!(timeline.until_offset(10), None);
+        assert_eq!(timeline.until_offset(5), None);
+        assert!(!timeline.budget_exhausted());
+    }
+
+    #[test]
+    fn timeline_budget_excludes_offset_waits() {
+        let clock = ManualClock::new(utc(2024, 5, 10, 3, 0));
+        let mut timeline = Timeline::new(&clock, Some(5));
+
+        clock.advance(Duration::from_secs(60));
+        timeline.wait(timeline.until_offset(30).unwrap());
+        assert!(!timeline.budget_exhausted());
+
+        clock.advance(Duration::from_secs(4 * 60));
+        assert!(!timeline.budget_exhausted());
+        clock.advance(Duration::from_secs(1));
+        assert!(timeline.budget_exhausted());
+    }
+
+    #[test]
+    fn labels_from_hook_and_flags() {
+        let mut config = Config::default();
+        config.label_hook = Some("/usr/local/bin/labels".to_string());
+        let options = BackupOptions {
+            labels: Labels::from([("reason".to_string(), "manual".to_string())]),
+            ..Default::default()
+        };
+        let runner = ScriptedRunner::default();
+        runner.succeed("reason=deploy\n# комментарий\nrelease=1.2\n");
+
+        let labels = collect_labels(&config, &options, &runner).unwrap();
+        assert_eq!(runner.commands(), vec!["/usr/local/bin/labels".to_string()]);
+        assert_eq!(labels.get("reason").map(String::as_str), Some("manual"));
+        assert_eq!(labels.get("release").map(String::as_str), Some("1.2"));
+    }
+
+    #[test]
+    fn label_hook_failure_stops_backup() {
+        let mut config = Config::default();
+        config.label_hook = Some("labels".to_string());
+        let runner = ScriptedRunner::default();
+        runner.fail(1, "нет доступа");
+        assert!(collect_labels(&config, &BackupOptions::default(), &runner).is_err());
+
+        runner.fail_to_start(io::Error::new(io::ErrorKind::NotFound, "labels"));
+        assert!(collect_labels(&config, &BackupOptions::default(), &runner).is_err());
+    }
+
+    #[test]
+    fn pre_upload_check_rejects_backup() {
+        let runner = ScriptedRunner::default();
+        runner.fail(2, "найдены незашифрованные ключи\n");
+        let error = run_pre_upload_check("check", "backup_1", "/tmp/backup_1", &runner).unwrap_err();
+        assert!(error.to_string().contains("найдены незашифрованные ключи"));
+
+        assert!(run_pre_upload_check("check", "backup_1", "/tmp/backup_1", &runner).is_ok());
+        assert_eq!(runner.commands().len(), 2);
+    }
+}
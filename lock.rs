@@ -0,0 +1,52 @@
+use crate::config::Config;
+use log::warn;
+use nix::errno::Errno;
+use nix::fcntl::{flock, FlockArg};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+
+/// Блокировка бэкапа профиля (~/.config/obt/backup.lock): бэкап из меню или `obt backup`
+/// и бэкап демона по расписанию не выполняются одновременно. Снимается, когда значение
+/// удаляется или процесс завершается, даже аварийно
+pub struct BackupLock {
+    _file: Option<File>,
+}
+
+impl BackupLock {
+    /// Захватывает блокировку; если бэкап уже идёт, возвращает ошибку с PID его процесса.
+    /// С `force` бэкап выполняется и при занятой блокировке
+    pub fn acquire(force: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_lock_path()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        // Файл не обрезается при открытии: в нём PID процесса, который держит блокировку
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+        match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+            Ok(()) => {
+                file.set_len(0)?;
+                file.rewind()?;
+                writeln!(file, "{}", std::process::id())?;
+                Ok(BackupLock { _file: Some(file) })
+            }
+            Err(Errno::EWOULDBLOCK) => {
+                let pid = fs::read_to_string(&path).unwrap_or_default().trim().to_string();
+                let holder = if pid.is_empty() { "другой процесс".to_string() } else { format!("PID {}", pid) };
+                if force {
+                    warn!("Бэкап запущен с --force, хотя другой бэкап ещё выполняется ({})", holder);
+                    return Ok(BackupLock { _file: None });
+                }
+                Err(format!(
+                    "Бэкап уже выполняется ({}): дождитесь его окончания или запустите obt backup --force",
+                    holder
+                )
+                .into())
+            }
+            Err(e) => Err(format!("Не удалось заблокировать {}: {}", path.display(), e).into()),
+        }
+    }
+
+    fn get_lock_path() -> std::io::Result<PathBuf> {
+        Ok(Config::config_dir()?.join("backup.lock"))
+    }
+}
@@ -0,0 +1,188 @@
+use crate::config::{Language, Priority};
+use crate::i18n::tf_in;
+use crate::index::FileEntry;
+use crate::labels::Labels;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Что лежит в tar-архиве: директория, отдельный файл или том Docker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveKind {
+    Dir,
+    File,
+    Volume,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub source: String,
+    #[serde(default)]
+    pub priority: Priority,
+    pub size: u64,
+    /// Для дампов: базы, попавшие в архив, и исключённые из него
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub databases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_databases: Vec<String>,
+    /// Файлы архива для `obt find`; в бэкапах старых версий отсутствует
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileEntry>,
+    /// Архив зашифрован: в папке бэкапа лежит `<name>.age`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+    /// Для архивов поверх базового бэкапа: файлы (пути как в `files`), удалённые с момента базового
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<String>,
+    /// SHA-256 файла архива в том виде, в каком он загружен (после шифрования)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Архив разделён на столько частей `<файл>.partNN` (`split_archive_mb`); 0 — целый.
+    /// `size` и `sha256` относятся к архиву целиком
+    #[serde(default, skip_serializing_if = "is_whole")]
+    pub parts: usize,
+    /// Исходное имя архива, если `name` приведено к допустимому на FAT/exFAT (`fat_safe_names`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_name: Option<String>,
+    /// Вид tar-архива; в бэкапах старых версий отсутствует и определяется по имени (`dir_`, `file_`, `volume_`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ArchiveKind>,
+}
+
+fn is_whole(parts: &usize) -> bool {
+    *parts == 0
+}
+
+impl ArchiveEntry {
+    /// Сжатый поток (дамп базы, вывод `exec` из контейнера Docker или образы проекта compose), а не tar
+    pub fn is_stream(&self) -> bool {
+        self.kind.is_none()
+            && self
+                .name
+                .strip_suffix(".gz")
+                .is_some_and(|n| n.ends_with(".sql") || n.starts_with("exec_") || n.starts_with("images_"))
+    }
+
+    /// Вид tar-архива; `None` у дампов и импортированных архивов. Имя архива пути
+    /// может задаваться `archive_name_template`, поэтому по нему судят только у старых бэкапов
+    pub fn kind(&self) -> Option<ArchiveKind> {
+        self.kind.or_else(|| {
+            if self.name.starts_with("dir_") {
+                Some(ArchiveKind::Dir)
+            } else if self.name.starts_with("file_") {
+                Some(ArchiveKind::File)
+            } else if self.name.starts_with("volume_") {
+                Some(ArchiveKind::Volume)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Имя файла архива в папке бэкапа
+    pub fn file_name(&self) -> String {
+        if self.encrypted {
+            format!("{}{}", self.name, crate::crypto::ENCRYPTED_SUFFIX)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Файлы, которые архив занимает в хранилище: он сам или его части
+    pub fn stored_files(&self) -> Vec<String> {
+        match self.parts {
+            0 => vec![self.file_name()],
+            parts => (0..parts).map(|part| crate::split::part_name(&self.file_name(), part)).collect(),
+        }
+    }
+}
+
+/// Путь, не попавший в бэкап (ошибка архивации или исчерпан бюджет времени)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedSource {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Архив, размер которого сильно отличается от прошлых полных бэкапов того же пути
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SizeAnomaly {
+    pub source: String,
+    pub size: u64,
+    /// Медиана размеров архива этого пути в прошлых бэкапах
+    pub typical: u64,
+}
+
+impl SizeAnomaly {
+    pub fn describe(&self) -> String {
+        self.describe_in(Language::Ru)
+    }
+
+    /// Описание на языке уведомлений
+    pub fn describe_in(&self, language: Language) -> String {
+        let (message, ratio) = if self.size >= self.typical {
+            ("{}: {} МБ, обычно {} МБ (больше в {} раза)", self.size as f64 / self.typical.max(1) as f64)
+        } else {
+            ("{}: {} МБ, обычно {} МБ (меньше в {} раза)", self.typical as f64 / self.size.max(1) as f64)
+        };
+        tf_in(
+            language,
+            message,
+            &[
+                &self.source,
+                &format!("{:.2}", self.size as f64 / 1_048_576.0),
+                &format!("{:.2}", self.typical as f64 / 1_048_576.0),
+                &format!("{:.1}", ratio),
+            ],
+        )
+    }
+}
+
+/// Машиночитаемое описание бэкапа, лежит рядом с backup_info.txt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub labels: Labels,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Бэкап, поверх которого сделан этот (инкрементальный или дифференциальный);
+    /// у полного бэкапа отсутствует
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Ветка Gitea, `s3://<бакет>/<prefix>` для бэкапов в S3 или `sftp://<сервер><path>`
+    pub branch: String,
+    pub paths: Vec<String>,
+    pub archives: Vec<ArchiveEntry>,
+    #[serde(default)]
+    pub skipped: Vec<SkippedSource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anomalies: Vec<SizeAnomaly>,
+    pub total_size: u64,
+    /// UUID запуска, сделавшего бэкап (как Obt-Run-Id коммита и в уведомлениях)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// Исходное имя папки бэкапа, если `name` приведено к допустимому на FAT/exFAT
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_name: Option<String>,
+    /// `backup_name` сервера: по нему узнаются свои бэкапы, если имя папки задано `folder_name_template`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_name: Option<String>,
+}
+
+impl Manifest {
+    pub fn write(&self, backup_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(backup_dir.join(MANIFEST_FILE), content)?;
+        Ok(())
+    }
+
+    pub fn read(backup_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(backup_dir.join(MANIFEST_FILE))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
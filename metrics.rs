@@ -0,0 +1,84 @@
+use crate::config::Config;
+use crate::BackupSummary;
+use log::info;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::time::Duration;
+
+/// Профиль, под которым метрики группируются в Pushgateway
+const DEFAULT_PROFILE: &str = "default";
+
+fn render(summary: Option<&BackupSummary>, duration: Duration) -> String {
+    let mut body = String::new();
+    let mut metric = |name: &str, help: &str, value: String| {
+        body.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n{} {}\n", name, help, name, name, value));
+    };
+
+    metric(
+        "obt_backup_success",
+        "1 if the last backup run succeeded, 0 otherwise",
+        if summary.is_some() { "1" } else { "0" }.to_string(),
+    );
+    metric(
+        "obt_backup_duration_seconds",
+        "Duration of the last backup run",
+        format!("{:.3}", duration.as_secs_f64()),
+    );
+    metric(
+        "obt_backup_last_run_timestamp_seconds",
+        "Unix time of the last backup run",
+        chrono::Utc::now().timestamp().to_string(),
+    );
+
+    // Метрики успешного запуска не отправляются при ошибке, чтобы в Pushgateway
+    // остались значения последнего удачного бэкапа
+    if let Some(summary) = summary {
+        metric(
+            "obt_backup_last_success_timestamp_seconds",
+            "Unix time of the last successful backup",
+            chrono::Utc::now().timestamp().to_string(),
+        );
+        metric(
+            "obt_backup_size_bytes",
+            "Total size of archives in the last successful backup",
+            summary.total_size.to_string(),
+        );
+        metric(
+            "obt_backup_archives",
+            "Number of archives in the last successful backup",
+            summary.archives.to_string(),
+        );
+        metric(
+            "obt_backup_skipped_sources",
+            "Number of paths skipped in the last successful backup",
+            summary.skipped.to_string(),
+        );
+    }
+
+    body
+}
+
+/// Отправляет метрики запуска в Prometheus Pushgateway, если он настроен
+pub fn push(config: &Config, summary: Option<&BackupSummary>, duration: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = match &config.pushgateway_url {
+        Some(url) => url.trim_end_matches('/'),
+        None => return Ok(()),
+    };
+
+    let instance = config.backup_name.as_deref().unwrap_or("obt");
+    let url = format!(
+        "{}/metrics/job/obt/instance/{}/profile/{}",
+        base_url,
+        utf8_percent_encode(instance, NON_ALPHANUMERIC),
+        utf8_percent_encode(&Config::profile().unwrap_or_else(|| DEFAULT_PROFILE.to_string()), NON_ALPHANUMERIC)
+    );
+
+    // POST заменяет только переданные метрики группы, остальные сохраняются
+    ureq::post(&url)
+        .timeout(Duration::from_secs(30))
+        .set("Content-Type", "text/plain; version=0.0.4")
+        .send_string(&render(summary, duration))
+        .map_err(|e| format!("Не удалось отправить метрики в Pushgateway: {}", config.redact_text(&e.to_string())))?;
+
+    info!("Метрики отправлены в Pushgateway: {}", config.redact_text(&url));
+    Ok(())
+}
@@ -0,0 +1,321 @@
+use crate::config::Config;
+use crate::crypto;
+use crate::find::original_path;
+use crate::index;
+use crate::layout;
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::prepare_repository;
+use crate::split;
+use crate::staging::StagingDir;
+use crate::timestamp;
+use colored::*;
+use fuser::{FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, Request};
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, PipeReader, PipeWriter, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Содержимое бэкапа не меняется, поэтому ядро может кешировать атрибуты сколько угодно
+const TTL: Duration = Duration::from_secs(3600);
+const ROOT_INO: u64 = 1;
+const ENOENT: i32 = nix::errno::Errno::ENOENT as i32;
+
+enum Content {
+    Dir(BTreeMap<String, u64>),
+    /// Файл внутри tar-архива: читается из архива при первом обращении
+    Member { archive: PathBuf, inner: String },
+    /// Файл, лежащий в папке бэкапа как есть (дамп базы)
+    Plain(PathBuf),
+}
+
+struct Node {
+    parent: u64,
+    size: u64,
+    mtime: SystemTime,
+    content: Content,
+}
+
+/// Файл из архива, читаемый потоком: программы читают файлы кусками подряд, поэтому
+/// следующий кусок продолжает распаковку, а файл целиком в памяти не держится
+struct Stream {
+    ino: u64,
+    position: u64,
+    reader: PipeReader,
+}
+
+impl Stream {
+    /// Распаковка идёт в отдельном потоке и пишет файл в канал; закрытый канал её останавливает
+    fn open(ino: u64, archive: PathBuf, inner: String) -> io::Result<Self> {
+        let (reader, mut writer) = io::pipe()?;
+        std::thread::spawn(move || {
+            if let Err(e) = send_member(&archive, &inner, &mut writer) {
+                warn!("Не удалось прочитать {} из {}: {}", inner, archive.display(), e);
+            }
+        });
+        Ok(Stream { ino, position: 0, reader })
+    }
+}
+
+fn send_member(archive: &Path, inner: &str, writer: &mut PipeWriter) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = index::open_archive(archive)?;
+    for entry in reader.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.to_string_lossy() != inner {
+            continue;
+        }
+        return match io::copy(&mut entry, writer) {
+            // Файл дочитали не до конца: программа закрыла его или перешла к другому
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+            _ => Ok(()),
+        };
+    }
+    Err("файла нет в архиве".into())
+}
+
+/// Бэкап как дерево исходных путей: `/etc/nginx/nginx.conf` лежит в `<точка>/etc/nginx/nginx.conf`
+struct BackupFs {
+    nodes: Vec<Node>,
+    /// Файл, который читается сейчас
+    stream: Option<Stream>,
+}
+
+impl BackupFs {
+    fn new(mtime: SystemTime) -> Self {
+        BackupFs {
+            nodes: vec![Node {
+                parent: ROOT_INO,
+                size: 0,
+                mtime,
+                content: Content::Dir(BTreeMap::new()),
+            }],
+            stream: None,
+        }
+    }
+
+    fn node(&self, ino: u64) -> Option<&Node> {
+        self.nodes.get(ino.checked_sub(1)? as usize)
+    }
+
+    /// Добавляет файл по пути, создавая недостающие каталоги
+    fn insert(&mut self, path: &str, size: u64, mtime: SystemTime, content: Content) {
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty() && *c != ".").collect();
+        let Some((file_name, dirs)) = components.split_last() else {
+            return;
+        };
+
+        let mut parent = ROOT_INO;
+        for dir in dirs {
+            parent = self.child(parent, dir, 0, mtime, || Content::Dir(BTreeMap::new()));
+        }
+        self.child(parent, file_name, size, mtime, || content);
+    }
+
+    /// Существующий потомок с этим именем или новый узел
+    fn child(&mut self, parent: u64, name: &str, size: u64, mtime: SystemTime, content: impl FnOnce() -> Content) -> u64 {
+        if let Content::Dir(children) = &self.nodes[parent as usize - 1].content {
+            if let Some(&ino) = children.get(name) {
+                return ino;
+            }
+        }
+
+        self.nodes.push(Node {
+            parent,
+            size,
+            mtime,
+            content: content(),
+        });
+        let ino = self.nodes.len() as u64;
+        if let Content::Dir(children) = &mut self.nodes[parent as usize - 1].content {
+            children.insert(name.to_string(), ino);
+        }
+        ino
+    }
+
+    fn attr(&self, ino: u64) -> Option<FileAttr> {
+        let node = self.node(ino)?;
+        let (kind, perm, nlink) = match node.content {
+            Content::Dir(_) => (FileType::Directory, 0o555, 2),
+            _ => (FileType::RegularFile, 0o444, 1),
+        };
+        Some(FileAttr {
+            ino,
+            size: node.size,
+            blocks: node.size.div_ceil(512),
+            atime: node.mtime,
+            mtime: node.mtime,
+            ctime: node.mtime,
+            crtime: node.mtime,
+            kind,
+            perm,
+            nlink,
+            uid: nix::unistd::getuid().as_raw(),
+            gid: nix::unistd::getgid().as_raw(),
+            rdev: 0,
+            blksize: 4096,
+            flags: 0,
+        })
+    }
+
+    /// До `size` байт файла начиная с `offset`
+    fn read_at(&mut self, ino: u64, offset: u64, size: u64) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut data = Vec::new();
+        let (archive, inner) = match &self.node(ino).ok_or("нет такого файла")?.content {
+            Content::Member { archive, inner } => (archive.clone(), inner.clone()),
+            Content::Plain(path) => {
+                let mut file = fs::File::open(path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                file.take(size).read_to_end(&mut data)?;
+                return Ok(data);
+            }
+            Content::Dir(_) => return Err("это каталог".into()),
+        };
+
+        // Назад по потоку не вернуться: чтение с начала файла распаковывает его заново
+        if !self.stream.as_ref().is_some_and(|stream| stream.ino == ino && stream.position <= offset) {
+            self.stream = Some(Stream::open(ino, archive, inner)?);
+        }
+        let stream = self.stream.as_mut().unwrap();
+        stream.position += io::copy(&mut (&mut stream.reader).take(offset - stream.position), &mut io::sink())?;
+        (&mut stream.reader).take(size).read_to_end(&mut data)?;
+        stream.position += data.len() as u64;
+        Ok(data)
+    }
+}
+
+impl Filesystem for BackupFs {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let ino = match self.node(parent).map(|n| &n.content) {
+            Some(Content::Dir(children)) => children.get(name.to_string_lossy().as_ref()).copied(),
+            _ => None,
+        };
+        match ino.and_then(|ino| self.attr(ino)) {
+            Some(attr) => reply.entry(&TTL, &attr, 0),
+            None => reply.error(ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyAttr) {
+        match self.attr(ino) {
+            Some(attr) => reply.attr(&TTL, &attr),
+            None => reply.error(ENOENT),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        match self.read_at(ino, offset.max(0) as u64, size as u64) {
+            Ok(data) => reply.data(&data),
+            Err(e) => {
+                warn!("Не удалось прочитать файл #{} из бэкапа: {}", ino, e);
+                reply.error(nix::errno::Errno::EIO as i32);
+            }
+        }
+    }
+
+    fn readdir(&mut self, _req: &Request<'_>, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
+        let node = match self.node(ino) {
+            Some(node) => node,
+            None => return reply.error(ENOENT),
+        };
+        let Content::Dir(children) = &node.content else {
+            return reply.error(nix::errno::Errno::ENOTDIR as i32);
+        };
+
+        let mut entries = vec![(ino, FileType::Directory, ".".to_string()), (node.parent, FileType::Directory, "..".to_string())];
+        for (name, &child) in children {
+            let kind = match self.node(child).map(|n| &n.content) {
+                Some(Content::Dir(_)) => FileType::Directory,
+                _ => FileType::RegularFile,
+            };
+            entries.push((child, kind, name.clone()));
+        }
+
+        for (i, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(ino, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+fn build(backup_dir: &Path, manifest: &Manifest) -> BackupFs {
+    let mut tree = BackupFs::new(SystemTime::now());
+    for archive in &manifest.archives {
+        let archive_path = backup_dir.join(&archive.name);
+        if archive.is_stream() {
+            tree.insert(&archive.name, archive.size, SystemTime::now(), Content::Plain(archive_path));
+            continue;
+        }
+
+        let files = if archive.files.is_empty() {
+            index::list_archive(&archive_path).unwrap_or_else(|e| {
+                warn!("Не удалось прочитать {}: {}", archive.name, e);
+                Vec::new()
+            })
+        } else {
+            archive.files.clone()
+        };
+        for file in files {
+            let path = original_path(archive, &file.path);
+            tree.insert(
+                &path,
+                file.size,
+                file.modified_at().map(SystemTime::from).unwrap_or(SystemTime::UNIX_EPOCH),
+                Content::Member {
+                    archive: archive_path.clone(),
+                    inner: file.path,
+                },
+            );
+        }
+    }
+    tree
+}
+
+/// `obt mount <имя> <точка>`: работает до размонтирования (`fusermount -u <точка>`)
+pub fn mount(config: &Config, name: &str, mountpoint: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let staging = StagingDir::new(format!("mount_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let work_dir = staging.path();
+
+    println!("🔄 Получение бэкапа {} из репозитория...", name);
+    prepare_repository(config, work_dir)?.download()?;
+
+    let backup_dir = layout::local_dir(Path::new(work_dir), name).ok_or(format!("Бэкап {} не найден", name))?;
+    let manifest: Manifest = serde_json::from_str(
+        &fs::read_to_string(backup_dir.join(MANIFEST_FILE))
+            .map_err(|_| format!("Бэкап {} не найден или в нём нет {}", name, MANIFEST_FILE))?,
+    )?;
+    split::join_archives(&backup_dir, &manifest)?;
+    crypto::decrypt_archives(config, &backup_dir, &manifest)?;
+    let tree = build(&backup_dir, &manifest);
+
+    info!("Бэкап {} смонтирован в {}", name, mountpoint.display());
+    println!(
+        "{}",
+        format!(
+            "📂 Бэкап {} доступен только для чтения в {}. Для завершения: fusermount -u {} (или umount от root)",
+            name,
+            mountpoint.display(),
+            mountpoint.display()
+        )
+        .green()
+    );
+    fuser::mount2(
+        tree,
+        mountpoint,
+        &[MountOption::RO, MountOption::FSName(format!("obt:{}", name))],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use crate::config::NetworkConfig;
+
+/// Класс сбоя при обращении к хранилищу или при выполнении команды: по нему решается,
+/// есть ли смысл повторять попытку, и подбирается подсказка
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    /// Имя сервера не находится в DNS
+    UnknownHost,
+    /// Адрес разрешается, но по нему никто не отвечает: отказ, таймаут, нет маршрута
+    Unreachable,
+    /// Сертификат TLS не прошёл проверку
+    Certificate,
+    /// Логин, пароль, токен или ключ не приняты
+    Auth,
+    /// Кончилось место на диске или квота хранилища
+    DiskFull,
+    /// Изменение отклонено из-за чужого: ветка ушла вперёд, объект изменён одновременно
+    Conflict,
+}
+
+/// Коды HTTP в тексте ошибок ureq (`status code 401`) и libgit2 (`unexpected http status code: 401`)
+fn has_status(message: &str, codes: &[u16]) -> bool {
+    codes
+        .iter()
+        .any(|code| message.contains(&format!("code {}", code)) || message.contains(&format!("code: {}", code)))
+}
+
+impl Failure {
+    /// Текст ошибки libgit2, ureq, ssh2 или команды → класс сбоя; `None` — обычная ошибка
+    pub fn classify(message: &str) -> Option<Self> {
+        let message = message.to_lowercase();
+        if ["no space left on device", "disk quota exceeded", "quotaexceeded", "insufficient storage"]
+            .iter()
+            .any(|m| message.contains(m))
+            || has_status(&message, &[507])
+        {
+            return Some(Failure::DiskFull);
+        }
+        if [
+            "authentication failed",
+            "authentication required",
+            "authentication replays",
+            "unauthorized",
+            "invalid credentials",
+            "permission denied (publickey",
+            "accessdenied",
+            "invalidaccesskeyid",
+            "signaturedoesnotmatch",
+            // Отказ из колбэка учётных данных git
+            "отклонил логин",
+            "отклонил ключ",
+        ]
+        .iter()
+        .any(|m| message.contains(m))
+            || has_status(&message, &[401, 403])
+        {
+            return Some(Failure::Auth);
+        }
+        if ["non-fast-forward", "fetch first", "cannot lock ref", "operationaborted"]
+            .iter()
+            .any(|m| message.contains(m))
+            || has_status(&message, &[409, 412])
+        {
+            return Some(Failure::Conflict);
+        }
+        // Временный сбой резолвера — не повод считать, что адрес сменился
+        if message.contains("temporary failure in name resolution") {
+            return None;
+        }
+        if ["failed to resolve", "name or service not known", "no address associated", "nodename nor servname"]
+            .iter()
+            .any(|m| message.contains(m))
+            || message.contains("dns failed")
+        {
+            return Some(Failure::UnknownHost);
+        }
+        if message.contains("certificate") || (message.contains("ssl") && message.contains("verif")) {
+            return Some(Failure::Certificate);
+        }
+        if ["connection refused", "timed out", "no route to host", "network is unreachable", "host is unreachable"]
+            .iter()
+            .any(|m| message.contains(m))
+        {
+            return Some(Failure::Unreachable);
+        }
+        None
+    }
+
+    /// Класс по коду ответа HTTP, когда текст ответа ничего не говорит
+    pub fn from_status(code: u16) -> Option<Self> {
+        match code {
+            401 | 403 => Some(Failure::Auth),
+            409 | 412 => Some(Failure::Conflict),
+            507 => Some(Failure::DiskFull),
+            _ => None,
+        }
+    }
+
+    /// Повтор через несколько секунд может помочь только недоступному серверу
+    /// (перезапуск, сбой сети); имя из DNS, сертификат, пароль, место на диске и чужие
+    /// изменения сами собой не исправятся
+    pub fn retriable(self) -> bool {
+        self == Failure::Unreachable
+    }
+
+    /// Ошибка с причиной и подсказкой: какой параметр config.json поправить, если сервер переехал
+    pub fn explain(self, host: &str, setting: &str, detail: &str) -> String {
+        let change = format!("obt config set {} <новый адрес>", setting);
+        match self {
+            Failure::UnknownHost => format!(
+                "Адрес {} не найден в DNS ({}). Если сервер переехал, укажите новый адрес: {}",
+                host, detail, change
+            ),
+            Failure::Unreachable => format!(
+                "Сервер {} недоступен ({}). Проверьте, что он работает; если сменился его IP или адрес — {}",
+                host, detail, change
+            ),
+            Failure::Certificate => format!(
+                "Сертификат {} не прошёл проверку ({}). Если сертификат обновили, он должен быть выдан \
+                 доверенным центром на имя {}; если сервер переехал на другой адрес — {}",
+                host, detail, host, change
+            ),
+            Failure::Auth => format!(
+                "{} не принял учётные данные ({}). Повтор не поможет: проверьте {} (obt config set)",
+                host,
+                detail,
+                credential_settings(setting)
+            ),
+            Failure::DiskFull => format!(
+                "В хранилище {} закончилось место ({}). Освободите место или увеличьте квоту; \
+                 уменьшить объём помогут retention_keep_last и exclude",
+                host, detail
+            ),
+            Failure::Conflict => format!(
+                "{} отклонил изменение из-за одновременного чужого ({}). Скорее всего, в то же хранилище \
+                 пишет другой сервер: запустите бэкап ещё раз или задайте on_divergence",
+                host, detail
+            ),
+        }
+    }
+}
+
+/// Поля config.json с учётными данными хранилища, адрес которого задан полем `setting`
+fn credential_settings(setting: &str) -> &'static str {
+    match setting {
+        "gitea_url" => "gitea_token или gitea_password",
+        "s3.endpoint" => "s3.access_key и s3.secret_key",
+        "sftp.host" => "sftp.username, sftp.password или sftp.key_file",
+        "git_ssh.url" => "git_ssh.key_file",
+        _ => "логин и пароль хранилища",
+    }
+}
+
+/// Имя сервера из адреса вида `https://host/...`, `ssh://user@host:port/...` или `host/path`
+pub fn host_of(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    host.split(':').next().unwrap_or(host).to_string()
+}
+
+/// Клиент HTTP хранилища с его тайм-аутами (`gitea_network`, `s3.network`, `webdav.network`).
+/// Общего тайм-аута запроса нет: архивы бывают по нескольку ГБ, а зависшую передачу
+/// обрывают тайм-ауты чтения и записи
+pub fn agent(settings: &NetworkConfig) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(settings.connect_timeout())
+        .timeout_read(settings.timeout())
+        .timeout_write(settings.timeout())
+        .build()
+}
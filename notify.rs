@@ -0,0 +1,194 @@
+use crate::config::{Config, SmtpConfig, TelegramConfig};
+use crate::smtp::{self, Attachment};
+use log::{info, warn};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Telegram не принимает сообщения длиннее 4096 символов
+const TELEGRAM_MAX_TEXT: usize = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+impl Level {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub level: Level,
+    pub title: String,
+    pub text: String,
+    /// UUID запуска бэкапа, о котором уведомление (Obt-Run-Id коммита, obt-run-id объектов S3)
+    pub run_id: Option<String>,
+    /// Файлы к письму (backup_info.txt или конец журнала); другие каналы их не отправляют
+    pub attachments: Vec<Attachment>,
+}
+
+/// Канал доставки уведомлений
+pub trait Notifier {
+    fn name(&self) -> &str;
+    fn send(&self, notification: &Notification) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Передаёт уведомление внешней команде: текст на stdin, заголовок, уровень и UUID запуска
+/// в OBT_TITLE, OBT_LEVEL и OBT_RUN_ID
+pub struct CommandNotifier {
+    command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), Box<dyn std::error::Error>> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("OBT_TITLE", &notification.title)
+            .env("OBT_LEVEL", notification.level.as_str())
+            .env("OBT_RUN_ID", notification.run_id.as_deref().unwrap_or_default())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(notification.text.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "команда уведомления завершилась с ошибкой: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// HTTP-клиент уведомлений: через свой прокси канала, если он задан, независимо от того,
+/// как подключается хранилище
+fn agent(proxy: Option<&str>) -> Result<ureq::Agent, Box<dyn std::error::Error>> {
+    let mut builder = ureq::AgentBuilder::new();
+    if let Some(proxy) = proxy {
+        // В тексте ошибки ureq может оказаться пароль прокси
+        let proxy = ureq::Proxy::new(proxy).map_err(|e| format!("неверный адрес прокси уведомлений: {}", e.kind()))?;
+        builder = builder.proxy(proxy);
+    }
+    Ok(builder.build())
+}
+
+/// Отправляет уведомление в чат Telegram через Bot API; ошибки выделены, чтобы их не пропустить
+pub struct TelegramNotifier {
+    config: TelegramConfig,
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), Box<dyn std::error::Error>> {
+        let mut text = match notification.level {
+            Level::Info => format!("✅ {}\n\n{}", notification.title, notification.text),
+            Level::Error => format!("🚨🚨🚨 {}\n\n{}", notification.title.to_uppercase(), notification.text),
+        };
+        if let Some(run_id) = &notification.run_id {
+            text.push_str(&format!("\n\nRun ID: {}", run_id));
+        }
+        if let Some((end, _)) = text.char_indices().nth(TELEGRAM_MAX_TEXT) {
+            text.truncate(end);
+            text.push('…');
+        }
+
+        agent(self.config.proxy.as_deref())?
+            .post(&format!("https://api.telegram.org/bot{}/sendMessage", self.config.bot_token))
+            .timeout(Duration::from_secs(30))
+            .send_json(serde_json::json!({
+                "chat_id": self.config.chat_id,
+                "text": text,
+                // Об успехе — без звука, об ошибке — со звуком
+                "disable_notification": notification.level == Level::Info,
+            }))
+            .map_err(|e| match e {
+                // В ответе Telegram объясняет причину, например «chat not found»
+                ureq::Error::Status(code, response) => {
+                    format!("Telegram ответил {}: {}", code, response.into_string().unwrap_or_default())
+                }
+                // Текст ошибки ureq содержит адрес с токеном бота
+                ureq::Error::Transport(e) => format!("не удалось подключиться к Telegram: {}", e.kind()),
+            })?;
+        Ok(())
+    }
+}
+
+/// Письмо через SMTP-сервер: к письму об успешном бэкапе приложен backup_info.txt, об ошибке — log.txt
+pub struct EmailNotifier {
+    config: SmtpConfig,
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), Box<dyn std::error::Error>> {
+        let mut text = notification.text.clone();
+        let mut headers = Vec::new();
+        if let Some(run_id) = &notification.run_id {
+            text.push_str(&format!("\n\nRun ID: {}", run_id));
+            headers.push(("X-Obt-Run-Id", run_id.as_str()));
+        }
+        let subject = match notification.level {
+            Level::Info => notification.title.clone(),
+            Level::Error => {
+                // Почтовые клиенты выделяют такие письма как важные
+                headers.push(("X-Priority", "1"));
+                format!("🚨 {}", notification.title)
+            }
+        };
+        smtp::send(&self.config, &subject, &text, &notification.attachments, &headers)
+    }
+}
+
+/// Все каналы, настроенные в конфигурации
+pub fn notifiers(config: &Config) -> Vec<Box<dyn Notifier>> {
+    let mut result: Vec<Box<dyn Notifier>> = Vec::new();
+    if let Some(command) = &config.notify_command {
+        result.push(Box::new(CommandNotifier {
+            command: command.clone(),
+        }));
+    }
+    if let Some(telegram) = &config.telegram {
+        result.push(Box::new(TelegramNotifier {
+            config: telegram.clone(),
+        }));
+    }
+    if let Some(smtp) = &config.smtp {
+        result.push(Box::new(EmailNotifier { config: smtp.clone() }));
+    }
+    result
+}
+
+/// Отправляет уведомление во все каналы; ошибка одного канала не мешает остальным
+pub fn send(config: &Config, notification: &Notification) {
+    for notifier in notifiers(config) {
+        match notifier.send(notification) {
+            Ok(_) => info!("Уведомление '{}' отправлено через {}", notification.title, notifier.name()),
+            Err(e) => warn!("Не удалось отправить уведомление через {}: {}", notifier.name(), e),
+        }
+    }
+}
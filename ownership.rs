@@ -0,0 +1,103 @@
+use nix::unistd::{fchownat, FchownatFlags, Gid, Group, Uid, User};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Таблица пересчёта владельцев: исходный UID/GID из архива → UID/GID на этой системе
+#[derive(Debug, Default)]
+pub struct OwnerMap {
+    users: HashMap<u32, u32>,
+    groups: HashMap<u32, u32>,
+}
+
+impl OwnerMap {
+    /// Читает таблицу вида `user 1001 www-data` / `group 1001 1000` (по строке на запись,
+    /// `#` — комментарий). Новый владелец задаётся именем на этой системе или числом.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Не удалось прочитать таблицу владельцев {}: {}", path.display(), e))?;
+
+        let mut map = OwnerMap::default();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let invalid = || format!("{}:{}: ожидается `user|group <старый id> <новый>`", path.display(), number + 1);
+            if fields.len() != 3 {
+                return Err(invalid().into());
+            }
+            let old: u32 = fields[1].parse().map_err(|_| invalid())?;
+
+            match fields[0] {
+                "user" => {
+                    let new = match fields[2].parse() {
+                        Ok(uid) => uid,
+                        Err(_) => User::from_name(fields[2])?
+                            .ok_or(format!("Пользователь {} не найден на этой системе", fields[2]))?
+                            .uid
+                            .as_raw(),
+                    };
+                    map.users.insert(old, new);
+                }
+                "group" => {
+                    let new = match fields[2].parse() {
+                        Ok(gid) => gid,
+                        Err(_) => Group::from_name(fields[2])?
+                            .ok_or(format!("Группа {} не найдена на этой системе", fields[2]))?
+                            .gid
+                            .as_raw(),
+                    };
+                    map.groups.insert(old, new);
+                }
+                _ => return Err(invalid().into()),
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// Как назначать владельцев файлов при распаковке на другой системе
+#[derive(Debug, Default)]
+pub enum Ownership {
+    /// По именам пользователей и групп из архива; если имени нет — исходный числовой ID (поведение tar)
+    #[default]
+    ByName,
+    /// Исходные числовые UID/GID без сопоставления имён
+    Numeric,
+    /// Исходные числовые UID/GID, пересчитанные по таблице; ID без записи остаются как есть
+    Remap(OwnerMap),
+}
+
+impl Ownership {
+    /// Назначает владельца одного распакованного файла по его заголовку в архиве
+    pub fn chown(&self, path: &Path, header: &tar::Header) -> Result<(), Box<dyn std::error::Error>> {
+        let (mut uid, mut gid) = (header.uid()? as u32, header.gid()? as u32);
+        match self {
+            Ownership::ByName => {
+                if let Some(user) = header.username().ok().flatten().and_then(|name| User::from_name(name).ok().flatten()) {
+                    uid = user.uid.as_raw();
+                }
+                if let Some(group) = header.groupname().ok().flatten().and_then(|name| Group::from_name(name).ok().flatten()) {
+                    gid = group.gid.as_raw();
+                }
+            }
+            Ownership::Numeric => {}
+            Ownership::Remap(map) => {
+                uid = map.users.get(&uid).copied().unwrap_or(uid);
+                gid = map.groups.get(&gid).copied().unwrap_or(gid);
+            }
+        }
+        fchownat(
+            None,
+            path,
+            Some(Uid::from_raw(uid)),
+            Some(Gid::from_raw(gid)),
+            FchownatFlags::NoFollowSymlink,
+        )
+        .map_err(|e| format!("Не удалось сменить владельца {}: {}", path.display(), e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use crate::config::Config;
+use crate::timestamp;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Пауза плановых запусков (~/.config/obt/pause.json), `obt pause` / `obt resume`.
+/// Пока файл есть, демон пропускает запуски по расписанию, а контроль свежести молчит
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pause {
+    pub since: String,
+    /// Без срока пауза длится до `obt resume`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Pause {
+    fn load() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let path = Self::get_pause_path()?;
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_pause_path()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Снимает паузу; `false`, если её не было
+    pub fn clear() -> Result<bool, Box<dyn std::error::Error>> {
+        let path = Self::get_pause_path()?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(path)?;
+        Ok(true)
+    }
+
+    fn get_pause_path() -> io::Result<PathBuf> {
+        Ok(Config::config_dir()?.join("pause.json"))
+    }
+
+    /// «до 2026-10-15 06:00:00 MSK (обновление панели)»
+    pub fn describe(&self) -> String {
+        let until = match &self.until {
+            Some(until) => format!("до {}", timestamp::display(until)),
+            None => "до obt resume".to_string(),
+        };
+        match &self.reason {
+            Some(reason) => format!("{} ({})", until, reason),
+            None => until,
+        }
+    }
+}
+
+/// Действующая пауза. Истёкшая снимается здесь же, так что `--until` не требует `obt resume`
+pub fn active() -> Option<Pause> {
+    let pause = match Pause::load() {
+        Ok(pause) => pause?,
+        Err(e) => {
+            // Испорченный файл не должен молча останавливать бэкапы
+            warn!("Не удалось прочитать pause.json, пауза не учитывается: {}", e);
+            return None;
+        }
+    };
+    let expired = pause.until.as_deref().and_then(timestamp::parse).is_some_and(|until| until <= Utc::now());
+    if !expired {
+        return Some(pause);
+    }
+    info!("Пауза бэкапов истекла ({}), плановые запуски возобновлены", pause.describe());
+    if let Err(e) = Pause::clear() {
+        warn!("Не удалось удалить pause.json: {}", e);
+    }
+    None
+}
+
+/// Срок паузы: время, как в `obt restore --as-of`, или длительность от текущего момента
+/// (`30m`, `4h`, `2d`)
+pub fn parse_until(value: &str) -> Result<DateTime<Utc>, Box<dyn std::error::Error>> {
+    let invalid = || format!("Неверный срок паузы: {} (ожидается время или длительность: 30m, 4h, 2d)", value);
+    let until = match value.char_indices().last() {
+        Some((split, unit @ ('m' | 'h' | 'd'))) => {
+            let amount: i64 = value[..split].parse().map_err(|_| invalid())?;
+            let duration = match unit {
+                'm' => Duration::minutes(amount),
+                'h' => Duration::hours(amount),
+                _ => Duration::days(amount),
+            };
+            Utc::now() + duration
+        }
+        _ => timestamp::parse_input(value).ok_or_else(invalid)?,
+    };
+    if until <= Utc::now() {
+        return Err(format!("Срок паузы уже прошёл: {}", timestamp::display_time(until)).into());
+    }
+    Ok(until)
+}
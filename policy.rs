@@ -0,0 +1,206 @@
+use crate::config::Config;
+use crate::git;
+use crate::staging::StagingDir;
+use crate::timestamp;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const DEFAULT_FILE: &str = "policy.yaml";
+
+/// Поля, которые относятся к самому серверу и политикой не задаются
+const LOCAL_FIELDS: &[&str] = &[
+    "policy",
+    "backup_name",
+    "gitea_password",
+    "gitea_token",
+    "last_backup",
+    "last_failure",
+    "last_error",
+    "last_error_output",
+    "last_verification",
+];
+
+/// Поля расписания: после их изменения нужно обновить юниты systemd и перезапустить демон
+const SCHEDULE_FIELDS: &[&str] = &["backup_frequency", "backup_time", "backup_cron", "timezone", "prune_schedule", "verify_schedule"];
+
+pub fn changes_schedule(changed: &[String]) -> bool {
+    changed.iter().any(|key| SCHEDULE_FIELDS.contains(&key.as_str()))
+}
+
+/// Последняя применённая политика (~/.config/obt/policy.json). По ней при следующей
+/// синхронизации видно, какие поля config.json администратор сервера поменял сам
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PolicyState {
+    pub url: String,
+    /// Коммит репозитория политики
+    pub revision: String,
+    pub synced_at: String,
+    pub applied: Map<String, Value>,
+}
+
+impl PolicyState {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_state_path()?;
+
+        if !path.exists() {
+            return Ok(PolicyState::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_state_path()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Поля политики, которым config.json сейчас не следует: на сервере задано своё значение
+    pub fn overridden(&self, config: &Config) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let tree = serde_json::to_value(config)?;
+        Ok(self
+            .applied
+            .iter()
+            .filter(|(key, value)| {
+                let current = tree.get(key.as_str()).unwrap_or(&Value::Null);
+                match (current, value) {
+                    // Свои элементы списка не в счёт, только изменённые или удалённые элементы политики
+                    (Value::Array(current), Value::Array(items)) => items.iter().any(|item| !current.contains(item)),
+                    (Value::Null, Value::Array(items)) => !items.is_empty(),
+                    _ => current != *value,
+                }
+            })
+            .map(|(key, _)| key.clone())
+            .collect())
+    }
+
+    fn get_state_path() -> io::Result<PathBuf> {
+        Ok(Config::config_dir()?.join("policy.json"))
+    }
+}
+
+/// Разбирает файл политики (YAML или JSON) и приводит поля к виду, в котором их
+/// записывает config.json: `"/etc/nginx"` в backup_paths становится `{"path": ..., "priority": "Normal"}`
+fn parse(content: &str) -> Result<Map<String, Value>, Box<dyn std::error::Error>> {
+    let policy: Map<String, Value> =
+        serde_yaml::from_str(content).map_err(|e| format!("Ошибка в файле политики: {}", e))?;
+    if let Some(field) = policy.keys().find(|key| LOCAL_FIELDS.contains(&key.as_str())) {
+        return Err(format!("Поле {} задаётся на каждом сервере и не может быть в политике", field).into());
+    }
+
+    let Value::Object(mut tree) = serde_json::to_value(Config::default())? else {
+        unreachable!("Config сериализуется в объект");
+    };
+    tree.extend(policy.clone());
+    let config: Config =
+        serde_json::from_value(Value::Object(tree)).map_err(|e| format!("Ошибка в файле политики: {}", e))?;
+    let Value::Object(normalized) = serde_json::to_value(&config)? else {
+        unreachable!("Config сериализуется в объект");
+    };
+
+    let mut result = Map::new();
+    for (key, value) in policy {
+        match normalized.get(&key) {
+            Some(normalized) => result.insert(key, normalized.clone()),
+            None if value.is_null() => result.insert(key, value),
+            None => return Err(format!("Неизвестный параметр в политике: {}", key).into()),
+        };
+    }
+    Ok(result)
+}
+
+/// Путь для элементов backup_paths, сам элемент — для остальных списков
+fn item_key(item: &Value) -> &Value {
+    item.get("path").unwrap_or(item)
+}
+
+/// Список из политики: добавленное в неё появляется на сервере, удалённое из неё — исчезает,
+/// если администратор сервера не менял этот элемент; свои элементы сервера не трогаются
+fn merge_items(ours: &[Value], base: &[Value], theirs: &[Value]) -> Vec<Value> {
+    let mut merged = Vec::new();
+    for item in ours {
+        if !base.contains(item) {
+            merged.push(item.clone());
+            continue;
+        }
+        if let Some(updated) = theirs.iter().find(|t| item_key(t) == item_key(item)) {
+            merged.push(updated.clone());
+        }
+    }
+    for item in theirs {
+        let known = base.iter().chain(ours).any(|known| item_key(known) == item_key(item));
+        if !known {
+            merged.push(item.clone());
+        }
+    }
+    merged
+}
+
+/// Трёхстороннее слияние: поле сервера принимает значение политики, если было равно её
+/// прошлому значению (то есть не менялось на сервере). Возвращает изменённые поля
+fn merge(ours: &mut Map<String, Value>, base: &Map<String, Value>, theirs: &Map<String, Value>) -> Vec<String> {
+    let keys: BTreeSet<String> = base.keys().chain(theirs.keys()).cloned().collect();
+    let mut changed = Vec::new();
+    for key in keys {
+        let current = ours.get(&key).cloned().unwrap_or(Value::Null);
+        let previous = base.get(&key).unwrap_or(&Value::Null);
+        let incoming = theirs.get(&key).unwrap_or(&Value::Null);
+        let merged = match &current {
+            // Поле, убранное из политики, — пустой список
+            Value::Array(current) if incoming.is_array() || previous.is_array() => Value::Array(merge_items(
+                current,
+                previous.as_array().map(Vec::as_slice).unwrap_or_default(),
+                incoming.as_array().map(Vec::as_slice).unwrap_or_default(),
+            )),
+            _ if current == *previous => incoming.clone(),
+            // Значение, заданное на сервере, важнее политики
+            _ => current.clone(),
+        };
+        if merged != current {
+            ours.insert(key.clone(), merged);
+            changed.push(key);
+        }
+    }
+    changed
+}
+
+/// Скачивает политику из `policy.url` и сливает её с config.json. Возвращает изменённые поля
+pub fn sync(config: &mut Config) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let policy = config.policy.clone().ok_or("Политика не настроена: задайте policy.url")?;
+    let file = policy.file.as_deref().unwrap_or(DEFAULT_FILE);
+    let staging = StagingDir::new(format!("policy_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let credentials = git::Credentials::for_url(config, &policy.url, "policy.url");
+    let (content, revision) = git::read_file(&policy.url, policy.branch.as_deref(), file, &credentials, staging.path())?;
+    let theirs = parse(&content)?;
+
+    let state = PolicyState::load()?;
+    // Политика из другого репозитория применяется как впервые
+    let base = if state.url == policy.url { state.applied } else { Map::new() };
+    let Value::Object(mut ours) = serde_json::to_value(&*config)? else {
+        unreachable!("Config сериализуется в объект");
+    };
+    let changed = merge(&mut ours, &base, &theirs);
+    if !changed.is_empty() {
+        config
+            .update_from(Value::Object(ours))
+            .map_err(|e| format!("Политика несовместима с config.json: {}", e))?;
+        config.save()?;
+        info!("Политика {} ({}) изменила поля: {}", policy.url, revision, changed.join(", "));
+    }
+
+    PolicyState {
+        url: policy.url,
+        revision,
+        synced_at: timestamp::format(chrono::Utc::now()),
+        applied: theirs,
+    }
+    .save()?;
+    Ok(changed)
+}
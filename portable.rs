@@ -0,0 +1,47 @@
+use crate::compression;
+use crate::manifest::ArchiveEntry;
+use log::info;
+use std::fs;
+use std::path::Path;
+
+/// Символы, недопустимые в именах файлов FAT и exFAT
+const RESERVED: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Предел имени на FAT/exFAT — 255 символов; запас оставлен под `.age` и `.partNN`
+const MAX_CHARS: usize = 240;
+
+/// Имя, допустимое на FAT и exFAT: недопустимые и управляющие символы заменяются на `_`,
+/// точки и пробелы в конце убираются, а слишком длинное имя укорачивается с сохранением
+/// расширения архива. Допустимое имя возвращается как есть
+pub fn name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| if RESERVED.contains(&c) || c.is_control() { '_' } else { c })
+        .collect();
+    let replaced = replaced.trim_end_matches(['.', ' ']).to_string();
+    if replaced.chars().count() <= MAX_CHARS {
+        return replaced;
+    }
+    let extension = match compression::tar_stem(&replaced) {
+        Some(stem) => &replaced[stem.len()..],
+        None => replaced.rfind('.').map_or("", |dot| &replaced[dot..]),
+    };
+    let stem: String = replaced.chars().take(MAX_CHARS.saturating_sub(extension.chars().count())).collect();
+    format!("{}{}", stem, extension)
+}
+
+/// Переименовывает созданный архив в папке бэкапа `dir` по `name`; исходное имя
+/// остаётся в manifest.json (`original_name`)
+pub fn rename_archive(dir: &Path, entry: &mut ArchiveEntry) -> Result<(), Box<dyn std::error::Error>> {
+    let portable = name(&entry.name);
+    if portable == entry.name {
+        return Ok(());
+    }
+    let from = dir.join(entry.file_name());
+    let original = std::mem::replace(&mut entry.name, portable);
+    fs::rename(&from, dir.join(entry.file_name()))
+        .map_err(|e| format!("Не удалось переименовать архив {} для FAT/exFAT: {}", original, e))?;
+    info!("Архив {} переименован в {} для FAT/exFAT", original, entry.name);
+    entry.original_name = Some(original);
+    Ok(())
+}
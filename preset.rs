@@ -0,0 +1,93 @@
+use crate::config::{Compression, CompressionFormat, Config, EncryptionConfig, Preset, SecretsPolicy};
+use crate::i18n::{t, tf};
+use crate::{read_input, read_input_or};
+use colored::*;
+use std::path::PathBuf;
+
+/// Название набора, как в config.json
+pub fn name(preset: Preset) -> &'static str {
+    match preset {
+        Preset::Fast => "Fast",
+        Preset::Small => "Small",
+        Preset::Paranoid => "Paranoid",
+    }
+}
+
+pub fn compression(preset: Preset) -> Compression {
+    match preset {
+        Preset::Fast => Compression { format: CompressionFormat::Zstd, level: Some(1) },
+        Preset::Small => Compression { format: CompressionFormat::Xz, level: Some(9) },
+        // gzip распаковывается чем угодно, даже без obt
+        Preset::Paranoid => Compression::default(),
+    }
+}
+
+/// `None` — как без набора: проверка после загрузки, если нет отдельного расписания проверок
+pub fn verify(preset: Preset) -> Option<bool> {
+    match preset {
+        Preset::Fast => Some(false),
+        Preset::Small => None,
+        Preset::Paranoid => Some(true),
+    }
+}
+
+pub fn keep_last(preset: Preset) -> usize {
+    match preset {
+        Preset::Fast => 7,
+        Preset::Small => 5,
+        Preset::Paranoid => 30,
+    }
+}
+
+pub fn secrets_scan(preset: Preset) -> Option<SecretsPolicy> {
+    match preset {
+        Preset::Paranoid => Some(SecretsPolicy::Block),
+        Preset::Fast | Preset::Small => None,
+    }
+}
+
+/// Набор Paranoid не загружает незашифрованные архивы
+pub fn check(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if config.preset == Some(Preset::Paranoid) && config.encryption.is_none() {
+        return Err("Набор Paranoid требует шифрования: задайте encryption или смените preset".into());
+    }
+    Ok(())
+}
+
+/// Выбор набора при первичной настройке; поля, уже заданные в config.json, набор не меняет
+pub fn setup(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n{}", t("Набор настроек бэкапа").green());
+    println!("{}", t("1. Fast — быстрое сжатие zstd, без проверки после загрузки, 7 последних бэкапов"));
+    println!("{}", t("2. Small — сильное сжатие xz, 5 последних бэкапов"));
+    println!("{}", t("3. Paranoid — шифрование, проверка после загрузки, 30 последних бэкапов"));
+    println!("{}", t("4. Без набора — настроить поля config.json самостоятельно"));
+
+    let preset = match read_input_or(t("Выберите набор (1-4, Enter — без набора): "), "4")?.as_str() {
+        "1" => Preset::Fast,
+        "2" => Preset::Small,
+        "3" => Preset::Paranoid,
+        "4" | "" => return Ok(()),
+        _ => {
+            println!("{}", t("Неверный выбор"));
+            return setup(config);
+        }
+    };
+
+    if preset == Preset::Paranoid && config.encryption.is_none() {
+        let key = read_input(t("Путь к файлу ключа age или пароль для шифрования архивов: "))?;
+        if key.is_empty() {
+            println!("{}", t("Без ключа набор Paranoid не выбран"));
+            return Ok(());
+        }
+        config.encryption = Some(if PathBuf::from(&key).is_file() {
+            EncryptionConfig { passphrase: None, key_file: Some(PathBuf::from(key)) }
+        } else {
+            EncryptionConfig { passphrase: Some(key), key_file: None }
+        });
+    }
+
+    config.preset = Some(preset);
+    config.save()?;
+    println!("{}", tf("Выбран набор {}", &[&name(preset)]).green());
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::cell::Cell;
+use std::io::{self, IsTerminal};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// `obt --quiet`: без полос прогресса, например в cron
+static QUIET: OnceLock<bool> = OnceLock::new();
+
+thread_local! {
+    /// Полосы скрыты только для бэкапа этого потока: `BackupRunner` во встраивающей программе
+    static THREAD_QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Общий вывод полос: архивы, создаваемые параллельно, и загрузка не затирают друг друга
+static BARS: OnceLock<MultiProgress> = OnceLock::new();
+
+/// Полоса текущей загрузки в хранилище: в неё идут отправленные байты всех хранилищ
+static UPLOAD: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+pub fn set_quiet(quiet: bool) {
+    let _ = QUIET.set(quiet);
+}
+
+pub fn set_thread_quiet(quiet: bool) {
+    THREAD_QUIET.with(|current| current.set(quiet));
+}
+
+pub fn thread_quiet() -> bool {
+    THREAD_QUIET.with(Cell::get)
+}
+
+/// Полосы выводятся только в терминал: в журнале systemd они были бы мусором
+pub fn visible() -> bool {
+    !QUIET.get().copied().unwrap_or(false) && !thread_quiet() && io::stdout().is_terminal()
+}
+
+/// Полоса объёма: с `total` — доля, скорость и оставшееся время, без него — только счётчик.
+/// Без терминала или с `--quiet` полоса скрыта, но её можно обновлять как обычно
+pub fn bar(total: Option<u64>, message: String) -> ProgressBar {
+    if !visible() {
+        return ProgressBar::hidden();
+    }
+    let bar = match total {
+        Some(total) => ProgressBar::new(total).with_style(
+            ProgressStyle::with_template("   {msg} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec}, ~{eta}")
+                .unwrap()
+                .progress_chars("=> "),
+        ),
+        None => ProgressBar::new_spinner()
+            .with_style(ProgressStyle::with_template("   {spinner} {msg} {bytes} {bytes_per_sec}").unwrap()),
+    };
+    let bar = BARS.get_or_init(MultiProgress::new).add(bar.with_message(message));
+    bar.enable_steady_tick(Duration::from_millis(200));
+    bar
+}
+
+/// Загрузка примерно `total` байт: полоса показывается, пока выполняется `operation`
+pub fn upload<T>(total: u64, operation: impl FnOnce() -> T) -> T {
+    *UPLOAD.lock().unwrap() = Some(bar(Some(total), "загрузка".to_string()));
+    let result = operation();
+    if let Some(bar) = UPLOAD.lock().unwrap().take() {
+        bar.finish_and_clear();
+    }
+    result
+}
+
+/// Отправлено ещё `bytes` байт; вне `upload` ничего не делает
+pub fn uploaded(bytes: u64) {
+    if let Some(bar) = UPLOAD.lock().unwrap().as_ref() {
+        bar.inc(bytes);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::i18n::{t, tf};
+use log::warn;
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use std::io::{self, IsTerminal, Write};
+use std::os::unix::io::AsRawFd;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Сколько ждать ответа на вопрос в терминале: `--prompt-timeout`, иначе `prompt_timeout_seconds`
+static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// `None` или 0 — ждать ответа сколько угодно
+pub fn set_timeout(seconds: Option<u64>) {
+    if let Some(seconds) = seconds.filter(|seconds| *seconds > 0) {
+        let _ = TIMEOUT.set(Duration::from_secs(seconds));
+    }
+}
+
+/// Ждёт введённую строку не дольше `timeout`; `false` — ответа не было
+fn wait(timeout: Duration) -> io::Result<bool> {
+    let stdin = io::stdin();
+    let mut fds = [PollFd::new(stdin.as_raw_fd(), PollFlags::POLLIN)];
+    let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+    loop {
+        match poll(&mut fds, timeout) {
+            Ok(ready) => return Ok(ready > 0),
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Задаёт вопрос и читает ответ. С тайм-аутом вопрос в терминале без ответа получает
+/// `default`, а если его нет — завершается ошибкой, чтобы забытый вопрос не держал
+/// подготовку сервера бесконечно. Ввод не из терминала читается как обычно
+pub fn read(prompt: &str, default: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    if let Some(timeout) = TIMEOUT.get().filter(|_| io::stdin().is_terminal()) {
+        if !wait(*timeout)? {
+            println!();
+            let question = prompt.trim().trim_end_matches(':');
+            let Some(default) = default else {
+                return Err(tf("Нет ответа за {} сек. на вопрос «{}»", &[&timeout.as_secs(), &question]).into());
+            };
+            let shown = match default {
+                "" => t("ответ по умолчанию").to_string(),
+                default => tf("ответ «{}»", &[&default]),
+            };
+            warn!("Нет ответа за {} сек. на вопрос «{}», выбран {}", timeout.as_secs(), question, shown);
+            println!("{}", tf("⏱️ Нет ответа за {} сек., выбран {}", &[&timeout.as_secs(), &shown]));
+            return Ok(default.to_string());
+        }
+    }
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
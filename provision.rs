@@ -0,0 +1,197 @@
+use crate::config::{
+    BackupFrequency, BackupPath, Config, DatabaseSource, DockerSource, GitSshConfig, PolicyConfig, RcloneConfig, ReplicaConfig, S3Config, SftpConfig, WebdavConfig,
+};
+use crate::{apply_schedule, policy, set_config_value, split_gitea_url, status};
+use chrono::NaiveTime;
+use colored::*;
+use log::{info, warn};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Файл ответов `obt provision --answers <файл>`: всё, о чём спрашивает первый запуск,
+/// в одном YAML (или JSON) для cloud-init и Ansible
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Answers {
+    /// Хранилище: ровно одно из gitea, s3, sftp, git_ssh, rclone, webdav
+    pub gitea: Option<GiteaAnswers>,
+    pub s3: Option<S3Config>,
+    pub sftp: Option<SftpConfig>,
+    pub git_ssh: Option<GitSshConfig>,
+    pub rclone: Option<RcloneConfig>,
+    pub webdav: Option<WebdavConfig>,
+    /// Второй репозиторий для `obt replicate`
+    pub replica: Option<ReplicaConfig>,
+    /// Имя бэкапа (обычно имя сервера)
+    pub name: String,
+    pub timezone: Option<String>,
+    /// Без расписания юниты systemd не устанавливаются
+    pub schedule: Option<ScheduleAnswers>,
+    #[serde(default)]
+    pub paths: Vec<BackupPath>,
+    /// Создать директории из `paths`, которых ещё нет (в образе сервера их могут создать позже)
+    #[serde(default)]
+    pub create_paths: bool,
+    #[serde(default)]
+    pub databases: Vec<DatabaseSource>,
+    #[serde(default)]
+    pub docker: Vec<DockerSource>,
+    /// Общая политика бэкапов: применяется поверх ответов, но не меняет заданное в них
+    pub policy: Option<PolicyConfig>,
+    /// Остальные параметры config.json по ключам, как в `obt config set`
+    #[serde(default)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+    /// Установить и запустить obt.service и obt.timer (по умолчанию да)
+    #[serde(default = "default_install")]
+    pub install: bool,
+}
+
+fn default_install() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GiteaAnswers {
+    /// Полный адрес репозитория, например `https://backups.example.com/alex/backup`
+    pub url: String,
+    pub username: String,
+    /// Токен доступа или пароль; подойдёт и ссылка `keyring:<имя>`
+    pub token: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleAnswers {
+    pub frequency: BackupFrequency,
+    /// ЧЧ:ММ
+    pub time: String,
+}
+
+impl Answers {
+    pub fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Не удалось прочитать {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&content).map_err(|e| format!("Ошибка в файле ответов {}: {}", path.display(), e).into())
+    }
+}
+
+/// Переносит ответы в `config`; расписание возвращается отдельно, чтобы юниты
+/// устанавливались уже после проверки хранилища
+fn apply(config: &mut Config, answers: Answers) -> Result<Option<ScheduleAnswers>, Box<dyn std::error::Error>> {
+    let storages = [
+        answers.gitea.is_some(),
+        answers.s3.is_some(),
+        answers.sftp.is_some(),
+        answers.git_ssh.is_some(),
+        answers.rclone.is_some(),
+        answers.webdav.is_some(),
+    ];
+    if storages.iter().filter(|set| **set).count() != 1 {
+        return Err("В файле ответов должно быть ровно одно хранилище: gitea, s3, sftp, git_ssh, rclone или webdav".into());
+    }
+
+    config.s3 = answers.s3;
+    config.sftp = answers.sftp;
+    config.git_ssh = answers.git_ssh;
+    config.rclone = answers.rclone;
+    config.webdav = answers.webdav;
+    if let Some(gitea) = answers.gitea {
+        let (url, repo) = split_gitea_url(&gitea.url)
+            .ok_or_else(|| format!("Неверный адрес репозитория Gitea: {}", gitea.url))?;
+        config.gitea_url = Some(url);
+        config.gitea_repo = Some(repo);
+        config.gitea_username = Some(gitea.username);
+        match (gitea.token, gitea.password) {
+            (Some(token), None) => {
+                config.gitea_token = Some(token);
+                config.gitea_password = None;
+            }
+            (None, Some(password)) => {
+                config.gitea_password = Some(password);
+                config.gitea_token = None;
+            }
+            _ => return Err("Для gitea укажите token или password (одно из двух)".into()),
+        }
+    }
+    if answers.replica.is_some() {
+        config.replica = answers.replica;
+    }
+
+    config.backup_name = Some(answers.name);
+    if answers.timezone.is_some() {
+        config.timezone = answers.timezone;
+        config.zone()?;
+    }
+
+    for path in &answers.paths {
+        if Path::new(&path.path).exists() {
+            continue;
+        }
+        if answers.create_paths {
+            fs::create_dir_all(&path.path)?;
+            info!("Создана директория {}", path.path);
+        } else {
+            warn!("Путь для бэкапа пока не существует: {}", path.path);
+            println!("{}", format!("⚠️ Путь пока не существует: {}", path.path).yellow());
+        }
+    }
+    config.backup_paths = answers.paths;
+    config.databases = answers.databases;
+    config.docker = answers.docker;
+    if answers.policy.is_some() {
+        config.policy = answers.policy;
+    }
+
+    for (key, value) in answers.settings {
+        *config = set_config_value(&serde_json::to_value(&*config)?, &key, value)?;
+    }
+    if let Some(smtp) = &config.smtp {
+        smtp.validate()?;
+    }
+    if let Some(schedule) = &answers.schedule {
+        NaiveTime::parse_from_str(&schedule.time, "%H:%M")
+            .map_err(|_| format!("Неверный формат времени: {} (ожидается ЧЧ:ММ)", schedule.time))?;
+    }
+    Ok(answers.schedule)
+}
+
+/// `obt provision --answers <файл>`: первичная настройка без вопросов. Повторный запуск
+/// с тем же файлом даёт ту же конфигурацию, поэтому годится для Ansible
+pub fn run(config: &mut Config, answers_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let answers = Answers::read(answers_path)?;
+    let install = answers.install;
+    let mut schedule = apply(config, answers)?;
+    config.save()?;
+    println!("{}", "✅ config.json заполнен из файла ответов".green());
+
+    if config.policy.is_some() {
+        let changed = policy::sync(config).map_err(|e| format!("Не удалось применить политику: {}", e))?;
+        println!("{}", format!("✅ Политика применена, изменены поля: {}", changed.join(", ")).green());
+        // Расписание из файла ответов важнее, иначе берётся расписание политики
+        if schedule.is_none() {
+            if let (Some(frequency), Some(time)) = (config.backup_frequency, config.backup_time.clone()) {
+                schedule = Some(ScheduleAnswers { frequency, time });
+            }
+        }
+    }
+
+    println!("🔌 Проверка хранилища...");
+    status::check_remote(config).map_err(|e| format!("Хранилище недоступно: {}", e))?;
+    println!("{}", "✅ Хранилище доступно".green());
+
+    match schedule {
+        Some(schedule) if install => apply_schedule(config, schedule.frequency, schedule.time)?,
+        Some(schedule) => {
+            config.backup_frequency = Some(schedule.frequency);
+            config.backup_time = Some(schedule.time);
+            config.save()?;
+            println!("Юниты systemd не устанавливались (install: false)");
+        }
+        None => println!("Расписание не задано: юниты systemd не устанавливались"),
+    }
+    info!("Сервер настроен из файла ответов {}", answers_path.display());
+    Ok(())
+}
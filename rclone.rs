@@ -0,0 +1,229 @@
+use crate::config::RcloneConfig;
+use crate::dest::{self, Check};
+use crate::forensics;
+use crate::progress;
+use crate::storage::{self, Backend};
+use log::info;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Код выхода rclone, когда папки нет
+const DIRECTORY_NOT_FOUND: i32 = 3;
+
+/// Ошибка команды rclone: код выхода (если процесс запустился) и текст
+struct Failed {
+    code: Option<i32>,
+    message: String,
+}
+
+/// Удалённое хранилище rclone: `<remote>/<папка бэкапа>/<файл>`. Сам протокол (Google Drive,
+/// Dropbox, B2, ...) и повторы при сетевых ошибках — забота rclone; obt только вызывает
+/// `rclone copyto`, `lsf`, `purge` и `about`
+pub struct Storage {
+    config: RcloneConfig,
+    dir: PathBuf,
+    upload_limit_kbps: Option<u64>,
+    /// Файлы рабочей папки, которые уже есть в хранилище
+    uploaded: RefCell<HashSet<String>>,
+    /// Папки удалённых бэкапов; удаляются из хранилища после загрузки нового
+    removed: RefCell<Vec<String>>,
+}
+
+impl Storage {
+    /// Проверяет, что rclone установлен и хранилище доступно, до начала архивации
+    pub fn open(config: &RcloneConfig, dir: &str, upload_limit_kbps: Option<u64>) -> Result<Self, Box<dyn std::error::Error>> {
+        let storage = Storage {
+            config: config.clone(),
+            dir: PathBuf::from(dir),
+            upload_limit_kbps,
+            uploaded: RefCell::new(HashSet::new()),
+            removed: RefCell::new(Vec::new()),
+        };
+        storage.run(&["mkdir", &storage.remote("")])?;
+        info!("Подключено хранилище {}", storage.location());
+        Ok(storage)
+    }
+
+    /// Путь в хранилище: `gdrive:backups` + `имя/файл`
+    fn remote(&self, relative: &str) -> String {
+        let root = self.config.remote.trim_end_matches('/');
+        match (relative, root.ends_with(':')) {
+            ("", _) => root.to_string(),
+            (_, true) => format!("{}{}", root, relative),
+            (_, false) => format!("{}/{}", root, relative),
+        }
+    }
+
+    fn try_run(&self, args: &[&str]) -> Result<String, Failed> {
+        let mut command = Command::new("rclone");
+        command.args(args);
+        if let Some(config_file) = &self.config.config_file {
+            command.arg("--config").arg(config_file);
+        }
+        // rclone ограничивает скорость в КиБ/с, а upload_limit_kbps — в килобитах
+        if let Some(kbps) = self.upload_limit_kbps {
+            command.arg("--bwlimit").arg(format!("{}k", (kbps / 8).max(1)));
+        }
+        command.args(&self.config.flags);
+        let output = command.output().map_err(|e| Failed {
+            code: None,
+            message: match e.kind() {
+                io::ErrorKind::NotFound => "rclone не найден: установите его (https://rclone.org/install/)".to_string(),
+                _ => format!("не удалось запустить rclone: {}", e),
+            },
+        })?;
+        if !output.status.success() {
+            forensics::record_output(&output.stderr);
+            return Err(Failed {
+                code: output.status.code(),
+                message: format!("rclone {}: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+        self.try_run(args).map_err(|failed| failed.message.into())
+    }
+
+    /// Файлы в папке хранилища (пути относительно корня)
+    fn remote_files(&self, relative: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let output = self.run(&["lsf", "-R", "--files-only", &self.remote(relative)])?;
+        Ok(output
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| if relative.is_empty() { line.to_string() } else { format!("{}/{}", relative, line) })
+            .collect())
+    }
+
+    fn put(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let local = self.dir.join(relative);
+        let size = fs::metadata(&local)?.len();
+        self.run(&["copyto", &local.to_string_lossy(), &self.remote(relative)])?;
+        progress::uploaded(size);
+        Ok(())
+    }
+
+    fn get(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let local = self.dir.join(relative);
+        if let Some(parent) = local.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.run(&["copyto", &self.remote(relative), &local.to_string_lossy()])?;
+        Ok(())
+    }
+
+    /// `obt dest check`: записывает пробный файл `name`, читает и сверяет его и удаляет,
+    /// а место узнаёт через `rclone about`, если хранилище его сообщает
+    pub fn probe(&self, name: &str, check: &mut Check) -> Result<(), Box<dyn std::error::Error>> {
+        let content = dest::probe_content(name);
+        let path = self.dir.join(name);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, &content)?;
+        check.step("запись", || self.put(name))?;
+        fs::remove_file(&path)?;
+        check.step("чтение", || {
+            if self.run(&["cat", &self.remote(name)])? != content {
+                return Err("прочитанный файл отличается от записанного".into());
+            }
+            Ok(())
+        })?;
+        check.step("удаление", || self.run(&["deletefile", &self.remote(name)]).map(|_| ()))?;
+        check.space = self.free_space();
+        Ok(())
+    }
+
+    /// Свободное место по `rclone about --json`; `None`, если хранилище его не сообщает
+    fn free_space(&self) -> Option<String> {
+        let output = self.run(&["about", "--json", &self.remote("")]).ok()?;
+        let about: serde_json::Value = serde_json::from_str(&output).ok()?;
+        let free = about.get("free")?.as_u64()?;
+        Some(match about.get("total").and_then(|total| total.as_u64()) {
+            Some(total) => format!(
+                "свободно {:.1} ГБ из {:.1} ГБ",
+                free as f64 / 1_073_741_824.0,
+                total as f64 / 1_073_741_824.0
+            ),
+            None => format!("свободно {:.1} ГБ", free as f64 / 1_073_741_824.0),
+        })
+    }
+}
+
+impl Backend for Storage {
+    fn location(&self) -> String {
+        format!("rclone://{}", self.remote(""))
+    }
+
+    fn describe(&self) -> String {
+        format!("rclone, {}", self.remote(""))
+    }
+
+    fn download(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let files = self.remote_files("")?;
+        println!("📥 Скачивание из {}: {} файлов...", self.remote(""), files.len());
+        self.run(&["copy", &self.remote(""), &self.dir.to_string_lossy()])?;
+        self.uploaded.borrow_mut().extend(files);
+        Ok(())
+    }
+
+    fn download_backup(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let files = if self.contains(name)? { self.remote_files(name)? } else { Vec::new() };
+        println!("📥 Скачивание {} из {}: {} файлов...", name, self.remote(""), files.len());
+        for file in files {
+            self.get(&file)?;
+            self.uploaded.borrow_mut().insert(file);
+        }
+        Ok(())
+    }
+
+    fn backups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(storage::backup_folders(&self.remote_files("")?))
+    }
+
+    fn contains(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        match self.try_run(&["lsf", "--max-depth", "1", &self.remote(name)]) {
+            Ok(output) => Ok(!output.trim().is_empty()),
+            Err(Failed { code: Some(DIRECTORY_NOT_FOUND), .. }) => Ok(false),
+            Err(failed) => Err(failed.message.into()),
+        }
+    }
+
+    fn remove(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        if !self.contains(name)? {
+            return Ok(false);
+        }
+        let dir = self.dir.join(name);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        self.removed.borrow_mut().push(name.to_string());
+        Ok(true)
+    }
+
+    fn upload_paths(&self, files: &[String], message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        for file in files {
+            if self.uploaded.borrow().contains(file) {
+                continue;
+            }
+            self.put(file)?;
+            self.uploaded.borrow_mut().insert(file.clone());
+        }
+        info!("Загружено в {}: {}", self.remote(""), message);
+        Ok(())
+    }
+
+    fn upload_all(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.upload_paths(&storage::local_files(&self.dir)?, message)?;
+
+        // Старые бэкапы удаляются только после того, как новый целиком оказался в хранилище
+        let removed: Vec<String> = self.removed.borrow_mut().drain(..).collect();
+        for name in &removed {
+            self.run(&["purge", &self.remote(name)])?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use crate::config::Config;
+use crate::staging::StagingDir;
+use crate::storage;
+use crate::{layout, prepare_repository, timestamp};
+use chrono::Utc;
+use log::info;
+use std::fs;
+use std::path::Path;
+
+pub struct ReplicationReport {
+    /// Папки бэкапов, которых не было в реплике до синхронизации
+    pub copied: Vec<String>,
+}
+
+/// Догоняющая синхронизация: копирует в реплику бэкапы основного хранилища, которых в ней нет,
+/// включая сделанные до того, как реплика была настроена. Хранилища открываются как при бэкапе,
+/// поэтому они могут быть разного вида (Gitea и S3), а архивы из реестра пакетов Gitea
+/// копируются вместе с метаданными
+pub fn replicate(config: &Config) -> Result<ReplicationReport, Box<dyn std::error::Error>> {
+    let replica = config
+        .replica
+        .as_ref()
+        .ok_or("Реплика не настроена (поле replica в config.json)")?
+        .as_primary(config)?;
+
+    let started = timestamp::folder(Utc::now());
+    let primary_staging = StagingDir::new(format!("replicate_{}", started));
+    let replica_staging = StagingDir::new(format!("replicate_{}_replica", started));
+    let primary_dir = primary_staging.path();
+    let replica_dir = replica_staging.path();
+
+    let primary = prepare_repository(config, primary_dir)?;
+    let secondary = prepare_repository(&replica, replica_dir)?;
+    info!("Репликация бэкапов из {} в {}", primary.describe(), secondary.describe());
+
+    let mut copied = Vec::new();
+    for path in primary.backups()? {
+        let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+        if layout::locate(secondary.as_ref(), &name)?.is_some() {
+            continue;
+        }
+        println!("📦 Копирование {} в реплику...", name);
+        primary.download_backup(&path)?;
+        // Бэкап остаётся в той же папке (в корне или в папке месяца), что и в основном хранилище
+        let target = Path::new(replica_dir).join(&path);
+        fs::create_dir_all(target.parent().ok_or("Неверный путь бэкапа")?)?;
+        fs::rename(Path::new(primary_dir).join(&path), &target)?;
+        let prefix = format!("{}/", path);
+        let files: Vec<String> = storage::local_files(Path::new(replica_dir))?
+            .into_iter()
+            .filter(|file| file.starts_with(&prefix))
+            .collect();
+        secondary.upload_paths(&files, &format!("🔁 Реплика {}", name))?;
+        info!("Бэкап {} скопирован в реплику ({} файлов)", name, files.len());
+        copied.push(name);
+    }
+
+    Ok(ReplicationReport { copied })
+}
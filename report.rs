@@ -0,0 +1,155 @@
+use crate::config::Language;
+use crate::i18n::{t_in, tf_in};
+use crate::labels;
+use crate::manifest::Manifest;
+use crate::timestamp;
+use std::fs;
+use std::path::Path;
+
+pub const REPORT_FILE: &str = "report.html";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_size(bytes: u64, language: Language) -> String {
+    tf_in(language, "{} МБ", &[&format!("{:.2}", bytes as f64 / 1_048_576.0)])
+}
+
+/// Самодостаточный HTML-отчёт о бэкапе (без внешних стилей и скриптов) на языке уведомлений
+pub fn render(manifest: &Manifest, server: &str, language: Language) -> String {
+    let t = |message| t_in(language, message);
+    let archive_rows = manifest
+        .archives
+        .iter()
+        .map(|a| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{}</td></tr>",
+                escape(&a.name),
+                escape(&a.source),
+                a.priority,
+                format_size(a.size, language)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut warnings = String::new();
+    if !manifest.skipped.is_empty() {
+        warnings.push_str(&format!(
+            "<table><tr><th>{}</th><th>{}</th></tr>\n{}\n</table>\n",
+            t("Путь"),
+            t("Причина"),
+            manifest
+                .skipped
+                .iter()
+                .map(|s| format!("<tr><td>{}</td><td>{}</td></tr>", escape(&s.path), escape(&s.reason)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    if !manifest.anomalies.is_empty() {
+        warnings.push_str(&format!(
+            "<p>{}</p>\n<table><tr><th>{}</th><th>{}</th><th>{}</th></tr>\n{}\n</table>\n",
+            t("Размер архивов сильно отличается от прошлых бэкапов:"),
+            t("Путь"),
+            t("Размер"),
+            t("Обычно"),
+            manifest
+                .anomalies
+                .iter()
+                .map(|a| {
+                    format!(
+                        "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                        escape(&a.source),
+                        format_size(a.size, language),
+                        format_size(a.typical, language)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    if warnings.is_empty() {
+        warnings = format!("<p class=\"ok\">{}</p>", t("Все пути заархивированы без ошибок."));
+    }
+    let clean = manifest.skipped.is_empty() && manifest.anomalies.is_empty();
+    let status = if !manifest.skipped.is_empty() {
+        t("Успешно, с пропущенными путями")
+    } else if !manifest.anomalies.is_empty() {
+        t("Успешно, с предупреждениями")
+    } else {
+        t("Успешно")
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+<meta charset="utf-8">
+<title>{backup} {name}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; color: #222; }}
+table {{ border-collapse: collapse; margin-bottom: 1.5em; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 10px; text-align: left; }}
+th {{ background: #f0f0f0; }}
+td.num {{ text-align: right; }}
+.status {{ font-size: 1.2em; font-weight: bold; color: {status_color}; }}
+.ok {{ color: #2a7a2a; }}
+</style>
+</head>
+<body>
+<h1>{backup} {name}</h1>
+<p class="status">{status}</p>
+<table>
+<tr><th>{server_title}</th><td>{server}</td></tr>
+<tr><th>{date_title}</th><td>{created_at}</td></tr>
+<tr><th>{labels_title}</th><td>{labels}</td></tr>
+<tr><th>{archive_count_title}</th><td>{archive_count}</td></tr>
+<tr><th>{total_size_title}</th><td>{total_size}</td></tr>
+</table>
+<h2>{archives_title}</h2>
+<table>
+<tr><th>{archive_title}</th><th>{source_title}</th><th>{priority_title}</th><th>{size_title}</th></tr>
+{archive_rows}
+</table>
+<h2>{warnings_title}</h2>
+{warnings}
+<p><small>OfficialVPN Backup Tool v{version}</small></p>
+</body>
+</html>
+"#,
+        lang = if language == Language::Ru { "ru" } else { "en" },
+        backup = t("Бэкап"),
+        name = escape(&manifest.name),
+        status = status,
+        status_color = if clean { "#2a7a2a" } else { "#b36b00" },
+        server_title = t("Сервер"),
+        date_title = t("Дата"),
+        labels_title = t("Метки"),
+        archive_count_title = t("Архивов"),
+        total_size_title = t("Общий размер"),
+        archives_title = t("Архивы"),
+        archive_title = t("Архив"),
+        source_title = t("Исходный путь"),
+        priority_title = t("Приоритет"),
+        size_title = t("Размер"),
+        warnings_title = t("Предупреждения"),
+        server = escape(server),
+        created_at = escape(&timestamp::display(&manifest.created_at)),
+        labels = if manifest.labels.is_empty() { "—".to_string() } else { escape(&labels::format(&manifest.labels)) },
+        archive_count = manifest.archives.len(),
+        total_size = format_size(manifest.total_size, language),
+        archive_rows = archive_rows,
+        warnings = warnings,
+        version = env!("CARGO_PKG_VERSION"),
+    )
+}
+
+pub fn write(manifest: &Manifest, server: &str, language: Language, backup_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(backup_dir.join(REPORT_FILE), render(manifest, server, language))?;
+    Ok(())
+}
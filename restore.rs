@@ -0,0 +1,619 @@
+use crate::cache;
+use crate::compression;
+use crate::config::Config;
+use crate::crypto;
+use crate::docker;
+use crate::index;
+use crate::labels;
+use crate::layout;
+use crate::manifest::{ArchiveEntry, ArchiveKind, Manifest, MANIFEST_FILE};
+use crate::ownership::Ownership;
+use crate::portable;
+use crate::split;
+use crate::staging::StagingDir;
+use crate::timestamp;
+use crate::{prepare_repository, read_input};
+use chrono::{DateTime, Utc};
+use colored::*;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Какой бэкап восстанавливать
+pub enum Selection {
+    /// Самый свежий бэкап этого сервера
+    Latest,
+    /// Последний бэкап этого сервера не позже указанного времени
+    AsOf(DateTime<Utc>),
+    Name(String),
+    /// Выбор из списка
+    Interactive,
+}
+
+pub struct RestoreOptions {
+    pub selection: Selection,
+    /// Селекторы меток, сужающие список бэкапов
+    pub selectors: Vec<String>,
+    /// Распаковать в эту папку (с сохранением исходных путей внутри) вместо исходных мест
+    pub target: Option<PathBuf>,
+    pub ownership: Ownership,
+    /// Не спрашивать подтверждение перед записью поверх исходных путей
+    pub assume_yes: bool,
+    /// Песочница без root: файлы принадлежат текущему пользователю, устройства, каналы
+    /// и setuid/setgid не восстанавливаются; требует `target`
+    pub no_privileges: bool,
+    /// Восстановить только этот файл (исходный путь на сервере)
+    pub file: Option<String>,
+}
+
+/// Папка бэкапа в репозитории
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub created_at: String,
+    /// У бэкапов, сделанных до появления manifest.json, его нет
+    pub manifest: Option<Manifest>,
+    /// Папка месяца (`2026/10`), если бэкап лежит не в корне хранилища (`shard_by_month`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+}
+
+impl Snapshot {
+    /// Путь папки бэкапа относительно корня хранилища
+    pub fn path(&self) -> String {
+        match &self.folder {
+            Some(folder) => format!("{}/{}", folder, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Папки бэкапов в рабочей копии репозитория, от старых к новым: в корне
+/// и в папках месяцев `<год>/<месяц>`
+pub fn available_backups(work_dir: &Path) -> Result<Vec<Snapshot>, Box<dyn std::error::Error>> {
+    let mut snapshots = Vec::new();
+    collect_backups(work_dir, None, &mut snapshots)?;
+    for year in fs::read_dir(work_dir)? {
+        let year = year?;
+        let year_name = year.file_name().to_string_lossy().to_string();
+        if !year.file_type()?.is_dir() || !layout::is_shard(&year_name, 4) {
+            continue;
+        }
+        for month in fs::read_dir(year.path())? {
+            let month = month?;
+            let month_name = month.file_name().to_string_lossy().to_string();
+            if month.file_type()?.is_dir() && layout::is_shard(&month_name, 2) {
+                collect_backups(&month.path(), Some(format!("{}/{}", year_name, month_name)), &mut snapshots)?;
+            }
+        }
+    }
+    snapshots.sort_by_key(|s| timestamp::parse(&s.created_at));
+    Ok(snapshots)
+}
+
+fn collect_backups(dir: &Path, folder: Option<String>, snapshots: &mut Vec<Snapshot>) -> Result<(), Box<dyn std::error::Error>> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !entry.file_type()?.is_dir() || name.starts_with('.') {
+            continue;
+        }
+
+        let manifest: Option<Manifest> = fs::read_to_string(entry.path().join(MANIFEST_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        let created_at = match &manifest {
+            Some(manifest) => manifest.created_at.clone(),
+            None => match timestamp::from_folder_name(&name) {
+                Some(created_at) => timestamp::format(created_at),
+                // Не папка бэкапа
+                None => continue,
+            },
+        };
+        snapshots.push(Snapshot {
+            name,
+            created_at,
+            manifest,
+            folder: folder.clone(),
+        });
+    }
+    Ok(())
+}
+
+fn choose<'a>(config: &Config, snapshots: &'a [Snapshot], options: &RestoreOptions) -> Result<&'a Snapshot, Box<dyn std::error::Error>> {
+    let mut snapshots: Vec<&Snapshot> = snapshots
+        .iter()
+        .filter(|s| {
+            options.selectors.is_empty()
+                || s.manifest
+                    .as_ref()
+                    .is_some_and(|m| labels::matches(&m.labels, &options.selectors))
+        })
+        .collect();
+    if snapshots.is_empty() {
+        return Err("В репозитории нет подходящих бэкапов".into());
+    }
+    // В общем репозитории могут лежать бэкапы нескольких серверов
+    let own = |s: &&Snapshot| {
+        config
+            .backup_name
+            .as_ref()
+            .is_none_or(|backup_name| match s.manifest.as_ref().and_then(|m| m.backup_name.as_ref()) {
+                Some(own) => own == backup_name,
+                // С fat_safe_names имя сервера в папке могло быть изменено
+                None => {
+                    s.name.starts_with(&format!("{}_", backup_name))
+                        || s.name.starts_with(&format!("{}_", portable::name(backup_name)))
+                }
+            })
+    };
+
+    match &options.selection {
+        Selection::Name(name) => snapshots
+            .into_iter()
+            .find(|s| &s.name == name)
+            .ok_or_else(|| format!("Бэкап {} не найден в репозитории", name).into()),
+        Selection::Latest => {
+            snapshots.retain(own);
+            snapshots.pop().ok_or_else(|| "Бэкапы этого сервера не найдены".into())
+        }
+        Selection::AsOf(time) => {
+            snapshots.retain(own);
+            snapshots.retain(|s| timestamp::parse(&s.created_at).is_some_and(|created| created <= *time));
+            snapshots.pop().ok_or_else(|| {
+                format!("Нет бэкапов этого сервера не позже {}", timestamp::display_time(*time)).into()
+            })
+        }
+        Selection::Interactive => {
+            println!("\nДоступные бэкапы:");
+            for (i, snapshot) in snapshots.iter().enumerate() {
+                let details = match &snapshot.manifest {
+                    Some(m) if !m.labels.is_empty() => format!(" | {}", labels::format(&m.labels)),
+                    Some(_) => String::new(),
+                    None => " | без manifest.json".to_string(),
+                };
+                println!(
+                    "{}. {} | {}{}",
+                    i + 1,
+                    snapshot.name.white().bold(),
+                    timestamp::display(&snapshot.created_at),
+                    details
+                );
+            }
+            loop {
+                let input = read_input(&format!("\nВыберите бэкап (1-{}): ", snapshots.len()))?;
+                match input.parse::<usize>() {
+                    Ok(n) if n >= 1 && n <= snapshots.len() => return Ok(snapshots[n - 1]),
+                    _ => println!("Неверный выбор, попробуйте снова"),
+                }
+            }
+        }
+    }
+}
+
+/// Бэкапы, которые нужно применить по порядку, чтобы восстановить `snapshot`:
+/// полный, затем сделанные поверх него; последний — сам `snapshot`
+pub fn chain<'a>(snapshots: &'a [Snapshot], snapshot: &'a Snapshot) -> Result<Vec<&'a Snapshot>, Box<dyn std::error::Error>> {
+    let mut chain = vec![snapshot];
+    let mut current = snapshot;
+    while let Some(base) = current.manifest.as_ref().and_then(|m| m.base.as_deref()) {
+        let found = snapshots
+            .iter()
+            .find(|s| s.name == base)
+            .ok_or_else(|| format!("Бэкап {} сделан поверх {}, которого нет в репозитории", current.name, base))?;
+        if chain.iter().any(|s| s.name == found.name) {
+            return Err(format!("Цепочка бэкапов {} замкнута на себя", snapshot.name).into());
+        }
+        chain.push(found);
+        current = found;
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Куда попадает исходный путь: на своё место или внутрь `--target`
+pub fn destination(source: &str, target: Option<&Path>) -> PathBuf {
+    match target {
+        Some(target) => target.join(source.trim_start_matches('/')),
+        None => PathBuf::from(source),
+    }
+}
+
+/// Путь, по которому запись архива окажется внутри `dest` (как в `unpack_in`)
+fn unpacked_path(dest: &Path, path: &Path) -> PathBuf {
+    let mut target = dest.to_path_buf();
+    for part in path.components() {
+        if let std::path::Component::Normal(part) = part {
+            target.push(part);
+        }
+    }
+    target
+}
+
+/// Создаёт устройство или FIFO из записи архива: tar-крейт превратил бы их в обычные файлы
+fn make_special(path: &Path, header: &tar::Header) -> Result<(), Box<dyn std::error::Error>> {
+    use nix::sys::stat::{makedev, mknod, Mode, SFlag};
+
+    let kind = match header.entry_type() {
+        tar::EntryType::Block => SFlag::S_IFBLK,
+        tar::EntryType::Char => SFlag::S_IFCHR,
+        _ => SFlag::S_IFIFO,
+    };
+    let device = makedev(
+        header.device_major().ok().flatten().unwrap_or(0) as u64,
+        header.device_minor().ok().flatten().unwrap_or(0) as u64,
+    );
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::symlink_metadata(path).is_ok() {
+        fs::remove_file(path)?;
+    }
+    mknod(path, kind, Mode::from_bits_truncate(header.mode()?), device)
+        .map_err(|e| format!("Не удалось создать {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Распаковывает tar-поток в `dest` без внешних программ. Пути с `..` пропускаются,
+/// директории создаются последними, чтобы их права не мешали распаковке вложенных файлов.
+/// Без `privileged` специальные файлы исключаются, SUID/SGID снимаются, а владельцем
+/// становится текущий пользователь.
+pub fn unpack<R: Read>(
+    mut archive: tar::Archive<R>,
+    dest: &Path,
+    ownership: &Ownership,
+    privileged: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(dest)?;
+    archive.set_preserve_permissions(privileged);
+    archive.set_preserve_mtime(true);
+    archive.set_overwrite(true);
+    let chown = privileged && nix::unistd::geteuid().is_root();
+
+    let mut directories = Vec::new();
+    let mut special = 0;
+    for file in archive.entries()? {
+        let mut file = file?;
+        let path = file.path()?.into_owned();
+        if path.components().any(|part| part == std::path::Component::ParentDir) {
+            warn!("Пропущен путь вне папки распаковки: {}", path.display());
+            continue;
+        }
+        let target = unpacked_path(dest, &path);
+
+        match file.header().entry_type() {
+            tar::EntryType::Directory => {
+                directories.push(file);
+                continue;
+            }
+            tar::EntryType::Block | tar::EntryType::Char | tar::EntryType::Fifo => {
+                special += 1;
+                if !privileged {
+                    continue;
+                }
+                make_special(&target, file.header())?;
+            }
+            _ => {
+                file.unpack_in(dest)?;
+            }
+        }
+        if chown {
+            set_owner(&target, file.header(), ownership)?;
+        }
+    }
+
+    // Глубокие директории раньше родительских: права родителя не должны мешать дочерним
+    directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut dir in directories {
+        let target = unpacked_path(dest, &dir.path()?);
+        dir.unpack_in(dest)?;
+        if chown {
+            set_owner(&target, dir.header(), ownership)?;
+        }
+    }
+
+    if special > 0 && !privileged {
+        info!("Пропущено специальных файлов в {}: {}", dest.display(), special);
+    }
+    Ok(())
+}
+
+/// Назначает владельца и заново выставляет права: смена владельца сбрасывает SUID/SGID
+fn set_owner(path: &Path, header: &tar::Header, ownership: &Ownership) -> Result<(), Box<dyn std::error::Error>> {
+    ownership.chown(path, header)?;
+    if header.entry_type() != tar::EntryType::Symlink {
+        fs::set_permissions(path, std::os::unix::fs::PermissionsExt::from_mode(header.mode()?))?;
+    }
+    Ok(())
+}
+
+fn extract_tar(archive: &Path, dest: &Path, options: &RestoreOptions) -> Result<(), Box<dyn std::error::Error>> {
+    unpack(index::open_archive(archive)?, dest, &options.ownership, !options.no_privileges)
+        .map_err(|e| format!("Не удалось распаковать {}: {}", archive.display(), e).into())
+}
+
+/// Распаковывает сжатый gzip поток (дамп базы, вывод `exec`) в файл
+pub fn gunzip(archive: &Path, dest: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut input = flate2::read::MultiGzDecoder::new(fs::File::open(archive)?);
+    let mut output = fs::File::create(dest)?;
+    std::io::copy(&mut input, &mut output)
+        .map_err(|e| format!("Не удалось распаковать {}: {}", archive.display(), e))?;
+    Ok(())
+}
+
+/// Восстанавливает один архив; `Ok(false)` — архив пропущен (известно, почему, сообщено пользователю)
+fn restore_archive(backup_dir: &Path, entry: &ArchiveEntry, options: &RestoreOptions) -> Result<bool, Box<dyn std::error::Error>> {
+    let archive = backup_dir.join(&entry.name);
+    let target = options.target.as_deref();
+
+    // Вывод `exec` из контейнера Docker и образы проекта compose восстанавливаются так же, как дамп
+    if let Some(stem) = entry.name.strip_suffix(".gz").filter(|_| entry.is_stream()) {
+        // Дамп не загружается в СУБД автоматически, а образы — в Docker: это решение администратора
+        let Some(target) = target else {
+            let kind = if entry.name.starts_with("images_") { "образы Docker" } else { "дамп базы" };
+            println!(
+                "{}",
+                format!("⏭️ {} ({}) — {}, восстановите с --target и загрузите вручную", entry.name, entry.source, kind).yellow()
+            );
+            return Ok(false);
+        };
+        fs::create_dir_all(target)?;
+        let dest = target.join(stem);
+        println!("🗄️ {} → {}", entry.name, dest.display());
+        gunzip(&archive, &dest)?;
+        return Ok(true);
+    }
+
+    let kind = entry.kind();
+    let dest = if kind == Some(ArchiveKind::Dir) {
+        destination(&entry.source, target)
+    } else if let Some(volume) = entry.source.strip_prefix("docker:volume/").filter(|_| kind == Some(ArchiveKind::Volume)) {
+        // Том ищется заново: на другом хосте или после пересоздания его папка другая
+        match target {
+            Some(target) => target.join(volume),
+            None => PathBuf::from(docker::mountpoint(volume).map_err(|e| {
+                format!("{} (создайте том, например запустив контейнер, или восстановите с --target)", e)
+            })?),
+        }
+    } else if kind == Some(ArchiveKind::File) {
+        // Файл архивировался вместе с именем относительно своей директории
+        let dest = destination(&entry.source, target);
+        dest.parent().map(Path::to_path_buf).unwrap_or(dest)
+    } else {
+        // Импортированный архив: исходное место неизвестно
+        let Some(target) = target else {
+            println!(
+                "{}",
+                format!("⏭️ {} — исходный путь неизвестен, восстановите с --target", entry.name).yellow()
+            );
+            return Ok(false);
+        };
+        let name = entry.original_name.as_deref().unwrap_or(&entry.name);
+        target.join(compression::tar_stem(name).unwrap_or(name))
+    };
+
+    println!("📦 {} → {}", entry.name, dest.display());
+    extract_tar(&archive, &dest, options)?;
+
+    // Файлы, удалённые после базового бэкапа, не должны вернуться из него
+    for path in &entry.deleted {
+        let path = dest.join(path);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&path)?,
+            Ok(_) => fs::remove_file(&path)?,
+            Err(_) => {}
+        }
+    }
+    Ok(true)
+}
+
+/// Бэкап без manifest.json: исходные пути неизвестны, каждый архив распаковывается в свою папку
+fn restore_legacy(backup_dir: &Path, options: &RestoreOptions) -> Result<usize, Box<dyn std::error::Error>> {
+    let target = options
+        .target
+        .as_deref()
+        .ok_or("В бэкапе нет manifest.json, исходные пути неизвестны — укажите --target")?;
+
+    let mut restored = 0;
+    for entry in fs::read_dir(backup_dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(stem) = compression::tar_stem(&name) {
+            let dest = target.join(stem);
+            println!("📦 {} → {}", name, dest.display());
+            extract_tar(&backup_dir.join(&name), &dest, options)?;
+            restored += 1;
+        }
+    }
+    Ok(restored)
+}
+
+/// Путь файла внутри tar-архива пути, если архив содержит исходный путь `path`
+fn inner_path(entry: &ArchiveEntry, path: &str) -> Option<String> {
+    // Только tar-архивы: дампы и вывод `exec` — один сжатый поток
+    compression::codec_of(&entry.name)?;
+    match entry.kind() {
+        Some(ArchiveKind::Dir) => path
+            .strip_prefix(entry.source.trim_end_matches('/'))
+            .and_then(|rest| rest.strip_prefix('/'))
+            .filter(|rest| !rest.is_empty())
+            .map(str::to_string),
+        Some(ArchiveKind::File) if entry.source == path => {
+            Path::new(path).file_name().map(|name| name.to_string_lossy().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Поток tar-архива из папки бэкапа: части читаются подряд, архив расшифровывается
+/// и распаковывается на лету, без копии архива на диске
+fn open_tar(backup_dir: &Path, entry: &ArchiveEntry, key: Option<&crypto::Key>) -> Result<tar::Archive<Box<dyn Read>>, Box<dyn std::error::Error>> {
+    let stored: Box<dyn Read> = match entry.parts {
+        0 => Box::new(fs::File::open(backup_dir.join(entry.file_name()))?),
+        _ => Box::new(split::open_parts(backup_dir, entry)?),
+    };
+    let plain = match (entry.encrypted, key) {
+        (false, _) => stored,
+        (true, Some(key)) => key.reader(stored)?,
+        (true, None) => return Err("Бэкап зашифрован: задайте ключ в поле encryption config.json".into()),
+    };
+    let codec = compression::codec_of(&entry.name).ok_or_else(|| format!("{} не является tar-архивом", entry.name))?;
+    Ok(tar::Archive::new(codec.decoder(plain)?))
+}
+
+/// Извлекает из архива файл `inner` в `dest`; `false`, если его в архиве нет
+fn extract_file(
+    mut archive: tar::Archive<Box<dyn Read>>,
+    inner: &str,
+    dest: &Path,
+    options: &RestoreOptions,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    archive.set_preserve_permissions(!options.no_privileges);
+    archive.set_preserve_mtime(true);
+    for file in archive.entries()? {
+        let mut file = file?;
+        if file.path()?.to_string_lossy().trim_start_matches("./") != inner {
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        file.unpack(dest)?;
+        if options.no_privileges {
+            let mode = fs::metadata(dest)?.mode() & 0o777;
+            fs::set_permissions(dest, std::os::unix::fs::PermissionsExt::from_mode(mode))?;
+        } else if nix::unistd::geteuid().is_root() {
+            options.ownership.chown(dest, file.header())?;
+        }
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// `obt restore --file`: находит в цепочке бэкапов последнюю сохранённую версию файла
+/// и извлекает только её, читая архив потоком
+fn restore_file(
+    config: &Config,
+    work_dir: &str,
+    chain: &[&Snapshot],
+    file: &str,
+    downloaded: bool,
+    options: &RestoreOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = file.trim_end_matches('/');
+    let key = crypto::Key::load(config)?;
+    let storage = if downloaded { None } else { Some(prepare_repository(config, work_dir)?) };
+    let dest = destination(path, options.target.as_deref());
+
+    // От выбранного бэкапа к полному: в инкрементальном нет файлов, не менявшихся с базового
+    for snapshot in chain.iter().rev() {
+        let Some(manifest) = &snapshot.manifest else { continue };
+        for entry in &manifest.archives {
+            let Some(inner) = inner_path(entry, path) else { continue };
+            if entry.deleted.iter().any(|deleted| deleted.trim_start_matches("./") == inner) {
+                return Err(format!("Файл {} был удалён к бэкапу {}", path, snapshot.name).into());
+            }
+            // По индексу файлов видно, есть ли файл в архиве, без его чтения
+            if !entry.files.is_empty() && !entry.files.iter().any(|f| f.path.trim_start_matches("./") == inner) {
+                continue;
+            }
+
+            if let Some(storage) = &storage {
+                storage.download_backup(&snapshot.path())?;
+            }
+            let backup_dir = Path::new(work_dir).join(snapshot.path());
+            println!("📄 {} из {} ({}) → {}", path, entry.name, snapshot.name, dest.display());
+            if extract_file(open_tar(&backup_dir, entry, key.as_ref())?, &inner, &dest, options)? {
+                info!("Файл {} восстановлен из бэкапа {} в {}", path, snapshot.name, dest.display());
+                println!("{}", format!("✅ Файл восстановлен: {}", dest.display()).green());
+                return Ok(());
+            }
+        }
+    }
+    Err(format!("Файла {} нет в бэкапе {}", path, chain.last().map(|s| s.name.as_str()).unwrap_or("")).into())
+}
+
+/// `obt restore`: получает репозиторий, выбирает бэкап и распаковывает его архивы;
+/// возвращает имя восстановленного бэкапа
+pub fn restore(config: &Config, options: &RestoreOptions) -> Result<String, Box<dyn std::error::Error>> {
+    if options.no_privileges {
+        let target = options.target.as_deref().ok_or("Для --no-privileges укажите --target")?;
+        fs::create_dir_all(target)?;
+        if fs::metadata(target)?.uid() != nix::unistd::getuid().as_raw() {
+            return Err(format!("Папка {} принадлежит другому пользователю", target.display()).into());
+        }
+    }
+
+    let staging = StagingDir::new(format!("restore_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let work_dir = staging.path();
+
+    println!("🔄 Получение бэкапов из репозитория...");
+    let (snapshots, downloaded) = cache::snapshots(config, work_dir)?;
+    let snapshot = choose(config, &snapshots, options)?;
+    let chain = chain(&snapshots, snapshot)?;
+    println!(
+        "♻️ Восстановление бэкапа {} ({})",
+        snapshot.name.white().bold(),
+        timestamp::display(&snapshot.created_at)
+    );
+
+    if options.target.is_none() && !options.assume_yes {
+        println!("{}", "Внимание! Файлы будут записаны поверх исходных путей!".red());
+        if read_input("Продолжить? (y/n): ")?.to_lowercase() != "y" {
+            return Err("Восстановление отменено".into());
+        }
+    }
+
+    if let Some(file) = &options.file {
+        restore_file(config, work_dir, &chain, file, downloaded, options)?;
+        return Ok(snapshot.name.clone());
+    }
+
+    if chain.len() > 1 {
+        println!(
+            "🔗 Цепочка бэкапов: {}",
+            chain.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(" → ")
+        );
+    }
+
+    // Список взят из кэша: архивы скачиваются только после выбора и подтверждения
+    if !downloaded {
+        let storage = prepare_repository(config, work_dir)?;
+        for snapshot in &chain {
+            storage.download_backup(&snapshot.path())?;
+        }
+    }
+
+    // Бэкапы цепочки распаковываются от полного к выбранному, более новые файлы перезаписывают старые
+    let mut restored = 0;
+    let mut skipped = 0;
+    for snapshot in &chain {
+        let backup_dir = Path::new(work_dir).join(snapshot.path());
+        match &snapshot.manifest {
+            Some(manifest) => {
+                split::join_archives(&backup_dir, manifest)?;
+                crypto::decrypt_archives(config, &backup_dir, manifest)?;
+                for entry in &manifest.archives {
+                    if restore_archive(&backup_dir, entry, options)? {
+                        restored += 1;
+                    } else {
+                        skipped += 1;
+                    }
+                }
+            }
+            None => {
+                warn!("Бэкап {} без manifest.json, восстановление в --target", snapshot.name);
+                restored += restore_legacy(&backup_dir, options)?;
+            }
+        }
+    }
+
+    info!("Бэкап {} восстановлен: {} архивов, пропущено {}", snapshot.name, restored, skipped);
+    println!("{}", format!("✅ Восстановлено архивов: {}", restored).green());
+    if skipped > 0 {
+        println!("{}", format!("⚠️ Пропущено архивов: {}", skipped).yellow());
+    }
+    Ok(snapshot.name.clone())
+}
@@ -0,0 +1,144 @@
+use crate::catalog::{Catalog, RunStatus};
+use crate::config::Config;
+use crate::labels;
+use crate::layout;
+use crate::staging::StagingDir;
+use crate::{prepare_repository, timestamp, watch};
+use chrono::{Duration, Utc};
+use log::info;
+use std::collections::HashSet;
+
+/// Сколько часов устаревший бэкап лежит в корзине, если `prune_grace_hours` не задан
+const DEFAULT_GRACE_HOURS: u64 = 24;
+
+/// Возвращает имена бэкапов этого сервера, которые больше не нужно хранить.
+/// Закреплённые бэкапы и бэкапы с метками из `retention_keep_labels` не удаляются никогда,
+/// а остатки оборвавшихся запусков не учитываются в `retention_keep_last` и удаляются сразу.
+/// Микро-бэкапы режима наблюдения хранятся отдельно: последние `watch.keep`.
+/// Базовые бэкапы хранятся, пока нужны оставшимся инкрементальным и дифференциальным.
+pub fn select_expired(config: &Config, catalog: &Catalog) -> Vec<String> {
+    let keep_watch = config.watch.as_ref().map(|watch| watch.keep());
+    if config.keep_last().is_none() && keep_watch.is_none() {
+        return Vec::new();
+    }
+
+    let abandoned = catalog
+        .entries
+        .iter()
+        .filter(|e| e.status == RunStatus::Partial)
+        .map(|e| e.name.clone());
+
+    let mut candidates: Vec<_> = catalog
+        .entries
+        .iter()
+        .filter(|e| e.status == RunStatus::Complete)
+        .filter(|e| !e.pinned && e.trashed_at.is_none())
+        .filter(|e| {
+            !config
+                .retention_keep_labels
+                .iter()
+                .any(|selector| labels::matches(&e.labels, std::slice::from_ref(selector)))
+        })
+        .collect();
+
+    candidates.sort_by_key(|e| std::cmp::Reverse(timestamp::parse(&e.created_at)));
+    let (micro, regular): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|e| e.labels.get(watch::LABEL.0).is_some_and(|value| value == watch::LABEL.1));
+    let expired: Vec<_> = [(regular, config.keep_last()), (micro, keep_watch)]
+        .into_iter()
+        .filter_map(|(group, keep)| keep.map(|keep| group.into_iter().skip(keep)))
+        .flatten()
+        .collect();
+    let expired_names: HashSet<&str> = expired.iter().map(|e| e.name.as_str()).collect();
+
+    // Цепочки оставшихся бэкапов должны восстанавливаться целиком
+    let mut needed = HashSet::new();
+    for entry in catalog.entries.iter().filter(|e| e.status == RunStatus::Complete && !expired_names.contains(e.name.as_str())) {
+        let mut base = entry.base.as_deref();
+        while let Some(name) = base {
+            if !needed.insert(name) {
+                break;
+            }
+            base = catalog.entries.iter().find(|e| e.name == name).and_then(|e| e.base.as_deref());
+        }
+    }
+
+    expired
+        .iter()
+        .filter(|e| !needed.contains(e.name.as_str()))
+        .map(|e| e.name.clone())
+        .chain(abandoned)
+        .collect()
+}
+
+/// Устаревшие бэкапы отправляются в корзину, а удаляются те, что пролежали в ней
+/// `prune_grace_hours`; остатки оборвавшихся запусков удаляются сразу. Возвращает бэкапы,
+/// которые нужно удалить из хранилища сейчас
+pub fn sweep(config: &Config, catalog: &mut Catalog) -> Vec<String> {
+    let expired = select_expired(config, catalog);
+    let grace = Duration::hours(config.prune_grace_hours.unwrap_or(DEFAULT_GRACE_HOURS) as i64);
+    if grace.is_zero() {
+        let trashed = catalog.entries.iter().filter(|e| e.trashed_at.is_some()).map(|e| e.name.clone());
+        return expired.into_iter().chain(trashed).collect();
+    }
+
+    let now = Utc::now();
+    let (abandoned, outdated): (Vec<String>, Vec<String>) = expired
+        .into_iter()
+        .partition(|name| catalog.find(name).is_some_and(|e| e.status == RunStatus::Partial));
+    for name in &outdated {
+        println!("🗑️ Бэкап {} отправлен в корзину, удалится через {} ч (obt prune --undo — вернуть)", name, grace.num_hours());
+        info!(event = "backup_trashed", backup = name.as_str(); "Бэкап отправлен в корзину по политике хранения: {}", name);
+    }
+    catalog.trash(&outdated, &timestamp::format(now));
+
+    let due = catalog
+        .entries
+        .iter()
+        .filter(|e| e.trashed_at.as_deref().and_then(timestamp::parse).is_some_and(|at| at + grace <= now))
+        .map(|e| e.name.clone());
+    abandoned.into_iter().chain(due).collect()
+}
+
+/// `obt prune --undo`: возвращает из корзины все бэкапы, которые ещё не удалены
+pub fn undo() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut catalog = Catalog::load()?;
+    let restored = catalog.restore_trash();
+    if !restored.is_empty() {
+        catalog.save()?;
+        info!("Из корзины возвращены бэкапы: {}", restored.join(", "));
+    }
+    Ok(restored)
+}
+
+/// Удаляет устаревшие бэкапы этого сервера из хранилища отдельно от бэкапа:
+/// `obt prune` и запуски по `prune_schedule`. Возвращает удалённые.
+pub fn prune(config: &Config) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut catalog = Catalog::load()?;
+    let expired = sweep(config, &mut catalog);
+    if expired.is_empty() {
+        info!("Удалять из хранилища нечего");
+        catalog.save()?;
+        return Ok(Vec::new());
+    }
+
+    let staging = StagingDir::new(format!("prune_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let storage = prepare_repository(config, staging.path())?;
+    let mut removed = Vec::new();
+    for name in &expired {
+        if layout::remove(storage.as_ref(), name)? {
+            println!("🗑️ Удаляется устаревший бэкап: {}", name);
+            info!(event = "backup_pruned", backup = name.as_str(); "Бэкап удалён по политике хранения: {}", name);
+            removed.push(name.clone());
+        }
+    }
+    if !removed.is_empty() {
+        storage.upload_all(&format!("🗑️ Очистка по политике хранения: {} бэкапов", removed.len()))?;
+    }
+
+    // Бэкапы, которых уже нет в хранилище, тоже убираются из каталога
+    catalog.remove(&expired);
+    catalog.save()?;
+    Ok(removed)
+}
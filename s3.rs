@@ -0,0 +1,387 @@
+use crate::config::{NetworkConfig, S3Config};
+use crate::dest::{self, Check};
+use crate::network::{self, Failure};
+use crate::storage::{self, Backend};
+use crate::{forensics, throttle};
+use log::{info, warn};
+use rusty_s3::actions::{CreateMultipartUpload, ListObjectsV2};
+use rusty_s3::{Bucket, Credentials, S3Action, UrlStyle};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+const ATTEMPTS: u32 = 3;
+/// Срок действия подписи запроса: проверяется при его начале, а не окончании
+const SIGN_FOR: Duration = Duration::from_secs(3600);
+/// Архивы больше этого размера загружаются частями (одним PUT S3 принимает не больше 5 ГБ)
+const PART_SIZE: u64 = 64 * 1_048_576;
+
+/// Тело запроса; файл открывается заново при каждой попытке
+enum Body<'a> {
+    Empty,
+    Text(&'a str),
+    /// `limit_kbps` — `upload_limit_kbps`, `network` — `s3.network` для обрыва медленной загрузки
+    File { path: &'a Path, offset: u64, len: u64, limit_kbps: Option<u64>, network: &'a NetworkConfig },
+}
+
+/// Выполняет подписанный запрос с повтором при сетевых ошибках и ответах 5xx
+fn call(agent: &ureq::Agent, what: &str, method: &str, url: &Url, body: Body) -> Result<ureq::Response, Box<dyn std::error::Error>> {
+    call_with_headers(agent, what, method, url, &[], body)
+}
+
+/// `call` с заголовками, которые вошли в подпись запроса (метаданные объекта)
+fn call_with_headers(
+    agent: &ureq::Agent,
+    what: &str,
+    method: &str,
+    url: &Url,
+    headers: &[(&str, String)],
+    body: Body,
+) -> Result<ureq::Response, Box<dyn std::error::Error>> {
+    let mut attempt = 1;
+    loop {
+        let request = headers
+            .iter()
+            .fold(agent.request(method, url.as_str()), |request, (name, value)| request.set(name, value));
+        let result = match body {
+            Body::Empty => request.call(),
+            Body::Text(text) => request.send_string(text),
+            Body::File { path, offset, len, limit_kbps, network } => {
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                request
+                    .set("Content-Length", &len.to_string())
+                    .send(throttle::Reader::new(file.take(len), limit_kbps).abort_below(network))
+            }
+        };
+        match result {
+            Ok(response) => return Ok(response),
+            // Неверный ключ, отсутствующий бакет или кончившуюся квоту повтором не исправить
+            Err(ureq::Error::Status(code, response))
+                if code < 500 || attempt >= ATTEMPTS || Failure::from_status(code).is_some() =>
+            {
+                // На HEAD сервер отвечает без тела
+                let body = response.into_string().unwrap_or_default();
+                let detail = match body.trim() {
+                    "" => format!("ответ {}", code),
+                    body => format!("ответ {}: {}", code, body),
+                };
+                return Err(match Failure::from_status(code).or_else(|| Failure::classify(&detail)) {
+                    Some(failure) => failure.explain(
+                        url.host_str().unwrap_or_default(),
+                        "s3.endpoint",
+                        &format!("{}, {}", what, detail),
+                    ),
+                    None => format!("S3 ({}): {}", what, detail),
+                }
+                .into());
+            }
+            Err(e) if attempt < ATTEMPTS && Failure::classify(&e.to_string()).is_none_or(Failure::retriable) => {
+                warn!("Попытка {} из {} не удалась ({}): {}", attempt, ATTEMPTS, what, e);
+                println!("Попытка {} не удалась, повтор через 5 сек...", attempt);
+                std::thread::sleep(Duration::from_secs(5));
+                attempt += 1;
+            }
+            Err(e) => {
+                let detail = e.to_string();
+                return Err(match Failure::classify(&detail) {
+                    Some(failure) => failure.explain(url.host_str().unwrap_or_default(), "s3.endpoint", &detail),
+                    None => format!("S3 ({}): {}", what, detail),
+                }
+                .into());
+            }
+        }
+    }
+}
+
+/// Метаданные загружаемых объектов: `x-amz-meta-obt-run-id` с UUID запуска бэкапа
+fn metadata() -> Vec<(&'static str, String)> {
+    forensics::run_id().map(|run_id| ("x-amz-meta-obt-run-id", run_id)).into_iter().collect()
+}
+
+/// Бакет S3: объект `<prefix>/<папка бэкапа>/<файл>` на каждый файл рабочей папки
+pub struct Storage {
+    bucket: Bucket,
+    credentials: Credentials,
+    /// Пустой или оканчивается на `/`
+    prefix: String,
+    dir: PathBuf,
+    upload_limit_kbps: Option<u64>,
+    /// `s3.network`
+    network: NetworkConfig,
+    agent: ureq::Agent,
+    /// Файлы рабочей папки, которые уже есть в бакете
+    uploaded: RefCell<HashSet<String>>,
+    /// Объекты удалённых бэкапов; удаляются из бакета после загрузки нового
+    removed: RefCell<Vec<String>>,
+}
+
+impl Storage {
+    /// Подключается к бакету и проверяет доступ к нему до начала архивации
+    pub fn open(config: &S3Config, dir: &str, upload_limit_kbps: Option<u64>) -> Result<Self, Box<dyn std::error::Error>> {
+        let endpoint = config
+            .endpoint
+            .parse()
+            .map_err(|e| format!("Неверный адрес S3 {}: {}", config.endpoint, e))?;
+        let style = if config.path_style.unwrap_or(true) { UrlStyle::Path } else { UrlStyle::VirtualHost };
+        let region = config.region.clone().unwrap_or_else(|| "us-east-1".to_string());
+        let bucket = Bucket::new(endpoint, style, config.bucket.clone(), region)
+            .map_err(|e| format!("Неверные настройки S3: {}", e))?;
+        let prefix = match config.prefix.as_deref().map(|p| p.trim_matches('/')) {
+            Some(prefix) if !prefix.is_empty() => format!("{}/", prefix),
+            _ => String::new(),
+        };
+
+        let network = config.network.clone().unwrap_or_default();
+        let storage = Storage {
+            bucket,
+            credentials: Credentials::new(&config.access_key, &config.secret_key),
+            prefix,
+            dir: PathBuf::from(dir),
+            upload_limit_kbps,
+            agent: network::agent(&network),
+            network,
+            uploaded: RefCell::new(HashSet::new()),
+            removed: RefCell::new(Vec::new()),
+        };
+        let url = storage.bucket.head_bucket(Some(&storage.credentials)).sign(SIGN_FOR);
+        call(&storage.agent, "проверка бакета", "HEAD", &url, Body::Empty)?;
+        info!("Подключено хранилище {}", storage.location());
+        Ok(storage)
+    }
+
+    fn key(&self, relative: &str) -> String {
+        format!("{}{}", self.prefix, relative)
+    }
+
+    /// Объекты, имена которых (без префикса хранилища) начинаются с `start`
+    fn list(&self, start: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let full = self.key(start);
+        let mut keys = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut action = self.bucket.list_objects_v2(Some(&self.credentials));
+            action.with_prefix(full.as_str());
+            if let Some(token) = token.take() {
+                action.with_continuation_token(token);
+            }
+            let url = action.sign(SIGN_FOR);
+            let body = call(&self.agent, "список объектов", "GET", &url, Body::Empty)?.into_string()?;
+            let page = ListObjectsV2::parse_response(&body).map_err(|e| format!("Неверный ответ S3: {}", e))?;
+            keys.extend(
+                page.contents
+                    .into_iter()
+                    .filter_map(|object| object.key.strip_prefix(&self.prefix).map(str::to_string)),
+            );
+            match page.next_continuation_token {
+                Some(next) => token = Some(next),
+                None => return Ok(keys),
+            }
+        }
+    }
+
+    fn put(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.dir.join(relative);
+        let key = self.key(relative);
+        let size = fs::metadata(&path)?.len();
+        if size > PART_SIZE {
+            return self.put_multipart(&path, &key, size);
+        }
+
+        let mut action = self.bucket.put_object(Some(&self.credentials), &key);
+        let headers = metadata();
+        for (name, value) in &headers {
+            action.headers_mut().insert(*name, value.clone());
+        }
+        call_with_headers(
+            &self.agent,
+            &format!("загрузка {}", relative),
+            "PUT",
+            &action.sign(SIGN_FOR),
+            &headers,
+            Body::File {
+                path: &path,
+                offset: 0,
+                len: size,
+                limit_kbps: self.upload_limit_kbps,
+                network: &self.network,
+            },
+        )?;
+        Ok(())
+    }
+
+    fn put_multipart(&self, path: &Path, key: &str, size: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let mut action = self.bucket.create_multipart_upload(Some(&self.credentials), key);
+        let headers = metadata();
+        for (name, value) in &headers {
+            action.headers_mut().insert(*name, value.clone());
+        }
+        let body = call_with_headers(&self.agent, "начало загрузки частями", "POST", &action.sign(SIGN_FOR), &headers, Body::Empty)?
+            .into_string()?;
+        let upload = CreateMultipartUpload::parse_response(&body).map_err(|e| format!("Неверный ответ S3: {}", e))?;
+        let upload_id = upload.upload_id();
+
+        let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+            let mut etags = Vec::new();
+            let mut offset = 0;
+            let mut part = 1;
+            while offset < size {
+                let len = PART_SIZE.min(size - offset);
+                let url = self
+                    .bucket
+                    .upload_part(Some(&self.credentials), key, part, upload_id)
+                    .sign(SIGN_FOR);
+                let response = call(
+                    &self.agent,
+                    &format!("часть {} из {}", part, size.div_ceil(PART_SIZE)),
+                    "PUT",
+                    &url,
+                    Body::File {
+                        path,
+                        offset,
+                        len,
+                        limit_kbps: self.upload_limit_kbps,
+                        network: &self.network,
+                    },
+                )?;
+                etags.push(response.header("ETag").ok_or("S3 не вернул ETag части")?.to_string());
+                offset += len;
+                part += 1;
+            }
+
+            let complete = self.bucket.complete_multipart_upload(
+                Some(&self.credentials),
+                key,
+                upload_id,
+                etags.iter().map(String::as_str),
+            );
+            let url = complete.sign(SIGN_FOR);
+            let body = complete.body();
+            call(&self.agent, "завершение загрузки частями", "POST", &url, Body::Text(&body))?;
+            Ok(())
+        })();
+
+        // Незавершённая загрузка частями занимает место в бакете, пока её не отменить
+        if result.is_err() {
+            let url = self
+                .bucket
+                .abort_multipart_upload(Some(&self.credentials), key, upload_id)
+                .sign(SIGN_FOR);
+            let _ = self.agent.delete(url.as_str()).call();
+        }
+        result
+    }
+
+    fn get(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let url = self.bucket.get_object(Some(&self.credentials), &self.key(relative)).sign(SIGN_FOR);
+        let response = call(&self.agent, &format!("скачивание {}", relative), "GET", &url, Body::Empty)?;
+        io::copy(&mut response.into_reader(), &mut File::create(&path)?)?;
+        Ok(())
+    }
+
+    fn delete(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let url = self.bucket.delete_object(Some(&self.credentials), &self.key(relative)).sign(SIGN_FOR);
+        call(&self.agent, &format!("удаление {}", relative), "DELETE", &url, Body::Empty)?;
+        Ok(())
+    }
+
+    /// `obt dest check`: загружает пробный объект `name`, скачивает и сверяет его и удаляет
+    pub fn probe(&self, name: &str, check: &mut Check) -> Result<(), Box<dyn std::error::Error>> {
+        let content = dest::probe_content(name);
+        let path = self.dir.join(name);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, &content)?;
+        check.step("запись", || self.put(name))?;
+        fs::remove_file(&path)?;
+        check.step("чтение", || {
+            self.get(name)?;
+            if fs::read_to_string(&path)? != content {
+                return Err("прочитанный объект отличается от записанного".into());
+            }
+            Ok(())
+        })?;
+        check.step("удаление", || self.delete(name))
+    }
+}
+
+impl Backend for Storage {
+    fn location(&self) -> String {
+        format!("s3://{}/{}", self.bucket.name(), self.prefix).trim_end_matches('/').to_string()
+    }
+
+    fn describe(&self) -> String {
+        format!("S3, {}", self.location())
+    }
+
+    fn download(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let keys = self.list("")?;
+        println!("📥 Скачивание из S3: {} файлов...", keys.len());
+        for key in keys {
+            self.get(&key)?;
+            self.uploaded.borrow_mut().insert(key);
+        }
+        Ok(())
+    }
+
+    fn download_backup(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let keys = self.list(&format!("{}/", name))?;
+        println!("📥 Скачивание {} из S3: {} файлов...", name, keys.len());
+        for key in keys {
+            self.get(&key)?;
+            self.uploaded.borrow_mut().insert(key);
+        }
+        Ok(())
+    }
+
+    fn backups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(storage::backup_folders(&self.list("")?))
+    }
+
+    fn contains(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(!self.list(&format!("{}/", name))?.is_empty())
+    }
+
+    fn remove(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let keys = self.list(&format!("{}/", name))?;
+        if keys.is_empty() {
+            return Ok(false);
+        }
+        let dir = self.dir.join(name);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        self.removed.borrow_mut().extend(keys);
+        Ok(true)
+    }
+
+    fn upload_paths(&self, files: &[String], message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        for file in files {
+            if self.uploaded.borrow().contains(file) {
+                continue;
+            }
+            self.put(file)?;
+            self.uploaded.borrow_mut().insert(file.clone());
+        }
+        info!("Загружено в S3: {}", message);
+        Ok(())
+    }
+
+    fn upload_all(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.upload_paths(&storage::local_files(&self.dir)?, message)?;
+
+        // Старые бэкапы удаляются только после того, как новый целиком оказался в бакете
+        let removed: Vec<String> = self.removed.borrow_mut().drain(..).collect();
+        for key in &removed {
+            self.delete(key)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use crate::config::{Config, SecretsPolicy};
+use colored::*;
+use log::warn;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Содержимое больших файлов не читается: ключи и .env всегда маленькие
+const MAX_SCANNED_FILE_SIZE: u64 = 1024 * 1024;
+/// Сколько находок показывать пользователю
+const MAX_REPORTED: usize = 20;
+
+const SECRET_FILE_NAMES: &[&str] = &["id_rsa", "id_dsa", "id_ecdsa", "id_ed25519", ".netrc", ".pgpass"];
+const SECRET_EXTENSIONS: &[&str] = &["pem", "key", "p12", "pfx"];
+const ENV_SECRET_WORDS: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "API_KEY"];
+
+/// Файл, похожий на незашифрованный секрет
+pub struct Finding {
+    pub path: PathBuf,
+    pub reason: &'static str,
+}
+
+fn read_head(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.len() > MAX_SCANNED_FILE_SIZE {
+        return None;
+    }
+    let mut content = Vec::new();
+    fs::File::open(path).ok()?.read_to_end(&mut content).ok()?;
+    Some(String::from_utf8_lossy(&content).into_owned())
+}
+
+fn is_env_file(name: &str) -> bool {
+    name == ".env" || name.starts_with(".env.") || name.ends_with(".env")
+}
+
+fn inspect_file(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_string_lossy();
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+
+    let content = read_head(path);
+    if let Some(content) = &content {
+        if content.contains("-----BEGIN") && content.contains("PRIVATE KEY-----") {
+            return Some("приватный ключ");
+        }
+        // Конфигурации WireGuard хранят ключ строкой `PrivateKey = ...`
+        if content.lines().any(|line| line.trim_start().starts_with("PrivateKey")) {
+            return Some("приватный ключ WireGuard");
+        }
+        if is_env_file(&name)
+            && content.lines().any(|line| {
+                line.split_once('=').is_some_and(|(key, value)| {
+                    let key = key.trim().to_uppercase();
+                    !value.trim().is_empty() && ENV_SECRET_WORDS.iter().any(|word| key.contains(word))
+                })
+            })
+        {
+            return Some(".env с токенами или паролями");
+        }
+    }
+
+    if SECRET_FILE_NAMES.contains(&name.as_ref()) {
+        return Some("файл ключа");
+    }
+    if extension.is_some_and(|e| SECRET_EXTENSIONS.contains(&e.as_str())) {
+        return Some("файл ключа или сертификата");
+    }
+    None
+}
+
+fn scan_path(path: &Path, allow: &[String], findings: &mut Vec<Finding>) {
+    if allow.iter().any(|prefix| path.starts_with(prefix)) {
+        return;
+    }
+    // Ссылки не раскрываются, как и при архивации
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+
+    if metadata.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                scan_path(&entry.path(), allow, findings);
+            }
+        }
+    } else if metadata.is_file() {
+        if let Some(reason) = inspect_file(path) {
+            findings.push(Finding {
+                path: path.to_path_buf(),
+                reason,
+            });
+        }
+    }
+}
+
+/// Ищет в путях бэкапа явные секреты (приватные ключи, .env с токенами)
+pub fn scan(config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for source in &config.backup_paths {
+        scan_path(Path::new(&source.path), &config.secrets_allow, &mut findings);
+    }
+    findings
+}
+
+/// Проверка перед загрузкой по политике `secrets_scan`. Архивы хранятся в репозитории
+/// без шифрования, поэтому любая находка означает, что секрет окажется на удалённом сервере.
+pub fn check(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let policy = match config.secrets_policy() {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+    // Зашифрованные архивы можно загружать с любыми секретами
+    if config.encryption.is_some() {
+        return Ok(());
+    }
+
+    println!("🔍 Проверка путей на незашифрованные секреты...");
+    let findings = scan(config);
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!("⚠️ Найдены незашифрованные секреты ({}), репозиторий не шифрует архивы:", findings.len()).yellow()
+    );
+    for finding in findings.iter().take(MAX_REPORTED) {
+        warn!("Секрет в бэкапе: {} ({})", finding.path.display(), finding.reason);
+        println!("  🔑 {} — {}", finding.path.display(), finding.reason);
+    }
+    if findings.len() > MAX_REPORTED {
+        println!("  ... и ещё {}", findings.len() - MAX_REPORTED);
+    }
+    println!("💡 Осознанно загружаемые пути можно добавить в secrets_allow");
+
+    match policy {
+        SecretsPolicy::Warn => Ok(()),
+        SecretsPolicy::Block => Err(format!(
+            "Бэкап остановлен политикой secrets_scan: найдено секретов — {}",
+            findings.len()
+        )
+        .into()),
+    }
+}
@@ -0,0 +1,383 @@
+use crate::config::{NetworkConfig, SftpConfig};
+use crate::dest::{self, Check};
+use crate::network::Failure;
+use crate::storage::{self, Backend};
+use crate::throttle;
+use log::{info, warn};
+use ssh2::{CheckResult, HashType, KnownHostFileKind, RenameFlags, Session, Sftp};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+const ATTEMPTS: u32 = 3;
+const DEFAULT_PORT: u16 = 22;
+
+fn connect(host: &str, port: u16, network: &NetworkConfig) -> Result<Session, Box<dyn std::error::Error>> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("Не удалось найти адрес {}", host))?;
+    let mut session = Session::new()?;
+    session.set_tcp_stream(TcpStream::connect_timeout(&address, network.connect_timeout())?);
+    session.set_timeout(network.timeout().as_millis().min(u32::MAX as u128) as u32);
+    session.handshake()?;
+    Ok(session)
+}
+
+/// Пока хранилище открыто, сессия напоминает о себе серверу: соединение держится весь бэкап,
+/// и за время архивации NAT или мобильный оператор успевают закрыть простаивающее.
+/// Поток останавливается, когда `Keepalive` уничтожен
+struct Keepalive {
+    _stop: Sender<()>,
+}
+
+impl Keepalive {
+    fn start(session: &Session, interval: Duration) -> Self {
+        let (stop, stopped) = mpsc::channel();
+        session.set_keepalive(false, interval.as_secs().min(u32::MAX as u64) as u32);
+        let session = session.clone();
+        std::thread::spawn(move || {
+            let mut wait = interval;
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(wait) {
+                match session.keepalive_send() {
+                    // libssh2 сообщает, через сколько секунд понадобится следующее напоминание
+                    Ok(seconds) => wait = Duration::from_secs(u64::from(seconds).max(1)),
+                    Err(e) => {
+                        warn!("Не удалось отправить keepalive SSH: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        Keepalive { _stop: stop }
+    }
+}
+
+/// Отпечаток ключа сервера в формате OpenSSH (`SHA256:...`)
+fn fingerprint(session: &Session) -> Result<String, Box<dyn std::error::Error>> {
+    use base64::prelude::{Engine, BASE64_STANDARD_NO_PAD};
+    let hash = session.host_key_hash(HashType::Sha256).ok_or("Сервер не передал ключ")?;
+    Ok(format!("SHA256:{}", BASE64_STANDARD_NO_PAD.encode(hash)))
+}
+
+/// Отпечаток ключа сервера для подтверждения при настройке
+pub fn host_fingerprint(host: &str, port: Option<u16>) -> Result<String, Box<dyn std::error::Error>> {
+    fingerprint(&connect(host, port.unwrap_or(DEFAULT_PORT), &NetworkConfig::default())?)
+}
+
+/// Ключ сервера сверяется с `host_key` из config.json, а без него — с ~/.ssh/known_hosts
+fn verify_host(session: &Session, config: &SftpConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let actual = fingerprint(session)?;
+    if let Some(expected) = &config.host_key {
+        if expected != &actual {
+            return Err(format!(
+                "Ключ сервера {} не совпадает с host_key: {} (ожидался {})",
+                config.host, actual, expected
+            )
+            .into());
+        }
+        return Ok(());
+    }
+
+    let (key, _) = session.host_key().ok_or("Сервер не передал ключ")?;
+    let mut known_hosts = session.known_hosts()?;
+    if let Some(path) = dirs::home_dir().map(|home| home.join(".ssh/known_hosts")).filter(|p| p.exists()) {
+        known_hosts.read_file(&path, KnownHostFileKind::OpenSSH)?;
+    }
+    match known_hosts.check_port(&config.host, config.port.unwrap_or(DEFAULT_PORT), key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(format!("Ключ сервера {} изменился ({}) — проверьте ~/.ssh/known_hosts", config.host, actual).into()),
+        CheckResult::NotFound | CheckResult::Failure => Err(format!(
+            "Ключ сервера {} неизвестен: добавьте \"host_key\": \"{}\" в sftp или git_ssh в config.json, если это ваш сервер",
+            config.host, actual
+        )
+        .into()),
+    }
+}
+
+/// Подключается к серверу с повторами, проверяет его ключ и входит. `setting` — параметр
+/// config.json с адресом сервера для подсказки, если сервер переехал
+pub fn login(config: &SftpConfig, setting: &str) -> Result<Session, Box<dyn std::error::Error>> {
+    let port = config.port.unwrap_or(DEFAULT_PORT);
+    let network = config.network.clone().unwrap_or_default();
+    let mut attempt = 1;
+    let session = loop {
+        match connect(&config.host, port, &network) {
+            Ok(session) => break session,
+            Err(e) if attempt < ATTEMPTS && Failure::classify(&e.to_string()).is_none_or(Failure::retriable) => {
+                warn!("Попытка {} из {} не удалась (подключение к {}): {}", attempt, ATTEMPTS, config.host, e);
+                println!("Попытка {} не удалась, повтор через 5 сек...", attempt);
+                std::thread::sleep(Duration::from_secs(5));
+                attempt += 1;
+            }
+            Err(e) => {
+                let detail = e.to_string();
+                return Err(match Failure::classify(&detail) {
+                    Some(failure) => failure.explain(&config.host, setting, &detail),
+                    None => format!("Не удалось подключиться к {}:{}: {}", config.host, port, detail),
+                }
+                .into());
+            }
+        }
+    };
+    verify_host(&session, config)?;
+
+    let auth = match (&config.key_file, &config.password) {
+        (Some(key_file), _) => session.userauth_pubkey_file(&config.username, None, key_file, None),
+        (None, Some(password)) => session.userauth_password(&config.username, password),
+        (None, None) => session.userauth_agent(&config.username),
+    };
+    // Неверный пароль или ключ повтором не исправить
+    if let Err(e) = auth {
+        let detail = format!("пользователь {}: {}", config.username, e.message());
+        return Err(Failure::Auth.explain(&config.host, setting, &detail).into());
+    }
+    if !session.authenticated() {
+        let detail = format!("пользователь {}", config.username);
+        return Err(Failure::Auth.explain(&config.host, setting, &detail).into());
+    }
+    Ok(session)
+}
+
+/// Папка на другом сервере, в которую бэкапы копируются по SFTP: `<path>/<папка бэкапа>/<файл>`
+pub struct Storage {
+    sftp: Sftp,
+    // Сессия должна жить, пока открыт канал SFTP; через неё же выполняются команды
+    session: Session,
+    host: String,
+    root: PathBuf,
+    dir: PathBuf,
+    upload_limit_kbps: Option<u64>,
+    /// `sftp.network`
+    network: NetworkConfig,
+    _keepalive: Option<Keepalive>,
+    /// Файлы рабочей папки, которые уже есть на сервере
+    uploaded: RefCell<HashSet<String>>,
+    /// Папки удалённых бэкапов; удаляются с сервера после загрузки нового
+    removed: RefCell<Vec<String>>,
+}
+
+impl Storage {
+    /// Подключается к серверу и проверяет доступ к папке бэкапов до начала архивации
+    pub fn open(config: &SftpConfig, dir: &str, upload_limit_kbps: Option<u64>) -> Result<Self, Box<dyn std::error::Error>> {
+        let session = login(config, "sftp.host")?;
+        let network = config.network.clone().unwrap_or_default();
+        let storage = Storage {
+            sftp: session.sftp()?,
+            _keepalive: network.keepalive().map(|interval| Keepalive::start(&session, interval)),
+            session,
+            host: config.host.clone(),
+            root: PathBuf::from(&config.path),
+            dir: PathBuf::from(dir),
+            upload_limit_kbps,
+            network,
+            uploaded: RefCell::new(HashSet::new()),
+            removed: RefCell::new(Vec::new()),
+        };
+        storage.create_dirs(&storage.root)?;
+        info!("Подключено хранилище {}", storage.location());
+        Ok(storage)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.sftp.stat(path).is_ok()
+    }
+
+    fn create_dirs(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if path.as_os_str().is_empty() || self.exists(path) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            self.create_dirs(parent)?;
+        }
+        self.sftp
+            .mkdir(path, 0o755)
+            .map_err(|e| format!("Не удалось создать {} на {}: {}", path.display(), self.host, e))?;
+        Ok(())
+    }
+
+    /// Файлы в папке сервера (пути относительно `root`)
+    fn remote_files(&self, relative: &str, files: &mut Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+        for (path, stat) in self.sftp.readdir(self.root.join(relative))? {
+            let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let path = if relative.is_empty() { name } else { format!("{}/{}", relative, name) };
+            if path.ends_with(".part") {
+                continue;
+            }
+            if stat.is_dir() {
+                self.remote_files(&path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    fn put(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let remote = self.root.join(relative);
+        if let Some(parent) = remote.parent() {
+            self.create_dirs(parent)?;
+        }
+        // Оборванная загрузка не должна выглядеть как целый архив
+        let partial = PathBuf::from(format!("{}.part", remote.display()));
+        let mut file = self.sftp.create(&partial)?;
+        let source = File::open(self.dir.join(relative))?;
+        io::copy(&mut throttle::Reader::new(source, self.upload_limit_kbps).abort_below(&self.network), &mut file)
+            .map_err(|e| format!("Ошибка загрузки {} на {}: {}", relative, self.host, e))?;
+        drop(file);
+        // Серверы с SFTP v3 (OpenSSH) не переименовывают поверх существующего файла
+        if self.exists(&remote) {
+            self.sftp.unlink(&remote)?;
+        }
+        self.sftp.rename(&partial, &remote, Some(RenameFlags::OVERWRITE | RenameFlags::ATOMIC))?;
+        Ok(())
+    }
+
+    fn get(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut self.sftp.open(self.root.join(relative))?, &mut File::create(&path)?)
+            .map_err(|e| format!("Ошибка скачивания {} с {}: {}", relative, self.host, e))?;
+        Ok(())
+    }
+
+    /// `obt dest check`: записывает пробный файл `name` в папку бэкапов, читает и сверяет
+    /// его и удаляет, а место на диске сервера узнаёт через `df`, если сервер позволяет команды
+    pub fn probe(&self, name: &str, check: &mut Check) -> Result<(), Box<dyn std::error::Error>> {
+        let content = dest::probe_content(name);
+        let path = self.dir.join(name);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, &content)?;
+        check.step("запись", || self.put(name))?;
+        fs::remove_file(&path)?;
+        check.step("чтение", || {
+            self.get(name)?;
+            if fs::read_to_string(&path)? != content {
+                return Err("прочитанный файл отличается от записанного".into());
+            }
+            Ok(())
+        })?;
+        check.step("удаление", || Ok(self.sftp.unlink(&self.root.join(name))?))?;
+        check.space = self.free_space();
+        Ok(())
+    }
+
+    /// Свободное место в папке бэкапов по `df -Pk`; `None` на серверах только с SFTP
+    fn free_space(&self) -> Option<String> {
+        let mut channel = self.session.channel_session().ok()?;
+        let root = self.root.display().to_string().replace('\'', "'\\''");
+        channel.exec(&format!("df -Pk '{}'", root)).ok()?;
+        let mut output = String::new();
+        channel.read_to_string(&mut output).ok()?;
+        channel.wait_close().ok()?;
+        // Filesystem 1024-blocks Used Available Capacity Mounted
+        let fields: Vec<&str> = output.lines().nth(1)?.split_whitespace().collect();
+        let total: u64 = fields.get(1)?.parse().ok()?;
+        let available: u64 = fields.get(3)?.parse().ok()?;
+        Some(format!(
+            "свободно {:.1} ГБ из {:.1} ГБ",
+            available as f64 / 1_048_576.0,
+            total as f64 / 1_048_576.0
+        ))
+    }
+
+    fn delete_dir(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        for (entry, stat) in self.sftp.readdir(path)? {
+            if stat.is_dir() {
+                self.delete_dir(&entry)?;
+            } else {
+                self.sftp.unlink(&entry)?;
+            }
+        }
+        self.sftp.rmdir(path)?;
+        Ok(())
+    }
+}
+
+impl Backend for Storage {
+    fn location(&self) -> String {
+        format!("sftp://{}{}", self.host, self.root.display())
+    }
+
+    fn describe(&self) -> String {
+        format!("SFTP, {}", self.location())
+    }
+
+    fn download(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        self.remote_files("", &mut files)?;
+        println!("📥 Скачивание с {}: {} файлов...", self.host, files.len());
+        for file in files {
+            self.get(&file)?;
+            self.uploaded.borrow_mut().insert(file);
+        }
+        Ok(())
+    }
+
+    fn download_backup(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        if self.contains(name)? {
+            self.remote_files(name, &mut files)?;
+        }
+        println!("📥 Скачивание {} с {}: {} файлов...", name, self.host, files.len());
+        for file in files {
+            self.get(&file)?;
+            self.uploaded.borrow_mut().insert(file);
+        }
+        Ok(())
+    }
+
+    fn backups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        self.remote_files("", &mut files)?;
+        Ok(storage::backup_folders(&files))
+    }
+
+    fn contains(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.exists(&self.root.join(name)))
+    }
+
+    fn remove(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        if !self.contains(name)? {
+            return Ok(false);
+        }
+        let dir = self.dir.join(name);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        self.removed.borrow_mut().push(name.to_string());
+        Ok(true)
+    }
+
+    fn upload_paths(&self, files: &[String], message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        for file in files {
+            if self.uploaded.borrow().contains(file) {
+                continue;
+            }
+            self.put(file)?;
+            self.uploaded.borrow_mut().insert(file.clone());
+        }
+        info!("Загружено на {}: {}", self.host, message);
+        Ok(())
+    }
+
+    fn upload_all(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.upload_paths(&storage::local_files(&self.dir)?, message)?;
+
+        // Старые бэкапы удаляются только после того, как новый целиком оказался на сервере
+        let removed: Vec<String> = self.removed.borrow_mut().drain(..).collect();
+        for name in &removed {
+            self.delete_dir(&self.root.join(name))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use crate::config::{SmtpConfig, SmtpTls};
+use crate::keyring;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Тайм-аут подключения и каждого ответа сервера
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Вложение письма, например backup_info.txt
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub name: String,
+    pub content: String,
+}
+
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+/// Соединение с SMTP-сервером: ответы читаются построчно, команды пишутся в тот же поток
+struct Session {
+    stream: BufReader<Box<dyn Stream>>,
+    server: String,
+}
+
+impl Session {
+    /// Ответ сервера (многострочный — `250-…`, последняя строка — `250 …`); код должен быть `expected`,
+    /// а кратное 100 допускает любой код этого класса: с `200` подходят и 250, и 251
+    fn reply(&mut self, what: &str, expected: u16) -> Result<(), Box<dyn std::error::Error>> {
+        let mut text = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(format!("SMTP-сервер {} закрыл соединение ({})", self.server, what).into());
+            }
+            let line = line.trim_end().to_string();
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            text.push(line);
+            if last {
+                break;
+            }
+        }
+        let code: Option<u16> = text.last().and_then(|line| line.get(..3)).and_then(|code| code.parse().ok());
+        let accepted = match expected % 100 {
+            0 => code.is_some_and(|code| code / 100 == expected / 100),
+            _ => code == Some(expected),
+        };
+        if !accepted {
+            return Err(format!("SMTP-сервер {} отклонил {}: {}", self.server, what, text.join(" / ")).into());
+        }
+        Ok(())
+    }
+
+    /// Отправляет команду; `what` — её название для ошибки, без паролей
+    fn command(&mut self, what: &str, line: &str, expected: u16) -> Result<(), Box<dyn std::error::Error>> {
+        let stream = self.stream.get_mut();
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        self.reply(what, expected)
+    }
+}
+
+fn tls(server: &str, stream: TcpStream) -> Result<Box<dyn Stream>, Box<dyn std::error::Error>> {
+    let roots = rustls::RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let name = rustls::pki_types::ServerName::try_from(server.to_string())
+        .map_err(|e| format!("Неверное имя SMTP-сервера {}: {}", server, e))?;
+    let connection = rustls::ClientConnection::new(Arc::new(config), name)?;
+    Ok(Box::new(rustls::StreamOwned::new(connection, stream)))
+}
+
+/// Имя сервера для EHLO
+fn hostname() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|host| host.into_string().ok())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "obt".to_string())
+}
+
+/// Base64 строками по 76 символов, как требует MIME; переводы строк текста — CRLF
+fn encode(content: &str) -> String {
+    let encoded = BASE64_STANDARD.encode(content.replace("\r\n", "\n").replace('\n', "\r\n"));
+    let lines: Vec<&str> = encoded.as_bytes().chunks(76).map(|line| std::str::from_utf8(line).unwrap()).collect();
+    lines.join("\r\n")
+}
+
+/// Письмо в формате RFC 5322: текст и вложения в base64, так что строк с точкой
+/// в начале, которые пришлось бы экранировать для DATA, в нём нет
+fn message(from: &str, to: &[String], subject: &str, text: &str, attachments: &[Attachment], headers: &[(&str, &str)]) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: =?utf-8?B?{}?=\r\nDate: {}\r\nMIME-Version: 1.0\r\n",
+        from,
+        to.join(", "),
+        BASE64_STANDARD.encode(subject),
+        chrono::Utc::now().to_rfc2822()
+    );
+    for (name, value) in headers {
+        message.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    let text_part = format!("Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n", encode(text));
+    if attachments.is_empty() {
+        message.push_str(&text_part);
+        return message;
+    }
+    let boundary = format!("obt-{}", uuid::Uuid::new_v4().simple());
+    message.push_str(&format!("Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n", boundary));
+    message.push_str(&format!("--{}\r\n{}", boundary, text_part));
+    for attachment in attachments {
+        message.push_str(&format!(
+            "--{}\r\nContent-Type: text/plain; charset=utf-8; name=\"{name}\"\r\nContent-Disposition: attachment; filename=\"{name}\"\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+            boundary,
+            encode(&attachment.content),
+            name = attachment.name
+        ));
+    }
+    message.push_str(&format!("--{}--\r\n", boundary));
+    message
+}
+
+/// Отправляет письмо всем адресатам из `smtp.to`; `headers` — дополнительные заголовки
+pub fn send(
+    config: &SmtpConfig,
+    subject: &str,
+    text: &str,
+    attachments: &[Attachment],
+    headers: &[(&str, &str)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if config.to.is_empty() {
+        return Err("В smtp.to не указано ни одного адресата".into());
+    }
+    // config.json могли поправить вручную, минуя `obt config set`
+    config.validate()?;
+    let from = config
+        .from
+        .clone()
+        .or_else(|| config.username.clone())
+        .ok_or("Укажите smtp.from: адрес отправителя")?;
+    let port = config.port.unwrap_or(match config.tls {
+        SmtpTls::Tls => 465,
+        SmtpTls::Starttls => 587,
+        SmtpTls::Plain => 25,
+    });
+
+    let address = (config.server.as_str(), port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("Не удалось найти адрес SMTP-сервера {}", config.server))?;
+    let tcp = TcpStream::connect_timeout(&address, TIMEOUT)
+        .map_err(|e| format!("Не удалось подключиться к SMTP-серверу {}:{}: {}", config.server, port, e))?;
+    tcp.set_read_timeout(Some(TIMEOUT))?;
+    tcp.set_write_timeout(Some(TIMEOUT))?;
+
+    let ehlo = format!("EHLO {}", hostname());
+    let stream: Box<dyn Stream> = match config.tls {
+        SmtpTls::Tls => tls(&config.server, tcp.try_clone()?)?,
+        SmtpTls::Starttls | SmtpTls::Plain => Box::new(tcp.try_clone()?),
+    };
+    let mut session = Session { stream: BufReader::new(stream), server: config.server.clone() };
+    session.reply("подключение", 220)?;
+    session.command("EHLO", &ehlo, 250)?;
+    if config.tls == SmtpTls::Starttls {
+        session.command("STARTTLS", "STARTTLS", 220)?;
+        // Сервер ждёт рукопожатия TLS, так что непрочитанного в буфере нет
+        session = Session { stream: BufReader::new(tls(&config.server, tcp)?), server: config.server.clone() };
+        session.command("EHLO", &ehlo, 250)?;
+    }
+
+    if let Some(username) = &config.username {
+        let password = match &config.password {
+            Some(password) => keyring::resolve(password)?,
+            None => String::new(),
+        };
+        let credentials = BASE64_STANDARD.encode(format!("\0{}\0{}", username, password));
+        session.command("вход (AUTH PLAIN)", &format!("AUTH PLAIN {}", credentials), 235)?;
+    }
+
+    session.command("отправителя", &format!("MAIL FROM:<{}>", from), 250)?;
+    // 251 — адресат не местный, сервер перешлёт письмо: это тоже согласие
+    for to in &config.to {
+        session.command(&format!("адресата {}", to), &format!("RCPT TO:<{}>", to), 200)?;
+    }
+    session.command("DATA", "DATA", 354)?;
+    let message = message(&from, &config.to, subject, text, attachments, headers);
+    session.command("письмо", &format!("{}.", message), 250)?;
+    // Письмо уже принято: ошибка QUIT ничего не меняет
+    let _ = session.command("QUIT", "QUIT", 221);
+    Ok(())
+}
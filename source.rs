@@ -0,0 +1,53 @@
+use crate::config::{BackupPath, Config, DatabaseSource, DockerSource, Priority};
+
+/// Любой источник данных бэкапа вместе с его порядковым номером в своём списке конфигурации
+pub enum Source<'a> {
+    Path { index: usize, entry: &'a BackupPath },
+    Database { index: usize, entry: &'a DatabaseSource },
+    Docker { index: usize, entry: &'a DockerSource },
+}
+
+impl<'a> Source<'a> {
+    pub fn all(config: &'a Config) -> Vec<Source<'a>> {
+        let paths = config
+            .backup_paths
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| Source::Path { index, entry });
+        let databases = config
+            .databases
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| Source::Database { index, entry });
+        let docker = config
+            .docker
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| Source::Docker { index, entry });
+        paths.chain(databases).chain(docker).collect()
+    }
+
+    pub fn priority(&self) -> Priority {
+        match self {
+            Source::Path { entry, .. } => entry.priority,
+            Source::Database { entry, .. } => entry.priority,
+            Source::Docker { entry, .. } => entry.priority,
+        }
+    }
+
+    pub fn offset_minutes(&self) -> Option<u64> {
+        match self {
+            Source::Path { entry, .. } => entry.offset_minutes,
+            Source::Database { entry, .. } => entry.offset_minutes,
+            Source::Docker { entry, .. } => entry.offset_minutes,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Source::Path { entry, .. } => entry.path.clone(),
+            Source::Database { entry, .. } => entry.describe(),
+            Source::Docker { entry, .. } => entry.describe(),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::manifest::{ArchiveEntry, Manifest};
+use log::info;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Имя части архива: `dir_1_etc.tar.gz.part01`
+pub fn part_name(file: &str, part: usize) -> String {
+    format!("{}.part{:02}", file, part + 1)
+}
+
+/// С `split_archive_mb` делит файл больше этого размера на части `<файл>.partNN`
+/// и удаляет его; возвращает число частей, 0 — файл остался целым
+pub fn split_above(path: &Path, split_archive_mb: Option<u64>) -> io::Result<usize> {
+    let Some(part_size) = split_archive_mb.filter(|mb| *mb > 0).map(|mb| mb * 1_048_576) else {
+        return Ok(0);
+    };
+    if fs::metadata(path)?.len() <= part_size {
+        return Ok(0);
+    }
+    split(path, part_size)
+}
+
+fn split(path: &Path, part_size: u64) -> io::Result<usize> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut source = File::open(path)?;
+    let mut parts = 0;
+    loop {
+        let part_path = path.with_file_name(part_name(&file_name, parts));
+        let written = io::copy(&mut (&mut source).take(part_size), &mut File::create(&part_path)?)?;
+        if written == 0 {
+            // Размер файла кратен части: последняя оказалась пустой
+            fs::remove_file(&part_path)?;
+            break;
+        }
+        parts += 1;
+        if written < part_size {
+            break;
+        }
+    }
+    fs::remove_file(path)?;
+    Ok(parts)
+}
+
+/// Части архива по порядку, как единый поток
+pub fn open_parts(dir: &Path, entry: &ArchiveEntry) -> io::Result<impl Read> {
+    let file = entry.file_name();
+    let mut reader: Box<dyn Read> = Box::new(io::empty());
+    for part in 0..entry.parts {
+        reader = Box::new(reader.chain(File::open(dir.join(part_name(&file, part)))?));
+    }
+    Ok(reader)
+}
+
+/// Собирает разделённые архивы бэкапа в его папке обратно в целые файлы, чтобы дальше
+/// с ними можно было работать как с обычными; возвращает число собранных
+pub fn join_archives(backup_dir: &Path, manifest: &Manifest) -> io::Result<usize> {
+    let split: Vec<_> = manifest.archives.iter().filter(|a| a.parts > 0).collect();
+    for archive in &split {
+        let whole = backup_dir.join(archive.file_name());
+        let mut out = File::create(&whole)?;
+        io::copy(&mut open_parts(backup_dir, archive)?, &mut out)?;
+        out.flush()?;
+        for part in 0..archive.parts {
+            fs::remove_file(backup_dir.join(part_name(&archive.file_name(), part)))?;
+        }
+    }
+    if !split.is_empty() {
+        info!("Собрано разделённых архивов бэкапа {}: {}", manifest.name, split.len());
+    }
+    Ok(split.len())
+}
@@ -0,0 +1,166 @@
+use crate::config::Config;
+use log::{info, warn};
+use nix::fcntl::{flock, FlockArg};
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Назначения временных папок (`<назначение>_<время>`): только такие папки считаются
+/// своими при уборке остатков, остальное содержимое /tmp не трогается
+const PURPOSES: &[&str] = &[
+    "backup", "backup_import", "bundle", "find", "mount", "obt_dest_check", "policy", "prune", "replicate", "restore",
+    "verify",
+];
+
+/// Папка для временных папок запусков: `work_dir` из config.json, иначе /tmp
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+thread_local! {
+    /// `work_dir` профиля, с которым работает поток демона или `BackupRunner`
+    static THREAD_ROOT: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Задаёт папку один раз при запуске obt
+pub fn set_root(root: Option<PathBuf>) {
+    if let Some(root) = root {
+        let _ = ROOT.set(root);
+    }
+}
+
+/// Папка для текущего потока: у каждого профиля свой `work_dir`, без него — /tmp
+pub fn set_thread_root(root: Option<PathBuf>) {
+    let root = root.unwrap_or_else(|| PathBuf::from("/tmp"));
+    THREAD_ROOT.with(|current| *current.borrow_mut() = Some(root));
+}
+
+pub fn root() -> PathBuf {
+    THREAD_ROOT
+        .with(|current| current.borrow().clone())
+        .unwrap_or_else(|| ROOT.get_or_init(|| PathBuf::from("/tmp")).clone())
+}
+
+/// Свободное место в файловой системе папки, в байтах
+pub fn free_space(path: &Path) -> Option<u64> {
+    let stat = nix::sys::statvfs::statvfs(path).ok()?;
+    Some(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+}
+
+/// Временная папка запуска в /tmp или `work_dir` (`<папка>/<назначение>_<время>`). В неё скачивается
+/// репозиторий и собираются архивы, поэтому она может занимать столько же, сколько
+/// все данные бэкапа. Удаляется при выходе из области видимости — и после ошибки
+/// на любом шаге, и при панике.
+pub struct StagingDir {
+    path: String,
+    /// Блокировка `<папка>.lock`, пока папка нужна: по ней уборка отличает папку
+    /// работающего процесса от оставшейся после сбоя
+    lock: Option<File>,
+}
+
+/// Файл блокировки временной папки
+fn lock_path(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.lock", path.display()))
+}
+
+/// Папка или её файл блокировки по имени: `backup_20261015_020000[_профиль][.lock]`
+fn is_staging_name(name: &str) -> bool {
+    PURPOSES.iter().any(|purpose| {
+        name.strip_prefix(purpose)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+    })
+}
+
+/// Удаляет временные папки запусков, оборвавшихся аварийно (kill -9, перезагрузка,
+/// нехватка памяти): их блокировку никто не держит. Вызывается при запуске obt
+pub fn clean_stale() {
+    let Ok(entries) = fs::read_dir(root()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".lock") || !is_staging_name(&name) || !entry.path().is_dir() {
+            continue;
+        }
+        let path = entry.path();
+        let lock = lock_path(&path);
+        // Папки версий obt без блокировок и папки, чья блокировка свободна, — остатки сбоев
+        if let Ok(file) = File::open(&lock) {
+            if flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock).is_err() {
+                continue;
+            }
+        }
+        match fs::remove_dir_all(&path) {
+            Ok(()) => info!("Удалена временная папка оборвавшегося запуска: {}", path.display()),
+            Err(e) => warn!("Не удалось удалить временную папку {}: {}", path.display(), e),
+        }
+        let _ = fs::remove_file(&lock);
+    }
+}
+
+impl StagingDir {
+    /// Папка `name` во временной папке; саму папку создаёт тот, кто её заполняет
+    /// (например, `prepare_repository`). К имени добавляется профиль: профили демона
+    /// могут начать запуск в одну секунду
+    pub fn new(name: String) -> Self {
+        let name = match Config::profile() {
+            Some(profile) => format!("{}_{}", name, profile),
+            None => name,
+        };
+        let root = root();
+        let path = root.join(name);
+        let lock = fs::create_dir_all(&root)
+            .and_then(|()| File::create(lock_path(&path)))
+            .inspect(|file| {
+                let _ = flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock);
+            })
+            .map_err(|e| warn!("Не удалось создать блокировку временной папки {}: {}", path.display(), e))
+            .ok();
+        StagingDir { path: path.to_string_lossy().into_owned(), lock }
+    }
+
+    /// Проверка до архивирования: хватит ли места под `required` байт архивов. Без неё
+    /// бэкап, не поместившийся в маленький /tmp (часто это tmpfs), падает на середине
+    pub fn preflight(&self, required: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(free) = free_space(Path::new(&self.path)) else {
+            return Ok(());
+        };
+        info!("Временная папка {}: нужно около {} байт, свободно {}", self.path, required, free);
+        if required > free {
+            return Err(format!(
+                "Во временной папке {} не хватит места: архивам нужно около {:.1} МБ, свободно {:.1} МБ. \
+                 Укажите папку на большом диске в work_dir (obt config set work_dir /var/tmp/obt) \
+                 или уменьшите объём бэкапа через exclude",
+                root().display(),
+                required as f64 / 1_048_576.0,
+                free as f64 / 1_048_576.0
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Свободное место во временной папке, если его осталось меньше `threshold` байт
+    pub fn low_on_space(&self, threshold: u64) -> Option<u64> {
+        free_space(Path::new(&self.path)).filter(|free| *free < threshold)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        if Path::new(&self.path).exists() {
+            match fs::remove_dir_all(&self.path) {
+                Ok(()) => info!("Временная папка удалена: {}", self.path),
+                Err(e) => warn!("Не удалось удалить временную папку {}: {}", self.path, e),
+            }
+        }
+        if self.lock.take().is_some() {
+            let _ = fs::remove_file(lock_path(Path::new(&self.path)));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::config::Config;
+use crate::timestamp;
+use crate::BackupSummary;
+use chrono::Utc;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// Папка отметок по умолчанию: её читают локальные агенты мониторинга, не зная про obt
+const DEFAULT_DIR: &str = "/var/lib/obt";
+
+/// Отметка последнего успешного бэкапа
+#[derive(Debug, Serialize)]
+struct Stamp<'a> {
+    /// Время завершения (RFC 3339, UTC)
+    timestamp: String,
+    /// Всегда `success`: после неудачного запуска отметка не меняется
+    result: &'static str,
+    /// Имя бэкапа в хранилище
+    backup: &'a str,
+    server: &'a str,
+    profile: Option<String>,
+    duration_seconds: f64,
+    size: u64,
+    archives: usize,
+}
+
+/// `stamp_file` из config.json, иначе /var/lib/obt/last-success.json
+/// (у профиля — last-success-<профиль>.json)
+pub fn path(config: &Config) -> PathBuf {
+    if let Some(path) = &config.stamp_file {
+        return path.clone();
+    }
+    let name = match Config::profile() {
+        Some(profile) => format!("last-success-{}.json", profile),
+        None => "last-success.json".to_string(),
+    };
+    PathBuf::from(DEFAULT_DIR).join(name)
+}
+
+/// Записывает отметку успешного бэкапа. Файл заменяется целиком, чтобы читающий
+/// не увидел его наполовину записанным
+pub fn write(config: &Config, summary: &BackupSummary, duration: std::time::Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path(config);
+    let stamp = Stamp {
+        timestamp: timestamp::format(Utc::now()),
+        result: "success",
+        backup: &summary.name,
+        server: config.backup_name.as_deref().unwrap_or("obt"),
+        profile: Config::profile(),
+        duration_seconds: duration.as_secs_f64(),
+        size: summary.total_size,
+        archives: summary.archives,
+    };
+    let write = || -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&stamp)? + "\n")?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    };
+    write().map_err(|e| format!("Не удалось записать отметку успешного бэкапа {}: {}", path.display(), e).into())
+}
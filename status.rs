@@ -0,0 +1,349 @@
+use crate::catalog::{Catalog, RunStatus};
+use crate::config::{BackupFrequency, Config, Verification};
+use crate::control::{self, ProfileQueue, QueuedRun, Request, Response};
+use crate::systemd::SystemdService;
+use crate::pause::{self, Pause};
+use crate::{git, gitea, labels, rclone, s3, sftp, timestamp, webdav};
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use colored::*;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct LastBackup {
+    pub name: String,
+    pub created_at: String,
+    pub archives: usize,
+    pub total_size: u64,
+    pub complete: bool,
+}
+
+#[derive(Serialize)]
+pub struct Daemon {
+    pub running: Option<QueuedRun>,
+    pub queued: Vec<QueuedRun>,
+    /// Очереди остальных профилей, которые обслуживает тот же демон
+    pub other_profiles: Vec<ProfileQueue>,
+}
+
+/// Сводка для `obt status`; время — в UTC, как в config.json
+#[derive(Serialize)]
+pub struct Status {
+    /// `None` — профиль по умолчанию
+    pub profile: Option<String>,
+    pub storage: String,
+    pub schedule: Option<String>,
+    pub next_run: Option<String>,
+    /// Очистка и проверка по отдельному расписанию
+    pub maintenance: Vec<String>,
+    /// `obt pause`: плановые запуски пропускаются
+    pub paused: Option<Pause>,
+    pub last_backup: Option<LastBackup>,
+    /// `success`, `failure` или `none`, если бэкапов ещё не было
+    pub last_result: &'static str,
+    pub last_failure: Option<String>,
+    pub last_error: Option<String>,
+    /// Конец вывода команды, на которой упал последний запуск
+    pub last_error_output: Option<String>,
+    pub last_verification: Option<Verification>,
+    /// `None`, если systemd недоступен
+    pub timer_active: Option<bool>,
+    pub service_active: Option<bool>,
+    /// `None`, если демон не запущен
+    pub daemon: Option<Daemon>,
+    pub remote_reachable: bool,
+    pub remote_error: Option<String>,
+}
+
+/// Хранилище из настроек, без подключения к нему
+fn storage(config: &Config) -> String {
+    if let Some(s3) = &config.s3 {
+        let prefix = s3.prefix.as_deref().unwrap_or("").trim_matches('/');
+        return format!("S3, s3://{}/{}", s3.bucket, prefix).trim_end_matches('/').to_string();
+    }
+    if let Some(sftp) = &config.sftp {
+        return format!("SFTP, sftp://{}{}", sftp.host, sftp.path);
+    }
+    if let Some(git_ssh) = &config.git_ssh {
+        return format!("Git по SSH, {}", git_ssh.url);
+    }
+    if let Some(rclone) = &config.rclone {
+        return format!("rclone, {}", rclone.remote);
+    }
+    if let Some(webdav) = &config.webdav {
+        return format!("WebDAV, {}", webdav.url);
+    }
+    match (&config.gitea_url, &config.gitea_repo) {
+        (Some(url), Some(repo)) => format!("Gitea, {}/{}", url, repo),
+        _ => "не настроено".to_string(),
+    }
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "по понедельникам",
+        Weekday::Tue => "по вторникам",
+        Weekday::Wed => "по средам",
+        Weekday::Thu => "по четвергам",
+        Weekday::Fri => "по пятницам",
+        Weekday::Sat => "по субботам",
+        Weekday::Sun => "по воскресеньям",
+    }
+}
+
+/// Очистка и проверка по отдельному расписанию: что, когда и ближайший запуск
+fn maintenance(config: &Config, now: DateTime<Utc>) -> Vec<String> {
+    let boot = config.boot_backup.as_ref().map(|boot| {
+        format!(
+            "после загрузки: через {} мин., если последний успешный бэкап старше {} ч.{}",
+            boot.delay().as_secs() / 60,
+            boot.older_than_hours,
+            if boot.timer { " (obt-boot.timer)" } else { "" }
+        )
+    });
+    [("очистка", &config.prune_schedule), ("проверка", &config.verify_schedule)]
+        .into_iter()
+        .filter_map(|(name, schedule)| {
+            let schedule = schedule.as_ref()?;
+            let frequency = match schedule.frequency {
+                BackupFrequency::Weekly => format!("еженедельно ({})", weekday_name(schedule.weekday())),
+                frequency => frequency_name(frequency).to_string(),
+            };
+            let next = schedule
+                .parsed_time()
+                .ok()
+                .and_then(|time| next_run(schedule.frequency, schedule.weekday(), time, now))
+                .map(|next| format!(", следующая {}", timestamp::display_time(next)))
+                .unwrap_or_default();
+            Some(format!("{}: {} в {}{}", name, frequency, schedule.time, next))
+        })
+        .chain(boot)
+        .collect()
+}
+
+fn frequency_name(frequency: BackupFrequency) -> &'static str {
+    match frequency {
+        BackupFrequency::Daily => "ежедневно",
+        BackupFrequency::Weekly => "еженедельно (по понедельникам)",
+        BackupFrequency::Monthly => "ежемесячно (1-го числа)",
+    }
+}
+
+/// Ближайший запуск по расписанию после `now`
+fn next_run(frequency: BackupFrequency, weekday: Weekday, time: NaiveTime, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let zone = timestamp::zone();
+    let today = now.with_timezone(&zone).date_naive();
+    (0..=62)
+        .map(|days| today + Duration::days(days))
+        .filter(|date| frequency.runs_on(*date, weekday))
+        .filter_map(|date| zone.from_local_datetime(&date.and_time(time)).earliest())
+        .map(|time| time.with_timezone(&Utc))
+        .find(|time| *time > now)
+}
+
+/// Проверяет подключение к хранилищу, ничего не скачивая
+pub fn check_remote(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(s3) = &config.s3 {
+        s3::Storage::open(s3, "", None)?;
+    } else if let Some(sftp) = &config.sftp {
+        sftp::Storage::open(sftp, "", None)?;
+    } else if let Some(git_ssh) = &config.git_ssh {
+        git::check_ssh_access(git_ssh)?;
+    } else if let Some(rclone) = &config.rclone {
+        rclone::Storage::open(rclone, "", None)?;
+    } else if let Some(webdav) = &config.webdav {
+        webdav::Storage::open(webdav, "", None)?;
+    } else {
+        gitea::check_access(config)?;
+    }
+    Ok(())
+}
+
+pub fn collect(config: &Config) -> Result<Status, Box<dyn std::error::Error>> {
+    let frequency = config.backup_frequency.unwrap_or(BackupFrequency::Daily);
+    let time = config
+        .backup_time
+        .as_deref()
+        .and_then(|time| NaiveTime::parse_from_str(time, "%H:%M").ok());
+
+    let catalog = Catalog::load()?;
+    let last_backup = catalog
+        .entries
+        .iter()
+        .max_by_key(|entry| timestamp::parse(&entry.created_at))
+        .map(|entry| LastBackup {
+            name: entry.name.clone(),
+            created_at: entry.created_at.clone(),
+            archives: entry.archives,
+            total_size: entry.total_size,
+            complete: entry.status == RunStatus::Complete,
+        });
+
+    let success = config.last_backup.as_deref().and_then(timestamp::parse);
+    let failure = config.last_failure.as_deref().and_then(timestamp::parse);
+    let last_result = match (success, failure) {
+        (_, Some(failure)) if success.is_none_or(|success| failure > success) => "failure",
+        (Some(_), _) => "success",
+        _ => "none",
+    };
+
+    let daemon = match control::send(&Request::Status) {
+        Ok(Response::Status { running, queued }) => {
+            let other_profiles = match control::send(&Request::Profiles) {
+                Ok(Response::Profiles { profiles }) => {
+                    profiles.into_iter().filter(|queue| queue.profile != Config::profile()).collect()
+                }
+                _ => Vec::new(),
+            };
+            Some(Daemon { running, queued, other_profiles })
+        }
+        _ => None,
+    };
+    let remote = check_remote(config);
+
+    let (schedule, next) = match (config.backup_cron.as_deref(), config.cron_schedule()) {
+        (Some(expression), Ok(Some(cron))) => (
+            Some(format!("cron «{}» ({})", expression, timestamp::zone())),
+            cron.next_after(Utc::now(), timestamp::zone()),
+        ),
+        (Some(expression), _) => (Some(format!("cron «{}» — неверное выражение", expression)), None),
+        (None, _) => (
+            time.map(|time| format!("{} в {} ({})", frequency_name(frequency), time.format("%H:%M"), timestamp::zone())),
+            time.and_then(|time| next_run(frequency, Weekday::Mon, time, Utc::now())),
+        ),
+    };
+
+    Ok(Status {
+        profile: Config::profile(),
+        storage: storage(config),
+        schedule,
+        next_run: next.map(timestamp::format),
+        maintenance: maintenance(config, Utc::now()),
+        paused: pause::active(),
+        last_backup,
+        last_result,
+        last_failure: config.last_failure.clone(),
+        last_error: if last_result == "failure" { config.last_error.clone() } else { None },
+        last_error_output: if last_result == "failure" { config.last_error_output.clone() } else { None },
+        last_verification: config.last_verification.clone(),
+        timer_active: SystemdService::is_active(&SystemdService::timer_unit()),
+        service_active: SystemdService::is_active("obt.service"),
+        daemon,
+        remote_reachable: remote.is_ok(),
+        remote_error: remote.err().map(|e| e.to_string()),
+    })
+}
+
+fn unit_state(active: Option<bool>) -> ColoredString {
+    match active {
+        Some(true) => "активен".green(),
+        Some(false) => "не активен".red(),
+        None => "systemd недоступен".yellow(),
+    }
+}
+
+pub fn print(status: &Status) {
+    if let Some(profile) = &status.profile {
+        println!("👤 Профиль: {}", profile.white().bold());
+    }
+    println!("💾 Хранилище: {}", status.storage);
+    match &status.remote_error {
+        None => println!("   {}", "доступно".green()),
+        Some(e) => println!("   {}", format!("недоступно: {}", e).red()),
+    }
+
+    println!("⏰ Расписание: {}", status.schedule.as_deref().unwrap_or("не настроено"));
+    if let Some(next) = &status.next_run {
+        println!("   Следующий запуск: {}", timestamp::display(next));
+    }
+    for line in &status.maintenance {
+        println!("   {}", line);
+    }
+    if let Some(pause) = &status.paused {
+        println!("   {}", format!("⏸️ Плановые запуски приостановлены {}", pause.describe()).yellow());
+    }
+    println!(
+        "   {}: {}, obt.service: {}",
+        SystemdService::timer_unit(),
+        unit_state(status.timer_active),
+        unit_state(status.service_active)
+    );
+
+    match &status.last_backup {
+        Some(backup) => println!(
+            "📦 Последний бэкап: {} | {} | {} архивов, {:.2} МБ{}",
+            backup.name.white().bold(),
+            timestamp::display(&backup.created_at),
+            backup.archives,
+            backup.total_size as f64 / 1_048_576.0,
+            if backup.complete { "" } else { " | ⚠️ не завершён" }
+        ),
+        None => println!("📦 Бэкапов ещё не было"),
+    }
+    match status.last_result {
+        "success" => println!("   Последний запуск: {}", "успешно".green()),
+        "failure" => println!(
+            "   Последний запуск: {} {}: {}",
+            "ошибка".red(),
+            status.last_failure.as_deref().map(timestamp::display).unwrap_or_default(),
+            status.last_error.as_deref().unwrap_or("неизвестна")
+        ),
+        _ => {}
+    }
+    if let Some(output) = &status.last_error_output {
+        println!("   Вывод команды:");
+        for line in output.lines() {
+            println!("     {}", line.dimmed());
+        }
+    }
+    if let Some(verification) = &status.last_verification {
+        println!(
+            "   Проверка {} ({}): {}",
+            verification.backup,
+            timestamp::display(&verification.verified_at),
+            if verification.problems.is_empty() {
+                "совпадает с загруженным".green()
+            } else {
+                format!("расхождений: {}", verification.problems.len()).red()
+            }
+        );
+    }
+
+    match &status.daemon {
+        None => println!("🤖 Демон не запущен"),
+        Some(daemon) => {
+            match &daemon.running {
+                Some(run) => println!(
+                    "▶️  Выполняется: #{} ({:?}, {:?}, с {})",
+                    run.id,
+                    run.task,
+                    run.source,
+                    timestamp::display(&run.queued_at)
+                ),
+                None => println!("🤖 Демон работает, бэкап сейчас не выполняется"),
+            }
+            for run in &daemon.queued {
+                println!(
+                    "   В очереди: #{} ({:?}, {:?}, поставлен {}){}",
+                    run.id,
+                    run.task,
+                    run.source,
+                    timestamp::display(&run.queued_at),
+                    if run.labels.is_empty() { String::new() } else { format!(" | {}", labels::format(&run.labels)) }
+                );
+            }
+            for queue in &daemon.other_profiles {
+                let name = queue.profile.as_deref().unwrap_or("default");
+                match &queue.running {
+                    Some(run) => println!(
+                        "   Профиль {}: выполняется #{} ({:?}), в очереди {}",
+                        name,
+                        run.id,
+                        run.task,
+                        queue.queued.len()
+                    ),
+                    None => println!("   Профиль {}: простаивает, в очереди {}", name, queue.queued.len()),
+                }
+            }
+        }
+    }
+}
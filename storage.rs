@@ -0,0 +1,128 @@
+use crate::layout;
+use crate::manifest::MANIFEST_FILE;
+use crate::timestamp;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Хранилище бэкапов. Бэкап собирается в локальной рабочей папке, повторяющей структуру
+/// хранилища (`<папка бэкапа>/<файл>`), и загружается из неё: в Gitea — коммитом
+/// рабочей копии, в S3 — объектами с теми же путями, по SFTP — файлами на другом сервере.
+pub trait Backend {
+    /// Ветка Gitea, адрес бакета или папки SFTP — для manifest.json
+    fn location(&self) -> String;
+
+    /// Описание хранилища для backup_info.txt
+    fn describe(&self) -> String;
+
+    /// Получает все бэкапы в рабочую папку (рабочая копия Gitea содержит их сразу после открытия)
+    fn download(&self) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Получает в рабочую папку только папку бэкапа `name` (путь от корня хранилища, см. layout)
+    fn download_backup(&self, name: &str) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Папки бэкапов в хранилище (пути от корня, как у `download_backup`) — без скачивания архивов
+    fn backups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>>;
+
+    /// Есть ли в хранилище папка бэкапа с таким именем
+    fn contains(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>>;
+
+    /// Удаляет папку бэкапа; в хранилище удаление попадает при `upload_all`.
+    /// `false`, если такого бэкапа нет.
+    fn remove(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>>;
+
+    /// Загружает только указанные файлы (пути относительно рабочей папки)
+    fn upload_paths(&self, files: &[String], message: &str) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Загружает все изменения рабочей папки, включая удалённые бэкапы
+    fn upload_all(&self, message: &str) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Версия хранилища после загрузки для каталога: хеш коммита в Gitea;
+    /// у хранилищ без истории версий — `None`
+    fn revision(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Файл метаданных, а не архив: служебный файл верхнего уровня, manifest.json или backup_info.txt
+pub fn is_metadata(file: &str) -> bool {
+    match file.rsplit_once('/') {
+        Some((_, name)) => name == MANIFEST_FILE || name == "backup_info.txt",
+        None => true,
+    }
+}
+
+/// Папки бэкапов по списку файлов хранилища: `<имя>/…` в корне и `<год>/<месяц>/<имя>/…`
+/// при `shard_by_month`. Папкой бэкапа считается папка с manifest.json или со временем в имени
+pub fn backup_folders(files: &[String]) -> Vec<String> {
+    let mut folders = BTreeSet::new();
+    for file in files {
+        let parts: Vec<&str> = file.split('/').collect();
+        let depth = match parts.as_slice() {
+            [year, month, _, _, ..] if layout::is_shard(year, 4) && layout::is_shard(month, 2) => 3,
+            [_, _, ..] => 1,
+            _ => continue,
+        };
+        let name = parts[depth - 1];
+        if name.starts_with('.') {
+            continue;
+        }
+        let folder = parts[..depth].join("/");
+        if parts[depth..] == [MANIFEST_FILE] || timestamp::from_folder_name(name).is_some() {
+            folders.insert(folder);
+        }
+    }
+    folders.into_iter().collect()
+}
+
+fn collect_files(dir: &Path, relative: &str, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if relative.is_empty() && name.starts_with('.') {
+            continue;
+        }
+        let path = if relative.is_empty() { name } else { format!("{}/{}", relative, name) };
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Файлы рабочей папки для хранилищ без git (пути через `/`, по порядку);
+/// служебные файлы верхнего уровня вроде .gitignore пропускаются
+pub fn local_files(dir: &Path) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    collect_files(dir, "", &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_folders_in_root_and_month_shards() {
+        let files: Vec<String> = [
+            "backup_info.txt",
+            ".gitignore",
+            "vpn_20261014T175529Z/manifest.json",
+            "vpn_20261014T175529Z/etc.tar.gz",
+            "2026/10/vpn_20261015T020000Z/etc.tar.gz",
+            "imported/manifest.json",
+            "notes/readme.txt",
+        ]
+        .iter()
+        .map(|file| file.to_string())
+        .collect();
+        assert_eq!(
+            backup_folders(&files),
+            ["2026/10/vpn_20261015T020000Z", "imported", "vpn_20261014T175529Z"]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::events::{self, EventSink};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::Duration;
+
+/// Часы планировщика и движка бэкапа
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+    fn sleep(&self, duration: Duration);
+
+    /// Сколько прошло с `start`; если часы перевели назад — ноль
+    fn elapsed_since(&self, start: DateTime<Utc>) -> Duration {
+        (self.now() - start).to_std().unwrap_or_default()
+    }
+}
+
+/// Запуск команд оболочки (`sh -c`)
+pub trait Runner {
+    fn run(&self, command: &str) -> io::Result<Output> {
+        self.run_with_env(command, &[])
+    }
+
+    /// Запуск с дополнительными переменными окружения
+    fn run_with_env(&self, command: &str, env: &[(&str, &str)]) -> io::Result<Output>;
+}
+
+/// Операции движка бэкапа с рабочей папкой; архивы создаются в нескольких потоках
+pub trait FileSystem: Sync {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    fn file_size(&self, path: &Path) -> io::Result<u64>;
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+pub struct Shell;
+
+impl Runner for Shell {
+    fn run_with_env(&self, command: &str, env: &[(&str, &str)]) -> io::Result<Output> {
+        Command::new("sh").arg("-c").arg(command).envs(env.iter().copied()).output()
+    }
+}
+
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn file_size(&self, path: &Path) -> io::Result<u64> {
+        Ok(fs::metadata(path)?.len())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+}
+
+/// Всё, с чем движок бэкапа работает вне своего кода
+pub struct System {
+    pub clock: Box<dyn Clock>,
+    pub runner: Box<dyn Runner>,
+    pub fs: Box<dyn FileSystem>,
+    /// Ход бэкапа; для встраивания — канал `mpsc::Sender<events::Event>`
+    pub events: Box<dyn EventSink>,
+}
+
+impl System {
+    pub fn real() -> Self {
+        System {
+            clock: Box::new(SystemClock),
+            runner: Box::new(Shell),
+            fs: Box::new(RealFileSystem),
+            events: Box::new(events::Console::default()),
+        }
+    }
+}
+
+/// Подмены для проверки расписания, повторов и хуков без ожидания и без системы
+#[cfg(test)]
+pub mod fake {
+    use super::{Clock, Runner};
+    use chrono::{DateTime, Utc};
+    use std::collections::VecDeque;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Часы, которые идут только по команде; `sleep` сразу переводит их вперёд
+    pub struct ManualClock {
+        now: Mutex<DateTime<Utc>>,
+        slept: Mutex<Vec<Duration>>,
+    }
+
+    impl ManualClock {
+        pub fn new(now: DateTime<Utc>) -> Self {
+            ManualClock {
+                now: Mutex::new(now),
+                slept: Mutex::new(Vec::new()),
+            }
+        }
+
+        pub fn set(&self, now: DateTime<Utc>) {
+            *self.now.lock().unwrap() = now;
+        }
+
+        pub fn advance(&self, duration: Duration) {
+            *self.now.lock().unwrap() += chrono::Duration::from_std(duration).unwrap();
+        }
+
+        /// Все паузы по порядку
+        pub fn slept(&self) -> Vec<Duration> {
+            self.slept.lock().unwrap().clone()
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.slept.lock().unwrap().push(duration);
+            self.advance(duration);
+        }
+    }
+
+    /// Отвечает на команды заранее заданными результатами по очереди; когда они
+    /// закончились — успехом без вывода. Запоминает выполненные команды.
+    #[derive(Default)]
+    pub struct ScriptedRunner {
+        results: Mutex<VecDeque<io::Result<Output>>>,
+        commands: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedRunner {
+        pub fn succeed(&self, stdout: &str) {
+            self.push(0, stdout, "");
+        }
+
+        pub fn fail(&self, code: i32, stderr: &str) {
+            self.push(code, "", stderr);
+        }
+
+        /// Команду не удалось даже запустить
+        pub fn fail_to_start(&self, error: io::Error) {
+            self.results.lock().unwrap().push_back(Err(error));
+        }
+
+        pub fn commands(&self) -> Vec<String> {
+            self.commands.lock().unwrap().clone()
+        }
+
+        fn push(&self, code: i32, stdout: &str, stderr: &str) {
+            self.results.lock().unwrap().push_back(Ok(Output {
+                status: ExitStatus::from_raw(code << 8),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: stderr.as_bytes().to_vec(),
+            }));
+        }
+    }
+
+    impl Runner for ScriptedRunner {
+        fn run_with_env(&self, command: &str, _env: &[(&str, &str)]) -> io::Result<Output> {
+            self.commands.lock().unwrap().push(command.to_string());
+            self.results.lock().unwrap().pop_front().unwrap_or_else(|| {
+                Ok(Output {
+                    status: ExitStatus::from_raw(0),
+                    stdout: Vec::new(),
+                    stderr: Vec::new(),
+                })
+            })
+        }
+    }
+}
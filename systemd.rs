@@ -1,9 +1,21 @@
 use crate::config::{BackupFrequency, Config};
+use crate::timestamp;
 use std::fs;
+use std::path::Path;
 use std::process::Command;
 
 pub struct SystemdService;
 
+/// Результат сверки таймера с config.json
+pub enum TimerCheck {
+    NotInstalled,
+    Matches,
+    Drift { installed: String, expected: String },
+}
+
+const BOOT_SERVICE: &str = "/etc/systemd/system/obt-boot.service";
+const BOOT_TIMER: &str = "/etc/systemd/system/obt-boot.timer";
+
 impl SystemdService {
     pub fn create(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
         let service_content = format!(
@@ -24,46 +36,193 @@ WantedBy=multi-user.target
             std::env::var("USER").unwrap_or_else(|_| "root".to_string())
         );
 
-        // Формируем расписание в зависимости от выбранной периодичности
-        let calendar = match config
-            .backup_frequency
-            .as_ref()
-            .unwrap_or(&BackupFrequency::Daily)
-        {
-            BackupFrequency::Daily => "*-*-*",
-            BackupFrequency::Weekly => "Mon *-*-*", // Каждый понедельник
-            BackupFrequency::Monthly => "*-*-1",    // Первый день каждого месяца
-        };
-
+        // Таймер профиля запускает свой oneshot-сервис, который ставит плановый бэкап профиля
+        // в очередь демона; obt.timer будит сам демон, ведущий расписание профиля по умолчанию
+        let unit = Self::profile_service().unwrap_or_else(|| "obt.service".to_string());
         let timer_content = format!(
             r#"[Unit]
-Description=OfficialVPN Backup Tool Timer
+Description=OfficialVPN Backup Tool Timer{}
 
 [Timer]
-OnCalendar={} {}:00
+OnCalendar={}
 Persistent=true
+Unit={}
 
 [Install]
 WantedBy=timers.target
 "#,
-            calendar,
-            config.backup_time.as_ref().unwrap_or(&"02:00".to_string())
+            Config::profile().map(|profile| format!(" ({})", profile)).unwrap_or_default(),
+            Self::on_calendar(config),
+            unit
         );
 
         if !Self::is_root() {
             return Err("Требуются права root для установки systemd сервиса".into());
         }
 
+        let timer = Self::timer_unit();
         fs::write("/etc/systemd/system/obt.service", service_content)?;
-        fs::write("/etc/systemd/system/obt.timer", timer_content)?;
+        if let Some(profile) = Config::profile() {
+            let trigger_content = format!(
+                r#"[Unit]
+Description=OfficialVPN Backup Tool scheduled backup ({0})
+Wants=obt.service
+After=obt.service
+
+[Service]
+Type=oneshot
+ExecStart={1} --profile {0} trigger --timer
+User={2}
+"#,
+                profile,
+                std::env::current_exe()?.display(),
+                std::env::var("USER").unwrap_or_else(|_| "root".to_string())
+            );
+            fs::write(format!("/etc/systemd/system/{}", unit), trigger_content)?;
+        }
+        fs::write(format!("/etc/systemd/system/{}", timer), timer_content)?;
 
         Self::run_systemctl(&["daemon-reload"])?;
-        Self::run_systemctl(&["enable", "obt.timer"])?;
-        Self::run_systemctl(&["start", "obt.timer"])?;
+        Self::run_systemctl(&["enable", &timer])?;
+        Self::run_systemctl(&["start", &timer])?;
+        if Config::profile().is_some() {
+            // Очередь профиля, обслуживание и бэкап после загрузки ведёт демон, у которого должен быть автозапуск
+            return Self::run_systemctl(&["enable", "obt.service"]);
+        }
+
+        Self::install_boot(config)
+    }
+
+    /// Бэкап после загрузки: с `boot_backup.timer` — obt-boot.timer (`OnBootSec=`),
+    /// запускающий `obt boot-backup`, иначе демон, который стартует вместе с системой
+    pub fn install_boot(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+        if !Self::is_root() {
+            return Err("Требуются права root для установки systemd сервиса".into());
+        }
+
+        match &config.boot_backup {
+            Some(boot) if boot.timer => {
+                let service_content = format!(
+                    r#"[Unit]
+Description=OfficialVPN Backup Tool backup after boot
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=oneshot
+ExecStart={} boot-backup
+User={}
+"#,
+                    std::env::current_exe()?.display(),
+                    std::env::var("USER").unwrap_or_else(|_| "root".to_string())
+                );
+                let timer_content = format!(
+                    r#"[Unit]
+Description=OfficialVPN Backup Tool backup after boot
+
+[Timer]
+OnBootSec={}s
 
+[Install]
+WantedBy=timers.target
+"#,
+                    boot.delay().as_secs()
+                );
+                fs::write(BOOT_SERVICE, service_content)?;
+                fs::write(BOOT_TIMER, timer_content)?;
+                Self::run_systemctl(&["daemon-reload"])?;
+                // Срок OnBootSec= считается от загрузки: таймер сработает со следующей
+                Self::run_systemctl(&["enable", "obt-boot.timer"])?;
+            }
+            boot => {
+                if Path::new(BOOT_TIMER).exists() {
+                    Self::run_systemctl(&["disable", "obt-boot.timer"])?;
+                    fs::remove_file(BOOT_TIMER)?;
+                    let _ = fs::remove_file(BOOT_SERVICE);
+                    Self::run_systemctl(&["daemon-reload"])?;
+                }
+                if boot.is_some() {
+                    Self::run_systemctl(&["enable", "obt.service"])?;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// `OnCalendar=` таймера по расписанию из config.json
+    fn on_calendar(config: &Config) -> String {
+        // Из config.json, а не из запуска: `obt provision` мог только что сменить пояс
+        let zone = config.zone().unwrap_or_else(|_| timestamp::zone());
+        if let Ok(Some(cron)) = config.cron_schedule() {
+            return format!("{} {}", cron.on_calendar(), zone);
+        }
+        // Формируем расписание в зависимости от выбранной периодичности
+        let calendar = match config
+            .backup_frequency
+            .as_ref()
+            .unwrap_or(&BackupFrequency::Daily)
+        {
+            BackupFrequency::Daily => "*-*-*",
+            BackupFrequency::Weekly => "Mon *-*-*", // Каждый понедельник
+            BackupFrequency::Monthly => "*-*-1",    // Первый день каждого месяца
+        };
+        format!(
+            "{} {}:00 {}",
+            calendar,
+            config.backup_time.as_ref().unwrap_or(&"02:00".to_string()),
+            zone
+        )
+    }
+
+    /// Сверяет расписание из config.json с `OnCalendar=` установленного таймера профиля:
+    /// после ручной правки config.json они расходятся, и таймер срабатывает не тогда
+    pub fn check_timer(config: &Config) -> TimerCheck {
+        let expected = Self::on_calendar(config);
+        let path = format!("/etc/systemd/system/{}", Self::timer_unit());
+        let Ok(content) = fs::read_to_string(&path) else {
+            return TimerCheck::NotInstalled;
+        };
+        // Таймер профиля прежних версий будил демон (Unit=obt.service) и бэкап не запускал
+        if let Some(service) = Self::profile_service() {
+            let triggers = content.lines().any(|line| line.trim() == format!("Unit={}", service));
+            if !triggers || !Path::new(&format!("/etc/systemd/system/{}", service)).exists() {
+                return TimerCheck::NotInstalled;
+            }
+        }
+        let installed = content
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("OnCalendar="))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default();
+        if installed == expected {
+            TimerCheck::Matches
+        } else {
+            TimerCheck::Drift { installed, expected }
+        }
+    }
+
+    /// Таймер текущего профиля: `obt.timer` у профиля по умолчанию, `obt-<профиль>.timer` у остальных
+    pub fn timer_unit() -> String {
+        match Config::profile() {
+            Some(profile) => format!("obt-{}.timer", profile),
+            None => "obt.timer".to_string(),
+        }
+    }
+
+    /// Сервис, который запускает таймер профиля: `obt-<профиль>.service`; у профиля по умолчанию его нет
+    fn profile_service() -> Option<String> {
+        Config::profile().map(|profile| format!("obt-{}.service", profile))
+    }
+
+    /// Запущен ли юнит (`obt.timer`, `obt.service`); `None`, если systemd недоступен
+    pub fn is_active(unit: &str) -> Option<bool> {
+        let output = Command::new("systemctl").args(["is-active", unit]).output().ok()?;
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "" => None,
+            state => Some(state == "active"),
+        }
+    }
+
     fn is_root() -> bool {
         nix::unistd::geteuid().is_root()
     }
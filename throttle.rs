@@ -0,0 +1,133 @@
+use crate::config::NetworkConfig;
+use crate::progress;
+use log::warn;
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+/// Ограничение скорости загрузки (`upload_limit_kbps`, килобиты в секунду): если с начала
+/// загрузки отправлено больше, чем позволяет лимит, отправка ждёт, пока средняя скорость
+/// не опустится до него
+pub struct Throttle {
+    bytes_per_sec: u64,
+    started: Instant,
+    sent: u64,
+}
+
+impl Throttle {
+    /// `None` — без ограничения
+    pub fn new(limit_kbps: Option<u64>) -> Option<Self> {
+        limit_kbps.filter(|kbps| *kbps > 0).map(|kbps| Throttle {
+            bytes_per_sec: (kbps * 1000 / 8).max(1),
+            started: Instant::now(),
+            sent: 0,
+        })
+    }
+
+    /// Всего отправлено `total` байт с начала загрузки; ждёт, если это быстрее лимита
+    pub fn sent_total(&mut self, total: u64) {
+        self.sent = total;
+        let due = Duration::from_secs_f64(self.sent as f64 / self.bytes_per_sec as f64);
+        if let Some(delay) = due.checked_sub(self.started.elapsed()) {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Порция данных не больше десятой доли секундного лимита, чтобы отправка шла
+    /// равномерно, а не рывками с долгими паузами
+    fn chunk(&self) -> usize {
+        (self.bytes_per_sec / 10).max(1) as usize
+    }
+}
+
+/// Обрыв слишком медленной передачи (`low_speed_kbps` дольше `low_speed_seconds`), как
+/// `http.lowSpeedLimit` у git: по мобильной связи передача может часами идти по килобайту
+/// в секунду, так и не упираясь в тайм-аут
+pub struct LowSpeed {
+    bytes_per_sec: u64,
+    period: Duration,
+    started: Instant,
+    transferred: u64,
+}
+
+impl LowSpeed {
+    /// `None` — без обрыва (`low_speed_kbps` не задан)
+    pub fn new(settings: &NetworkConfig) -> Option<Self> {
+        settings.low_speed_kbps.filter(|kbps| *kbps > 0).map(|kbps| LowSpeed {
+            bytes_per_sec: (kbps * 1000 / 8).max(1),
+            period: settings.low_speed_period(),
+            started: Instant::now(),
+            transferred: 0,
+        })
+    }
+
+    /// Передано ещё `bytes` байт; ошибка, если за последний период передано меньше порога
+    pub fn transferred(&mut self, bytes: u64) -> Result<(), String> {
+        self.transferred += bytes;
+        let elapsed = self.started.elapsed();
+        if elapsed < self.period {
+            return Ok(());
+        }
+        let speed = self.transferred as f64 / elapsed.as_secs_f64();
+        if speed < self.bytes_per_sec as f64 {
+            return Err(format!(
+                "передача шла медленнее {} кбит/с ({:.1} кбит/с) дольше {} сек. (low_speed_kbps)",
+                self.bytes_per_sec * 8 / 1000,
+                speed * 8.0 / 1000.0,
+                self.period.as_secs()
+            ));
+        }
+        self.started = Instant::now();
+        self.transferred = 0;
+        Ok(())
+    }
+}
+
+/// Источник данных загрузки, читаемый не быстрее лимита; прочитанное идёт в полосу загрузки
+pub struct Reader<R> {
+    inner: R,
+    throttle: Option<Throttle>,
+    low_speed: Option<LowSpeed>,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(inner: R, limit_kbps: Option<u64>) -> Self {
+        Reader {
+            inner,
+            throttle: Throttle::new(limit_kbps),
+            low_speed: None,
+        }
+    }
+
+    /// Обрывать загрузку медленнее `low_speed_kbps` из сетевых настроек хранилища
+    pub fn abort_below(mut self, settings: &NetworkConfig) -> Self {
+        self.low_speed = LowSpeed::new(settings);
+        // Собственный лимит скорости иначе сам оборвал бы загрузку
+        if let (Some(throttle), Some(low_speed)) = (&self.throttle, &self.low_speed) {
+            if throttle.bytes_per_sec <= low_speed.bytes_per_sec {
+                warn!("low_speed_kbps не меньше upload_limit_kbps: медленная загрузка не обрывается");
+                self.low_speed = None;
+            }
+        }
+        self
+    }
+}
+
+impl<R: Read> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = match &self.throttle {
+            Some(throttle) => buf.len().min(throttle.chunk()),
+            None => buf.len(),
+        };
+        let read = self.inner.read(&mut buf[..len])?;
+        progress::uploaded(read as u64);
+        if let Some(throttle) = &mut self.throttle {
+            let total = throttle.sent + read as u64;
+            throttle.sent_total(total);
+        }
+        // Следующая порция читается, когда предыдущая ушла в сеть: это и есть скорость передачи
+        if let Some(low_speed) = self.low_speed.as_mut().filter(|_| read > 0) {
+            low_speed.transferred(read as u64).map_err(|e| io::Error::new(io::ErrorKind::TimedOut, e))?;
+        }
+        Ok(read)
+    }
+}
@@ -0,0 +1,106 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Europe::Moscow;
+use chrono_tz::Tz;
+use std::cell::Cell;
+use std::sync::OnceLock;
+
+static ZONE: OnceLock<Tz> = OnceLock::new();
+
+thread_local! {
+    /// Часовой пояс профиля, с которым работает поток демона
+    static THREAD_ZONE: Cell<Option<Tz>> = const { Cell::new(None) };
+}
+
+/// Задаёт часовой пояс расписания и вывода (`timezone` из config.json) один раз при запуске
+pub fn set_zone(zone: Tz) {
+    let _ = ZONE.set(zone);
+}
+
+/// Часовой пояс профиля для текущего потока: у каждого профиля демона он свой
+pub fn set_thread_zone(zone: Tz) {
+    THREAD_ZONE.with(|current| current.set(Some(zone)));
+}
+
+/// Часовой пояс расписания и вывода; до `set_zone` — системный
+pub fn zone() -> Tz {
+    THREAD_ZONE.with(Cell::get).unwrap_or_else(|| *ZONE.get_or_init(system_zone))
+}
+
+/// Часовой пояс системы (`/etc/localtime`, `TZ`); UTC, если его не удалось определить
+pub fn system_zone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Текущее время в часовом поясе пользователя
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&zone())
+}
+
+/// Время хранится в UTC (RFC 3339: `2026-10-14T17:55:29Z`): строки сортируются по времени
+/// и сравнимы между серверами. В часовой пояс пользователя время переводится только при выводе.
+pub fn format(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Отметка времени для имён папок (ISO 8601, базовый формат без двоеточий): `20261014T175529Z`
+pub fn folder(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Разбирает сохранённое время; понимает и формат старых версий `ГГГГ-ММ-ДД ЧЧ:ММ:СС MSK`
+pub fn parse(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(value.trim_end_matches(" MSK"), "%Y-%m-%d %H:%M:%S").ok()?;
+    Moscow.from_local_datetime(&naive).single().map(|t| t.with_timezone(&Utc))
+}
+
+/// Время, введённое пользователем: RFC 3339 или `ГГГГ-ММ-ДД[ ЧЧ:ММ[:СС]]` в его часовом поясе.
+/// Дата без времени означает конец этого дня.
+pub fn parse_input(value: &str) -> Option<DateTime<Utc>> {
+    if let Some(time) = parse(value) {
+        return Some(time);
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(23, 59, 59))?;
+    zone().from_local_datetime(&naive).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+/// Время из имени папки бэкапа: `<имя>_20261014T175529Z[_метка]` или, у старых версий,
+/// `<имя>_20261014_205529[_метка]` по Москве
+pub fn from_folder_name(name: &str) -> Option<DateTime<Utc>> {
+    let parts: Vec<&str> = name.split('_').collect();
+    let digits = |s: &str, len: usize| s.len() == len && s.chars().all(|c| c.is_ascii_digit());
+
+    parts
+        .iter()
+        .find_map(|part| {
+            NaiveDateTime::parse_from_str(part, "%Y%m%dT%H%M%SZ")
+                .ok()
+                .map(|naive| Utc.from_utc_datetime(&naive))
+        })
+        .or_else(|| {
+            parts.windows(2).find_map(|pair| {
+                if !digits(pair[0], 8) || !digits(pair[1], 6) {
+                    return None;
+                }
+                let naive = NaiveDateTime::parse_from_str(&format!("{}{}", pair[0], pair[1]), "%Y%m%d%H%M%S").ok()?;
+                Moscow.from_local_datetime(&naive).single().map(|t| t.with_timezone(&Utc))
+            })
+        })
+}
+
+/// Время для пользователя: в его часовом поясе, как и всё остальное в интерфейсе
+pub fn display_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&zone()).format("%Y-%m-%d %H:%M:%S %Z").to_string()
+}
+
+/// То же для сохранённой строки; нераспознанная строка выводится как есть
+pub fn display(value: &str) -> String {
+    parse(value).map(display_time).unwrap_or_else(|| value.to_string())
+}
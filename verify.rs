@@ -0,0 +1,257 @@
+use crate::catalog::{Catalog, CatalogEntry};
+use crate::config::{Config, Verification};
+use crate::layout;
+use crate::manifest::{ArchiveEntry, Manifest};
+use crate::split;
+use crate::staging::StagingDir;
+use crate::{prepare_repository, run_parallel, timestamp};
+use chrono::Utc;
+use colored::*;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::RandomState;
+use std::fs::{self, File};
+use std::hash::BuildHasher;
+use std::io;
+use std::path::Path;
+
+/// Какие бэкапы каталога проверяет `obt verify` без имени
+#[derive(Debug, Clone, Copy)]
+pub enum Sample {
+    /// Только последний
+    Latest,
+    /// Случайные, процент от всех (не меньше одного)
+    Random(u8),
+    All,
+}
+
+/// SHA-256 файла в шестнадцатеричном виде
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// SHA-256 архива в папке бэкапа, собранного из частей, если он разделён
+fn sha256_stored(dir: &Path, entry: &ArchiveEntry) -> io::Result<String> {
+    if entry.parts == 0 {
+        return sha256_file(&dir.join(entry.file_name()));
+    }
+    let mut hasher = Sha256::new();
+    io::copy(&mut split::open_parts(dir, entry)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Сверяет архивы в папке бэкапа `dir` с `manifest`: по SHA-256, а у бэкапов
+/// старых версий, где его нет, — по размеру. Разделённый архив сверяется целиком,
+/// по всем частям подряд
+pub fn check(name: &str, dir: &Path, manifest: &Manifest) -> Verification {
+    let mut archives = 0;
+    let mut problems = Vec::new();
+    'archives: for entry in &manifest.archives {
+        let file = entry.file_name();
+        let mut size = 0;
+        for stored in entry.stored_files() {
+            match fs::metadata(dir.join(&stored)) {
+                Ok(metadata) => size += metadata.len(),
+                Err(_) => {
+                    problems.push(format!("{}: нет в хранилище", stored));
+                    continue 'archives;
+                }
+            }
+        }
+        match &entry.sha256 {
+            Some(expected) => match sha256_stored(dir, entry) {
+                Ok(actual) if actual == *expected => archives += 1,
+                Ok(actual) => problems.push(format!("{}: SHA-256 {} вместо {}", file, actual, expected)),
+                Err(e) => problems.push(format!("{}: не удалось прочитать: {}", file, e)),
+            },
+            None if size != entry.size => problems.push(format!("{}: {} байт вместо {}", file, size, entry.size)),
+            None => {}
+        }
+    }
+    Verification {
+        backup: name.to_string(),
+        verified_at: timestamp::format(Utc::now()),
+        archives,
+        problems,
+    }
+}
+
+/// Скачивает бэкап `name` из хранилища в отдельную временную папку и сверяет его
+/// с `manifest`, а без него — с manifest.json из самого бэкапа
+pub fn remote(config: &Config, name: &str, manifest: Option<&Manifest>) -> Result<Verification, Box<dyn std::error::Error>> {
+    let staging = StagingDir::new(format!("verify_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let storage = prepare_repository(config, staging.path())?;
+    let path = layout::locate(storage.as_ref(), name)?.ok_or(format!("Бэкап {} не найден в хранилище", name))?;
+    storage.download_backup(&path)?;
+
+    let dir = Path::new(staging.path()).join(path);
+    let downloaded;
+    let manifest = match manifest {
+        Some(manifest) => manifest,
+        None => {
+            downloaded = Manifest::read(&dir).map_err(|_| format!("В бэкапе {} нет manifest.json — сверять не с чем", name))?;
+            &downloaded
+        }
+    };
+    Ok(check(name, &dir, manifest))
+}
+
+/// Строки для backup_info.txt
+pub fn summary(verification: &Verification) -> String {
+    let checked_at = timestamp::display(&verification.verified_at);
+    if verification.problems.is_empty() {
+        format!("🔍 Проверка после загрузки ({}): архивов сверено по SHA-256: {}", checked_at, verification.archives)
+    } else {
+        format!(
+            "❌ Проверка после загрузки ({}) не пройдена:\n{}",
+            checked_at,
+            verification.problems.iter().map(|p| format!("  - {}", p)).collect::<Vec<_>>().join("\n")
+        )
+    }
+}
+
+pub fn print(verification: &Verification) {
+    if verification.problems.is_empty() {
+        println!(
+            "{}",
+            format!("✅ Бэкап {} совпадает с загруженным: архивов сверено по SHA-256: {}", verification.backup, verification.archives)
+                .green()
+        );
+        info!(
+            event = "backup_verified", backup = verification.backup.as_str(), archives = verification.archives;
+            "Проверка бэкапа {} пройдена", verification.backup
+        );
+        return;
+    }
+    println!("{}", format!("❌ Бэкап {} в хранилище отличается от загруженного:", verification.backup).red());
+    for problem in &verification.problems {
+        println!("   {}", problem.red());
+        warn!(event = "verification_failed", backup = verification.backup.as_str(); "Проверка бэкапа {}: {}", verification.backup, problem);
+    }
+}
+
+/// Бэкапы каталога по `sample`, от новых к старым; бэкапы в корзине не проверяются
+fn select(catalog: &Catalog, sample: Sample) -> Vec<String> {
+    let mut entries: Vec<&CatalogEntry> = catalog.entries.iter().filter(|entry| entry.trashed_at.is_none()).collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(timestamp::parse(&entry.created_at)));
+    let count = match sample {
+        Sample::Latest => 1,
+        Sample::Random(percent) => (entries.len() * percent.min(100) as usize).div_ceil(100).max(1),
+        Sample::All => entries.len(),
+    };
+    if let Sample::Random(_) = sample {
+        // Хеш со случайным ключом процесса: каждый запуск проверяет свою выборку
+        let random = RandomState::new();
+        let mut shuffled = entries.clone();
+        shuffled.sort_by_cached_key(|entry| random.hash_one(&entry.name));
+        shuffled.truncate(count);
+        entries.retain(|entry| shuffled.iter().any(|chosen| chosen.name == entry.name));
+    }
+    entries.into_iter().take(count).map(|entry| entry.name.clone()).collect()
+}
+
+/// Проверяет несколько бэкапов за одно подключение к хранилищу: бэкапы скачиваются
+/// по `jobs` штук, их SHA-256 сверяются параллельно, после чего папки удаляются,
+/// так что на диске не больше `jobs` бэкапов сразу
+fn remote_many(config: &Config, names: &[String], jobs: usize) -> Result<Vec<Verification>, Box<dyn std::error::Error>> {
+    let staging = StagingDir::new(format!("verify_{}", timestamp::now().format("%Y%m%d_%H%M%S")));
+    let storage = prepare_repository(config, staging.path())?;
+    let mut verifications = Vec::new();
+    for batch in names.chunks(jobs) {
+        let mut downloaded = Vec::new();
+        for name in batch {
+            let path = match layout::locate(storage.as_ref(), name) {
+                Ok(Some(path)) => path,
+                Ok(None) => {
+                    downloaded.push((name, Err("нет в хранилище".to_string())));
+                    continue;
+                }
+                Err(e) => {
+                    downloaded.push((name, Err(format!("не удалось найти в хранилище: {}", e))));
+                    continue;
+                }
+            };
+            let dir = Path::new(staging.path()).join(&path);
+            match storage.download_backup(&path) {
+                Ok(()) => downloaded.push((name, Ok(dir))),
+                Err(e) => downloaded.push((name, Err(format!("не удалось скачать: {}", e)))),
+            }
+        }
+
+        verifications.extend(run_parallel(&downloaded, jobs, |(name, dir)| match dir {
+            Ok(dir) => match Manifest::read(dir) {
+                Ok(manifest) => check(name, dir, &manifest),
+                Err(_) => failure(name, "нет manifest.json — сверять не с чем".to_string()),
+            },
+            Err(problem) => failure(name, problem.clone()),
+        }));
+        for (_, dir) in &downloaded {
+            if let Ok(dir) = dir {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+    }
+    Ok(verifications)
+}
+
+fn failure(name: &str, problem: String) -> Verification {
+    Verification {
+        backup: name.to_string(),
+        verified_at: timestamp::format(Utc::now()),
+        archives: 0,
+        problems: vec![problem],
+    }
+}
+
+/// `obt verify [name]`: без имени проверяются бэкапы этого сервера из каталога по `sample`,
+/// до `jobs` одновременно, с общим итогом. В `last_verification` сохраняется первая
+/// непройденная проверка, а если все пройдены — проверка самого нового бэкапа
+pub fn run(config: &mut Config, name: Option<&str>, sample: Sample, jobs: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let verifications = match name {
+        Some(name) => {
+            println!("🔍 Проверка бэкапа {}...", name);
+            vec![remote(config, name, None)?]
+        }
+        None => {
+            let names = select(&Catalog::load()?, sample);
+            if names.is_empty() {
+                return Err("В каталоге этого сервера нет бэкапов — укажите имя бэкапа".into());
+            }
+            match names.as_slice() {
+                [name] => println!("🔍 Проверка бэкапа {}...", name),
+                _ => println!("🔍 Проверка бэкапов: {} (до {} одновременно)...", names.len(), jobs),
+            }
+            remote_many(config, &names, jobs.max(1))?
+        }
+    };
+    for verification in &verifications {
+        print(verification);
+    }
+
+    let failed: Vec<&str> =
+        verifications.iter().filter(|v| !v.problems.is_empty()).map(|v| v.backup.as_str()).collect();
+    if verifications.len() > 1 {
+        let archives: usize = verifications.iter().map(|v| v.archives).sum();
+        println!(
+            "\n📋 Проверено бэкапов: {}, архивов сверено по SHA-256: {}, не прошли проверку: {}",
+            verifications.len(),
+            archives,
+            failed.len()
+        );
+        info!(
+            event = "backups_verified", backups = verifications.len(), failed = failed.len();
+            "Проверено бэкапов: {}, не прошли проверку: {}", verifications.len(), failed.len()
+        );
+    }
+
+    let report = verifications.iter().find(|v| !v.problems.is_empty()).or(verifications.first()).cloned();
+    config.last_verification = report;
+    config.save()?;
+    match failed.as_slice() {
+        [] => Ok(()),
+        [name] => Err(format!("Бэкап {} не прошёл проверку", name).into()),
+        names => Err(format!("Не прошли проверку бэкапы: {}", names.join(", ")).into()),
+    }
+}
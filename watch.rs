@@ -0,0 +1,176 @@
+use crate::config::{BackupPath, Priority, WatchConfig};
+use crate::control::{RunQueue, Task, TriggerSource};
+use crate::labels::Labels;
+use crate::timestamp;
+use chrono::Utc;
+use log::{info, warn};
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor};
+use std::collections::HashMap;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Метка микро-бэкапов режима наблюдения: по ней они хранятся отдельно от обычных
+pub const LABEL: (&str, &str) = ("trigger", "watch");
+
+const DEFAULT_DEBOUNCE_SECONDS: u64 = 60;
+const DEFAULT_MIN_INTERVAL_MINUTES: u64 = 15;
+const DEFAULT_KEEP: usize = 10;
+
+impl WatchConfig {
+    pub fn debounce(&self) -> Duration {
+        Duration::from_secs(self.debounce_seconds.unwrap_or(DEFAULT_DEBOUNCE_SECONDS))
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        Duration::from_secs(self.min_interval_minutes.unwrap_or(DEFAULT_MIN_INTERVAL_MINUTES) * 60)
+    }
+
+    pub fn keep(&self) -> usize {
+        self.keep.unwrap_or(DEFAULT_KEEP)
+    }
+
+    /// Пути микро-бэкапа: критичные, чтобы ошибка любого из них была видна сразу
+    pub fn backup_paths(&self) -> Vec<BackupPath> {
+        self.paths
+            .iter()
+            .map(|path| BackupPath {
+                path: path.clone(),
+                priority: Priority::Critical,
+                offset_minutes: None,
+                label: None,
+            })
+            .collect()
+    }
+}
+
+/// Метки запуска микро-бэкапа
+pub fn labels() -> Labels {
+    Labels::from([(LABEL.0.to_string(), LABEL.1.to_string())])
+}
+
+const EVENTS: AddWatchFlags = AddWatchFlags::IN_CLOSE_WRITE
+    .union(AddWatchFlags::IN_MODIFY)
+    .union(AddWatchFlags::IN_CREATE)
+    .union(AddWatchFlags::IN_DELETE)
+    .union(AddWatchFlags::IN_MOVED_FROM)
+    .union(AddWatchFlags::IN_MOVED_TO)
+    .union(AddWatchFlags::IN_ATTRIB);
+
+/// Наблюдаемая директория; для файла следим за его директорией, потому что файл
+/// могут заменить переименованием, а SQLite пишет рядом `-wal` и `-journal`
+struct Watched {
+    dir: PathBuf,
+    /// Только изменения файлов с именем, начинающимся с этого (для наблюдаемого файла)
+    prefix: Option<String>,
+}
+
+struct Watcher {
+    inotify: Inotify,
+    watched: HashMap<WatchDescriptor, Watched>,
+}
+
+impl Watcher {
+    fn new(paths: &[String]) -> nix::Result<Self> {
+        let mut watcher = Watcher {
+            inotify: Inotify::init(InitFlags::IN_CLOEXEC | InitFlags::IN_NONBLOCK)?,
+            watched: HashMap::new(),
+        };
+        for path in paths {
+            let path = Path::new(path);
+            if path.is_dir() {
+                watcher.add_tree(path);
+            } else if let (Some(dir), Some(name)) = (path.parent(), path.file_name()) {
+                watcher.add(dir, Some(name.to_string_lossy().to_string()));
+            }
+        }
+        Ok(watcher)
+    }
+
+    fn add(&mut self, dir: &Path, prefix: Option<String>) {
+        match self.inotify.add_watch(dir, EVENTS) {
+            Ok(wd) => {
+                self.watched.insert(wd, Watched { dir: dir.to_path_buf(), prefix });
+            }
+            Err(e) => warn!("Не удалось наблюдать за {}: {}", dir.display(), e),
+        }
+    }
+
+    /// inotify не следит за вложенными директориями сам, поэтому каждая добавляется отдельно
+    fn add_tree(&mut self, dir: &Path) {
+        self.add(dir, None);
+        let Ok(entries) = std::fs::read_dir(dir) else { return };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                self.add_tree(&entry.path());
+            }
+        }
+    }
+
+    /// Разбирает накопившиеся события; `true`, если среди них есть изменение наблюдаемых путей
+    fn changed(&mut self) -> bool {
+        let Ok(events) = self.inotify.read_events() else { return false };
+        let mut changed = false;
+        for event in events {
+            let Some(watched) = self.watched.get(&event.wd) else { continue };
+            let name = event.name.as_ref().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if watched.prefix.as_ref().is_some_and(|prefix| !name.starts_with(prefix.as_str())) {
+                continue;
+            }
+            changed = true;
+            // Новая поддиректория наблюдаемой директории
+            if watched.prefix.is_none() && event.mask.contains(AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ISDIR) {
+                let dir = watched.dir.join(&name);
+                self.add_tree(&dir);
+            }
+        }
+        changed
+    }
+}
+
+/// Поток демона: после изменения наблюдаемых путей ждёт `debounce_seconds` тишины и ставит
+/// в очередь микро-бэкап только этих путей, но не чаще раза в `min_interval_minutes`
+pub fn run(watch: WatchConfig, queue: &RunQueue) {
+    let mut watcher = match Watcher::new(&watch.paths) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Режим наблюдения не запущен: inotify недоступен: {}", e);
+            return;
+        }
+    };
+    info!("Наблюдение за изменениями: {}", watch.paths.join(", "));
+
+    let mut last_change: Option<Instant> = None;
+    let mut last_queued: Option<Instant> = None;
+    loop {
+        let mut fds = [PollFd::new(watcher.inotify.as_raw_fd(), PollFlags::POLLIN)];
+        // Без изменений ждём событий сколько угодно, с ними — проверяем раз в секунду
+        let timeout = if last_change.is_some() { 1000 } else { -1 };
+        if let Err(e) = poll(&mut fds, timeout) {
+            if e != nix::errno::Errno::EINTR {
+                warn!("Режим наблюдения остановлен: {}", e);
+                return;
+            }
+        }
+        if watcher.changed() {
+            last_change = Some(Instant::now());
+        }
+
+        let Some(changed_at) = last_change else { continue };
+        if changed_at.elapsed() < watch.debounce() {
+            continue;
+        }
+        if last_queued.is_some_and(|queued| queued.elapsed() < watch.min_interval()) {
+            continue;
+        }
+        // Уже ожидающий микро-бэкап ещё не начался и сохранит и это изменение,
+        // а изменения во время выполняющегося сохранит следующий
+        if !queue.has_queued(Task::Watch) {
+            let (id, position) = queue.push(TriggerSource::Watch, Task::Watch, labels(), timestamp::format(Utc::now()));
+            info!("Микро-бэкап #{} после изменения наблюдаемых путей поставлен в очередь (позиция {})", id, position);
+            last_queued = Some(Instant::now());
+        }
+        last_change = None;
+    }
+}
@@ -0,0 +1,368 @@
+use crate::config::{NetworkConfig, WebdavConfig};
+use crate::dest::{self, Check};
+use crate::keyring;
+use crate::network::{self, Failure};
+use crate::storage::{self, Backend};
+use crate::throttle;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use log::{info, warn};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Сколько раз пытаться выполнить запрос при сетевых ошибках и ответах 5xx
+const ATTEMPTS: u32 = 3;
+
+/// Свойства PROPFIND: тип ресурса для списка и квота для `obt dest check`
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:quota-available-bytes/><d:quota-used-bytes/></d:prop></d:propfind>"#;
+
+enum Body<'a> {
+    Empty,
+    Text(&'a str),
+    /// `limit_kbps` — `upload_limit_kbps`
+    File { path: &'a PathBuf, limit_kbps: Option<u64> },
+}
+
+/// Папка WebDAV (Nextcloud, ownCloud, Apache mod_dav и т.п.), в которую бэкапы копируются
+/// вместо Gitea: `<url>/<папка бэкапа>/<файл>`
+pub struct Storage {
+    /// Адрес папки бэкапов без `/` в конце
+    root: String,
+    /// Путь папки бэкапов на сервере: по нему из ответов PROPFIND получаются относительные пути
+    root_path: String,
+    host: String,
+    authorization: String,
+    dir: PathBuf,
+    upload_limit_kbps: Option<u64>,
+    /// `webdav.network`
+    network: NetworkConfig,
+    agent: ureq::Agent,
+    /// Папки, которые уже есть на сервере
+    created: RefCell<HashSet<String>>,
+    /// Файлы рабочей папки, которые уже есть на сервере
+    uploaded: RefCell<HashSet<String>>,
+    /// Папки удалённых бэкапов; удаляются с сервера после загрузки нового
+    removed: RefCell<Vec<String>>,
+}
+
+/// Значения элемента `<…name>значение</…name>` с любым префиксом пространства имён
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("{}>", name);
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(found) = rest.find(&open) {
+        let tag_start = rest[..found].rfind('<').unwrap_or(found);
+        let after = &rest[found + open.len()..];
+        // `</d:href>` и `<d:href/>` — не начало значения
+        if !rest[tag_start..found].starts_with("</") && !rest[tag_start..found].ends_with('/') {
+            if let Some(end) = after.find('<') {
+                values.push(after[..end].trim());
+            }
+        }
+        rest = after;
+    }
+    values
+}
+
+impl Storage {
+    /// Проверяет доступ к папке бэкапов и создаёт её, если её нет, до начала архивации
+    pub fn open(config: &WebdavConfig, dir: &str, upload_limit_kbps: Option<u64>) -> Result<Self, Box<dyn std::error::Error>> {
+        let url = url::Url::parse(&config.url).map_err(|e| format!("Неверный адрес WebDAV {}: {}", config.url, e))?;
+        let password = keyring::resolve(&config.password)?;
+        let network = config.network.clone().unwrap_or_default();
+        let storage = Storage {
+            root: config.url.trim_end_matches('/').to_string(),
+            root_path: percent_decode_str(url.path()).decode_utf8()?.trim_end_matches('/').to_string(),
+            host: url.host_str().unwrap_or_default().to_string(),
+            authorization: format!("Basic {}", BASE64_STANDARD.encode(format!("{}:{}", config.username, password))),
+            dir: PathBuf::from(dir),
+            upload_limit_kbps,
+            agent: network::agent(&network),
+            network,
+            created: RefCell::new(HashSet::new()),
+            uploaded: RefCell::new(HashSet::new()),
+            removed: RefCell::new(Vec::new()),
+        };
+        storage.mkcol("")?;
+        info!("Подключено хранилище {}", storage.location());
+        Ok(storage)
+    }
+
+    /// Адрес файла или папки: каждая часть пути кодируется отдельно
+    fn url(&self, relative: &str) -> String {
+        let mut url = self.root.clone();
+        for segment in relative.split('/').filter(|segment| !segment.is_empty()) {
+            url.push('/');
+            url.push_str(&utf8_percent_encode(segment, NON_ALPHANUMERIC).to_string());
+        }
+        url
+    }
+
+    /// Выполняет запрос с повтором при сетевых ошибках и ответах 5xx. Коды из `expected`
+    /// не ошибка: они возвращаются как `Ok(Err(код))`, и их разбирает вызывающий
+    fn call(
+        &self,
+        what: &str,
+        method: &str,
+        relative: &str,
+        headers: &[(&str, &str)],
+        body: Body,
+        expected: &[u16],
+    ) -> Result<Result<ureq::Response, u16>, Box<dyn std::error::Error>> {
+        let url = self.url(relative);
+        let mut attempt = 1;
+        loop {
+            let mut request = self.agent.request(method, &url).set("Authorization", &self.authorization);
+            for (name, value) in headers {
+                request = request.set(name, value);
+            }
+            let result = match &body {
+                Body::Empty => request.call(),
+                Body::Text(text) => request.send_string(text),
+                Body::File { path, limit_kbps } => request
+                    .set("Content-Length", &fs::metadata(path)?.len().to_string())
+                    .send(throttle::Reader::new(File::open(path)?, *limit_kbps).abort_below(&self.network)),
+            };
+            match result {
+                Ok(response) => return Ok(Ok(response)),
+                Err(ureq::Error::Status(code, _)) if expected.contains(&code) => return Ok(Err(code)),
+                Err(ureq::Error::Status(code, response)) if code < 500 || attempt >= ATTEMPTS => {
+                    let body = response.into_string().unwrap_or_default();
+                    let detail = format!("ответ {}", code);
+                    return Err(match Failure::from_status(code).or_else(|| Failure::classify(&body)) {
+                        Some(failure) => failure.explain(&self.host, "webdav.url", &format!("{}, {}", what, detail)),
+                        None => format!("WebDAV ({}): {}", what, detail),
+                    }
+                    .into());
+                }
+                Err(e) if attempt < ATTEMPTS && Failure::classify(&e.to_string()).is_none_or(Failure::retriable) => {
+                    warn!("Попытка {} из {} не удалась ({}): {}", attempt, ATTEMPTS, what, e);
+                    println!("Попытка {} не удалась, повтор через 5 сек...", attempt);
+                    std::thread::sleep(Duration::from_secs(5));
+                    attempt += 1;
+                }
+                Err(e) => {
+                    let detail = e.to_string();
+                    return Err(match Failure::classify(&detail) {
+                        Some(failure) => failure.explain(&self.host, "webdav.url", &detail),
+                        None => format!("WebDAV ({}): {}", what, detail),
+                    }
+                    .into());
+                }
+            }
+        }
+    }
+
+    /// Создаёт папку; ответ 405 означает, что она уже есть, 409 — что нет родительской
+    fn mkcol(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.created.borrow().contains(relative) {
+            return Ok(());
+        }
+        let what = format!("создание папки {}", relative);
+        if let Err(409) = self.call(&what, "MKCOL", relative, &[], Body::Empty, &[405, 409])? {
+            return Err(format!("Папка WebDAV {} недоступна: родительской папки нет", self.url(relative)).into());
+        }
+        self.created.borrow_mut().insert(relative.to_string());
+        Ok(())
+    }
+
+    /// Создаёт папки на пути к файлу `relative`, начиная с верхней
+    fn create_parents(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut parent = String::new();
+        let segments: Vec<&str> = relative.split('/').collect();
+        for segment in &segments[..segments.len() - 1] {
+            if !parent.is_empty() {
+                parent.push('/');
+            }
+            parent.push_str(segment);
+            self.mkcol(&parent)?;
+        }
+        Ok(())
+    }
+
+    /// PROPFIND папки или файла; `None`, если его нет
+    fn propfind(&self, relative: &str, depth: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let headers = [("Depth", depth), ("Content-Type", "application/xml; charset=utf-8")];
+        let what = format!("список {}", relative);
+        match self.call(&what, "PROPFIND", relative, &headers, Body::Text(PROPFIND_BODY), &[404])? {
+            Ok(response) => Ok(Some(response.into_string()?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Файлы в папке сервера (пути относительно корня). Папки в ответе PROPFIND
+    /// отличаются по `/` в конце адреса, как у Nextcloud, ownCloud и Apache
+    fn remote_files(&self, relative: &str, files: &mut Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(listing) = self.propfind(relative, "1")? else {
+            return Ok(());
+        };
+        for href in elements(&listing, "href") {
+            let path = match url::Url::parse(href) {
+                Ok(url) => url.path().to_string(),
+                Err(_) => href.to_string(),
+            };
+            let path = percent_decode_str(&path).decode_utf8()?.to_string();
+            let Some(found) = path.strip_prefix(&self.root_path).map(|p| p.trim_start_matches('/')) else {
+                continue;
+            };
+            let is_dir = found.ends_with('/');
+            let found = found.trim_end_matches('/');
+            if found == relative || found.is_empty() {
+                continue;
+            }
+            if is_dir {
+                self.remote_files(found, files)?;
+            } else {
+                files.push(found.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn put(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.create_parents(relative)?;
+        let path = self.dir.join(relative);
+        let body = Body::File { path: &path, limit_kbps: self.upload_limit_kbps };
+        let _ = self.call(&format!("загрузка {}", relative), "PUT", relative, &[], body, &[])?;
+        Ok(())
+    }
+
+    fn get(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let response = self
+            .call(&format!("скачивание {}", relative), "GET", relative, &[], Body::Empty, &[404])?
+            .map_err(|_| format!("Файла {} нет на сервере WebDAV", relative))?;
+        io::copy(&mut response.into_reader(), &mut File::create(&path)?)
+            .map_err(|e| format!("Ошибка скачивания {} с {}: {}", relative, self.host, e))?;
+        Ok(())
+    }
+
+    fn delete(&self, relative: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // 404: файла уже нет, например его удалили вручную
+        let _ = self.call(&format!("удаление {}", relative), "DELETE", relative, &[], Body::Empty, &[404])?;
+        Ok(())
+    }
+
+    /// `obt dest check`: записывает пробный файл `name` в папку бэкапов, читает и сверяет
+    /// его и удаляет, а место узнаёт из квоты WebDAV, если сервер её сообщает
+    pub fn probe(&self, name: &str, check: &mut Check) -> Result<(), Box<dyn std::error::Error>> {
+        let content = dest::probe_content(name);
+        let path = self.dir.join(name);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, &content)?;
+        check.step("запись", || self.put(name))?;
+        fs::remove_file(&path)?;
+        check.step("чтение", || {
+            self.get(name)?;
+            if fs::read_to_string(&path)? != content {
+                return Err("прочитанный файл отличается от записанного".into());
+            }
+            Ok(())
+        })?;
+        check.step("удаление", || self.delete(name))?;
+        check.space = self.free_space();
+        Ok(())
+    }
+
+    /// Свободное место по свойствам quota-available-bytes и quota-used-bytes (RFC 4331)
+    fn free_space(&self) -> Option<String> {
+        let properties = self.propfind("", "0").ok()??;
+        // Отрицательная квота у Nextcloud означает «без ограничения»
+        let available: u64 = elements(&properties, "quota-available-bytes").first()?.parse().ok()?;
+        let used: Option<u64> = elements(&properties, "quota-used-bytes").first().and_then(|used| used.parse().ok());
+        Some(match used {
+            Some(used) => format!(
+                "свободно {:.1} ГБ из {:.1} ГБ",
+                available as f64 / 1_073_741_824.0,
+                (available + used) as f64 / 1_073_741_824.0
+            ),
+            None => format!("свободно {:.1} ГБ", available as f64 / 1_073_741_824.0),
+        })
+    }
+}
+
+impl Backend for Storage {
+    fn location(&self) -> String {
+        self.root.clone()
+    }
+
+    fn describe(&self) -> String {
+        format!("WebDAV, {}", self.location())
+    }
+
+    fn download(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        self.remote_files("", &mut files)?;
+        println!("📥 Скачивание с {}: {} файлов...", self.host, files.len());
+        for file in files {
+            self.get(&file)?;
+            self.uploaded.borrow_mut().insert(file);
+        }
+        Ok(())
+    }
+
+    fn download_backup(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        self.remote_files(name, &mut files)?;
+        println!("📥 Скачивание {} с {}: {} файлов...", name, self.host, files.len());
+        for file in files {
+            self.get(&file)?;
+            self.uploaded.borrow_mut().insert(file);
+        }
+        Ok(())
+    }
+
+    fn backups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        self.remote_files("", &mut files)?;
+        Ok(storage::backup_folders(&files))
+    }
+
+    fn contains(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.propfind(name, "0")?.is_some())
+    }
+
+    fn remove(&self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        if !self.contains(name)? {
+            return Ok(false);
+        }
+        let dir = self.dir.join(name);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        self.removed.borrow_mut().push(name.to_string());
+        Ok(true)
+    }
+
+    fn upload_paths(&self, files: &[String], message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        for file in files {
+            if self.uploaded.borrow().contains(file) {
+                continue;
+            }
+            self.put(file)?;
+            self.uploaded.borrow_mut().insert(file.clone());
+        }
+        info!("Загружено на {}: {}", self.host, message);
+        Ok(())
+    }
+
+    fn upload_all(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.upload_paths(&storage::local_files(&self.dir)?, message)?;
+
+        // Старые бэкапы удаляются только после того, как новый целиком оказался на сервере;
+        // DELETE папки удаляет её вместе с содержимым
+        let removed: Vec<String> = self.removed.borrow_mut().drain(..).collect();
+        for name in &removed {
+            self.delete(name)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use crate::config::Config;
+use log::{info, warn};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Итог запуска бэкапа, который получают вебхуки
+#[derive(Debug, Serialize)]
+pub struct Payload {
+    /// `success` или `failure`
+    pub status: &'static str,
+    pub server: String,
+    /// Имя бэкапа; у неудачного запуска его нет
+    pub name: Option<String>,
+    pub duration_seconds: f64,
+    /// Объём архивов в байтах
+    pub size: Option<u64>,
+    pub archives: Option<usize>,
+    pub skipped: Option<usize>,
+    pub error: Option<String>,
+    /// UUID запуска: Obt-Run-Id коммита, obt-run-id объектов S3, run_id в manifest.json
+    pub run_id: Option<String>,
+}
+
+/// Адрес для журнала: путь вебхука Slack, Discord и Mattermost сам по себе является секретом
+pub fn redact(address: &str) -> String {
+    match url::Url::parse(address) {
+        Ok(url) => format!("{}://{}/***", url.scheme(), url.host_str().unwrap_or_default()),
+        Err(_) => "***".to_string(),
+    }
+}
+
+fn post(address: &str, payload: &Payload) -> Result<(), String> {
+    ureq::post(address)
+        .timeout(Duration::from_secs(30))
+        .send_json(payload)
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => {
+                format!("ответ {}: {}", code, response.into_string().unwrap_or_default())
+            }
+            // Текст ошибки ureq содержит полный адрес
+            ureq::Error::Transport(e) => format!("не удалось подключиться: {}", e.kind()),
+        })?;
+    Ok(())
+}
+
+/// Отправляет итог запуска на все адреса `webhooks`; ошибка одного адреса не мешает остальным
+pub fn send(config: &Config, payload: &Payload) {
+    for address in &config.webhooks {
+        match post(address, payload) {
+            Ok(_) => info!("Итог бэкапа отправлен на вебхук {}", redact(address)),
+            Err(e) => warn!("Не удалось отправить итог бэкапа на вебхук {}: {}", redact(address), e),
+        }
+    }
+}